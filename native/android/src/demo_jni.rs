@@ -5,15 +5,16 @@
 
 use std::ffi::CString;
 
+use unicode_segmentation::UnicodeSegmentation;
+
 use jni::objects::{JClass, JString};
 use jni::sys::{jdouble, jint, jstring};
 use jni::JNIEnv;
 
-use crate::editor_view::EditorView;
 use crate::{
-    hone_editor_begin_frame, hone_editor_create, hone_editor_end_frame, hone_editor_measure_text,
-    hone_editor_render_line, hone_editor_set_cursor, hone_editor_set_font,
-    hone_editor_set_selection,
+    hone_editor_begin_frame, hone_editor_column_at_x, hone_editor_create_handle,
+    hone_editor_end_frame, hone_editor_measure_text, hone_editor_render_line,
+    hone_editor_set_cursor, hone_editor_set_font, hone_editor_set_selection, ViewHandle,
 };
 
 // ── DemoEditor state ────────────────────────────────────────────
@@ -28,7 +29,7 @@ struct DemoEditor {
     sel_anchor: Option<(usize, usize)>,
     scroll_y: f64,
     view_height: f64,
-    editor_ptr: *mut EditorView,
+    editor: ViewHandle,
     char_width: f64,
     line_height: f64,
 }
@@ -191,7 +192,7 @@ fn validate_tokens_json(tokens_json: &str, orig_text: &str, curr_text: &str) ->
 static mut DEMO: Option<DemoEditor> = None;
 
 impl DemoEditor {
-    fn new(editor_ptr: *mut EditorView, char_width: f64, line_height: f64, view_height: f64) -> Self {
+    fn new(editor: ViewHandle, char_width: f64, line_height: f64, view_height: f64) -> Self {
         let content = initial_content();
         let lines: Vec<String> = content.iter().map(|(t, _)| t.clone()).collect();
         let line_origins = (0..lines.len()).collect();
@@ -204,7 +205,7 @@ impl DemoEditor {
             sel_anchor: None,
             scroll_y: 0.0,
             view_height,
-            editor_ptr,
+            editor,
             char_width,
             line_height,
         }
@@ -425,7 +426,7 @@ impl DemoEditor {
         if self.cursor_col > 0 {
             let line = &self.lines[self.cursor_line];
             self.cursor_col = line[..self.cursor_col]
-                .char_indices()
+                .grapheme_indices(true)
                 .next_back()
                 .map(|(i, _)| i)
                 .unwrap_or(0);
@@ -454,7 +455,7 @@ impl DemoEditor {
         if self.cursor_col < line_len {
             let line = &self.lines[self.cursor_line];
             self.cursor_col = line[self.cursor_col..]
-                .char_indices()
+                .grapheme_indices(true)
                 .nth(1)
                 .map(|(i, _)| self.cursor_col + i)
                 .unwrap_or(line_len);
@@ -544,23 +545,25 @@ impl DemoEditor {
         let line = line.min(self.lines.len().saturating_sub(1));
 
         let text_x = x - gutter_w;
-        let col = if text_x <= 0.0 {
-            0
-        } else {
-            // Approximate column from x position using monospace char width
-            let approx_col = (text_x / self.char_width).round() as usize;
-            approx_col.min(self.lines[line].len())
-        };
+        let col = self.column_at_x(line, text_x);
 
         self.cursor_line = line;
         self.cursor_col = col;
         self.sel_anchor = None;
     }
 
+    /// Byte column in line `idx` nearest to `text_x` (relative to the text
+    /// start), measured with the editor's font so tabs and multi-byte
+    /// characters line up with what is drawn.
+    fn column_at_x(&self, idx: usize, text_x: f64) -> usize {
+        let text = CString::new(self.lines[idx].as_str()).unwrap_or_default();
+        hone_editor_column_at_x(self.editor, text.as_ptr(), text_x).max(0) as usize
+    }
+
     // ── Rendering ───────────────────────────────────────────────
 
     fn render(&self) {
-        let editor = self.editor_ptr;
+        let editor = self.editor;
         let gutter_w = self.gutter_width();
 
         hone_editor_begin_frame(editor);
@@ -680,7 +683,7 @@ pub extern "system" fn Java_com_honeide_demo_NativeLib_nativeInit(
     width: jdouble,
     height: jdouble,
 ) {
-    let editor = hone_editor_create(width, height);
+    let editor = hone_editor_create_handle(width, height);
     let font_family = CString::new("monospace").unwrap();
     hone_editor_set_font(editor, font_family.as_ptr(), 14.0);
 
@@ -719,7 +722,7 @@ pub extern "system" fn Java_com_honeide_demo_NativeLib_nativeDestroy(
 ) {
     unsafe {
         if let Some(ref demo) = DEMO {
-            crate::hone_editor_destroy(demo.editor_ptr);
+            crate::hone_editor_destroy_handle(demo.editor);
         }
         DEMO = None;
     }
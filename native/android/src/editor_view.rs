@@ -6,23 +6,27 @@
 //! - Canvas.drawText() for each token span
 //! - Pre-render lines to Bitmap objects for fast scrolling
 
+use hone_editor_common::graphemes::grapheme_boundaries;
+use hone_editor_common::handles::ViewHandle;
+use hone_editor_common::hit_test::column_at_x;
+use hone_editor_common::words::word_range_at;
 use serde::Deserialize;
 use std::ffi::{c_char, CString};
 
 // ── Callback types ──────────────────────────────────────────────
 
 /// Called when the user types printable text. `text` is a null-terminated UTF-8 C string.
-pub type TextInputCallback = extern "C" fn(view: *mut EditorView, text: *const c_char);
+pub type TextInputCallback = extern "C" fn(view: ViewHandle, text: *const c_char);
 
 /// Called when an action fires (arrow keys, delete, enter, etc.).
 /// `action` is the action name as a null-terminated UTF-8 C string.
-pub type ActionCallback = extern "C" fn(view: *mut EditorView, action: *const c_char);
+pub type ActionCallback = extern "C" fn(view: ViewHandle, action: *const c_char);
 
 /// Called when the user taps in the editor view. `x` and `y` are in view coordinates.
-pub type MouseDownCallback = extern "C" fn(view: *mut EditorView, x: f64, y: f64);
+pub type MouseDownCallback = extern "C" fn(view: ViewHandle, x: f64, y: f64);
 
 /// Called when the user scrolls. `dx`/`dy` are pixel deltas.
-pub type ScrollCallback = extern "C" fn(view: *mut EditorView, dx: f64, dy: f64);
+pub type ScrollCallback = extern "C" fn(view: ViewHandle, dx: f64, dy: f64);
 
 /// A custom context menu item added by the host application.
 pub struct ContextMenuItem {
@@ -52,6 +56,9 @@ pub struct SelectionRegion {
 pub struct CursorData {
     pub x: f64,
     pub y: f64,
+    /// 0 = line, 1 = block, 2 = underline. Set per cursor, so a block primary
+    /// can be drawn alongside line secondaries. Missing means line.
+    #[serde(default)]
     pub style: i32,
 }
 
@@ -66,6 +73,15 @@ pub struct DecorationOverlay {
     pub kind: String,
 }
 
+#[derive(Deserialize)]
+struct LineBatchEntry {
+    line_number: i32,
+    text: String,
+    #[serde(default)]
+    tokens: serde_json::Value,
+    y_offset: f64,
+}
+
 pub struct LineRenderData {
     pub line_number: i32,
     pub text: String,
@@ -109,6 +125,13 @@ pub struct EditorView {
 
     // Context menu
     context_menu_items: Vec<ContextMenuItem>,
+
+    // Caret mode: 0 = insert (line caret), 1 = overwrite (block caret).
+    // Supplies the style for cursors pushed with style -1.
+    caret_mode: i32,
+    // Handle the host knows this view by; 0 until lib.rs registers it
+    pub(crate) handle: ViewHandle,
+    grapheme_buf: Vec<u32>,
 }
 
 impl EditorView {
@@ -133,6 +156,9 @@ impl EditorView {
             mouse_down_callback: None,
             scroll_callback: None,
             context_menu_items: Vec::new(),
+            caret_mode: 0,
+            handle: 0,
+            grapheme_buf: Vec::new(),
         }
     }
 
@@ -147,6 +173,38 @@ impl EditorView {
         text.len() as f64 * self.font_size * 0.6
     }
 
+    /// Byte column of the grapheme boundary in `text` nearest `x`, measured
+    /// from the text's left edge with this view's font.
+    pub fn column_at_x(&self, text: &str, x: f64) -> usize {
+        column_at_x(text, x, |p| self.measure_text(p))
+    }
+
+    /// Advance width of `text[start..end]` (byte offsets). Returns 0 for an
+    /// empty range or offsets that are out of bounds or not on a char boundary.
+    pub fn measure_range(&self, text: &str, start: usize, end: usize) -> f64 {
+        match text.get(start..end) {
+            Some(span) if !span.is_empty() => self.measure_text(span),
+            _ => 0.0,
+        }
+    }
+
+    /// Byte offsets of the extended grapheme cluster boundaries in `text`,
+    /// from 0 through `text.len()`. Valid until the next call.
+    pub fn grapheme_boundaries(&mut self, text: &str) -> &[u32] {
+        self.grapheme_buf.clear();
+        self.grapheme_buf.extend(grapheme_boundaries(text).map(|i| i as u32));
+        &self.grapheme_buf
+    }
+
+    /// Byte range of the word around byte column `col` in `text`, by Unicode
+    /// word segmentation. A caret just after a word picks that word;
+    /// otherwise the segment under `col` is used, so a run of punctuation or
+    /// whitespace selects as a unit. With `subword`, identifiers also split
+    /// at underscores and case changes (`fooBar` gives `foo` and `Bar`).
+    pub fn word_range_at(&self, text: &str, col: usize, subword: bool) -> (usize, usize) {
+        word_range_at(text, col, subword)
+    }
+
     // ── Frame buffer API ─────────────────────────────────────────
 
     pub fn begin_frame(&mut self) {
@@ -172,13 +230,45 @@ impl EditorView {
         });
     }
 
+    /// Queue a whole viewport in one call. `lines_json` is an array of
+    /// `{line_number, text, tokens, y_offset}` objects, where `tokens` is
+    /// the same token array `render_line` takes as a string.
+    pub fn render_lines(&mut self, lines_json: &str) {
+        let lines: Vec<LineBatchEntry> = serde_json::from_str(lines_json).unwrap_or_default();
+        for line in lines {
+            let tokens_json = if line.tokens.is_array() { line.tokens.to_string() } else { "[]".to_string() };
+            self.render_line(line.line_number, &line.text, &tokens_json, line.y_offset);
+        }
+    }
+
+    pub fn set_caret_mode(&mut self, mode: i32) {
+        self.caret_mode = mode;
+    }
+
+    /// Resolve style -1 ("use mode default") to the caret mode's shape.
+    /// Explicit styles (0, 1, 2) are kept as-is.
+    fn resolve_cursor_style(&self, style: i32) -> i32 {
+        match style {
+            -1 if self.caret_mode == 1 => 1,
+            -1 => 0,
+            s => s,
+        }
+    }
+
     pub fn set_cursor(&mut self, x: f64, y: f64, style: i32) {
+        let style = self.resolve_cursor_style(style);
         self.cursor = Some(CursorData { x, y, style });
         self.needs_display = true;
     }
 
+    /// Replace the multi-cursor list for this frame. `begin_frame` clears it;
+    /// calling this again within a frame overwrites rather than appends.
     pub fn set_cursors(&mut self, cursors_json: &str) {
-        self.cursors = serde_json::from_str(cursors_json).unwrap_or_default();
+        let mut cursors: Vec<CursorData> = serde_json::from_str(cursors_json).unwrap_or_default();
+        for c in &mut cursors {
+            c.style = self.resolve_cursor_style(c.style);
+        }
+        self.cursors = cursors;
     }
 
     pub fn set_selection(&mut self, regions_json: &str) {
@@ -210,6 +300,14 @@ impl EditorView {
         self.needs_display = true;
     }
 
+    /// Blank the view outside a frame (e.g. when the file closes): drops all
+    /// frame content, as an empty `begin_frame`/`end_frame` pair would, and
+    /// redraws.
+    pub fn clear(&mut self) {
+        self.begin_frame();
+        self.end_frame();
+    }
+
     pub fn invalidate(&mut self) {
         self.needs_display = true;
     }
@@ -236,8 +334,7 @@ impl EditorView {
     pub fn on_text_input(&mut self, text: &str) {
         if let Some(cb) = self.text_input_callback {
             if let Ok(c_text) = CString::new(text) {
-                let self_ptr = self as *mut EditorView;
-                cb(self_ptr, c_text.as_ptr());
+                cb(self.handle, c_text.as_ptr());
             }
         }
     }
@@ -246,8 +343,7 @@ impl EditorView {
     pub fn on_action(&mut self, action: &str) {
         if let Some(cb) = self.action_callback {
             if let Ok(c_action) = CString::new(action) {
-                let self_ptr = self as *mut EditorView;
-                cb(self_ptr, c_action.as_ptr());
+                cb(self.handle, c_action.as_ptr());
             }
         }
     }
@@ -255,16 +351,14 @@ impl EditorView {
     /// Called from the Android View's touch handler.
     pub fn on_mouse_down(&mut self, x: f64, y: f64) {
         if let Some(cb) = self.mouse_down_callback {
-            let self_ptr = self as *mut EditorView;
-            cb(self_ptr, x, y);
+            cb(self.handle, x, y);
         }
     }
 
     /// Called from the Android View's scroll handler.
     pub fn on_scroll(&mut self, dx: f64, dy: f64) {
         if let Some(cb) = self.scroll_callback {
-            let self_ptr = self as *mut EditorView;
-            cb(self_ptr, dx, dy);
+            cb(self.handle, dx, dy);
         }
     }
 
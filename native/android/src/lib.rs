@@ -3,6 +3,8 @@
 //! Uses JNI to call android.graphics.Canvas and android.graphics.Paint
 //! for text rendering. Integrates with InputMethodManager for soft keyboard.
 
+use hone_editor_common::handles::HandleTable;
+use std::cell::RefCell;
 use std::ffi::{c_char, CStr};
 
 mod editor_view;
@@ -10,95 +12,175 @@ mod input_handler;
 mod demo_jni;
 
 pub use editor_view::EditorView;
+pub use hone_editor_common::handles::ViewHandle;
 
 use editor_view::{ActionCallback, MouseDownCallback, ScrollCallback, TextInputCallback};
 
+thread_local! {
+    /// Views created on this thread. Views are single-threaded, so a handle
+    /// only resolves on the thread that created it.
+    static VIEWS: RefCell<HandleTable<EditorView>> = const { RefCell::new(HandleTable::new()) };
+}
+
+/// Resolve a handle from the host to its live view. Returns None for 0, for
+/// a destroyed view's handle, and on any thread other than the view's own,
+/// so such calls are ignored (mutating ones panic in debug builds).
+fn view_ref<'a>(view: ViewHandle) -> Option<&'a EditorView> {
+    VIEWS.with(|views| views.borrow().get(view)).map(|view| unsafe { &*view })
+}
+
+/// `view_ref` for calls that mutate the view. Views are single-threaded, so
+/// debug builds panic if the handle belongs to a view on another thread.
+fn view_mut<'a>(view: ViewHandle) -> Option<&'a mut EditorView> {
+    let found = VIEWS.with(|views| views.borrow().get(view));
+    #[cfg(debug_assertions)]
+    if found.is_none() {
+        hone_editor_common::handles::owners::assert_not_foreign(view);
+    }
+    found.map(|view| unsafe { &mut *view })
+}
+
+/// Move a new view into this thread's table and return its handle, or 0 if
+/// the table is full (the view is dropped).
+fn register(view: Box<EditorView>) -> ViewHandle {
+    VIEWS.with(|views| {
+        let mut views = views.borrow_mut();
+        let Some(handle) = views.insert(view) else {
+            return 0;
+        };
+        if let Some(view) = views.get(handle) {
+            unsafe { (*view).handle = handle };
+        }
+        #[cfg(debug_assertions)]
+        hone_editor_common::handles::owners::claim(handle);
+        handle
+    })
+}
+
 // === FFI Contract Implementation ===
 
 #[no_mangle]
-pub extern "C" fn hone_editor_create(width: f64, height: f64) -> *mut EditorView {
-    Box::into_raw(Box::new(EditorView::new(width, height)))
+pub extern "C" fn hone_editor_create_handle(width: f64, height: f64) -> ViewHandle {
+    register(Box::new(EditorView::new(width, height)))
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_attach_to_view(view: *mut EditorView, parent_view: i64) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_attach_to_view(view: ViewHandle, parent_view: i64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.parent_view = parent_view as *mut std::ffi::c_void;
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_destroy(view: *mut EditorView) {
-    if !view.is_null() { unsafe { drop(Box::from_raw(view)); } }
+pub extern "C" fn hone_editor_destroy_handle(view: ViewHandle) {
+    // Drop outside the table borrow so teardown can't observe it.
+    let Some(removed) = VIEWS.with(|views| views.borrow_mut().remove(view)) else {
+        return;
+    };
+    #[cfg(debug_assertions)]
+    hone_editor_common::handles::owners::release(view);
+    drop(removed);
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_set_font(view: *mut EditorView, family: *const c_char, size: f64) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_set_font(view: ViewHandle, family: *const c_char, size: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let family_str = unsafe { CStr::from_ptr(family) }.to_str().unwrap_or("monospace");
     view.set_font(family_str, size);
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_render_line(view: *mut EditorView, line_number: i32, text: *const c_char, tokens_json: *const c_char, y_offset: f64) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_render_line(view: ViewHandle, line_number: i32, text: *const c_char, tokens_json: *const c_char, y_offset: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     let tokens_str = unsafe { CStr::from_ptr(tokens_json) }.to_str().unwrap_or("[]");
     view.render_line(line_number, text_str, tokens_str, y_offset);
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_set_cursor(view: *mut EditorView, x: f64, y: f64, style: i32) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_set_cursor(view: ViewHandle, x: f64, y: f64, style: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_cursor(x, y, style);
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_set_selection(view: *mut EditorView, regions_json: *const c_char) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_set_selection(view: ViewHandle, regions_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(regions_json) }.to_str().unwrap_or("[]");
     view.set_selection(json_str);
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_scroll(view: *mut EditorView, offset_y: f64) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_scroll(view: ViewHandle, offset_y: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.scroll(offset_y);
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_measure_text(view: *mut EditorView, text: *const c_char) -> f64 {
-    let view = unsafe { &*view };
+pub extern "C" fn hone_editor_measure_text(view: ViewHandle, text: *const c_char) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     view.measure_text(text_str)
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_invalidate(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_invalidate(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.invalidate();
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_begin_frame(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_begin_frame(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.begin_frame();
 }
 
 #[no_mangle]
-pub extern "C" fn hone_editor_end_frame(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_end_frame(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.end_frame();
 }
 
+/// Blank the view outside a frame: clears lines, cursors, selections,
+/// decorations, and ghost text, then redraws.
+#[no_mangle]
+pub extern "C" fn hone_editor_clear(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.clear();
+}
+
 // === Extended FFI (matching iOS/macOS) ===
 
 /// Render decorations (underlines, backgrounds) for a line.
 #[no_mangle]
 pub extern "C" fn hone_editor_render_decorations(
-    view: *mut EditorView,
+    view: ViewHandle,
     decorations_json: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(decorations_json) }.to_str().unwrap_or("[]");
     view.render_decorations(json_str);
 }
@@ -106,25 +188,31 @@ pub extern "C" fn hone_editor_render_decorations(
 /// Render ghost text (semi-transparent inline completion).
 #[no_mangle]
 pub extern "C" fn hone_editor_render_ghost_text(
-    view: *mut EditorView,
+    view: ViewHandle,
     text: *const c_char,
     x: f64,
     y: f64,
     color: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     let color_str = unsafe { CStr::from_ptr(color) }.to_str().unwrap_or("#808080");
     view.render_ghost_text(text_str, x, y, color_str);
 }
 
-/// Set multiple cursor positions.
+/// Set multiple cursor positions. `cursors_json` is a JSON array of
+/// `{x, y, style}`; each entry's style (0=line, 1=block, 2=underline) is drawn
+/// independently. Replaces any cursors set earlier in the frame.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_cursors(
-    view: *mut EditorView,
+    view: ViewHandle,
     cursors_json: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(cursors_json) }.to_str().unwrap_or("[]");
     view.set_cursors(json_str);
 }
@@ -132,51 +220,61 @@ pub extern "C" fn hone_editor_set_cursors(
 /// Set the callback for text input (printable characters).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_text_input_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: TextInputCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_text_input_callback(callback);
 }
 
 /// Set the callback for action selectors (arrows, delete, enter, etc.).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_action_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: ActionCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_action_callback(callback);
 }
 
 /// Set the callback for touch-down events (tap to position cursor).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_mouse_down_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: MouseDownCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_mouse_down_callback(callback);
 }
 
 /// Set the callback for scroll events (pan gesture).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_scroll_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: ScrollCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_scroll_callback(callback);
 }
 
 /// Add a custom item to the editor's context menu.
 #[no_mangle]
 pub extern "C" fn hone_editor_add_context_menu_item(
-    view: *mut EditorView,
+    view: ViewHandle,
     title: *const c_char,
     action_id: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let title_str = unsafe { CStr::from_ptr(title) }.to_str().unwrap_or("");
     let action_str = unsafe { CStr::from_ptr(action_id) }.to_str().unwrap_or("");
     view.add_context_menu_item(title_str, action_str);
@@ -184,14 +282,224 @@ pub extern "C" fn hone_editor_add_context_menu_item(
 
 /// Remove all custom context menu items.
 #[no_mangle]
-pub extern "C" fn hone_editor_clear_context_menu_items(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_clear_context_menu_items(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.clear_context_menu_items();
 }
 
 /// Get the Android parent view handle (as a raw pointer).
 #[no_mangle]
-pub extern "C" fn hone_editor_android_view(view: *mut EditorView) -> i64 {
-    let view = unsafe { &*view };
+pub extern "C" fn hone_editor_android_view(view: ViewHandle) -> i64 {
+    let Some(view) = view_ref(view) else {
+        return 0;
+    };
     view.parent_view as i64
 }
+
+/// Set the caret mode: 0 = insert (line caret), 1 = overwrite (block caret).
+/// Cursors pushed with style -1 take the mode's shape; explicit styles
+/// (0=line, 1=block, 2=underline) always win.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_caret_mode(view: ViewHandle, mode: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_caret_mode(mode);
+}
+
+#[no_mangle]
+pub extern "C" fn hone_editor_render_lines(view: ViewHandle, lines_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json = unsafe { CStr::from_ptr(lines_json) }.to_str().unwrap_or("[]");
+    view.render_lines(json);
+}
+
+/// Render a single line from length-delimited UTF-8 slices. Neither slice
+/// needs a NUL terminator, so the host can pass views into an existing
+/// buffer instead of allocating a CString per line. Invalid UTF-8 is
+/// replaced with U+FFFD.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_line_ptr(
+    view: ViewHandle,
+    line_number: i32,
+    text_ptr: *const u8,
+    text_len: usize,
+    tokens_ptr: *const u8,
+    tokens_len: usize,
+    y_offset: f64,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text = unsafe { utf8_lossy(text_ptr, text_len) };
+    let tokens = unsafe { utf8_lossy(tokens_ptr, tokens_len) };
+    let tokens = if tokens.is_empty() { "[]" } else { &tokens };
+    view.render_line(line_number, &text, tokens, y_offset);
+}
+
+/// Borrow `len` bytes at `ptr` as UTF-8. Null or empty input yields "".
+unsafe fn utf8_lossy<'a>(ptr: *const u8, len: usize) -> std::borrow::Cow<'a, str> {
+    if ptr.is_null() || len == 0 {
+        return std::borrow::Cow::Borrowed("");
+    }
+    String::from_utf8_lossy(std::slice::from_raw_parts(ptr, len))
+}
+
+#[no_mangle]
+pub extern "C" fn hone_editor_measure_range(view: ViewHandle, text: *const c_char, start_byte: usize, end_byte: usize) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    view.measure_range(text_str, start_byte, end_byte)
+}
+
+/// Check that the caller is on the thread that created `view`. Handles only
+/// resolve on their owning thread, so this is false for a foreign thread (or
+/// a destroyed view): exactly the calls the rest of the API ignores.
+#[no_mangle]
+pub extern "C" fn hone_editor_assert_thread(view: ViewHandle) -> bool {
+    view_ref(view).is_some()
+}
+
+#[no_mangle]
+pub extern "C" fn hone_editor_grapheme_boundaries(view: ViewHandle, text: *const c_char, out_len: *mut usize) -> *const u32 {
+    if !out_len.is_null() {
+        unsafe { *out_len = 0 };
+    }
+    let Some(view) = view_mut(view) else {
+        return std::ptr::null();
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let bounds = view.grapheme_boundaries(text_str);
+    if !out_len.is_null() {
+        unsafe { *out_len = bounds.len() };
+    }
+    bounds.as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn hone_editor_word_range_at(
+    view: ViewHandle,
+    text: *const c_char,
+    col: i32,
+    subword: bool,
+    out_start: *mut i32,
+    out_end: *mut i32,
+) -> bool {
+    if text.is_null() || out_start.is_null() || out_end.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let (start, end) = view.word_range_at(text_str, col.max(0) as usize, subword);
+    unsafe {
+        *out_start = start as i32;
+        *out_end = end as i32;
+    }
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn hone_editor_column_at_x(view: ViewHandle, text: *const c_char, x: f64) -> i32 {
+    if text.is_null() {
+        return 0;
+    }
+    let Some(view) = view_ref(view) else {
+        return 0;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    view.column_at_x(text_str, x) as i32
+}
+
+// === Pointer Compatibility ===
+//
+// Hosts written against the original pointer-based contract keep working
+// while they migrate: `hone_editor_create` and `hone_editor_destroy` keep
+// their pointer signatures, and `hone_editor_handle_for_view` turns such a
+// pointer into the handle every other entry point takes. Pointers are looked
+// up in this thread's handle table, never dereferenced, so one used after
+// destroy is ignored unless a newer view was allocated at the same address.
+// Only handles rule that out.
+
+#[no_mangle]
+pub extern "C" fn hone_editor_create(width: f64, height: f64) -> *mut EditorView {
+    hone_editor_view_for_handle(hone_editor_create_handle(width, height))
+}
+
+#[no_mangle]
+pub extern "C" fn hone_editor_destroy(view: *mut EditorView) {
+    hone_editor_destroy_handle(hone_editor_handle_for_view(view));
+}
+
+#[no_mangle]
+pub extern "C" fn hone_editor_handle_for_view(view: *mut EditorView) -> ViewHandle {
+    VIEWS.with(|views| views.borrow().find(view)).unwrap_or(0)
+}
+
+#[no_mangle]
+pub extern "C" fn hone_editor_view_for_handle(view: ViewHandle) -> *mut EditorView {
+    VIEWS.with(|views| views.borrow().get(view)).unwrap_or(std::ptr::null_mut())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn column_at_x_measures_a_tab_indented_line() {
+        let view = hone_editor_create_handle(800.0, 600.0);
+        let family = CString::new("monospace").unwrap();
+        hone_editor_set_font(view, family.as_ptr(), 10.0);
+        // 6pt per byte at 10pt: the tab covers 0..6 and "x" 6..12.
+        let text = CString::new("\tx = 1").unwrap();
+        assert_eq!(hone_editor_column_at_x(view, text.as_ptr(), 2.0), 0);
+        assert_eq!(hone_editor_column_at_x(view, text.as_ptr(), 4.0), 1);
+        assert_eq!(hone_editor_column_at_x(view, text.as_ptr(), 10.0), 2);
+        assert_eq!(hone_editor_column_at_x(view, text.as_ptr(), 500.0), 6);
+        hone_editor_destroy_handle(view);
+        assert_eq!(hone_editor_column_at_x(view, text.as_ptr(), 10.0), 0);
+    }
+
+    #[test]
+    fn pointer_callers_reach_the_view_through_its_handle() {
+        let ptr = hone_editor_create(800.0, 600.0);
+        let view = hone_editor_handle_for_view(ptr);
+        assert_ne!(view, 0);
+        assert_eq!(hone_editor_view_for_handle(view), ptr);
+        let text = CString::new("abc").unwrap();
+        assert_eq!(hone_editor_column_at_x(view, text.as_ptr(), 500.0), 3);
+        hone_editor_destroy(ptr);
+        assert_eq!(hone_editor_handle_for_view(ptr), 0);
+        assert!(hone_editor_view_for_handle(view).is_null());
+        // A second destroy through the stale pointer is ignored.
+        hone_editor_destroy(ptr);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn mutating_another_threads_view_panics() {
+        let (handle_tx, handle_rx) = std::sync::mpsc::channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        let owner = std::thread::spawn(move || {
+            let view = hone_editor_create_handle(800.0, 600.0);
+            handle_tx.send(view).unwrap();
+            done_rx.recv().unwrap();
+            hone_editor_destroy_handle(view);
+        });
+        let view = handle_rx.recv().unwrap();
+        // Through view_mut: a panic can't unwind out of the extern "C" API.
+        let panic = std::panic::catch_unwind(|| view_mut(view).is_none()).unwrap_err();
+        done_tx.send(()).unwrap();
+        owner.join().unwrap();
+        let message = panic.downcast_ref::<&str>().unwrap();
+        assert!(message.contains("view mutated off the thread that created it"));
+    }
+}
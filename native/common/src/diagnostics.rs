@@ -0,0 +1,25 @@
+//! Diagnostic severities as the TypeScript layer names them.
+
+/// Index into `diagnostic_colors` for a diagnostic severity name.
+pub fn severity_index(severity: &str) -> Option<usize> {
+    match severity {
+        "error" => Some(0),
+        "warning" => Some(1),
+        "info" => Some(2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_severities_index_error_warning_info() {
+        assert_eq!(severity_index("error"), Some(0));
+        assert_eq!(severity_index("warning"), Some(1));
+        assert_eq!(severity_index("info"), Some(2));
+        assert_eq!(severity_index("hint"), None);
+        assert_eq!(severity_index("Error"), None);
+    }
+}
@@ -0,0 +1,95 @@
+//! Sharing loaded fonts between views.
+//!
+//! Each platform keeps one `FontCache` per UI thread. Views asking for the
+//! same family, size, weight and slant get the same loaded fonts, and the
+//! cache holds them weakly so they are freed with the last view using them.
+
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+/// Family, point size bits, weight and slant.
+type FontKey = (String, u64, u16, bool);
+
+/// Live fonts of type `T` by family, point size, weight and slant.
+pub struct FontCache<T> {
+    fonts: HashMap<FontKey, Weak<T>>,
+}
+
+impl<T> FontCache<T> {
+    pub fn new() -> Self {
+        Self { fonts: HashMap::new() }
+    }
+
+    /// The fonts another view already holds for `family` at `size` in this
+    /// weight and slant, or the result of `load`, registered for the next
+    /// view to reuse.
+    pub fn shared(
+        &mut self,
+        family: &str,
+        size: f64,
+        weight: u16,
+        italic: bool,
+        load: impl FnOnce() -> T,
+    ) -> Rc<T> {
+        let key = (family.to_string(), size.to_bits(), weight, italic);
+        if let Some(shared) = self.fonts.get(&key).and_then(Weak::upgrade) {
+            return shared;
+        }
+        self.fonts.retain(|_, f| f.strong_count() > 0);
+        let shared = Rc::new(load());
+        self.fonts.insert(key, Rc::downgrade(&shared));
+        shared
+    }
+}
+
+impl<T> Default for FontCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ten_views_share_one_font_object() {
+        let mut cache = FontCache::new();
+        let mut loads = 0;
+        let views: Vec<Rc<String>> = (0..10)
+            .map(|_| {
+                cache.shared("Menlo", 13.0, 400, false, || {
+                    loads += 1;
+                    "Menlo 13".to_string()
+                })
+            })
+            .collect();
+        assert_eq!(loads, 1);
+        assert!(views.iter().all(|v| Rc::ptr_eq(v, &views[0])));
+        assert_eq!(Rc::strong_count(&views[0]), 10);
+    }
+
+    #[test]
+    fn size_weight_and_slant_each_get_their_own_fonts() {
+        let mut cache = FontCache::new();
+        let base = cache.shared("Menlo", 13.0, 400, false, || 0);
+        let sized = cache.shared("Menlo", 14.0, 400, false, || 1);
+        let bold = cache.shared("Menlo", 13.0, 700, false, || 2);
+        let italic = cache.shared("Menlo", 13.0, 400, true, || 3);
+        let other = cache.shared("Monaco", 13.0, 400, false, || 4);
+        for f in [&sized, &bold, &italic, &other] {
+            assert!(!Rc::ptr_eq(&base, f));
+        }
+    }
+
+    #[test]
+    fn fonts_are_freed_with_their_last_view() {
+        let mut cache = FontCache::new();
+        let first = cache.shared("Menlo", 13.0, 400, false, || 1);
+        let weak = Rc::downgrade(&first);
+        drop(first);
+        assert!(weak.upgrade().is_none());
+        let reloaded = cache.shared("Menlo", 13.0, 400, false, || 2);
+        assert_eq!(*reloaded, 2);
+    }
+}
@@ -0,0 +1,57 @@
+//! Extended grapheme cluster boundaries, for moving and deleting by what
+//! the user sees as one character.
+
+use std::iter;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Byte offsets of the grapheme cluster boundaries in `text`, from 0
+/// through `text.len()`.
+pub fn grapheme_boundaries(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.grapheme_indices(true).map(|(i, _)| i).chain(iter::once(text.len()))
+}
+
+/// The same boundaries as UTF-16 offsets, from 0 through the UTF-16 length,
+/// matching JavaScript string indices.
+pub fn grapheme_boundaries_utf16(text: &str) -> impl Iterator<Item = u32> + '_ {
+    let mut offset = 0u32;
+    let starts = text.graphemes(true).map(move |g| {
+        let start = offset;
+        offset += g.encode_utf16().count() as u32;
+        start
+    });
+    starts.chain(iter::once(text.encode_utf16().count() as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "e" + combining acute, a family emoji (ZWJ sequence), then "!".
+    const CLUSTERS: &str = "e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}!";
+
+    #[test]
+    fn combining_marks_and_zwj_sequences_stay_whole() {
+        assert_eq!(grapheme_boundaries(CLUSTERS).collect::<Vec<_>>(), [0, 3, 21, 22]);
+        assert_eq!(grapheme_boundaries_utf16(CLUSTERS).collect::<Vec<_>>(), [0, 2, 10, 11]);
+    }
+
+    #[test]
+    fn flags_and_hangul_jamo_are_one_cluster_each() {
+        // Two regional-indicator flags, then a conjoining-jamo syllable.
+        let text = "\u{1F1EF}\u{1F1F5}\u{1F1EB}\u{1F1F7}\u{1100}\u{1161}\u{11A8}";
+        assert_eq!(grapheme_boundaries(text).collect::<Vec<_>>(), [0, 8, 16, 25]);
+        assert_eq!(grapheme_boundaries_utf16(text).collect::<Vec<_>>(), [0, 4, 8, 11]);
+    }
+
+    #[test]
+    fn empty_text_has_one_boundary() {
+        assert_eq!(grapheme_boundaries("").collect::<Vec<_>>(), [0]);
+        assert_eq!(grapheme_boundaries_utf16("").collect::<Vec<_>>(), [0]);
+    }
+
+    #[test]
+    fn crlf_is_one_cluster() {
+        assert_eq!(grapheme_boundaries("a\r\nb").collect::<Vec<_>>(), [0, 1, 3, 4]);
+    }
+}
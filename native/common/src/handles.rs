@@ -0,0 +1,247 @@
+//! Generation-checked handle table for editor views.
+//!
+//! The FFI identifies a view by an opaque `ViewHandle` rather than a pointer.
+//! The low 16 bits index a slot (offset by one, so 0 is never a valid
+//! handle) and the high 16 bits carry the slot's generation, which is bumped
+//! when the view is removed. A handle therefore stops resolving the moment
+//! its view is destroyed, even after the slot is reused by a newer view.
+//! Freed slots are reused oldest-first, so a stale handle would only alias a
+//! new view after its slot had been recycled 65536 times.
+//!
+//! Handles fit in a `u32` so every host, including JavaScript, can hold one
+//! as a plain number.
+
+use std::collections::VecDeque;
+
+/// Opaque identity of a view across the FFI. 0 is never issued.
+pub type ViewHandle = u32;
+
+const INDEX_BITS: u32 = 16;
+const INDEX_MASK: u32 = (1 << INDEX_BITS) - 1;
+
+struct Slot<T> {
+    /// The live value, or null when the slot is free.
+    value: *mut T,
+    generation: u16,
+}
+
+/// Slot table mapping handles to boxed values. Insert, lookup and removal
+/// are all O(1).
+pub struct HandleTable<T> {
+    slots: Vec<Slot<T>>,
+    // Free slot indices, reused oldest-first.
+    free: VecDeque<usize>,
+}
+
+impl<T> HandleTable<T> {
+    pub const fn new() -> Self {
+        Self { slots: Vec::new(), free: VecDeque::new() }
+    }
+
+    /// Take ownership of `value` and return its handle, or None if all
+    /// 65535 slots are in use.
+    pub fn insert(&mut self, value: Box<T>) -> Option<ViewHandle> {
+        let index = match self.free.pop_front() {
+            Some(index) => index,
+            None if self.slots.len() < INDEX_MASK as usize => {
+                self.slots.push(Slot { value: std::ptr::null_mut(), generation: 0 });
+                self.slots.len() - 1
+            }
+            None => return None,
+        };
+        let slot = &mut self.slots[index];
+        slot.value = Box::into_raw(value);
+        Some(encode(index, slot.generation))
+    }
+
+    /// The value behind `handle`, or None if the handle is 0, stale or was
+    /// never issued by this table. The pointer stays valid until the handle
+    /// is removed.
+    pub fn get(&self, handle: ViewHandle) -> Option<*mut T> {
+        let index = self.index_of(handle)?;
+        Some(self.slots[index].value)
+    }
+
+    /// The handle of the live value at address `value`, or None if no live
+    /// value is there. O(n); only for callers that still hold addresses.
+    pub fn find(&self, value: *const T) -> Option<ViewHandle> {
+        if value.is_null() {
+            return None;
+        }
+        let index = self.slots.iter().position(|slot| std::ptr::eq(slot.value, value))?;
+        Some(encode(index, self.slots[index].generation))
+    }
+
+    /// Invalidate `handle` and hand back ownership of its value. Returns
+    /// None if the handle is not live.
+    pub fn remove(&mut self, handle: ViewHandle) -> Option<Box<T>> {
+        let index = self.index_of(handle)?;
+        let slot = &mut self.slots[index];
+        let value = std::mem::replace(&mut slot.value, std::ptr::null_mut());
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push_back(index);
+        Some(unsafe { Box::from_raw(value) })
+    }
+
+    fn index_of(&self, handle: ViewHandle) -> Option<usize> {
+        let index = ((handle & INDEX_MASK) as usize).checked_sub(1)?;
+        let generation = (handle >> INDEX_BITS) as u16;
+        let slot = self.slots.get(index)?;
+        (!slot.value.is_null() && slot.generation == generation).then_some(index)
+    }
+}
+
+impl<T> Default for HandleTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn encode(index: usize, generation: u16) -> ViewHandle {
+    ((generation as u32) << INDEX_BITS) | (index as u32 + 1)
+}
+
+/// Debug-build record of which thread owns each live handle, shared by all
+/// threads. Tables are per-thread, so a call from the wrong thread simply
+/// misses; this lets the miss be reported instead of silently ignored.
+///
+/// Each thread numbers its handles independently, so one value can be live
+/// on two threads at once. A miss is only reported when another thread
+/// holds the value, which a stale handle from this thread can also hit.
+#[cfg(debug_assertions)]
+pub mod owners {
+    use std::sync::{Mutex, MutexGuard};
+    use std::thread::{self, ThreadId};
+
+    use super::ViewHandle;
+
+    static OWNERS: Mutex<Vec<(ViewHandle, ThreadId)>> = Mutex::new(Vec::new());
+
+    fn owners() -> MutexGuard<'static, Vec<(ViewHandle, ThreadId)>> {
+        // A panic while holding the lock leaves the list itself consistent.
+        OWNERS.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record that the calling thread created the view behind `handle`.
+    pub fn claim(handle: ViewHandle) {
+        owners().push((handle, thread::current().id()));
+    }
+
+    /// Forget the calling thread's claim on `handle` once its view is gone.
+    pub fn release(handle: ViewHandle) {
+        let me = thread::current().id();
+        owners().retain(|&owner| owner != (handle, me));
+    }
+
+    /// Panic if `handle`, which did not resolve on the calling thread,
+    /// belongs to a view on another thread.
+    pub fn assert_not_foreign(handle: ViewHandle) {
+        let me = thread::current().id();
+        let foreign = owners().iter().any(|&(h, thread)| h == handle && thread != me);
+        assert!(
+            !foreign,
+            "hone_editor: view mutated off the thread that created it; \
+             all calls must come from the UI thread"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_is_never_issued_or_resolved() {
+        let mut table = HandleTable::new();
+        let handle = table.insert(Box::new(1)).unwrap();
+        assert_ne!(handle, 0);
+        assert!(table.get(0).is_none());
+    }
+
+    #[test]
+    fn resolves_live_handles() {
+        let mut table = HandleTable::new();
+        let a = table.insert(Box::new("a")).unwrap();
+        let b = table.insert(Box::new("b")).unwrap();
+        assert_eq!(unsafe { *table.get(a).unwrap() }, "a");
+        assert_eq!(unsafe { *table.get(b).unwrap() }, "b");
+    }
+
+    #[test]
+    fn removed_handle_stops_resolving() {
+        let mut table = HandleTable::new();
+        let handle = table.insert(Box::new(7)).unwrap();
+        assert_eq!(table.remove(handle).map(|v| *v), Some(7));
+        assert!(table.get(handle).is_none());
+        assert!(table.remove(handle).is_none());
+    }
+
+    #[test]
+    fn stale_handle_does_not_resolve_to_slot_reuse() {
+        let mut table = HandleTable::new();
+        let old = table.insert(Box::new(1)).unwrap();
+        table.remove(old);
+        let new = table.insert(Box::new(2)).unwrap();
+        // Same slot, newer generation.
+        assert_eq!(old & INDEX_MASK, new & INDEX_MASK);
+        assert_ne!(old, new);
+        assert!(table.get(old).is_none());
+        assert!(table.remove(old).is_none());
+        assert_eq!(unsafe { *table.get(new).unwrap() }, 2);
+    }
+
+    #[test]
+    fn freed_slots_are_reused_oldest_first() {
+        let mut table = HandleTable::new();
+        let a = table.insert(Box::new(0)).unwrap();
+        let b = table.insert(Box::new(0)).unwrap();
+        table.remove(a);
+        table.remove(b);
+        let c = table.insert(Box::new(0)).unwrap();
+        let d = table.insert(Box::new(0)).unwrap();
+        assert_eq!(c & INDEX_MASK, a & INDEX_MASK);
+        assert_eq!(d & INDEX_MASK, b & INDEX_MASK);
+    }
+
+    #[test]
+    fn finds_live_values_by_address() {
+        let mut table = HandleTable::new();
+        let handle = table.insert(Box::new(3)).unwrap();
+        let value = table.get(handle).unwrap();
+        assert_eq!(table.find(value), Some(handle));
+        assert_eq!(table.find(std::ptr::null()), None);
+        table.remove(handle);
+        assert_eq!(table.find(value), None);
+    }
+
+    #[test]
+    fn unknown_index_does_not_resolve() {
+        let table: HandleTable<i32> = HandleTable::new();
+        assert!(table.get(encode(5, 0)).is_none());
+    }
+
+    // Handle values here are unlike any other test's, since the owner list
+    // is shared by the whole test process.
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "view mutated off the thread that created it")]
+    fn another_threads_handle_panics() {
+        std::thread::spawn(|| owners::claim(0xbeef_0001)).join().unwrap();
+        owners::assert_not_foreign(0xbeef_0001);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn own_and_released_handles_do_not_panic() {
+        owners::claim(0xbeef_0002);
+        owners::assert_not_foreign(0xbeef_0002);
+        std::thread::spawn(|| {
+            owners::claim(0xbeef_0003);
+            owners::release(0xbeef_0003);
+        })
+        .join()
+        .unwrap();
+        owners::assert_not_foreign(0xbeef_0003);
+    }
+}
@@ -0,0 +1,181 @@
+//! Mapping a horizontal position on a line of text to a caret column.
+//!
+//! Widths come from a caller-supplied `measure` closure that returns the
+//! advance width of a prefix of the line, so each platform keeps its own
+//! shaping (kerning, ligatures, tab stops) and this module only decides
+//! where the caret lands.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Byte column of the grapheme boundary nearest `x`, measured from the
+/// line's left edge. Points left of the text give 0 and points past the
+/// middle of the last grapheme give `text.len()`; a cluster is never split.
+///
+/// Prefix widths only grow, so the boundary is found by binary search and
+/// `measure` runs O(log n) times rather than once per grapheme.
+pub fn column_at_x(text: &str, x: f64, mut measure: impl FnMut(&str) -> f64) -> usize {
+    if text.is_empty() || x <= 0.0 {
+        return 0;
+    }
+    let mut bounds: Vec<usize> = text.grapheme_indices(true).map(|(i, _)| i).collect();
+    bounds.push(text.len());
+    let mut width = |k: usize| if k == 0 { 0.0 } else { measure(&text[..bounds[k]]) };
+
+    // First boundary whose prefix reaches x.
+    let (mut lo, mut hi) = (1, bounds.len() - 1);
+    let full = width(hi);
+    if full < x {
+        return text.len();
+    }
+    let mut right = full;
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let w = width(mid);
+        if w < x {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+            right = w;
+        }
+    }
+    let left = width(lo - 1);
+    if x < (left + right) / 2.0 {
+        bounds[lo - 1]
+    } else {
+        bounds[lo]
+    }
+}
+
+/// Byte column under view x-coordinate `x` on a line whose text starts at
+/// `text_x` (the gutter width less any horizontal scroll). Points over the
+/// gutter, `x < gutter_w`, map to column 0.
+pub fn column_at_point(
+    text: &str,
+    x: f64,
+    gutter_w: f64,
+    text_x: f64,
+    measure: impl FnMut(&str) -> f64,
+) -> usize {
+    if x < gutter_w {
+        return 0;
+    }
+    column_at_x(text, x - text_x, measure)
+}
+
+/// Byte offset of UTF-16 column `col` in `text`, for callers that index
+/// text as JavaScript strings do. A column inside a surrogate pair rounds
+/// down to the pair; one at or past the end gives `text.len()`.
+pub fn byte_at_utf16(text: &str, col: u32) -> usize {
+    let mut units = 0u32;
+    for (i, ch) in text.char_indices() {
+        units += ch.len_utf16() as u32;
+        if units > col {
+            return i;
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAR_W: f64 = 10.0;
+
+    fn mono(prefix: &str) -> f64 {
+        prefix.chars().count() as f64 * CHAR_W
+    }
+
+    /// Monospace width with tabs advancing to the next multiple of 4 columns.
+    fn tabbed(prefix: &str) -> f64 {
+        let mut col = 0;
+        for c in prefix.chars() {
+            col = if c == '\t' { (col / 4 + 1) * 4 } else { col + 1 };
+        }
+        col as f64 * CHAR_W
+    }
+
+    #[test]
+    fn snaps_to_the_nearest_boundary() {
+        assert_eq!(column_at_x("hello", 14.0, mono), 1);
+        assert_eq!(column_at_x("hello", 15.0, mono), 2);
+        assert_eq!(column_at_x("hello", 26.0, mono), 3);
+    }
+
+    #[test]
+    fn points_left_of_the_text_give_column_zero() {
+        assert_eq!(column_at_x("hello", 0.0, mono), 0);
+        assert_eq!(column_at_x("hello", -30.0, mono), 0);
+        assert_eq!(column_at_x("hello", 4.0, mono), 0);
+    }
+
+    #[test]
+    fn points_past_the_end_of_the_line_give_its_length() {
+        assert_eq!(column_at_x("hello", 46.0, mono), 5);
+        assert_eq!(column_at_x("hello", 500.0, mono), 5);
+    }
+
+    #[test]
+    fn empty_lines_always_give_column_zero() {
+        assert_eq!(column_at_x("", 0.0, mono), 0);
+        assert_eq!(column_at_x("", 120.0, mono), 0);
+        assert_eq!(column_at_point("", 120.0, 40.0, 40.0, mono), 0);
+    }
+
+    #[test]
+    fn gutter_clicks_give_column_zero() {
+        // Text starts at x = 40 behind a 40pt gutter.
+        assert_eq!(column_at_point("hello", 10.0, 40.0, 40.0, mono), 0);
+        assert_eq!(column_at_point("hello", 39.9, 40.0, 40.0, mono), 0);
+        assert_eq!(column_at_point("hello", 61.0, 40.0, 40.0, mono), 2);
+        // Scrolled 30pt right: the gutter still covers the text behind it.
+        assert_eq!(column_at_point("hello", 20.0, 40.0, 10.0, mono), 0);
+        assert_eq!(column_at_point("hello", 41.0, 40.0, 10.0, mono), 3);
+    }
+
+    #[test]
+    fn grapheme_clusters_are_never_split() {
+        // "e" + combining acute is one cluster of two chars (three bytes).
+        let text = "ae\u{301}b";
+        let width = |p: &str| p.graphemes(true).count() as f64 * CHAR_W;
+        assert_eq!(column_at_x(text, 14.0, width), 1);
+        assert_eq!(column_at_x(text, 16.0, width), 4);
+        assert_eq!(column_at_x(text, 26.0, width), 5);
+    }
+
+    #[test]
+    fn tab_indented_lines_use_the_tab_stop_width() {
+        // "\tx": the tab spans columns 0..4, so x sits at 40..50.
+        assert_eq!(column_at_x("\tx", 19.0, tabbed), 0);
+        assert_eq!(column_at_x("\tx", 21.0, tabbed), 1);
+        assert_eq!(column_at_x("\tx", 44.0, tabbed), 1);
+        assert_eq!(column_at_x("\tx", 46.0, tabbed), 2);
+        // "ab\tc": the tab only spans columns 2..4.
+        assert_eq!(column_at_x("ab\tc", 31.0, tabbed), 3);
+        assert_eq!(column_at_x("ab\tc", 41.0, tabbed), 3);
+        assert_eq!(column_at_x("ab\tc", 46.0, tabbed), 4);
+    }
+
+    #[test]
+    fn utf16_columns_round_down_inside_surrogate_pairs() {
+        assert_eq!(byte_at_utf16("hello", 3), 3);
+        assert_eq!(byte_at_utf16("hello", 99), 5);
+        // U+1F600 is four bytes and two UTF-16 units.
+        assert_eq!(byte_at_utf16("\u{1F600}x", 1), 0);
+        assert_eq!(byte_at_utf16("\u{1F600}x", 2), 4);
+        assert_eq!(byte_at_utf16("\u{1F600}x", 3), 5);
+        assert_eq!(byte_at_utf16("", 0), 0);
+    }
+
+    #[test]
+    fn measures_a_logarithmic_number_of_prefixes() {
+        let text = "x".repeat(4096);
+        let mut calls = 0;
+        let col = column_at_x(&text, 20_005.0, |p| {
+            calls += 1;
+            mono(p)
+        });
+        assert_eq!(col, 2001);
+        assert!(calls <= 15, "{calls} measurements");
+    }
+}
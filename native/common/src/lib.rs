@@ -0,0 +1,16 @@
+//! Platform-independent pieces shared by the native Hone Editor crates.
+//!
+//! Everything here is pure Rust with no platform bindings, so it builds and
+//! is tested on any host regardless of which rendering backend it targets.
+
+pub mod columns;
+pub mod diagnostics;
+pub mod font_cache;
+pub mod graphemes;
+pub mod handles;
+pub mod hit_test;
+pub mod selection;
+pub mod selectors;
+pub mod tokens;
+pub mod whitespace;
+pub mod words;
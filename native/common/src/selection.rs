@@ -0,0 +1,246 @@
+//! Selection rectangles from the TypeScript layer and the geometry the
+//! desktop renderers share for filling them.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelectionRegion {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+    /// Optional "#rrggbb" fill for this region (e.g. the active search match).
+    /// Drawn with the theme selection alpha; falls back to `selection_color`.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// True when the selection continues past the end of this line, i.e.
+    /// the line's newline is selected.
+    #[serde(default)]
+    pub eol: bool,
+}
+
+/// Append `region`, or extend the previous rect when `region` stacks onto
+/// it. Rows covering the same span fill as one tall rect: fewer fills, and
+/// no seams between rows at fractional offsets.
+pub fn push_stacked(regions: &mut Vec<SelectionRegion>, region: SelectionRegion) {
+    match regions.last_mut() {
+        Some(prev) if stacks_onto(prev, &region) => {
+            prev.h = region.y + region.h - prev.y;
+        }
+        _ => regions.push(region),
+    }
+}
+
+/// True when `next` starts where `prev` ends and covers the same span in
+/// the same color, so the two can be filled as one rect.
+fn stacks_onto(prev: &SelectionRegion, next: &SelectionRegion) -> bool {
+    prev.eol == next.eol
+        && prev.color == next.color
+        && (next.y - (prev.y + prev.h)).abs() < 0.5
+        && (next.x - prev.x).abs() < 0.5
+        && (next.w - prev.w).abs() < 0.5
+}
+
+/// Group vertically contiguous, horizontally overlapping regions of the same
+/// color and trace each group's outline as a clockwise polygon. Regions
+/// whose newline is selected are widened by `eol_width`.
+///
+/// Returns `(outline, index_of_first_region)` pairs; the index lets the caller
+/// look up the group's color.
+pub fn selection_outlines(
+    regions: &[SelectionRegion],
+    eol_width: f64,
+) -> Vec<(Vec<(f64, f64)>, usize)> {
+    let width = |r: &SelectionRegion| if r.eol { r.w + eol_width } else { r.w };
+    let mut order: Vec<usize> =
+        (0..regions.len()).filter(|&i| width(&regions[i]) > 0.0).collect();
+    order.sort_by(|&a, &b| regions[a].y.total_cmp(&regions[b].y));
+
+    let mut outlines = Vec::new();
+    let mut i = 0;
+    while i < order.len() {
+        // Extend the group while the next row touches the previous one.
+        let mut group = vec![&regions[order[i]]];
+        let first = order[i];
+        i += 1;
+        while i < order.len() {
+            let prev = group[group.len() - 1];
+            let next = &regions[order[i]];
+            let touches = (next.y - (prev.y + prev.h)).abs() < 0.5;
+            let overlaps = next.x < prev.x + width(prev) && prev.x < next.x + width(next);
+            if !touches || !overlaps || next.color != prev.color {
+                break;
+            }
+            group.push(next);
+            i += 1;
+        }
+
+        // Right edge top-to-bottom, then left edge bottom-to-top. Row tops
+        // snap to the previous row's bottom so the steps line up exactly.
+        let mut points = Vec::with_capacity(group.len() * 4);
+        let mut top = group[0].y;
+        for r in &group {
+            let bottom = top + r.h;
+            points.push((r.x + width(r), top));
+            points.push((r.x + width(r), bottom));
+            top = bottom;
+        }
+        for r in group.iter().rev() {
+            let bottom = top;
+            top = bottom - r.h;
+            points.push((r.x, bottom));
+            points.push((r.x, top));
+        }
+        outlines.push((simplify_outline(points), first));
+    }
+    outlines
+}
+
+/// Drop duplicate and collinear vertices so every remaining vertex is a real corner.
+fn simplify_outline(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    loop {
+        let n = points.len();
+        if n < 3 {
+            return points;
+        }
+        let redundant = (0..n).find(|&i| {
+            let (px, py) = points[(i + n - 1) % n];
+            let (cx, cy) = points[i];
+            let (nx, ny) = points[(i + 1) % n];
+            let cross = (cx - px) * (ny - cy) - (cy - py) * (nx - cx);
+            ((cx - px).abs() < 0.01 && (cy - py).abs() < 0.01) || cross.abs() < 0.01
+        });
+        match redundant {
+            Some(i) => {
+                points.remove(i);
+            }
+            None => return points,
+        }
+    }
+}
+
+/// A rounded corner: straight edge up to `start`, then a quadratic curve
+/// through the polygon vertex `ctrl` to `end`.
+pub struct RoundedCorner {
+    pub start: (f64, f64),
+    pub ctrl: (f64, f64),
+    pub end: (f64, f64),
+}
+
+/// Compute the rounded corners of a polygon, clamping the radius so adjacent
+/// corners never overlap on short edges.
+pub fn rounded_corners(points: &[(f64, f64)], radius: f64) -> Vec<RoundedCorner> {
+    let n = points.len();
+    let toward = |from: (f64, f64), to: (f64, f64), dist: f64| {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let len = (dx * dx + dy * dy).sqrt().max(f64::EPSILON);
+        (from.0 + dx / len * dist, from.1 + dy / len * dist)
+    };
+    let edge_len =
+        |a: (f64, f64), b: (f64, f64)| ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+
+    (0..n)
+        .map(|i| {
+            let prev = points[(i + n - 1) % n];
+            let cur = points[i];
+            let next = points[(i + 1) % n];
+            let r = radius.min(edge_len(prev, cur) / 2.0).min(edge_len(cur, next) / 2.0);
+            RoundedCorner {
+                start: toward(cur, prev, r),
+                ctrl: cur,
+                end: toward(cur, next, r),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(y: f64, x: f64, w: f64, eol: bool) -> SelectionRegion {
+        SelectionRegion { x, y, w, h: 20.0, color: None, eol }
+    }
+
+    #[test]
+    fn hundred_line_selection_stacks_into_three_rects() {
+        // Partial first row, 98 full rows, partial last row.
+        let mut regions = Vec::new();
+        push_stacked(&mut regions, row(0.0, 40.0, 160.0, true));
+        for i in 1..99 {
+            push_stacked(&mut regions, row(i as f64 * 20.0, 0.0, 200.0, true));
+        }
+        push_stacked(&mut regions, row(99.0 * 20.0, 0.0, 60.0, false));
+        assert_eq!(regions.len(), 3);
+        assert_eq!((regions[1].y, regions[1].h), (20.0, 98.0 * 20.0));
+        assert_eq!(regions[2].y, 99.0 * 20.0);
+    }
+
+    #[test]
+    fn rows_with_a_gap_or_another_color_do_not_stack() {
+        let mut regions = Vec::new();
+        push_stacked(&mut regions, row(0.0, 0.0, 200.0, true));
+        push_stacked(&mut regions, row(40.0, 0.0, 200.0, true));
+        let mut colored = row(60.0, 0.0, 200.0, true);
+        colored.color = Some("#ff0000".to_string());
+        push_stacked(&mut regions, colored);
+        assert_eq!(regions.len(), 3);
+    }
+
+    #[test]
+    fn two_staggered_rows_merge_into_one_outline() {
+        let rows = [row(0.0, 40.0, 160.0, false), row(20.0, 0.0, 120.0, false)];
+        let outlines = selection_outlines(&rows, 0.0);
+        assert_eq!(outlines.len(), 1);
+        let (outline, first) = &outlines[0];
+        assert_eq!(*first, 0);
+        assert_eq!(
+            outline,
+            &[(200.0, 0.0), (200.0, 20.0), (120.0, 20.0), (120.0, 40.0),
+              (0.0, 40.0), (0.0, 20.0), (40.0, 20.0), (40.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn three_staggered_rows_drop_collinear_vertices() {
+        // With their selected newlines the first two rows end at the same x,
+        // so their right edges make one straight side.
+        let rows = [
+            row(0.0, 40.0, 160.0, true),
+            row(20.0, 0.0, 200.0, true),
+            row(40.0, 0.0, 100.0, false),
+        ];
+        let outlines = selection_outlines(&rows, 8.0);
+        assert_eq!(outlines.len(), 1);
+        assert_eq!(
+            outlines[0].0,
+            [(208.0, 0.0), (208.0, 40.0), (100.0, 40.0), (100.0, 60.0),
+             (0.0, 60.0), (0.0, 20.0), (40.0, 20.0), (40.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn rows_that_do_not_overlap_or_differ_in_color_outline_separately() {
+        let mut colored = row(40.0, 0.0, 50.0, false);
+        colored.color = Some("#ff0000".to_string());
+        let rows = [row(0.0, 0.0, 50.0, false), row(20.0, 100.0, 50.0, false), colored];
+        let firsts: Vec<usize> =
+            selection_outlines(&rows, 0.0).into_iter().map(|(_, first)| first).collect();
+        assert_eq!(firsts, [0, 1, 2]);
+    }
+
+    #[test]
+    fn corner_radius_is_clamped_on_short_edges() {
+        // A 100x4 strip: the 4px sides only leave room for a 2px radius.
+        let strip = [(0.0, 0.0), (100.0, 0.0), (100.0, 4.0), (0.0, 4.0)];
+        let corners = rounded_corners(&strip, 10.0);
+        assert_eq!(corners.len(), 4);
+        let first = &corners[0];
+        assert_eq!((first.start, first.ctrl, first.end), ((0.0, 2.0), (0.0, 0.0), (2.0, 0.0)));
+        assert_eq!((corners[2].start, corners[2].end), ((100.0, 2.0), (98.0, 4.0)));
+        // Long edges keep the full radius.
+        let square = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)];
+        let corners = rounded_corners(&square, 10.0);
+        assert_eq!((corners[1].start, corners[1].end), ((90.0, 0.0), (100.0, 10.0)));
+    }
+}
@@ -0,0 +1,49 @@
+//! Cocoa-style action selectors (`moveLeft:`, `paste:`, ...) that the
+//! desktop key handlers pass to the TypeScript layer.
+
+/// Selectors that change the text, swallowed in read-only mode. Movement,
+/// selection, `copy:`, scrolling and zoom still go through.
+pub fn is_edit_selector(selector: &str) -> bool {
+    selector.starts_with("insert")
+        || selector.starts_with("delete")
+        || matches!(
+            selector,
+            "cut:" | "paste:" | "undo:" | "redo:" | "indent:" | "outdent:" | "transpose:" | "yank:"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_text_changing_selectors_are_edits() {
+        let table = [
+            ("insertText:", true),
+            ("insertNewline:", true),
+            ("deleteBackward:", true),
+            ("deleteWordForward:", true),
+            ("cut:", true),
+            ("paste:", true),
+            ("undo:", true),
+            ("redo:", true),
+            ("indent:", true),
+            ("outdent:", true),
+            ("transpose:", true),
+            ("yank:", true),
+            ("copy:", false),
+            ("selectAll:", false),
+            ("moveLeft:", false),
+            ("moveWordRightAndModifySelection:", false),
+            ("scrollPageDown:", false),
+            ("scrollPageUp:", false),
+            ("pageDown:", false),
+            ("cancelOperation:", false),
+            ("zoomIn:", false),
+            ("zoomOut:", false),
+        ];
+        for (selector, edits) in table {
+            assert_eq!(is_edit_selector(selector), edits, "{selector}");
+        }
+    }
+}
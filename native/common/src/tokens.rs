@@ -0,0 +1,140 @@
+//! Syntax-colored token runs from the TypeScript layer, and the ways the
+//! desktop renderers reshape them before drawing a line.
+
+use serde::Deserialize;
+
+/// Token data from the TypeScript layer.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RenderToken {
+    /// Start column: a byte offset, or a UTF-16 offset on macOS.
+    pub s: usize,
+    /// End column, in the same units as `s`.
+    pub e: usize,
+    /// Hex color string (e.g., "#569cd6").
+    pub c: String,
+    /// Font style: "normal", "italic", or "bold".
+    pub st: String,
+    /// Optional font size in points for this run (e.g. a smaller inline hint).
+    /// Runs of any size share the line's baseline. Defaults to the line font.
+    #[serde(default)]
+    pub sz: Option<f64>,
+}
+
+/// `tokens` clipped to the columns `start..end` and shifted to start there.
+pub fn slice_tokens(tokens: &[RenderToken], start: usize, end: usize) -> Vec<RenderToken> {
+    tokens
+        .iter()
+        .filter_map(|t| {
+            let (s, e) = (t.s.max(start), t.e.min(end));
+            (s < e).then(|| RenderToken {
+                s: s - start,
+                e: e - start,
+                c: t.c.clone(),
+                st: t.st.clone(),
+                sz: t.sz,
+            })
+        })
+        .collect()
+}
+
+/// Split `tokens` at the edges of `spans` (`(start, end, color)`, later spans
+/// winning) and give the covered pieces the span's color. Pieces keep their
+/// token's style and size, so glyph advances are unchanged; span text outside
+/// any token gets a plain-styled token. Adjacent identical pieces are joined.
+pub fn overlay_colors(tokens: &[RenderToken], spans: &[(usize, usize, &str)]) -> Vec<RenderToken> {
+    let mut cuts: Vec<usize> = tokens
+        .iter()
+        .flat_map(|t| [t.s, t.e])
+        .chain(spans.iter().flat_map(|&(s, e, _)| [s, e]))
+        .collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    let mut out: Vec<RenderToken> = Vec::new();
+    for w in cuts.windows(2) {
+        let (s, e) = (w[0], w[1]);
+        let base = tokens.iter().rev().find(|t| t.s <= s && e <= t.e);
+        let span = spans.iter().rev().find(|&&(a, b, _)| a <= s && e <= b);
+        let c = match (span, base) {
+            (Some(&(_, _, c)), _) => c,
+            (None, Some(t)) => t.c.as_str(),
+            (None, None) => continue,
+        };
+        let st = base.map_or("normal", |t| t.st.as_str());
+        let sz = base.and_then(|t| t.sz);
+        match out.last_mut() {
+            Some(prev) if prev.e == s && prev.c == c && prev.st == st && prev.sz == sz => {
+                prev.e = e;
+            }
+            _ => out.push(RenderToken {
+                s,
+                e,
+                c: c.to_string(),
+                st: st.to_string(),
+                sz,
+            }),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(s: usize, e: usize, c: &str) -> RenderToken {
+        RenderToken { s, e, c: c.to_string(), st: "normal".to_string(), sz: None }
+    }
+
+    #[test]
+    fn tokens_straddling_the_bounds_are_clipped() {
+        let tokens = [token(0, 4, "#f00"), token(4, 10, "#00f"), token(10, 14, "#0f0")];
+        assert_eq!(
+            slice_tokens(&tokens, 2, 12),
+            [token(0, 2, "#f00"), token(2, 8, "#00f"), token(8, 10, "#0f0")]
+        );
+    }
+
+    #[test]
+    fn tokens_outside_or_touching_the_bounds_are_dropped() {
+        let tokens = [token(0, 4, "#f00"), token(4, 10, "#00f"), token(10, 14, "#0f0")];
+        assert_eq!(slice_tokens(&tokens, 4, 10), [token(0, 6, "#00f")]);
+        assert!(slice_tokens(&tokens, 14, 20).is_empty());
+    }
+
+    #[test]
+    fn a_token_spanning_the_whole_slice_keeps_its_style() {
+        let mut hint = token(0, 20, "#888");
+        hint.st = "italic".to_string();
+        hint.sz = Some(10.0);
+        let sliced = slice_tokens(&[hint.clone()], 5, 9);
+        assert_eq!(sliced, [RenderToken { s: 0, e: 4, ..hint }]);
+    }
+
+    #[test]
+    fn a_span_recolors_the_pieces_it_covers() {
+        let mut keyword = token(0, 4, "#00f");
+        keyword.st = "bold".to_string();
+        let tokens = [keyword, token(4, 10, "#fff")];
+        let mut covered = token(2, 4, "#ff0");
+        covered.st = "bold".to_string();
+        assert_eq!(
+            overlay_colors(&tokens, &[(2, 6, "#ff0")]),
+            [
+                RenderToken { e: 2, ..tokens[0].clone() },
+                covered,
+                token(4, 6, "#ff0"),
+                token(6, 10, "#fff"),
+            ]
+        );
+    }
+
+    #[test]
+    fn later_spans_win_and_uncovered_text_gets_a_plain_token() {
+        let tokens = [token(0, 4, "#fff")];
+        let spans = [(0, 8, "#f00"), (2, 3, "#0f0")];
+        assert_eq!(
+            overlay_colors(&tokens, &spans),
+            [token(0, 2, "#f00"), token(2, 3, "#0f0"), token(3, 8, "#f00")]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Leading and trailing whitespace on a line, for indent guides and the
+//! trailing-whitespace highlight.
+
+/// Byte offsets where each `tab_width`-column indent level of `text`'s
+/// leading whitespace ends. A trailing partial level ends at the first
+/// non-whitespace byte.
+pub fn indent_level_ends(text: &str, tab_width: usize) -> Vec<usize> {
+    let indent = text.len() - text.trim_start_matches([' ', '\t']).len();
+    let mut ends = Vec::new();
+    let mut col = 0;
+    for (i, b) in text.bytes().take(indent).enumerate() {
+        col += if b == b'\t' { tab_width - col % tab_width } else { 1 };
+        if col % tab_width == 0 {
+            ends.push(i + 1);
+        }
+    }
+    if indent > 0 && ends.last() != Some(&indent) {
+        ends.push(indent);
+    }
+    ends
+}
+
+/// Byte offset where a line's trailing run of spaces/tabs begins, if it has one.
+pub fn trailing_whitespace_start(text: &str) -> Option<usize> {
+    let trimmed = text.trim_end_matches([' ', '\t']);
+    (trimmed.len() < text.len()).then_some(trimmed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_and_tabs_end_levels_at_tab_stops() {
+        assert_eq!(indent_level_ends("        x", 4), [4, 8]);
+        assert_eq!(indent_level_ends("\t\tx", 4), [1, 2]);
+        // Two spaces then a tab reach the first stop together.
+        assert_eq!(indent_level_ends("  \tx", 4), [3]);
+        assert_eq!(indent_level_ends("\t    x", 4), [1, 5]);
+    }
+
+    #[test]
+    fn a_partial_level_ends_at_the_text() {
+        assert_eq!(indent_level_ends("      x", 4), [4, 6]);
+        assert_eq!(indent_level_ends("  x", 4), [2]);
+        assert_eq!(indent_level_ends("\t  x", 8), [1, 3]);
+    }
+
+    #[test]
+    fn unindented_lines_have_no_levels() {
+        assert!(indent_level_ends("x = 1", 4).is_empty());
+        assert!(indent_level_ends("", 4).is_empty());
+        assert_eq!(indent_level_ends("    ", 4), [4]);
+    }
+
+    #[test]
+    fn trailing_run_starts_after_the_last_visible_byte() {
+        assert_eq!(trailing_whitespace_start("x = 1 \t "), Some(5));
+        assert_eq!(trailing_whitespace_start("\tx\t"), Some(2));
+        // Only spaces and tabs count.
+        assert_eq!(trailing_whitespace_start("x\u{a0}"), None);
+    }
+
+    #[test]
+    fn all_whitespace_lines_trail_from_the_start() {
+        assert_eq!(trailing_whitespace_start("  \t "), Some(0));
+        assert_eq!(trailing_whitespace_start("\t"), Some(0));
+    }
+
+    #[test]
+    fn lines_without_trailing_whitespace_have_none() {
+        assert_eq!(trailing_whitespace_start("  x = 1"), None);
+        assert_eq!(trailing_whitespace_start(""), None);
+    }
+}
@@ -0,0 +1,152 @@
+//! Word and subword ranges for double-click selection and word movement.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Byte range of the word around byte column `col` in `text`, by Unicode
+/// word segmentation. A caret just after a word picks that word; otherwise
+/// the segment under `col` is used, so a run of punctuation or whitespace
+/// selects as a unit. With `subword`, identifiers also split at underscores
+/// and case changes (`fooBar` gives `foo` and `Bar`). A `col` inside a
+/// character counts from that character's start.
+pub fn word_range_at(text: &str, col: usize, subword: bool) -> (usize, usize) {
+    let mut col = col.min(text.len());
+    while !text.is_char_boundary(col) {
+        col -= 1;
+    }
+    let Some((start, end)) = segment_at(text.split_word_bound_indices(), col) else {
+        return (col, col);
+    };
+    if !subword {
+        return (start, end);
+    }
+    let word = &text[start..end];
+    segment_at(subword_segments(word).into_iter(), col - start)
+        .map_or((start, end), |(s, e)| (start + s, start + e))
+}
+
+/// The subword counterpart of a word-movement selector, or `selector` itself.
+pub fn subword_selector(selector: &str) -> &str {
+    match selector {
+        "moveWordLeft:" => "moveSubwordLeft:",
+        "moveWordRight:" => "moveSubwordRight:",
+        "moveWordLeftAndModifySelection:" => "moveSubwordLeftAndModifySelection:",
+        "moveWordRightAndModifySelection:" => "moveSubwordRightAndModifySelection:",
+        other => other,
+    }
+}
+
+/// `(start, end)` of the segment under byte offset `col`, preferring a
+/// word-like segment that ends exactly at `col`.
+fn segment_at<'a>(
+    segments: impl Iterator<Item = (usize, &'a str)>,
+    col: usize,
+) -> Option<(usize, usize)> {
+    for (i, seg) in segments {
+        let end = i + seg.len();
+        let ends_word = end == col && seg.chars().any(char::is_alphanumeric);
+        if ends_word || (i <= col && col < end) {
+            return Some((i, end));
+        }
+    }
+    None
+}
+
+/// Split an identifier into subwords: runs of underscores stand alone, and a
+/// new subword starts at a lower/digit-to-upper change or at the last capital
+/// of an acronym followed by lowercase (`HTMLParser` gives `HTML`, `Parser`).
+fn subword_segments(word: &str) -> Vec<(usize, &str)> {
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    let mut pieces = Vec::new();
+    let mut piece_start = 0;
+    for k in 1..chars.len() {
+        let (i, c) = chars[k];
+        let prev = chars[k - 1].1;
+        let next_lower = chars.get(k + 1).is_some_and(|&(_, n)| n.is_lowercase());
+        let split = (prev == '_') != (c == '_')
+            || (c.is_uppercase() && (prev.is_lowercase() || prev.is_numeric()))
+            || (c.is_uppercase() && prev.is_uppercase() && next_lower);
+        if split {
+            pieces.push((piece_start, &word[piece_start..i]));
+            piece_start = i;
+        }
+    }
+    pieces.push((piece_start, &word[piece_start..]));
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifiers_select_whole() {
+        let text = "let fooBar_baz = 1;";
+        assert_eq!(word_range_at(text, 6, false), (4, 14));
+        // A caret just after a word picks that word.
+        assert_eq!(word_range_at(text, 14, false), (4, 14));
+        assert_eq!(word_range_at(text, 0, false), (0, 3));
+    }
+
+    #[test]
+    fn subwords_split_at_underscores_and_case_changes() {
+        let text = "let fooBar_baz = 1;";
+        assert_eq!(word_range_at(text, 6, true), (4, 7));
+        assert_eq!(word_range_at(text, 8, true), (7, 10));
+        assert_eq!(word_range_at(text, 11, true), (11, 14));
+        // An acronym keeps its last capital for the following word.
+        assert_eq!(word_range_at("HTMLParser", 2, true), (0, 4));
+        assert_eq!(word_range_at("HTMLParser", 6, true), (4, 10));
+        assert_eq!(word_range_at("utf8Decode", 5, true), (4, 10));
+    }
+
+    #[test]
+    fn subwords_split_mixed_snake_and_camel_case() {
+        // What word movement steps over with subword navigation on.
+        let text = "parse_httpHeader()";
+        assert_eq!(word_range_at(text, 12, false), (0, 16));
+        assert_eq!(word_range_at(text, 12, true), (10, 16));
+        assert_eq!(word_range_at(text, 2, true), (0, 5));
+        assert_eq!(word_range_at(text, 7, true), (6, 10));
+    }
+
+    #[test]
+    fn punctuation_and_whitespace_are_their_own_segments() {
+        let text = "x += f(a);";
+        assert_eq!(word_range_at(text, 2, false), (2, 3));
+        assert_eq!(word_range_at(text, 3, false), (3, 4));
+        assert_eq!(word_range_at(text, 6, false), (5, 6));
+        assert_eq!(word_range_at(text, 9, false), (9, 10));
+        assert_eq!(word_range_at(text, 10, false), (10, 10));
+        assert_eq!(word_range_at("", 0, false), (0, 0));
+    }
+
+    #[test]
+    fn cjk_text_segments_by_ideograph() {
+        // Each ideograph is three bytes and, without a dictionary, its own word.
+        let text = "你好世界 ok";
+        assert_eq!(word_range_at(text, 0, false), (0, 3));
+        assert_eq!(word_range_at(text, 3, false), (0, 3));
+        assert_eq!(word_range_at(text, 7, false), (3, 6));
+        assert_eq!(word_range_at(text, 12, false), (9, 12));
+        assert_eq!(word_range_at(text, 13, false), (13, 15));
+    }
+
+    #[test]
+    fn word_movement_maps_to_its_subword_counterpart() {
+        assert_eq!(subword_selector("moveWordLeft:"), "moveSubwordLeft:");
+        assert_eq!(
+            subword_selector("moveWordRightAndModifySelection:"),
+            "moveSubwordRightAndModifySelection:"
+        );
+        // Everything else passes through unchanged.
+        assert_eq!(subword_selector("moveLeft:"), "moveLeft:");
+        assert_eq!(subword_selector("deleteWordBackward:"), "deleteWordBackward:");
+    }
+
+    #[test]
+    fn columns_inside_a_character_count_from_its_start() {
+        assert_eq!(word_range_at("你好", 1, false), (0, 3));
+        assert_eq!(word_range_at("你好", 4, false), (0, 3));
+        assert_eq!(word_range_at("ab", 99, false), (0, 2));
+    }
+}
@@ -11,8 +11,11 @@
 
 /**
  * Opaque handle to a native editor view.
- * In Rust, this is a *mut EditorView pointer.
- * In TypeScript, we treat it as an opaque number (pointer value).
+ * In Rust, this is a generation-checked `ViewHandle` returned by
+ * `hone_editor_create_handle`, so a handle kept past `destroy` is ignored
+ * rather than dereferenced. It is a u32 rather than a u64 so it stays an
+ * exact JavaScript number.
+ * In TypeScript, we treat it as an opaque number.
  */
 export type NativeViewHandle = number;
 
@@ -28,6 +31,8 @@ export interface RenderToken {
   c: string;
   /** Font style: "normal", "italic", or "bold". */
   st: string;
+  /** Optional font size in points for this run; shares the line's baseline. */
+  sz?: number;
 }
 
 /**
@@ -38,6 +43,10 @@ export interface SelectionRegion {
   y: number;
   w: number;
   h: number;
+  /** Optional hex color override for this region (e.g., the current search match). */
+  color?: string;
+  /** True when the selection continues past this line's end (its newline is selected). */
+  eol?: boolean;
 }
 
 /**
@@ -49,7 +58,70 @@ export interface DecorationOverlay {
   w: number;
   h: number;
   color: string;
-  type: 'background' | 'underline' | 'underline-wavy';
+  type: 'background' | 'underline' | 'underline-wavy' | 'line-accent';
+}
+
+/**
+ * Caret position nearest a point on a line (see NativeEditorFFI.hitTest).
+ */
+export interface HitTestResult {
+  line: number;
+  /** UTF-16 column, always on a grapheme boundary. */
+  col: number;
+  /** Left edge of the caret at col, relative to the text's left edge. */
+  x: number;
+}
+
+/**
+ * Line and column under a view point (see NativeEditorFFI.pointToPosition).
+ */
+export interface PointPosition {
+  line: number;
+  /** Byte offset into the line's UTF-8 text. */
+  col: number;
+}
+
+/**
+ * Caret and selection summary for a status bar (see NativeEditorFFI.statusInfo).
+ */
+export interface StatusInfo {
+  /** 1-based caret line; 0 when no caret is set this frame. */
+  line: number;
+  /** 1-based display column: tabs expanded, wide characters two cells. */
+  column: number;
+  /** Selected characters, each line break counting one. */
+  selectionChars: number;
+  /** Lines the selection touches; 0 with no selection. */
+  selectionLines: number;
+}
+
+/**
+ * A rendered syntax token (see NativeEditorFFI.tokenAt).
+ */
+export interface TokenSpan {
+  /** Byte offsets into the line's UTF-8 text. */
+  start: number;
+  end: number;
+  /** 0xRRGGBB. */
+  color: number;
+}
+
+/**
+ * Word span around a column (see NativeEditorFFI.wordRangeAt).
+ */
+export interface WordRange {
+  start: number;
+  end: number;
+}
+
+/**
+ * The primary caret's rect in view coordinates (see NativeEditorFFI.caretRect).
+ */
+export interface CaretRect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
 }
 
 /**
@@ -59,6 +131,86 @@ export const CursorStyle = {
   Line: 0,
   Block: 1,
   Underline: 2,
+  /** Use the shape implied by the caret mode (see setCaretMode). */
+  ModeDefault: -1,
+} as const;
+
+/**
+ * Reveal mode constants for reveal().
+ */
+export const RevealMode = {
+  /** Scroll the minimum amount to bring the line to the nearest edge. */
+  Nearest: 0,
+  /** Center the line vertically. */
+  Center: 1,
+} as const;
+
+/**
+ * Caret mode constants.
+ */
+export const CaretMode = {
+  /** Insert mode: line caret. */
+  Insert: 0,
+  /** Overwrite mode: block caret. */
+  Overwrite: 1,
+} as const;
+
+/**
+ * End-of-file marker modes for setEofMarkers().
+ */
+export const EofMarkers = {
+  None: 0,
+  /** A faint tilde on every row past the last line, as in vim. */
+  Tilde: 1,
+  /** A single end symbol on the row below the last line. */
+  EndSymbol: 2,
+} as const;
+
+/**
+ * Gutter numbering modes for setLineNumberMode().
+ */
+export const LineNumberMode = {
+  Absolute: 0,
+  /** Distance from the active line, which shows 0. */
+  Relative: 1,
+  /** Distance from the active line, which shows its absolute number. */
+  Hybrid: 2,
+} as const;
+
+/**
+ * Diagnostic severities for setDiagnostics() and setDiagnosticColor().
+ */
+export const DiagnosticSeverity = {
+  /** Red squiggle by default. */
+  Error: 'error',
+  /** Yellow squiggle by default. */
+  Warning: 'warning',
+  /** Blue squiggle by default. */
+  Info: 'info',
+} as const;
+
+/**
+ * Selection style constants.
+ */
+export const SelectionStyle = {
+  /** One rectangle per selected line (default). */
+  Rect: 0,
+  /** Contiguous lines merged into one outline with rounded corners. */
+  Rounded: 1,
+} as const;
+
+/**
+ * Built-in theme preset names for setThemePreset().
+ */
+export const ThemePreset = {
+  /** VS Code Dark+ (the native default). */
+  VSCodeDark: 'vscode-dark',
+  /** VS Code Light+. */
+  VSCodeLight: 'vscode-light',
+  /** White on black; token colors are flattened to the text color. */
+  HighContrastDark: 'high-contrast-dark',
+  /** Black on white; token colors are flattened to the text color. */
+  HighContrastLight: 'high-contrast-light',
 } as const;
 
 /**
@@ -89,6 +241,65 @@ export interface NativeEditorFFI {
     yOffset: number,
   ): void;
 
+  /**
+   * Render a line in a row taller than the font's line height, reserving
+   * space below the text for inline diffs or widgets. Cursors and
+   * selections on the line span the whole row; later lines' yOffsets must
+   * account for the extra height. macOS and Linux only.
+   * @param height - Row height in pixels.
+   */
+  renderLineWithHeight?(
+    handle: NativeViewHandle,
+    lineNumber: number,
+    text: string,
+    tokensJson: string,
+    yOffset: number,
+    height: number,
+  ): void;
+
+  /**
+   * Keep pushed lines across beginFrame so only changed lines need to be
+   * sent; renderLine then replaces the line with the same number. Cursors,
+   * selections and overlays stay per-frame. Call clear after deleting lines.
+   */
+  setPersistentFrame?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * Replace (or add) one line outside a frame and redraw just its row, with
+   * no beginFrame/endFrame. For persistent-frame mode.
+   */
+  updateLine?(
+    handle: NativeViewHandle,
+    lineNumber: number,
+    text: string,
+    tokensJson: string,
+    yOffset: number,
+  ): void;
+
+  /**
+   * Draw a bitmap at a view point this frame (inline media), clipped to the
+   * text area and cleared on beginFrame. Reserve room for it with
+   * renderLineWithHeight. macOS, Windows and Linux.
+   * @param rgba - width * height * 4 bytes: rows top to bottom, 8-bit
+   *   R, G, B, A per pixel with straight (not premultiplied) alpha.
+   */
+  renderImage?(
+    handle: NativeViewHandle,
+    rgba: Uint8Array,
+    width: number,
+    height: number,
+    x: number,
+    y: number,
+  ): void;
+
+  /**
+   * Render a batch of lines in a single call.
+   * @param linesJson - JSON array of `{line_number, text, tokens, y_offset}`,
+   *   where `tokens` is an inline RenderToken[] (not a string). macOS and
+   *   Linux also take an optional `height` (see renderLineWithHeight).
+   */
+  renderLines?(handle: NativeViewHandle, linesJson: string): void;
+
   /**
    * Set the primary cursor position and style.
    * @param style - 0=line, 1=block, 2=underline.
@@ -101,37 +312,585 @@ export interface NativeEditorFFI {
    */
   setSelection(handle: NativeViewHandle, regionsJson: string): void;
 
+  /**
+   * Set the selection from logical ranges instead of pixel rects; the native
+   * side measures each rendered line. Call after the lines are rendered.
+   * Stacked rows covering the same span are filled as a single rect.
+   * @param rangesJson - JSON array of {startLine, startCol, endLine, endCol,
+   *   color?} with character columns (either end may come first).
+   */
+  setSelectionLogical?(handle: NativeViewHandle, rangesJson: string): void;
+
   /**
    * Set the vertical scroll offset.
    */
   scroll(handle: NativeViewHandle, offsetY: number): void;
 
+  /**
+   * Set the horizontal scroll offset. Line text slides under the fixed gutter.
+   */
+  setScrollX?(handle: NativeViewHandle, offsetX: number): void;
+
+  /**
+   * Show a soft shadow at the gutter edge while scrolled horizontally (default on).
+   */
+  setGutterShadow?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * Show or hide the whole gutter (default shown). Hidden, it takes no width
+   * (no line numbers, fold/diff area or background) and text starts at x = 0,
+   * for inline snippets and single-line inputs. Desktop hosts only; on the
+   * web use EditorViewModel.setGutterVisible.
+   */
+  setGutterVisible?(handle: NativeViewHandle, visible: boolean): void;
+
+  /**
+   * Draw a "↪" in the gutter instead of a repeated line number for rows that
+   * continue a wrapped line (default on). Hosts that wrap push one row per
+   * visual line with the same line number; rows after the first are marked.
+   * macOS and Linux.
+   */
+  setWrapMarkers?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * One-line input mode for search boxes and inline rename: the gutter is
+   * hidden, Enter emits "submit:" instead of "insertNewline:", vertical
+   * scrolling is dropped, and the row is centered vertically and clipped to
+   * one line. Desktop hosts only; on the web use
+   * EditorViewModel.setSingleLine.
+   */
+  setSingleLine?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * Set the total number of document lines. Bounds library-driven scrolling.
+   */
+  setLineCount?(handle: NativeViewHandle, count: number): void;
+
+  /**
+   * Scrollable content size for sizing a host scroll container: the widest
+   * line pushed so far (excluding the gutter) and the document height.
+   */
+  contentSize?(handle: NativeViewHandle): { width: number; height: number };
+
+  /**
+   * Scroll so the line at content-space yOffset is visible, then report the
+   * new offset through the scroll callback.
+   * @param mode - RevealMode.Nearest or RevealMode.Center.
+   */
+  reveal?(handle: NativeViewHandle, yOffset: number, mode: number): void;
+
+  /** Scroll so the line at content-space yOffset is vertically centered. */
+  centerOn?(handle: NativeViewHandle, yOffset: number): void;
+
+  /** Lines of context to keep around a line revealed with RevealMode.Nearest. */
+  setScrolloff?(handle: NativeViewHandle, lines: number): void;
+
+  /** Lines the view may scroll past the end of the document (0 = none). */
+  setOverscroll?(handle: NativeViewHandle, lines: number): void;
+
+  /**
+   * Mark where the document ends, in the gutter below the last line set
+   * with setLineCount.
+   * @param mode - EofMarkers.None, EofMarkers.Tilde or EofMarkers.EndSymbol.
+   */
+  setEofMarkers?(handle: NativeViewHandle, mode: number): void;
+
+  /**
+   * Hand the whole document to the view so it lays out the visible rows
+   * itself as it scrolls, instead of the host pushing them every frame.
+   * The view then applies wheel and pan deltas itself, clamped to the
+   * document, and reports the applied delta through the scroll callback;
+   * scroll, reveal and centerOn clamp the same way. Frames keep these rows;
+   * beginFrame only resets overlays. Desktop and iOS hosts only.
+   * @param linesJson - JSON array of {text, tokens?} (line N is index N-1),
+   *   or "null" to go back to host-pushed rows.
+   */
+  setContent?(handle: NativeViewHandle, linesJson: string): void;
+
+  /**
+   * Rows laid out past each edge of the viewport in setContent mode
+   * (default 2). More rows cost layout on every scroll but leave fewer
+   * blank bands during fast scrolls.
+   */
+  setOverscan?(handle: NativeViewHandle, lines: number): void;
+
   /**
    * Measure the width of a text string in the current font.
    * Returns width in pixels.
    */
   measureText(handle: NativeViewHandle, text: string): number;
 
+  /**
+   * Width of one character cell: the advance of "0" (the CSS ch unit), the
+   * value the native side uses for the gutter, rulers and block caret.
+   * Exact for monospace fonts; an approximation for proportional ones, where
+   * measureText gives precise widths.
+   */
+  charWidth?(handle: NativeViewHandle): number;
+
+  /**
+   * Whether every character of text has a glyph in the view's font or the
+   * platform fallback fonts, so hosts can warn about tofu before showing
+   * content. Control characters always pass.
+   */
+  fontHasGlyphs?(handle: NativeViewHandle, text: string): boolean;
+
+  /**
+   * Width of text.slice(0, col) (UTF-16 col) in one call, without slicing
+   * and measuring on the JS side.
+   */
+  measurePrefix?(handle: NativeViewHandle, text: string, col: number): number;
+
+  /**
+   * Caret column nearest x (relative to the text's left edge) on a line.
+   */
+  hitTest?(handle: NativeViewHandle, line: number, text: string, x: number): HitTestResult;
+
+  /**
+   * Offsets of the grapheme cluster boundaries in text, from 0 through
+   * text.length, in UTF-16 code units. Move the caret between these so
+   * emoji sequences and combining marks are never split.
+   */
+  graphemeBoundaries?(handle: NativeViewHandle, text: string): number[];
+
+  /**
+   * Display column of UTF-16 col in text on a character grid: a tab advances
+   * to the next tab stop (see setTabWidth) and an East Asian Wide or
+   * Fullwidth character (CJK, fullwidth forms, emoji) takes two cells. Use
+   * it for "Col N" status text and column alignment. Desktop hosts only.
+   */
+  visualColumn?(handle: NativeViewHandle, text: string, col: number): number;
+
+  /**
+   * "Ln/Col/Sel" for a status bar from this frame's setCursor and
+   * setSelectionLogical, with the column from visualColumn, so every host
+   * reports them the same way. Pixel selections from setSelection aren't
+   * counted. Desktop hosts only.
+   */
+  statusInfo?(handle: NativeViewHandle): StatusInfo;
+
+  /**
+   * Line number and column under a view point, using the lines pushed this
+   * frame, the gutter width and the horizontal scroll. col is 0 in the
+   * gutter and the line length past its end. Returns null when no lines
+   * are buffered.
+   */
+  pointToPosition?(handle: NativeViewHandle, x: number, y: number): PointPosition | null;
+
+  /**
+   * The syntax token covering byte column col on line, from the lines pushed
+   * this frame (or the persisted frame), so go-to-definition and hover use
+   * exactly the rendered span without re-parsing tokens. Null when the line
+   * isn't buffered or no token covers col. Desktop hosts only.
+   */
+  tokenAt?(handle: NativeViewHandle, line: number, col: number): TokenSpan | null;
+
+  /**
+   * The word around col in text, by Unicode word segmentation, for
+   * double-click selection and word-wise caret movement. A caret just after
+   * a word picks that word; otherwise the segment under col is used, so
+   * punctuation and whitespace runs select as a unit. With subword,
+   * identifiers also split at underscores and case changes (fooBar gives
+   * foo and Bar). Offsets are UTF-8 bytes on native hosts and UTF-16 code
+   * units on the web.
+   */
+  wordRangeAt?(handle: NativeViewHandle, text: string, col: number, subword: boolean): WordRange;
+
+  /**
+   * Make word movement stop at camelCase humps and snake_case underscores.
+   * While enabled the native views emit "moveSubwordLeft:" /
+   * "moveSubwordRight:" (and their AndModifySelection: forms) in place of
+   * the word-movement selectors, and wordRangeAt always splits subwords.
+   * Desktop hosts only.
+   */
+  setSubwordNavigation?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * The primary caret's rect in view coordinates (last setCursor this frame,
+   * caret width, line height), for anchoring host popups. Null when no
+   * caret is set.
+   */
+  caretRect?(handle: NativeViewHandle): CaretRect | null;
+
   /** Invalidate the view, triggering a redraw. */
   invalidate(handle: NativeViewHandle): void;
 
+  /**
+   * Redraw only the x, y, w, h rect (view coordinates, clamped to the view),
+   * when the host knows just that region changed. Linux and Web redraw the
+   * whole view.
+   */
+  invalidateRect?(handle: NativeViewHandle, x: number, y: number, w: number, h: number): void;
+
+  /**
+   * Render the current frame offscreen (no window needed) for snapshot tests.
+   * Returns premultiplied RGBA pixels, top row first, or null on failure.
+   */
+  renderToBuffer?(handle: NativeViewHandle, width: number, height: number): Uint8Array | null;
+
   /**
    * Render decorations (underlines, backgrounds) for a line.
    * @param decorationsJson - JSON array of DecorationOverlay[].
    */
   renderDecorations?(handle: NativeViewHandle, decorationsJson: string): void;
 
+  /**
+   * Flash a single DecorationOverlay (JSON) and fade it out over durationMs
+   * (e.g. 400). Survives beginFrame and is removed once fully faded.
+   */
+  flashDecoration?(handle: NativeViewHandle, decorationJson: string, durationMs: number): void;
+
+  /**
+   * Visual bell: briefly wash the whole view in the theme's text color,
+   * fading out over ~150ms, for "invalid command" feedback without audio.
+   * Buffered lines are left alone. Desktop hosts only.
+   */
+  visualBell?(handle: NativeViewHandle): void;
+
   /**
    * Render ghost text (semi-transparent inline completion).
    */
   renderGhostText?(handle: NativeViewHandle, text: string, x: number, y: number, color: string): void;
 
+  /**
+   * Draw a "⋯" badge after column afterCol of a folded line. Clicking it
+   * emits the action "unfold:<line>". Cleared on beginFrame.
+   */
+  renderFoldPlaceholder?(handle: NativeViewHandle, line: number, afterCol: number): void;
+
+  /**
+   * Underline links on rendered lines. linksJson is a JSON array of
+   * {line, startCol, endCol, url}. Ctrl/Cmd+click emits the action
+   * "openLink:<url>" and hovering shows a hand cursor. Cleared on beginFrame.
+   */
+  setLinks?(handle: NativeViewHandle, linksJson: string): void;
+
+  /**
+   * Squiggle diagnostics on rendered lines. diagnosticsJson is a JSON array
+   * of {line, startCol, endCol, severity} with character columns and a
+   * DiagnosticSeverity; the view measures the underline and colors it from
+   * the theme. An empty range marks one cell. Cleared on beginFrame.
+   */
+  setDiagnostics?(handle: NativeViewHandle, diagnosticsJson: string): void;
+
+  /**
+   * Recolor spans on top of the line tokens, e.g. for semantic highlighting.
+   * overridesJson is a JSON array of {line, startCol, endCol, color}; tokens
+   * are split at span edges and keep their font style. Cleared on beginFrame.
+   * Desktop hosts only.
+   */
+  setColorOverrides?(handle: NativeViewHandle, overridesJson: string): void;
+
+  /**
+   * Show faint placeholder text (e.g. "Start typing...") on the first row
+   * while the document is empty. Pass an empty string to remove it.
+   */
+  setPlaceholder?(handle: NativeViewHandle, text: string, color: string): void;
+
+  /**
+   * Set mouse cursor regions. regionsJson is a JSON array of
+   * {x, y, w, h, cursor} in view coordinates, cursor one of "text", "arrow",
+   * "hand". Regions override the built-in arrow over the gutter and hand
+   * over links; the rest of the view keeps the I-beam. Persists across frames.
+   */
+  setCursorRegions?(handle: NativeViewHandle, regionsJson: string): void;
+
+  /**
+   * Pin scope headers to the top of the viewport (sticky scroll).
+   * Clicking a pinned row emits the action "revealLine:<n>".
+   * @param linesJson - JSON array of { line_number, text, tokens }. Pass "[]" to clear.
+   */
+  setStickyLines?(handle: NativeViewHandle, linesJson: string): void;
+
   /**
    * Set multiple cursor positions (for multi-cursor rendering).
+   * Each entry carries its own style, so shapes can be mixed. Replaces any
+   * cursors set earlier in the frame; beginFrame clears them.
    * @param cursorsJson - JSON array of {x, y, style}[].
    */
   setCursors?(handle: NativeViewHandle, cursorsJson: string): void;
 
+  /**
+   * Set how selection regions are drawn.
+   * @param style - SelectionStyle.Rect or SelectionStyle.Rounded.
+   */
+  setSelectionStyle?(handle: NativeViewHandle, style: number): void;
+
+  /**
+   * Extend regions marked `eol` past the line end so selected newlines
+   * (and selected empty lines) are visible.
+   */
+  setSelectionFullLine?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * Outline other occurrences of the selected text, under the selection.
+   * regionsJson has the SelectionRegion shape; cleared on beginFrame.
+   */
+  setOccurrenceHighlights?(handle: NativeViewHandle, regionsJson: string): void;
+
+  /** Occurrence outline color ("#rrggbb"), until the next theme change. */
+  setOccurrenceColor?(handle: NativeViewHandle, color: string): void;
+
+  /**
+   * Squiggle color ("#rrggbb") for one DiagnosticSeverity, until the next
+   * theme change. Returns false for an unknown severity.
+   */
+  setDiagnosticColor?(handle: NativeViewHandle, severity: string, color: string): boolean;
+
+  /**
+   * Tint the gutter behind every line a selection touches.
+   * @param color - "#rrggbb"; omit to use the theme selection color.
+   */
+  setGutterSelectionHighlight?(handle: NativeViewHandle, enabled: boolean, color?: string): void;
+
+  /**
+   * Shade vertical spans across the text area, faint and under the text,
+   * e.g. the block under a hovered fold chevron. Cleared on beginFrame.
+   * @param regionsJson - JSON array of { startY, endY, color } ("#rrggbb").
+   */
+  setRegionShading?(handle: NativeViewHandle, regionsJson: string): void;
+
+  /**
+   * Collaborators' carets, each drawn in its color with a name tag above.
+   * Cleared on beginFrame.
+   * @param cursorsJson - JSON array of `{x, y, color, label}`.
+   */
+  setRemoteCursors?(handle: NativeViewHandle, cursorsJson: string): void;
+
+  /**
+   * Collaborators' selections, drawn over the local selection and under the
+   * local caret. Cleared on beginFrame.
+   * @param regionsJson - JSON array of SelectionRegion with per-owner color.
+   */
+  setRemoteSelections?(handle: NativeViewHandle, regionsJson: string): void;
+
+  /**
+   * Draw a rounded label just above the caret at (x, y) this frame, e.g.
+   * "you are here" presence or the active signature parameter. Cleared on
+   * beginFrame.
+   * @param bgColor - "#rrggbb" label fill.
+   * @param fgColor - "#rrggbb" text color.
+   */
+  renderCaretLabel?(
+    handle: NativeViewHandle,
+    x: number,
+    y: number,
+    text: string,
+    bgColor: string,
+    fgColor: string,
+  ): void;
+
+  /**
+   * Show a bordered hover card (signature help, parameter hints) below the
+   * caret row at (x, y), flipped above it near the bottom edge. Drawn over
+   * everything else; cleared on beginFrame.
+   * @param linesJson - JSON array of { text, tokens? }, tokens as in renderLine.
+   * @param maxWidth - Card width in points; longer lines wrap.
+   */
+  renderHoverCard?(
+    handle: NativeViewHandle,
+    x: number,
+    y: number,
+    linesJson: string,
+    maxWidth: number,
+  ): void;
+
+  /**
+   * Show a completion list below the caret row at (x, y), flipped above it
+   * near the bottom edge. Long lists scroll inside the popup. Clicking an
+   * item emits "acceptCompletion:<index>" through the action callback; arrow
+   * keys arrive through the key callback, and the host re-renders with the
+   * new selectedIndex. Cleared on beginFrame.
+   * @param itemsJson - JSON array of { label, detail?, icon?, iconColor? }.
+   * @param selectedIndex - Item to highlight and scroll into view; -1 for none.
+   */
+  renderCompletionPopup?(
+    handle: NativeViewHandle,
+    x: number,
+    y: number,
+    itemsJson: string,
+    selectedIndex: number,
+  ): void;
+
+  /**
+   * Set the caret mode. Cursors sent with CursorStyle.ModeDefault take the
+   * mode's shape (line for insert, block for overwrite); explicit styles win.
+   * @param mode - CaretMode.Insert or CaretMode.Overwrite.
+   */
+  setCaretMode?(handle: NativeViewHandle, mode: number): void;
+
+  /**
+   * Width of the line caret and thickness of the underline caret in
+   * pixels (default 2, minimum 1). Thicker carets help low-vision users.
+   */
+  setCaretWidth?(handle: NativeViewHandle, px: number): void;
+
+  /**
+   * Set text antialiasing: 0 = none, 1 = grayscale, 2 = subpixel.
+   * Any other value restores the platform default.
+   */
+  setTextAntialiasing?(handle: NativeViewHandle, mode: number): void;
+
+  /**
+   * Set the line height multiplier (e.g. 1.5) relative to the font's natural
+   * line height. Text widths are unaffected.
+   */
+  setLineSpacing?(handle: NativeViewHandle, multiplier: number): void;
+
+  /**
+   * Change the font size but keep the family (zoom). Cheaper than setFont:
+   * only size-dependent metrics are rebuilt.
+   */
+  setFontSize?(handle: NativeViewHandle, size: number): void;
+
+  /**
+   * Like setFont, with the base face's weight (100-900, 400 is regular) and
+   * slant, e.g. a Medium UI font or an italic comment font. Bold token runs
+   * use a heavier weight and keep the slant. Desktop hosts only.
+   */
+  setFontEx?(
+    handle: NativeViewHandle,
+    family: string,
+    size: number,
+    weight: number,
+    italic: boolean,
+  ): void;
+
+  /**
+   * Register a TrueType/OpenType font from memory (e.g. one bundled with the
+   * app) so setFont/setFontEx can use it as `name`. The registration lasts
+   * until the view is destroyed. Returns false if the bytes are not a font.
+   * Desktop hosts only.
+   */
+  registerFontBytes?(handle: NativeViewHandle, name: string, bytes: Uint8Array): boolean;
+
+  /**
+   * Force fixed monospace metrics for tests and headless runs. While set,
+   * measureText returns charCount * charWidth. Pass charWidth <= 0 to clear.
+   */
+  setMetricsOverride?(handle: NativeViewHandle, charWidth: number, lineHeight: number, ascent: number): void;
+
+  /**
+   * Set vertical column rulers.
+   * @param columnsJson - JSON array of character columns, e.g. [80, 120].
+   */
+  setRulers?(handle: NativeViewHandle, columnsJson: string): void;
+
+  /**
+   * Tint each indent level of a line's leading whitespace, cycling through
+   * the given colors (drawn faint) under the text and selection.
+   * @param colorsJson - JSON array of "#rrggbb" strings; [] turns it off.
+   */
+  setIndentRainbow?(handle: NativeViewHandle, colorsJson: string): void;
+
+  /** Columns per indent level and per tab stop (default 4). */
+  setTabWidth?(handle: NativeViewHandle, width: number): void;
+
+  /**
+   * Fill the text area with a vertical gradient from topColor to bottomColor
+   * ("#rrggbb") instead of the flat theme background. The gutter keeps its
+   * own color. Pass enabled = false to go back to the flat fill (the
+   * default). Desktop hosts only.
+   */
+  setBackgroundGradient?(
+    handle: NativeViewHandle,
+    enabled: boolean,
+    topColor: string,
+    bottomColor: string,
+  ): void;
+
+  /**
+   * Apply a built-in color theme (see ThemePreset). Covers the editor chrome
+   * only; token colors still come from the host. Returns false for an
+   * unknown name.
+   */
+  setThemePreset?(handle: NativeViewHandle, name: string): boolean;
+
+  /**
+   * Follow the OS light/dark setting, switching between the VSCodeDark and
+   * VSCodeLight presets live as it changes.
+   */
+  setFollowSystemAppearance?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * Force the high-contrast palette on, as the OS high contrast setting
+   * (Windows High Contrast, macOS Increase contrast, CSS forced-colors)
+   * does. Disabling returns to the system setting and the chosen preset.
+   */
+  setForceHighContrast?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * Set the accessible name of the editor (web: the aria-label of the
+   * role="textbox" container). Defaults to "Code editor".
+   */
+  setAriaLabel?(handle: NativeViewHandle, label: string): void;
+
+  /**
+   * Dim the content while the view is unfocused, as a background-colored
+   * overlay with opacity alpha (0 turns it off), so the active pane stands
+   * out in split layouts.
+   */
+  setInactiveDim?(handle: NativeViewHandle, alpha: number): void;
+
+  /**
+   * Draw carets as a 1px outline instead of filled while the view is
+   * unfocused (on by default). Pass false to keep them solid.
+   */
+  setHollowCaretWhenUnfocused?(handle: NativeViewHandle, enabled: boolean): void;
+
+  /**
+   * Read-only mode for non-editable content (diffs, output panes): carets
+   * are hidden and typed text and editing actions (insert, delete, cut,
+   * paste, undo, indent) are dropped, while movement, selection, copy and
+   * selectAll still reach the host. tint ("#rrggbb") is drawn faint over the
+   * text area; omit it for none. Desktop hosts only.
+   */
+  setReadOnly?(handle: NativeViewHandle, enabled: boolean, tint?: string): void;
+
+  /**
+   * Supply the full document text and caret position (UTF-16 offset) read
+   * by screen readers and input methods (the iOS keyboard's autocorrect and
+   * selection handles). The native side only sees visible lines otherwise.
+   */
+  setAccessibleText?(handle: NativeViewHandle, text: string, cursorOffset: number): void;
+
+  /**
+   * Extend the selection from `anchorOffset` (UTF-16) to the caret passed to
+   * the last setAccessibleText. Input methods and Services read it (macOS,
+   * iOS). On iOS, selection handles and the keyboard move the selection by
+   * emitting "setSelectedRange:<location>,<length>" through the action
+   * callback.
+   */
+  setAccessibleSelection?(handle: NativeViewHandle, anchorOffset: number): void;
+
+  /**
+   * Hint which line the caret is on (-1 clears the hint).
+   * Native layers skip per-line effects on this line to avoid flicker while typing.
+   */
+  setActiveLine?(handle: NativeViewHandle, lineNumber: number): void;
+
+  /**
+   * Draw the active line's number (see setActiveLine) bold and in color
+   * ("#rrggbb"), marking the caret's row without a full-line band. Omit
+   * color to use the theme text color. Desktop hosts only.
+   */
+  setActiveLineGutter?(handle: NativeViewHandle, enabled: boolean, color?: string): void;
+
+  /**
+   * Number the gutter absolutely or relative to the active line (see
+   * setActiveLine). In Hybrid mode relative numbers stay right-aligned and
+   * the active line's absolute number is left-aligned in the number column,
+   * so its extra digits don't make the gutter jitter. Desktop hosts only.
+   * @param mode - LineNumberMode.Absolute, Relative or Hybrid.
+   */
+  setLineNumberMode?(handle: NativeViewHandle, mode: number): void;
+
+  /**
+   * Enable or disable the trailing-whitespace highlight.
+   * @param color - Hex color, painted at reduced opacity.
+   */
+  setHighlightTrailingWhitespace?(handle: NativeViewHandle, enabled: boolean, color: string): void;
+
   /**
    * Begin a frame batch. Called before rendering visible lines.
    * Native layer can use this to prepare buffers.
@@ -143,14 +902,34 @@ export interface NativeEditorFFI {
    * Native layer can use this to flush/present.
    */
   endFrame?(handle: NativeViewHandle): void;
+
+  /**
+   * Blank the view outside a frame (e.g. when the file closes), clearing
+   * lines, cursors, selections, decorations, and ghost text.
+   */
+  clear?(handle: NativeViewHandle): void;
 }
 
+/**
+ * Graphemes NoOpFFI.visualColumn counts as two cells: the main East Asian
+ * Wide/Fullwidth blocks (Hangul Jamo, CJK, kana, Hangul, fullwidth forms)
+ * and emoji presentation.
+ */
+const WIDE_CHAR = new RegExp(
+  '^(?:[\\u1100-\\u115F\\u2E80-\\u303E\\u3041-\\u33FF\\u3400-\\u4DBF\\u4E00-\\u9FFF' +
+    '\\uA000-\\uA4CF\\uAC00-\\uD7A3\\uF900-\\uFAFF\\uFE30-\\uFE6F\\uFF00-\\uFF60\\uFFE0-\\uFFE6]' +
+    '|\\p{Emoji_Presentation})',
+  'u',
+);
+
 /**
  * No-op FFI implementation for testing.
  * Records all calls for verification.
  */
 export class NoOpFFI implements NativeEditorFFI {
   private _nextHandle = 1;
+  private readonly subwordNavigation = new Set<NativeViewHandle>();
+  private readonly tabWidths = new Map<NativeViewHandle, number>();
   readonly calls: { method: string; args: any[] }[] = [];
 
   create(width: number, height: number): NativeViewHandle {
@@ -170,6 +949,43 @@ export class NoOpFFI implements NativeEditorFFI {
     this.calls.push({ method: 'renderLine', args: [handle, lineNumber, text, tokensJson, yOffset] });
   }
 
+  renderLineWithHeight(
+    handle: NativeViewHandle,
+    lineNumber: number,
+    text: string,
+    tokensJson: string,
+    yOffset: number,
+    height: number,
+  ): void {
+    this.calls.push({
+      method: 'renderLineWithHeight',
+      args: [handle, lineNumber, text, tokensJson, yOffset, height],
+    });
+  }
+
+  setPersistentFrame(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setPersistentFrame', args: [handle, enabled] });
+  }
+
+  updateLine(handle: NativeViewHandle, lineNumber: number, text: string, tokensJson: string, yOffset: number): void {
+    this.calls.push({ method: 'updateLine', args: [handle, lineNumber, text, tokensJson, yOffset] });
+  }
+
+  renderImage(
+    handle: NativeViewHandle,
+    rgba: Uint8Array,
+    width: number,
+    height: number,
+    x: number,
+    y: number,
+  ): void {
+    this.calls.push({ method: 'renderImage', args: [handle, rgba, width, height, x, y] });
+  }
+
+  renderLines(handle: NativeViewHandle, linesJson: string): void {
+    this.calls.push({ method: 'renderLines', args: [handle, linesJson] });
+  }
+
   setCursor(handle: NativeViewHandle, x: number, y: number, style: number): void {
     this.calls.push({ method: 'setCursor', args: [handle, x, y, style] });
   }
@@ -178,32 +994,423 @@ export class NoOpFFI implements NativeEditorFFI {
     this.calls.push({ method: 'setSelection', args: [handle, regionsJson] });
   }
 
+  setSelectionLogical(handle: NativeViewHandle, rangesJson: string): void {
+    this.calls.push({ method: 'setSelectionLogical', args: [handle, rangesJson] });
+  }
+
   scroll(handle: NativeViewHandle, offsetY: number): void {
     this.calls.push({ method: 'scroll', args: [handle, offsetY] });
   }
 
+  setScrollX(handle: NativeViewHandle, offsetX: number): void {
+    this.calls.push({ method: 'setScrollX', args: [handle, offsetX] });
+  }
+
+  setGutterShadow(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setGutterShadow', args: [handle, enabled] });
+  }
+
+  setGutterVisible(handle: NativeViewHandle, visible: boolean): void {
+    this.calls.push({ method: 'setGutterVisible', args: [handle, visible] });
+  }
+
+  setWrapMarkers(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setWrapMarkers', args: [handle, enabled] });
+  }
+
+  setSingleLine(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setSingleLine', args: [handle, enabled] });
+  }
+
+  setLineCount(handle: NativeViewHandle, count: number): void {
+    this.calls.push({ method: 'setLineCount', args: [handle, count] });
+  }
+
+  contentSize(handle: NativeViewHandle): { width: number; height: number } {
+    this.calls.push({ method: 'contentSize', args: [handle] });
+    return { width: 0, height: 0 };
+  }
+
+  reveal(handle: NativeViewHandle, yOffset: number, mode: number): void {
+    this.calls.push({ method: 'reveal', args: [handle, yOffset, mode] });
+  }
+
+  centerOn(handle: NativeViewHandle, yOffset: number): void {
+    this.calls.push({ method: 'centerOn', args: [handle, yOffset] });
+  }
+
+  setScrolloff(handle: NativeViewHandle, lines: number): void {
+    this.calls.push({ method: 'setScrolloff', args: [handle, lines] });
+  }
+
+  setOverscroll(handle: NativeViewHandle, lines: number): void {
+    this.calls.push({ method: 'setOverscroll', args: [handle, lines] });
+  }
+
+  setEofMarkers(handle: NativeViewHandle, mode: number): void {
+    this.calls.push({ method: 'setEofMarkers', args: [handle, mode] });
+  }
+
+  setContent(handle: NativeViewHandle, linesJson: string): void {
+    this.calls.push({ method: 'setContent', args: [handle, linesJson] });
+  }
+
+  setOverscan(handle: NativeViewHandle, lines: number): void {
+    this.calls.push({ method: 'setOverscan', args: [handle, lines] });
+  }
+
   measureText(handle: NativeViewHandle, text: string): number {
     this.calls.push({ method: 'measureText', args: [handle, text] });
     // Return a fixed width per character for testing (8px monospace)
     return text.length * 8;
   }
 
+  charWidth(handle: NativeViewHandle): number {
+    this.calls.push({ method: 'charWidth', args: [handle] });
+    return 8;
+  }
+
+  fontHasGlyphs(handle: NativeViewHandle, text: string): boolean {
+    this.calls.push({ method: 'fontHasGlyphs', args: [handle, text] });
+    return true;
+  }
+
+  measurePrefix(handle: NativeViewHandle, text: string, col: number): number {
+    this.calls.push({ method: 'measurePrefix', args: [handle, text, col] });
+    return Math.min(Math.max(col, 0), text.length) * 8;
+  }
+
+  hitTest(handle: NativeViewHandle, line: number, text: string, x: number): HitTestResult {
+    this.calls.push({ method: 'hitTest', args: [handle, line, text, x] });
+    const segmenter = new Intl.Segmenter(undefined, { granularity: 'grapheme' });
+    let col = 0;
+    for (const { segment } of segmenter.segment(text)) {
+      const w = segment.length * 8;
+      if (x < col * 8 + w / 2) break;
+      col += segment.length;
+    }
+    return { line, col, x: col * 8 };
+  }
+
+  statusInfo(handle: NativeViewHandle): StatusInfo {
+    this.calls.push({ method: 'statusInfo', args: [handle] });
+    return { line: 0, column: 0, selectionChars: 0, selectionLines: 0 };
+  }
+
+  pointToPosition(handle: NativeViewHandle, x: number, y: number): PointPosition | null {
+    this.calls.push({ method: 'pointToPosition', args: [handle, x, y] });
+    return null;
+  }
+
+  tokenAt(handle: NativeViewHandle, line: number, col: number): TokenSpan | null {
+    this.calls.push({ method: 'tokenAt', args: [handle, line, col] });
+    return null;
+  }
+
+  caretRect(handle: NativeViewHandle): CaretRect | null {
+    this.calls.push({ method: 'caretRect', args: [handle] });
+    return null;
+  }
+
+  wordRangeAt(handle: NativeViewHandle, text: string, col: number, subword: boolean): WordRange {
+    this.calls.push({ method: 'wordRangeAt', args: [handle, text, col, subword] });
+    const segmenter = new Intl.Segmenter(undefined, { granularity: 'word' });
+    const segments = [...segmenter.segment(text)].map((s) => ({
+      start: s.index,
+      end: s.index + s.segment.length,
+      word: s.isWordLike ?? false,
+    }));
+    subword = subword || this.subwordNavigation.has(handle);
+    const pick = (spans: { start: number; end: number; word: boolean }[]) =>
+      spans.find((s) => (s.end === col && s.word) || (s.start <= col && col < s.end));
+    const found = pick(segments);
+    if (!found) return { start: col, end: col };
+    if (!subword) return { start: found.start, end: found.end };
+    const word = text.slice(found.start, found.end);
+    const splits = /_+|[\p{Lu}\d]+(?!\p{Ll})|\p{Lu}?[\p{Ll}\d]+|[^_\p{Lu}\p{Ll}\d]+/gu;
+    let offset = found.start;
+    const pieces = (word.match(splits) ?? []).map((m) => {
+      const start = offset;
+      offset += m.length;
+      return { start, end: offset, word: m[0] !== '_' };
+    });
+    const piece = pick(pieces) ?? found;
+    return { start: piece.start, end: piece.end };
+  }
+
+  setSubwordNavigation(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setSubwordNavigation', args: [handle, enabled] });
+    if (enabled) this.subwordNavigation.add(handle);
+    else this.subwordNavigation.delete(handle);
+  }
+
+  graphemeBoundaries(handle: NativeViewHandle, text: string): number[] {
+    this.calls.push({ method: 'graphemeBoundaries', args: [handle, text] });
+    const segmenter = new Intl.Segmenter(undefined, { granularity: 'grapheme' });
+    const bounds = [...segmenter.segment(text)].map((s) => s.index);
+    bounds.push(text.length);
+    return bounds;
+  }
+
+  visualColumn(handle: NativeViewHandle, text: string, col: number): number {
+    this.calls.push({ method: 'visualColumn', args: [handle, text, col] });
+    const tabWidth = this.tabWidths.get(handle) ?? 4;
+    const segmenter = new Intl.Segmenter(undefined, { granularity: 'grapheme' });
+    let cells = 0;
+    for (const { segment, index } of segmenter.segment(text)) {
+      if (index + segment.length > col) break;
+      if (segment === '\t') cells += tabWidth - (cells % tabWidth);
+      else cells += WIDE_CHAR.test(segment) ? 2 : 1;
+    }
+    return cells;
+  }
+
   invalidate(handle: NativeViewHandle): void {
     this.calls.push({ method: 'invalidate', args: [handle] });
   }
 
+  invalidateRect(handle: NativeViewHandle, x: number, y: number, w: number, h: number): void {
+    this.calls.push({ method: 'invalidateRect', args: [handle, x, y, w, h] });
+  }
+
+  renderToBuffer(handle: NativeViewHandle, width: number, height: number): Uint8Array | null {
+    this.calls.push({ method: 'renderToBuffer', args: [handle, width, height] });
+    return new Uint8Array(width * height * 4);
+  }
+
   renderDecorations(handle: NativeViewHandle, decorationsJson: string): void {
     this.calls.push({ method: 'renderDecorations', args: [handle, decorationsJson] });
   }
 
+  flashDecoration(handle: NativeViewHandle, decorationJson: string, durationMs: number): void {
+    this.calls.push({ method: 'flashDecoration', args: [handle, decorationJson, durationMs] });
+  }
+
+  visualBell(handle: NativeViewHandle): void {
+    this.calls.push({ method: 'visualBell', args: [handle] });
+  }
+
   renderGhostText(handle: NativeViewHandle, text: string, x: number, y: number, color: string): void {
     this.calls.push({ method: 'renderGhostText', args: [handle, text, x, y, color] });
   }
 
+  renderFoldPlaceholder(handle: NativeViewHandle, line: number, afterCol: number): void {
+    this.calls.push({ method: 'renderFoldPlaceholder', args: [handle, line, afterCol] });
+  }
+
+  setLinks(handle: NativeViewHandle, linksJson: string): void {
+    this.calls.push({ method: 'setLinks', args: [handle, linksJson] });
+  }
+
+  setDiagnostics(handle: NativeViewHandle, diagnosticsJson: string): void {
+    this.calls.push({ method: 'setDiagnostics', args: [handle, diagnosticsJson] });
+  }
+
+  setColorOverrides(handle: NativeViewHandle, overridesJson: string): void {
+    this.calls.push({ method: 'setColorOverrides', args: [handle, overridesJson] });
+  }
+
+  setPlaceholder(handle: NativeViewHandle, text: string, color: string): void {
+    this.calls.push({ method: 'setPlaceholder', args: [handle, text, color] });
+  }
+
+  setCursorRegions(handle: NativeViewHandle, regionsJson: string): void {
+    this.calls.push({ method: 'setCursorRegions', args: [handle, regionsJson] });
+  }
+
+  setStickyLines(handle: NativeViewHandle, linesJson: string): void {
+    this.calls.push({ method: 'setStickyLines', args: [handle, linesJson] });
+  }
+
   setCursors(handle: NativeViewHandle, cursorsJson: string): void {
     this.calls.push({ method: 'setCursors', args: [handle, cursorsJson] });
   }
 
+  setCaretMode(handle: NativeViewHandle, mode: number): void {
+    this.calls.push({ method: 'setCaretMode', args: [handle, mode] });
+  }
+
+  setCaretWidth(handle: NativeViewHandle, px: number): void {
+    this.calls.push({ method: 'setCaretWidth', args: [handle, px] });
+  }
+
+  setMetricsOverride(handle: NativeViewHandle, charWidth: number, lineHeight: number, ascent: number): void {
+    this.calls.push({ method: 'setMetricsOverride', args: [handle, charWidth, lineHeight, ascent] });
+  }
+
+  setLineSpacing(handle: NativeViewHandle, multiplier: number): void {
+    this.calls.push({ method: 'setLineSpacing', args: [handle, multiplier] });
+  }
+
+  setFontSize(handle: NativeViewHandle, size: number): void {
+    this.calls.push({ method: 'setFontSize', args: [handle, size] });
+  }
+
+  setFontEx(
+    handle: NativeViewHandle,
+    family: string,
+    size: number,
+    weight: number,
+    italic: boolean,
+  ): void {
+    this.calls.push({ method: 'setFontEx', args: [handle, family, size, weight, italic] });
+  }
+
+  registerFontBytes(handle: NativeViewHandle, name: string, bytes: Uint8Array): boolean {
+    this.calls.push({ method: 'registerFontBytes', args: [handle, name, bytes] });
+    return true;
+  }
+
+  setTextAntialiasing(handle: NativeViewHandle, mode: number): void {
+    this.calls.push({ method: 'setTextAntialiasing', args: [handle, mode] });
+  }
+
+  setSelectionStyle(handle: NativeViewHandle, style: number): void {
+    this.calls.push({ method: 'setSelectionStyle', args: [handle, style] });
+  }
+
+  setSelectionFullLine(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setSelectionFullLine', args: [handle, enabled] });
+  }
+
+  setOccurrenceHighlights(handle: NativeViewHandle, regionsJson: string): void {
+    this.calls.push({ method: 'setOccurrenceHighlights', args: [handle, regionsJson] });
+  }
+
+  setOccurrenceColor(handle: NativeViewHandle, color: string): void {
+    this.calls.push({ method: 'setOccurrenceColor', args: [handle, color] });
+  }
+
+  setDiagnosticColor(handle: NativeViewHandle, severity: string, color: string): boolean {
+    this.calls.push({ method: 'setDiagnosticColor', args: [handle, severity, color] });
+    return (Object.values(DiagnosticSeverity) as string[]).includes(severity);
+  }
+
+  setGutterSelectionHighlight(handle: NativeViewHandle, enabled: boolean, color?: string): void {
+    this.calls.push({ method: 'setGutterSelectionHighlight', args: [handle, enabled, color] });
+  }
+
+  setRegionShading(handle: NativeViewHandle, regionsJson: string): void {
+    this.calls.push({ method: 'setRegionShading', args: [handle, regionsJson] });
+  }
+
+  setRemoteCursors(handle: NativeViewHandle, cursorsJson: string): void {
+    this.calls.push({ method: 'setRemoteCursors', args: [handle, cursorsJson] });
+  }
+
+  setRemoteSelections(handle: NativeViewHandle, regionsJson: string): void {
+    this.calls.push({ method: 'setRemoteSelections', args: [handle, regionsJson] });
+  }
+
+  renderCaretLabel(
+    handle: NativeViewHandle,
+    x: number,
+    y: number,
+    text: string,
+    bgColor: string,
+    fgColor: string,
+  ): void {
+    this.calls.push({ method: 'renderCaretLabel', args: [handle, x, y, text, bgColor, fgColor] });
+  }
+
+  renderHoverCard(
+    handle: NativeViewHandle,
+    x: number,
+    y: number,
+    linesJson: string,
+    maxWidth: number,
+  ): void {
+    this.calls.push({ method: 'renderHoverCard', args: [handle, x, y, linesJson, maxWidth] });
+  }
+
+  renderCompletionPopup(
+    handle: NativeViewHandle,
+    x: number,
+    y: number,
+    itemsJson: string,
+    selectedIndex: number,
+  ): void {
+    this.calls.push({
+      method: 'renderCompletionPopup',
+      args: [handle, x, y, itemsJson, selectedIndex],
+    });
+  }
+
+  setRulers(handle: NativeViewHandle, columnsJson: string): void {
+    this.calls.push({ method: 'setRulers', args: [handle, columnsJson] });
+  }
+
+  setIndentRainbow(handle: NativeViewHandle, colorsJson: string): void {
+    this.calls.push({ method: 'setIndentRainbow', args: [handle, colorsJson] });
+  }
+
+  setTabWidth(handle: NativeViewHandle, width: number): void {
+    this.calls.push({ method: 'setTabWidth', args: [handle, width] });
+    this.tabWidths.set(handle, Math.max(1, width));
+  }
+
+  setBackgroundGradient(
+    handle: NativeViewHandle,
+    enabled: boolean,
+    topColor: string,
+    bottomColor: string,
+  ): void {
+    this.calls.push({ method: 'setBackgroundGradient', args: [handle, enabled, topColor, bottomColor] });
+  }
+
+  setThemePreset(handle: NativeViewHandle, name: string): boolean {
+    this.calls.push({ method: 'setThemePreset', args: [handle, name] });
+    return (Object.values(ThemePreset) as string[]).includes(name);
+  }
+
+  setFollowSystemAppearance(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setFollowSystemAppearance', args: [handle, enabled] });
+  }
+
+  setForceHighContrast(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setForceHighContrast', args: [handle, enabled] });
+  }
+
+  setAriaLabel(handle: NativeViewHandle, label: string): void {
+    this.calls.push({ method: 'setAriaLabel', args: [handle, label] });
+  }
+
+  setInactiveDim(handle: NativeViewHandle, alpha: number): void {
+    this.calls.push({ method: 'setInactiveDim', args: [handle, alpha] });
+  }
+
+  setHollowCaretWhenUnfocused(handle: NativeViewHandle, enabled: boolean): void {
+    this.calls.push({ method: 'setHollowCaretWhenUnfocused', args: [handle, enabled] });
+  }
+
+  setReadOnly(handle: NativeViewHandle, enabled: boolean, tint?: string): void {
+    this.calls.push({ method: 'setReadOnly', args: [handle, enabled, tint] });
+  }
+
+  setAccessibleText(handle: NativeViewHandle, text: string, cursorOffset: number): void {
+    this.calls.push({ method: 'setAccessibleText', args: [handle, text, cursorOffset] });
+  }
+
+  setAccessibleSelection(handle: NativeViewHandle, anchorOffset: number): void {
+    this.calls.push({ method: 'setAccessibleSelection', args: [handle, anchorOffset] });
+  }
+
+  setActiveLine(handle: NativeViewHandle, lineNumber: number): void {
+    this.calls.push({ method: 'setActiveLine', args: [handle, lineNumber] });
+  }
+
+  setActiveLineGutter(handle: NativeViewHandle, enabled: boolean, color?: string): void {
+    this.calls.push({ method: 'setActiveLineGutter', args: [handle, enabled, color] });
+  }
+
+  setLineNumberMode(handle: NativeViewHandle, mode: number): void {
+    this.calls.push({ method: 'setLineNumberMode', args: [handle, mode] });
+  }
+
+  setHighlightTrailingWhitespace(handle: NativeViewHandle, enabled: boolean, color: string): void {
+    this.calls.push({ method: 'setHighlightTrailingWhitespace', args: [handle, enabled, color] });
+  }
+
   beginFrame(handle: NativeViewHandle): void {
     this.calls.push({ method: 'beginFrame', args: [handle] });
   }
@@ -212,6 +1419,10 @@ export class NoOpFFI implements NativeEditorFFI {
     this.calls.push({ method: 'endFrame', args: [handle] });
   }
 
+  clear(handle: NativeViewHandle): void {
+    this.calls.push({ method: 'clear', args: [handle] });
+  }
+
   /** Clear recorded calls. */
   reset(): void {
     this.calls.length = 0;
@@ -10,6 +10,13 @@ export {
   type SelectionRegion,
   type DecorationOverlay,
   CursorStyle,
+  RevealMode,
+  CaretMode,
+  EofMarkers,
+  DiagnosticSeverity,
+  LineNumberMode,
+  SelectionStyle,
+  ThemePreset,
   NoOpFFI,
 } from './ffi-bridge';
 
@@ -9,14 +9,18 @@
 extern crate objc;
 
 use std::ffi::{c_char, c_void, CStr, CString};
+
+use unicode_segmentation::UnicodeSegmentation;
 use std::ptr::null_mut;
 
 use objc::declare::ClassDecl;
 use objc::runtime::{Class, Object, Sel, BOOL, YES};
 
+use hone_editor_ios::ViewHandle;
 use hone_editor_ios::{
-    hone_editor_attach_to_view, hone_editor_begin_frame, hone_editor_create,
-    hone_editor_end_frame, hone_editor_measure_text, hone_editor_render_line,
+    hone_editor_attach_to_view, hone_editor_begin_frame, hone_editor_column_at_x,
+    hone_editor_create_handle, hone_editor_end_frame, hone_editor_measure_text,
+    hone_editor_point_to_position, hone_editor_render_line,
     hone_editor_set_action_callback, hone_editor_set_cursor, hone_editor_set_font,
     hone_editor_set_mouse_down_callback, hone_editor_set_scroll_callback,
     hone_editor_set_selection, hone_editor_set_text_input_callback, hone_editor_uiview,
@@ -40,7 +44,7 @@ struct DemoEditor {
     sel_anchor: Option<(usize, usize)>,
     scroll_y: f64,
     view_height: f64,
-    editor_ptr: *mut u8,
+    editor: ViewHandle,
     char_width: f64,
     line_height: f64,
 }
@@ -200,7 +204,7 @@ fn validate_tokens_json(tokens_json: &str, orig_text: &str, curr_text: &str) ->
 static mut DEMO: Option<DemoEditor> = None;
 
 impl DemoEditor {
-    fn new(editor_ptr: *mut u8, char_width: f64, line_height: f64, view_height: f64) -> Self {
+    fn new(editor: ViewHandle, char_width: f64, line_height: f64, view_height: f64) -> Self {
         let content = initial_content();
         let lines: Vec<String> = content.iter().map(|(t, _)| t.clone()).collect();
         let line_origins = (0..lines.len()).collect();
@@ -213,7 +217,7 @@ impl DemoEditor {
             sel_anchor: None,
             scroll_y: 0.0,
             view_height,
-            editor_ptr,
+            editor,
             char_width,
             line_height,
         }
@@ -232,40 +236,13 @@ impl DemoEditor {
 
     /// Position cursor from a tap at (x, y) in view coordinates.
     fn click_to_cursor(&mut self, x: f64, y: f64) {
-        let editor = self.editor_ptr as *mut hone_editor_ios::EditorView;
-        let gutter_w = self.gutter_width();
-
-        // Determine line from y (account for scroll offset)
-        let line = ((y + self.scroll_y) / self.line_height).floor() as usize;
-        let line = line.min(self.lines.len().saturating_sub(1));
-
-        // Determine column from x
-        let text_x = x - gutter_w;
-        let col = if text_x <= 0.0 {
-            0
-        } else {
-            let line_str = &self.lines[line];
-            let mut best_col = 0;
-            let mut best_dist = text_x;
-            for (byte_idx, _) in line_str.char_indices() {
-                let end = byte_idx + line_str[byte_idx..].chars().next().unwrap().len_utf8();
-                let prefix = &line_str[..end];
-                let c_prefix = CString::new(prefix).unwrap_or_default();
-                let px = hone_editor_measure_text(editor, c_prefix.as_ptr());
-                let dist = (text_x - px).abs();
-                if dist < best_dist {
-                    best_dist = dist;
-                    best_col = end;
-                }
-                if px > text_x + self.char_width {
-                    break;
-                }
-            }
-            best_col
-        };
-
-        self.cursor_line = line;
-        self.cursor_col = col;
+        let editor = self.editor;
+        let (mut line, mut col) = (0, 0);
+        if !hone_editor_point_to_position(editor, x, y, &mut line, &mut col) {
+            return;
+        }
+        self.cursor_line = (line - 1).max(0) as usize;
+        self.cursor_col = col.max(0) as usize;
         self.sel_anchor = None;
     }
 
@@ -289,6 +266,18 @@ impl DemoEditor {
         }
     }
 
+    /// Column on line `idx` under the caret's current x, measured with the
+    /// editor's font, so up/down keep their place across tabs and
+    /// mixed-width glyphs.
+    fn column_under_caret(&self, idx: usize) -> usize {
+        let current = &self.lines[self.cursor_line];
+        let prefix = current.get(..self.cursor_col).unwrap_or(current);
+        let c_prefix = CString::new(prefix).unwrap_or_default();
+        let x = hone_editor_measure_text(self.editor, c_prefix.as_ptr());
+        let c_text = CString::new(self.lines[idx].as_str()).unwrap_or_default();
+        hone_editor_column_at_x(self.editor, c_text.as_ptr(), x).max(0) as usize
+    }
+
     fn total_content_height(&self) -> f64 {
         self.lines.len() as f64 * self.line_height
     }
@@ -481,7 +470,7 @@ impl DemoEditor {
         if self.cursor_col > 0 {
             let line = &self.lines[self.cursor_line];
             self.cursor_col = line[..self.cursor_col]
-                .char_indices()
+                .grapheme_indices(true)
                 .next_back()
                 .map(|(i, _)| i)
                 .unwrap_or(0);
@@ -510,7 +499,7 @@ impl DemoEditor {
         if self.cursor_col < line_len {
             let line = &self.lines[self.cursor_line];
             self.cursor_col = line[self.cursor_col..]
-                .char_indices()
+                .grapheme_indices(true)
                 .nth(1)
                 .map(|(i, _)| self.cursor_col + i)
                 .unwrap_or(line_len);
@@ -535,6 +524,7 @@ impl DemoEditor {
             self.sel_anchor = None;
         }
         if self.cursor_line > 0 {
+            self.cursor_col = self.column_under_caret(self.cursor_line - 1);
             self.cursor_line -= 1;
             self.clamp_cursor();
         }
@@ -556,6 +546,7 @@ impl DemoEditor {
             self.sel_anchor = None;
         }
         if self.cursor_line + 1 < self.lines.len() {
+            self.cursor_col = self.column_under_caret(self.cursor_line + 1);
             self.cursor_line += 1;
             self.clamp_cursor();
         }
@@ -636,7 +627,7 @@ impl DemoEditor {
     // ── Rendering ───────────────────────────────────────────────
 
     fn render(&self) {
-        let editor = self.editor_ptr as *mut hone_editor_ios::EditorView;
+        let editor = self.editor;
         let gutter_w = self.gutter_width();
 
         hone_editor_begin_frame(editor);
@@ -722,7 +713,7 @@ impl DemoEditor {
 // ── Callbacks ───────────────────────────────────────────────────
 
 extern "C" fn on_text_input(
-    _view: *mut hone_editor_ios::EditorView,
+    _view: ViewHandle,
     text: *const c_char,
 ) {
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
@@ -738,7 +729,7 @@ extern "C" fn on_text_input(
 }
 
 extern "C" fn on_action(
-    _view: *mut hone_editor_ios::EditorView,
+    _view: ViewHandle,
     selector: *const c_char,
 ) {
     let sel_str = unsafe { CStr::from_ptr(selector) }.to_str().unwrap_or("");
@@ -789,7 +780,7 @@ extern "C" fn on_action(
 }
 
 extern "C" fn on_mouse_down(
-    _view: *mut hone_editor_ios::EditorView,
+    _view: ViewHandle,
     x: f64,
     y: f64,
 ) {
@@ -802,7 +793,7 @@ extern "C" fn on_mouse_down(
 }
 
 extern "C" fn on_scroll(
-    _view: *mut hone_editor_ios::EditorView,
+    _view: ViewHandle,
     _dx: f64,
     dy: f64,
 ) {
@@ -1034,7 +1025,7 @@ extern "C" fn view_did_load(this: &Object, _sel: Sel) {
         let view_height = bounds.size.height;
 
         // Create the editor
-        let editor = hone_editor_create(view_width, view_height);
+        let editor = hone_editor_create_handle(view_width, view_height);
 
         // Set font
         let font_family = CString::new("Menlo").unwrap();
@@ -1055,7 +1046,7 @@ extern "C" fn view_did_load(this: &Object, _sel: Sel) {
 
         // Initialize demo state
         DEMO = Some(DemoEditor::new(
-            editor as *mut u8,
+            editor,
             char_width,
             line_height,
             view_height,
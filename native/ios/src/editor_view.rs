@@ -5,6 +5,10 @@
 //! endFrame the UIView is invalidated, and drawRect: calls draw() which
 //! paints everything via Core Graphics / Core Text.
 
+use hone_editor_common::graphemes::grapheme_boundaries;
+use hone_editor_common::handles::ViewHandle;
+use hone_editor_common::hit_test::column_at_x;
+use hone_editor_common::words::word_range_at;
 use core_graphics::context::CGContext;
 use core_graphics::geometry::{CGPoint, CGRect, CGSize};
 use objc::runtime::Object;
@@ -14,6 +18,7 @@ use std::ffi::{c_char, CString};
 use std::ptr::null_mut;
 
 use crate::text_renderer::{self, FontSet, RenderToken};
+use crate::theme::{self, Theme};
 use crate::view;
 
 /// Alias for Objective-C object pointer (replaces cocoa::base::id on iOS).
@@ -25,17 +30,27 @@ const NIL: Id = null_mut();
 // -- Callback types ----------------------------------------------------------
 
 /// Called when the user types printable text. `text` is a null-terminated UTF-8 C string.
-pub type TextInputCallback = extern "C" fn(view: *mut EditorView, text: *const c_char);
+pub type TextInputCallback = extern "C" fn(view: ViewHandle, text: *const c_char);
 
 /// Called when an action selector fires (delete, enter, arrows, etc.).
 /// `selector` is the selector name as a null-terminated UTF-8 C string.
-pub type ActionCallback = extern "C" fn(view: *mut EditorView, selector: *const c_char);
+pub type ActionCallback = extern "C" fn(view: ViewHandle, selector: *const c_char);
 
 /// Called when the user taps in the editor view. `x` and `y` are in view coordinates.
-pub type MouseDownCallback = extern "C" fn(view: *mut EditorView, x: f64, y: f64);
+pub type MouseDownCallback = extern "C" fn(view: ViewHandle, x: f64, y: f64);
 
 /// Called when the user scrolls (pan gesture). `dx`/`dy` are pixel deltas.
-pub type ScrollCallback = extern "C" fn(view: *mut EditorView, dx: f64, dy: f64);
+pub type ScrollCallback = extern "C" fn(view: ViewHandle, dx: f64, dy: f64);
+
+/// Called after the color theme changes (a preset or the system appearance),
+/// so the host can recolor tokens. `appearance` is "dark", "light",
+/// "high-contrast-dark" or "high-contrast-light"; high-contrast themes
+/// already draw tokens in the plain text color.
+pub type ThemeChangedCallback = extern "C" fn(view: ViewHandle, appearance: *const c_char);
+
+/// Called when the view becomes (`focused` = true) or stops being first
+/// responder.
+pub type FocusCallback = extern "C" fn(view: ViewHandle, focused: bool);
 
 /// A custom context menu item added by the host application.
 pub struct ContextMenuItem {
@@ -57,6 +72,9 @@ pub struct SelectionRegion {
 pub struct CursorData {
     pub x: f64,
     pub y: f64,
+    /// 0 = line, 1 = block, 2 = underline. Set per cursor, so a block primary
+    /// can be drawn alongside line secondaries. Missing means line.
+    #[serde(default)]
     pub style: i32,
 }
 
@@ -71,13 +89,23 @@ pub struct DecorationOverlay {
     pub kind: String,
 }
 
+#[derive(Deserialize)]
 struct LineRenderData {
     line_number: i32,
     text: String,
+    #[serde(default)]
     tokens: Vec<RenderToken>,
     y_offset: f64,
 }
 
+/// One document line from `set_content`; its line number is its index + 1.
+#[derive(Deserialize)]
+struct ContentLine {
+    text: String,
+    #[serde(default)]
+    tokens: Vec<RenderToken>,
+}
+
 struct GhostTextData {
     text: String,
     x: f64,
@@ -89,8 +117,8 @@ struct GhostTextData {
 
 /// Top-level editor view state.
 ///
-/// This is the object behind the opaque `*mut EditorView` pointer
-/// returned by `hone_editor_create()`.
+/// This is the object behind the opaque `ViewHandle`
+/// returned by `hone_editor_create_handle()`.
 pub struct EditorView {
     pub renderer: FontSet,
     uiview: Id,
@@ -107,6 +135,10 @@ pub struct EditorView {
     ghost_text: Option<GhostTextData>,
     scroll_offset: f64,
     max_line_number: i32,
+    // Whole document from `set_content`; `Some` while the view picks its own rows
+    content: Option<Vec<ContentLine>>,
+    // Rows laid out past each viewport edge in content mode
+    overscan: i32,
 
     // Input callbacks
     text_input_callback: Option<TextInputCallback>,
@@ -124,6 +156,30 @@ pub struct EditorView {
     default_text_color: (f64, f64, f64),
     selection_color: (f64, f64, f64, f64),
     cursor_color: (f64, f64, f64),
+
+    // Caret mode: 0 = insert (line caret), 1 = overwrite (block caret).
+    // Supplies the style for cursors pushed with style -1.
+    caret_mode: i32,
+    // Handle the host knows this view by; 0 until lib.rs registers it
+    pub(crate) handle: ViewHandle,
+    theme_name: &'static str,
+    theme_changed_callback: Option<ThemeChangedCallback>,
+    grapheme_buf: Vec<u32>,
+    flatten_tokens: bool,
+    focused: bool,
+    focus_callback: Option<FocusCallback>,
+    caret_width: f64,
+    /// Host-supplied document text and caret (UTF-16 offset) for UITextInput;
+    /// the selection runs from the anchor to the caret.
+    accessible_text: Option<String>,
+    accessible_cursor: usize,
+    accessible_anchor: usize,
+    /// In-progress keyboard composition and its selected UTF-16 `(location, length)`.
+    marked_text: Option<String>,
+    marked_selection: (usize, usize),
+    /// Set while forwarding keyboard edits, whose echoes through
+    /// `set_accessible_*` must not be reported back to the keyboard.
+    keyboard_edit: bool,
 }
 
 impl EditorView {
@@ -144,6 +200,8 @@ impl EditorView {
             ghost_text: None,
             scroll_offset: 0.0,
             max_line_number: 0,
+            content: None,
+            overscan: 2,
             text_input_callback: None,
             action_callback: None,
             mouse_down_callback: None,
@@ -156,6 +214,21 @@ impl EditorView {
             default_text_color: (0.843, 0.843, 0.843),   // #d7d7d7
             selection_color: (0.153, 0.306, 0.482, 0.4), // #264f7a @ 40%
             cursor_color: (0.918, 0.918, 0.918),          // #eaeaea
+            caret_mode: 0,
+            handle: 0,
+            theme_name: theme::VSCODE_DARK.name,
+            theme_changed_callback: None,
+            grapheme_buf: Vec::new(),
+            flatten_tokens: false,
+            focused: false,
+            focus_callback: None,
+            caret_width: 2.0,
+            accessible_text: None,
+            accessible_cursor: 0,
+            accessible_anchor: 0,
+            marked_text: None,
+            marked_selection: (0, 0),
+            keyboard_edit: false,
         }
     }
 
@@ -182,8 +255,7 @@ impl EditorView {
     pub fn on_text_input(&mut self, text: &str) {
         if let Some(cb) = self.text_input_callback {
             if let Ok(c_text) = CString::new(text) {
-                let self_ptr = self as *mut EditorView;
-                cb(self_ptr, c_text.as_ptr());
+                cb(self.handle, c_text.as_ptr());
             }
         }
     }
@@ -192,8 +264,7 @@ impl EditorView {
     pub fn on_action(&mut self, selector: &str) {
         if let Some(cb) = self.action_callback {
             if let Ok(c_sel) = CString::new(selector) {
-                let self_ptr = self as *mut EditorView;
-                cb(self_ptr, c_sel.as_ptr());
+                cb(self.handle, c_sel.as_ptr());
             }
         }
     }
@@ -205,8 +276,7 @@ impl EditorView {
     /// Called from the UIView's touchesBegan: handler.
     pub fn on_mouse_down(&mut self, x: f64, y: f64) {
         if let Some(cb) = self.mouse_down_callback {
-            let self_ptr = self as *mut EditorView;
-            cb(self_ptr, x, y);
+            cb(self.handle, x, y);
         }
     }
 
@@ -216,9 +286,18 @@ impl EditorView {
 
     /// Called from the UIView's touchesMoved: handler (pan gesture).
     pub fn on_scroll(&mut self, dx: f64, dy: f64) {
+        // In content mode the view scrolls itself; the host hears the delta
+        // left after clamping
+        let mut dy = dy;
+        if self.content.is_some() {
+            let offset = self.clamp_scroll(self.scroll_offset + dy);
+            dy = offset - self.scroll_offset;
+            self.scroll_offset = offset;
+            self.fill_visible_lines();
+            self.invalidate();
+        }
         if let Some(cb) = self.scroll_callback {
-            let self_ptr = self as *mut EditorView;
-            cb(self_ptr, dx, dy);
+            cb(self.handle, dx, dy);
         }
     }
 
@@ -239,25 +318,360 @@ impl EditorView {
 
     pub fn set_font(&mut self, family: &str, size: f64) {
         self.renderer = FontSet::new(family, size);
+        self.fill_visible_lines();
         if self.uiview != NIL {
             view::invalidate_view(self.uiview);
         }
     }
 
+    /// Apply a built-in color preset by name. Returns false (and changes
+    /// nothing) for an unknown name.
+    pub fn set_theme_preset(&mut self, name: &str) -> bool {
+        match theme::preset(name) {
+            Some(t) => {
+                self.apply_theme(t);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn apply_theme(&mut self, t: &'static Theme) {
+        let hex = text_renderer::parse_hex_color;
+        self.background_color = hex(t.background);
+        self.gutter_bg_color = hex(t.gutter_background);
+        self.gutter_fg_color = hex(t.gutter_foreground);
+        self.default_text_color = hex(t.text);
+        let (r, g, b) = hex(t.selection);
+        self.selection_color = (r, g, b, t.selection_alpha);
+        self.cursor_color = hex(t.cursor);
+        self.flatten_tokens = t.high_contrast;
+        if self.theme_name != t.name {
+            self.theme_name = t.name;
+            self.on_theme_changed(t);
+        }
+    }
+
+    /// Tokens to draw for a line: none under a high-contrast theme, so every
+    /// glyph takes the plain text color.
+    fn visible_tokens<'a>(&self, tokens: &'a [RenderToken]) -> &'a [RenderToken] {
+        if self.flatten_tokens {
+            &[]
+        } else {
+            tokens
+        }
+    }
+
+    pub fn set_focus_callback(&mut self, cb: FocusCallback) {
+        self.focus_callback = Some(cb);
+    }
+
+    /// Called from becomeFirstResponder / resignFirstResponder. Repaints and
+    /// notifies the host when the state actually changes.
+    pub fn on_focus_changed(&mut self, focused: bool) {
+        if self.focused == focused {
+            return;
+        }
+        self.focused = focused;
+        if let Some(cb) = self.focus_callback {
+            cb(self.handle, focused);
+        }
+        self.invalidate();
+    }
+
+    pub fn set_theme_changed_callback(&mut self, cb: ThemeChangedCallback) {
+        self.theme_changed_callback = Some(cb);
+    }
+
+    fn on_theme_changed(&mut self, t: &'static Theme) {
+        if let Some(cb) = self.theme_changed_callback {
+            let appearance = CString::new(t.appearance()).unwrap();
+            cb(self.handle, appearance.as_ptr());
+        }
+    }
+
     pub fn measure_text(&self, text: &str) -> f64 {
         self.renderer.measure_text(text)
     }
 
+    /// Byte column of the grapheme boundary in `text` nearest `x`, measured
+    /// from the text's left edge with this view's font.
+    pub fn column_at_x(&self, text: &str, x: f64) -> usize {
+        column_at_x(text, x, |p| self.measure_text(p))
+    }
+
+    /// Advance width of `text[start..end]` (byte offsets). Returns 0 for an
+    /// empty range or offsets that are out of bounds or not on a char boundary.
+    pub fn measure_range(&self, text: &str, start: usize, end: usize) -> f64 {
+        match text.get(start..end) {
+            Some(span) if !span.is_empty() => self.measure_text(span),
+            _ => 0.0,
+        }
+    }
+
+    /// Byte offsets of the extended grapheme cluster boundaries in `text`,
+    /// from 0 through `text.len()`. Valid until the next call.
+    pub fn grapheme_boundaries(&mut self, text: &str) -> &[u32] {
+        self.grapheme_buf.clear();
+        self.grapheme_buf.extend(grapheme_boundaries(text).map(|i| i as u32));
+        &self.grapheme_buf
+    }
+
+    /// Byte range of the word around byte column `col` in `text`, by Unicode
+    /// word segmentation. A caret just after a word picks that word;
+    /// otherwise the segment under `col` is used, so a run of punctuation or
+    /// whitespace selects as a unit. With `subword`, identifiers also split
+    /// at underscores and case changes (`fooBar` gives `foo` and `Bar`).
+    pub fn word_range_at(&self, text: &str, col: usize, subword: bool) -> (usize, usize) {
+        word_range_at(text, col, subword)
+    }
+
+    /// Buffered line number and byte column under view point `(x, y)`.
+    /// Rows above or below the pushed lines clamp to the nearest one, points
+    /// in the gutter map to column 0 and points past the end of a line to
+    /// its length; otherwise the column snaps to the nearest grapheme
+    /// boundary. `None` when no lines were pushed this frame.
+    pub fn point_to_position(&self, x: f64, y: f64) -> Option<(i32, usize)> {
+        let line_h = self.renderer.line_height;
+        let row_dist = |l: &&LineRenderData| {
+            if y < l.y_offset {
+                l.y_offset - y
+            } else {
+                (y - (l.y_offset + line_h)).max(0.0)
+            }
+        };
+        let line = self
+            .frame_lines
+            .iter()
+            .min_by(|a, b| row_dist(a).total_cmp(&row_dist(b)))?;
+        let gutter_w = self.gutter_width();
+        let col = if x < gutter_w { 0 } else { self.column_at_x(&line.text, x - gutter_w) };
+        Some((line.line_number, col))
+    }
+
+    // -- Text input (UITextInput) --------------------------------------------
+
+    /// Supply the authoritative document text and caret position (a UTF-16
+    /// offset into `text`) that the keyboard reads for autocorrect, predictive
+    /// text and selection. Until set, the lines in the current frame stand in.
+    /// Collapses the selection to the caret.
+    pub fn set_accessible_text(&mut self, text: &str, cursor_offset: usize) {
+        let notify = !self.keyboard_edit;
+        if notify {
+            view::text_will_change(self.uiview);
+        }
+        self.accessible_text = Some(text.to_string());
+        self.accessible_cursor = cursor_offset;
+        self.accessible_anchor = cursor_offset;
+        if notify {
+            view::text_did_change(self.uiview);
+        }
+    }
+
+    /// Extend the selection from `anchor_offset` (UTF-16) to the caret from
+    /// the last `set_accessible_text`.
+    pub fn set_accessible_selection(&mut self, anchor_offset: usize) {
+        let notify = !self.keyboard_edit;
+        if notify {
+            view::selection_will_change(self.uiview);
+        }
+        self.accessible_anchor = anchor_offset;
+        if notify {
+            view::selection_did_change(self.uiview);
+        }
+    }
+
+    /// The document text: the host's, or the frame's lines joined in order.
+    pub fn document_text(&self) -> String {
+        match self.accessible_text {
+            Some(ref text) => text.clone(),
+            None => self
+                .frame_lines
+                .iter()
+                .map(|l| l.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Length of the document text in UTF-16 units.
+    pub fn document_length(&self) -> usize {
+        self.document_text().encode_utf16().count()
+    }
+
+    /// The selection as a UTF-16 `(location, length)` into the document
+    /// text. While composing, the keyboard's selection within the marked text.
+    pub fn selected_range(&self) -> (usize, usize) {
+        let start = self.accessible_anchor.min(self.accessible_cursor);
+        match self.marked_text {
+            Some(_) => (start + self.marked_selection.0, self.marked_selection.1),
+            None => (start, self.accessible_anchor.max(self.accessible_cursor) - start),
+        }
+    }
+
+    /// UTF-16 `(location, length)` of the marked text, which stands in for
+    /// the selection until it is committed.
+    pub fn marked_range(&self) -> Option<(usize, usize)> {
+        let text = self.marked_text.as_ref()?;
+        let start = self.accessible_anchor.min(self.accessible_cursor);
+        Some((start, text.encode_utf16().count()))
+    }
+
+    /// Show `text` as an uncommitted composition at the caret, with the
+    /// keyboard's `selection` (UTF-16, relative to `text`). Empty text
+    /// cancels it.
+    pub fn set_marked_text(&mut self, text: &str, selection: (usize, usize)) {
+        self.marked_text = (!text.is_empty()).then(|| text.to_string());
+        self.marked_selection = selection;
+        self.invalidate();
+    }
+
+    /// Commit any marked text as typed input.
+    pub fn unmark_text(&mut self) {
+        if let Some(text) = self.marked_text.take() {
+            self.invalidate();
+            self.keyboard_edit = true;
+            self.on_text_input(&text);
+            self.keyboard_edit = false;
+        }
+    }
+
+    /// Commit `text` from the keyboard, replacing any composition. A lone
+    /// newline is sent as the `insertNewline:` action.
+    pub fn insert_text(&mut self, text: &str) {
+        if self.marked_text.take().is_some() {
+            self.invalidate();
+        }
+        self.keyboard_edit = true;
+        if text == "\n" || text == "\r" {
+            self.on_action("insertNewline:");
+        } else if !text.is_empty() {
+            self.on_text_input(text);
+        }
+        self.keyboard_edit = false;
+    }
+
+    /// Forward the keyboard's delete key as `deleteBackward:`.
+    pub fn delete_backward(&mut self) {
+        self.keyboard_edit = true;
+        self.on_action("deleteBackward:");
+        self.keyboard_edit = false;
+    }
+
+    /// Ask the host to select the UTF-16 `(location, length)` with the
+    /// `"setSelectedRange:<location>,<length>"` action (selection handles,
+    /// the magnifier and autocorrect move the selection this way). The local
+    /// copy is updated so queries made before the host answers agree.
+    pub fn select_range(&mut self, location: usize, length: usize) {
+        self.accessible_anchor = location;
+        self.accessible_cursor = location + length;
+        self.keyboard_edit = true;
+        self.on_action(&format!("setSelectedRange:{},{}", location, length));
+        self.keyboard_edit = false;
+    }
+
+    /// Replace the UTF-16 `(location, length)` with `text`: selects it unless
+    /// it already is the selection, then inserts.
+    pub fn replace_range(&mut self, location: usize, length: usize, text: &str) {
+        if self.marked_text.is_none() && self.selected_range() != (location, length) {
+            self.select_range(location, length);
+        }
+        self.insert_text(text);
+    }
+
+    /// The document text in UTF-16 `(location, length)`, clamped to the text.
+    pub fn document_substring(&self, location: usize, length: usize) -> String {
+        let units: Vec<u16> = self.document_text().encode_utf16().collect();
+        let start = location.min(units.len());
+        let end = location.saturating_add(length).min(units.len());
+        String::from_utf16_lossy(&units[start..end])
+    }
+
+    /// UTF-16 offset into the document text of the character under view
+    /// point `(x, y)`, via `point_to_position`.
+    pub fn character_index_at(&self, x: f64, y: f64) -> Option<usize> {
+        let (line_number, col) = self.point_to_position(x, y)?;
+        let row = match self.accessible_text {
+            Some(_) => usize::try_from(line_number - 1).ok()?,
+            None => self.frame_lines.iter().position(|l| l.line_number == line_number)?,
+        };
+        let text = self.document_text();
+        let mut offset = 0;
+        for (i, line) in text.split('\n').enumerate() {
+            if i == row {
+                let prefix = line.get(..col).unwrap_or(line);
+                return Some(offset + prefix.encode_utf16().count());
+            }
+            offset += line.encode_utf16().count() + 1;
+        }
+        None
+    }
+
+    /// Caret rect `(x, y, w, h)` in view coordinates for UTF-16 `offset`.
+    /// `None` when its line is not in the current frame.
+    pub fn position_rect(&self, offset: usize) -> Option<(f64, f64, f64, f64)> {
+        let (line, col) = self.offset_to_line(offset)?;
+        let x = self.gutter_width() + self.measure_range(&line.text, 0, col);
+        Some((x, line.y_offset, self.caret_width, self.renderer.line_height))
+    }
+
+    /// One `(x, y, w, h)` per visible row covering the UTF-16 range
+    /// `start..end`.
+    pub fn range_rects(&self, start: usize, end: usize) -> Vec<(f64, f64, f64, f64)> {
+        let text = self.document_text();
+        let mut rects = Vec::new();
+        let mut line_start = 0;
+        for line in text.split('\n') {
+            let line_end = line_start + line.encode_utf16().count();
+            if line_start > end {
+                break;
+            }
+            if line_end >= start {
+                let from = self.position_rect(start.max(line_start));
+                let to = self.position_rect(end.min(line_end));
+                if let (Some((x0, y, _, h)), Some((x1, _, _, _))) = (from, to) {
+                    // Rows the range continues past run to the view's right edge
+                    let x1 = if end > line_end { self.width } else { x1 };
+                    rects.push((x0, y, (x1 - x0).max(0.0), h));
+                }
+            }
+            line_start = line_end + 1;
+        }
+        rects
+    }
+
+    /// Frame line holding UTF-16 document `offset`, and the byte column of
+    /// the offset within it.
+    fn offset_to_line(&self, offset: usize) -> Option<(&LineRenderData, usize)> {
+        let text = self.document_text();
+        let mut line_start = 0;
+        for (row, line) in text.split('\n').enumerate() {
+            let len = line.encode_utf16().count();
+            if offset <= line_start + len {
+                let frame_line = match self.accessible_text {
+                    Some(_) => self.frame_lines.iter().find(|l| l.line_number as usize == row + 1),
+                    None => self.frame_lines.get(row),
+                }?;
+                let col = utf16_to_byte(&frame_line.text, offset - line_start);
+                return Some((frame_line, col));
+            }
+            line_start += len + 1;
+        }
+        None
+    }
+
     // -- Frame buffer API ----------------------------------------------------
 
     pub fn begin_frame(&mut self) {
-        self.frame_lines.clear();
+        if self.content.is_none() {
+            self.frame_lines.clear();
+            self.max_line_number = 0;
+        }
         self.cursor = None;
         self.cursors.clear();
         self.selections.clear();
         self.decorations.clear();
         self.ghost_text = None;
-        self.max_line_number = 0;
     }
 
     pub fn render_line(&mut self, line_number: i32, text: &str, tokens_json: &str, y_offset: f64) {
@@ -273,20 +687,136 @@ impl EditorView {
         });
     }
 
+    /// Queue a whole viewport in one call. `lines_json` is an array of
+    /// `{line_number, text, tokens, y_offset}` objects, where `tokens` is
+    /// the same token array `render_line` takes as a string.
+    pub fn render_lines(&mut self, lines_json: &str) {
+        let lines: Vec<LineRenderData> = serde_json::from_str(lines_json).unwrap_or_default();
+        for line in lines {
+            if line.line_number > self.max_line_number {
+                self.max_line_number = line.line_number;
+            }
+            self.frame_lines.push(line);
+        }
+    }
+
+    /// Hand the whole document to the view, as a JSON array of
+    /// `{text, tokens?}` (line numbers are 1-based indexes), and let it pick
+    /// the rows to lay out from the scroll offset instead of the host
+    /// pushing them each frame. Frames keep these rows; `begin_frame` only
+    /// resets overlays. `null` hands rows back to the host.
+    pub fn set_content(&mut self, lines_json: &str) {
+        let lines: Option<Vec<ContentLine>> = serde_json::from_str(lines_json).unwrap_or_default();
+        self.frame_lines.clear();
+        self.max_line_number = 0;
+        self.content = lines;
+        self.fill_visible_lines();
+        self.invalidate();
+    }
+
+    /// Rows laid out above and below the viewport in `set_content` mode
+    /// (default 2). More rows cost layout and memory on every scroll but
+    /// leave fewer blank bands when the view moves before the rows are
+    /// rebuilt; 0 lays out only what is on screen.
+    pub fn set_overscan(&mut self, lines: i32) {
+        self.overscan = lines.max(0);
+        self.fill_visible_lines();
+        self.invalidate();
+    }
+
+    /// Keep a content-mode scroll offset between the top and the point
+    /// where the last line sits at the bottom edge.
+    fn clamp_scroll(&self, offset: f64) -> f64 {
+        let lines = self.content.as_ref().map_or(0, Vec::len);
+        let max = (lines as f64 * self.renderer.line_height - self.height).max(0.0);
+        offset.clamp(0.0, max)
+    }
+
+    /// In content mode, rebuild the frame buffer from the document: the
+    /// rows in view plus `overscan` on each side, placed for the current
+    /// scroll offset.
+    fn fill_visible_lines(&mut self) {
+        let Some(content) = &self.content else {
+            return;
+        };
+        let line_h = self.renderer.line_height;
+        let mut rows = Vec::new();
+        if line_h > 0.0 {
+            let overscan = self.overscan as f64;
+            let first = ((self.scroll_offset / line_h).floor() - overscan).max(0.0) as usize;
+            let last = ((self.scroll_offset + self.height) / line_h).ceil() + overscan;
+            for (i, line) in content.iter().enumerate().take(last.max(0.0) as usize).skip(first) {
+                rows.push(LineRenderData {
+                    line_number: i as i32 + 1,
+                    text: line.text.clone(),
+                    tokens: line.tokens.clone(),
+                    y_offset: i as f64 * line_h - self.scroll_offset,
+                });
+            }
+        }
+        self.max_line_number = rows.last().map_or(0, |l| l.line_number);
+        self.frame_lines = rows;
+    }
+
+    pub fn set_caret_mode(&mut self, mode: i32) {
+        self.caret_mode = mode;
+    }
+
+    /// Resolve style -1 ("use mode default") to the caret mode's shape.
+    /// Explicit styles (0, 1, 2) are kept as-is.
+    fn resolve_cursor_style(&self, style: i32) -> i32 {
+        match style {
+            -1 if self.caret_mode == 1 => 1,
+            -1 => 0,
+            s => s,
+        }
+    }
+
+    /// Width of the line caret and thickness of the underline caret, in
+    /// points (default 2). Clamped to at least 1.
+    pub fn set_caret_width(&mut self, px: f64) {
+        self.caret_width = if px.is_finite() { px.max(1.0) } else { 2.0 };
+        self.invalidate();
+    }
+
     pub fn set_cursor(&mut self, x: f64, y: f64, style: i32) {
+        let style = self.resolve_cursor_style(style);
         self.cursor = Some(CursorData { x, y, style });
     }
 
+    /// The primary caret's rect in view coordinates as `(x, y, w, h)`: the
+    /// position from `set_cursor`, the drawn caret width (a cell for block
+    /// and underline carets) and the row height. `None` when no caret was
+    /// set this frame.
+    pub fn caret_rect(&self) -> Option<(f64, f64, f64, f64)> {
+        let c = self.cursor.as_ref()?;
+        let w = if c.style == 0 { self.caret_width } else { self.renderer.char_width };
+        Some((c.x, c.y, w, self.renderer.line_height))
+    }
+
+    /// Replace the multi-cursor list for this frame. `begin_frame` clears it;
+    /// calling this again within a frame overwrites rather than appends.
     pub fn set_cursors(&mut self, cursors_json: &str) {
-        self.cursors = serde_json::from_str(cursors_json).unwrap_or_default();
+        let mut cursors: Vec<CursorData> = serde_json::from_str(cursors_json).unwrap_or_default();
+        for c in &mut cursors {
+            c.style = self.resolve_cursor_style(c.style);
+        }
+        self.cursors = cursors;
     }
 
     pub fn set_selection(&mut self, regions_json: &str) {
         self.selections = serde_json::from_str(regions_json).unwrap_or_default();
     }
 
+    /// Set the scroll offset. Clamped to the document in content mode, where
+    /// the view owns scrolling; host-pushed frames take it as given.
     pub fn scroll(&mut self, offset_y: f64) {
-        self.scroll_offset = offset_y;
+        self.scroll_offset = if self.content.is_some() {
+            self.clamp_scroll(offset_y)
+        } else {
+            offset_y
+        };
+        self.fill_visible_lines();
     }
 
     pub fn render_decorations(&mut self, decorations_json: &str) {
@@ -310,6 +840,15 @@ impl EditorView {
         }
     }
 
+    /// Blank the view outside a frame (e.g. when the file closes): drops all
+    /// frame content, as an empty `begin_frame`/`end_frame` pair would, and
+    /// redraws.
+    pub fn clear(&mut self) {
+        self.content = None;
+        self.begin_frame();
+        self.end_frame();
+    }
+
     pub fn invalidate(&mut self) {
         if self.uiview != NIL {
             view::invalidate_view(self.uiview);
@@ -403,7 +942,7 @@ impl EditorView {
             text_renderer::draw_line(
                 ctx,
                 &line.text,
-                &line.tokens,
+                self.visible_tokens(&line.tokens),
                 gutter_w,
                 line.y_offset,
                 &self.renderer,
@@ -480,20 +1019,47 @@ impl EditorView {
             );
         }
 
+        // 6a. Keyboard marked text over the caret, underlined until committed
+        if let Some(ref text) = self.marked_text {
+            self.draw_marked_text(ctx, text);
+        }
+
         // 7. Draw cursors
         self.draw_cursors(ctx);
     }
 
+    fn draw_marked_text(&self, ctx: &CGContext, text: &str) {
+        let Some((x, y, _, h)) = self.caret_rect() else {
+            return;
+        };
+        let w = self.measure_text(text);
+        let (r, g, b) = self.background_color;
+        ctx.set_rgb_fill_color(r, g, b, 1.0);
+        ctx.fill_rect(CGRect::new(&CGPoint::new(x, y), &CGSize::new(w, h)));
+        text_renderer::draw_text(
+            ctx,
+            text,
+            x,
+            y,
+            &self.renderer.normal,
+            self.renderer.ascent,
+            self.default_text_color,
+        );
+        let (r, g, b) = self.default_text_color;
+        ctx.set_rgb_fill_color(r, g, b, 1.0);
+        ctx.fill_rect(CGRect::new(&CGPoint::new(x, y + h - 1.0), &CGSize::new(w, 1.0)));
+    }
+
     fn draw_cursors(&self, ctx: &CGContext) {
         let draw_one = |cursor: &CursorData| {
             let (w, h) = match cursor.style {
-                0 => (2.0, self.renderer.line_height), // Line cursor
+                0 => (self.caret_width, self.renderer.line_height), // Line cursor
                 1 => (self.renderer.char_width, self.renderer.line_height), // Block cursor
-                2 => (self.renderer.char_width, 2.0),  // Underline cursor
-                _ => (2.0, self.renderer.line_height),
+                2 => (self.renderer.char_width, self.caret_width), // Underline cursor
+                _ => (self.caret_width, self.renderer.line_height),
             };
             let y = if cursor.style == 2 {
-                cursor.y + self.renderer.line_height - 2.0
+                cursor.y + self.renderer.line_height - self.caret_width
             } else {
                 cursor.y
             };
@@ -522,6 +1088,18 @@ impl EditorView {
     }
 }
 
+/// Byte offset in `text` of UTF-16 column `col`, clamped to the end.
+fn utf16_to_byte(text: &str, col: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= col {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
 impl Drop for EditorView {
     fn drop(&mut self) {
         if self.uiview != NIL {
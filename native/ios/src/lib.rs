@@ -8,47 +8,103 @@
 extern crate objc;
 
 mod text_renderer;
+mod theme;
 mod view;
 mod editor_view;
 
 pub use editor_view::EditorView;
+pub use hone_editor_common::handles::ViewHandle;
 
-use editor_view::{ActionCallback, MouseDownCallback, ScrollCallback, TextInputCallback};
+use editor_view::{
+    ActionCallback, FocusCallback, MouseDownCallback, ScrollCallback, TextInputCallback,
+    ThemeChangedCallback,
+};
+use hone_editor_common::handles::HandleTable;
+use std::cell::RefCell;
 use std::ffi::{c_char, CStr};
 
+thread_local! {
+    /// Views created on this thread. Views are single-threaded, so a handle
+    /// only resolves on the thread that created it.
+    static VIEWS: RefCell<HandleTable<EditorView>> = const { RefCell::new(HandleTable::new()) };
+}
+
+/// Resolve a handle from the host to its live view. Returns None for 0, for
+/// a destroyed view's handle, and on any thread other than the view's own,
+/// so such calls are ignored (mutating ones panic in debug builds).
+fn view_ref<'a>(view: ViewHandle) -> Option<&'a EditorView> {
+    VIEWS.with(|views| views.borrow().get(view)).map(|view| unsafe { &*view })
+}
+
+/// `view_ref` for calls that mutate the view. Views are single-threaded, so
+/// debug builds panic if the handle belongs to a view on another thread.
+fn view_mut<'a>(view: ViewHandle) -> Option<&'a mut EditorView> {
+    let found = VIEWS.with(|views| views.borrow().get(view));
+    #[cfg(debug_assertions)]
+    if found.is_none() {
+        hone_editor_common::handles::owners::assert_not_foreign(view);
+    }
+    found.map(|view| unsafe { &mut *view })
+}
+
+/// Move a new view into this thread's table and return its handle, or 0 if
+/// the table is full (the view is dropped).
+fn register(view: Box<EditorView>) -> ViewHandle {
+    VIEWS.with(|views| {
+        let mut views = views.borrow_mut();
+        let Some(handle) = views.insert(view) else {
+            return 0;
+        };
+        if let Some(view) = views.get(handle) {
+            unsafe { (*view).handle = handle };
+        }
+        #[cfg(debug_assertions)]
+        hone_editor_common::handles::owners::claim(handle);
+        handle
+    })
+}
+
 // === FFI Contract Implementation ===
 
-/// Create a new editor view with the given dimensions.
+/// Create a new editor view with the given dimensions and return its handle.
 #[no_mangle]
-pub extern "C" fn hone_editor_create(width: f64, height: f64) -> *mut EditorView {
+pub extern "C" fn hone_editor_create_handle(width: f64, height: f64) -> ViewHandle {
     let mut ev = Box::new(EditorView::new(width, height));
     ev.init_uiview();
-    Box::into_raw(ev)
+    register(ev)
 }
 
 /// Attach the editor view to a parent UIView.
 #[no_mangle]
-pub extern "C" fn hone_editor_attach_to_view(view: *mut EditorView, parent_view: i64) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_attach_to_view(view: ViewHandle, parent_view: i64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.attach_to_parent(parent_view as *mut std::ffi::c_void);
 }
 
-/// Destroy an editor view and free all resources.
+/// Destroy an editor view and free all resources. Stale handles are ignored.
 #[no_mangle]
-pub extern "C" fn hone_editor_destroy(view: *mut EditorView) {
-    if !view.is_null() {
-        unsafe { drop(Box::from_raw(view)); }
-    }
+pub extern "C" fn hone_editor_destroy_handle(view: ViewHandle) {
+    // Drop outside the table borrow so teardown can't observe it.
+    let Some(removed) = VIEWS.with(|views| views.borrow_mut().remove(view)) else {
+        return;
+    };
+    #[cfg(debug_assertions)]
+    hone_editor_common::handles::owners::release(view);
+    drop(removed);
 }
 
 /// Set the editor font family and size.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_font(
-    view: *mut EditorView,
+    view: ViewHandle,
     family: *const c_char,
     size: f64,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let family_str = unsafe { CStr::from_ptr(family) }.to_str().unwrap_or("Menlo");
     view.set_font(family_str, size);
 }
@@ -56,13 +112,15 @@ pub extern "C" fn hone_editor_set_font(
 /// Render a single line of text with syntax coloring.
 #[no_mangle]
 pub extern "C" fn hone_editor_render_line(
-    view: *mut EditorView,
+    view: ViewHandle,
     line_number: i32,
     text: *const c_char,
     tokens_json: *const c_char,
     y_offset: f64,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     let tokens_str = unsafe { CStr::from_ptr(tokens_json) }.to_str().unwrap_or("[]");
     view.render_line(line_number, text_str, tokens_str, y_offset);
@@ -71,48 +129,58 @@ pub extern "C" fn hone_editor_render_line(
 /// Set the cursor position and style.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_cursor(
-    view: *mut EditorView,
+    view: ViewHandle,
     x: f64,
     y: f64,
     style: i32,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_cursor(x, y, style);
 }
 
 /// Set selection highlight regions.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_selection(
-    view: *mut EditorView,
+    view: ViewHandle,
     regions_json: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(regions_json) }.to_str().unwrap_or("[]");
     view.set_selection(json_str);
 }
 
 /// Set the vertical scroll offset.
 #[no_mangle]
-pub extern "C" fn hone_editor_scroll(view: *mut EditorView, offset_y: f64) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_scroll(view: ViewHandle, offset_y: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.scroll(offset_y);
 }
 
 /// Measure the width of a text string in the current font.
 #[no_mangle]
 pub extern "C" fn hone_editor_measure_text(
-    view: *mut EditorView,
+    view: ViewHandle,
     text: *const c_char,
 ) -> f64 {
-    let view = unsafe { &*view };
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     view.measure_text(text_str)
 }
 
 /// Invalidate the view, triggering a redraw on the next frame.
 #[no_mangle]
-pub extern "C" fn hone_editor_invalidate(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_invalidate(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.invalidate();
 }
 
@@ -121,10 +189,12 @@ pub extern "C" fn hone_editor_invalidate(view: *mut EditorView) {
 /// Render decorations (underlines, backgrounds) for a line.
 #[no_mangle]
 pub extern "C" fn hone_editor_render_decorations(
-    view: *mut EditorView,
+    view: ViewHandle,
     decorations_json: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(decorations_json) }.to_str().unwrap_or("[]");
     view.render_decorations(json_str);
 }
@@ -132,25 +202,31 @@ pub extern "C" fn hone_editor_render_decorations(
 /// Render ghost text (semi-transparent inline completion).
 #[no_mangle]
 pub extern "C" fn hone_editor_render_ghost_text(
-    view: *mut EditorView,
+    view: ViewHandle,
     text: *const c_char,
     x: f64,
     y: f64,
     color: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     let color_str = unsafe { CStr::from_ptr(color) }.to_str().unwrap_or("#808080");
     view.render_ghost_text(text_str, x, y, color_str);
 }
 
-/// Set multiple cursor positions.
+/// Set multiple cursor positions. `cursors_json` is a JSON array of
+/// `{x, y, style}`; each entry's style (0=line, 1=block, 2=underline) is drawn
+/// independently. Replaces any cursors set earlier in the frame.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_cursors(
-    view: *mut EditorView,
+    view: ViewHandle,
     cursors_json: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(cursors_json) }.to_str().unwrap_or("[]");
     view.set_cursors(json_str);
 }
@@ -158,40 +234,48 @@ pub extern "C" fn hone_editor_set_cursors(
 /// Set the callback for text input (printable characters).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_text_input_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: TextInputCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_text_input_callback(callback);
 }
 
 /// Set the callback for action selectors (arrows, delete, enter, etc.).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_action_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: ActionCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_action_callback(callback);
 }
 
 /// Set the callback for touch-down events (tap to position cursor).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_mouse_down_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: MouseDownCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_mouse_down_callback(callback);
 }
 
 /// Set the callback for scroll events (pan gesture).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_scroll_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: ScrollCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_scroll_callback(callback);
 }
 
@@ -199,11 +283,13 @@ pub extern "C" fn hone_editor_set_scroll_callback(
 /// The `action_id` is dispatched through the action callback when the item is clicked.
 #[no_mangle]
 pub extern "C" fn hone_editor_add_context_menu_item(
-    view: *mut EditorView,
+    view: ViewHandle,
     title: *const c_char,
     action_id: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let title_str = unsafe { CStr::from_ptr(title) }.to_str().unwrap_or("");
     let action_str = unsafe { CStr::from_ptr(action_id) }.to_str().unwrap_or("");
     view.add_context_menu_item(title_str, action_str);
@@ -211,28 +297,395 @@ pub extern "C" fn hone_editor_add_context_menu_item(
 
 /// Remove all custom context menu items.
 #[no_mangle]
-pub extern "C" fn hone_editor_clear_context_menu_items(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_clear_context_menu_items(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.clear_context_menu_items();
 }
 
 /// Get the UIView handle for the editor view (as a raw pointer).
 #[no_mangle]
-pub extern "C" fn hone_editor_uiview(view: *mut EditorView) -> *mut std::ffi::c_void {
-    let view = unsafe { &*view };
+pub extern "C" fn hone_editor_uiview(view: ViewHandle) -> *mut std::ffi::c_void {
+    let Some(view) = view_ref(view) else {
+        return std::ptr::null_mut();
+    };
     view.uiview() as *mut std::ffi::c_void
 }
 
 /// Begin a frame batch.
 #[no_mangle]
-pub extern "C" fn hone_editor_begin_frame(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_begin_frame(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.begin_frame();
 }
 
 /// End a frame batch.
 #[no_mangle]
-pub extern "C" fn hone_editor_end_frame(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_end_frame(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.end_frame();
 }
+
+/// Blank the view outside a frame: clears lines, cursors, selections,
+/// decorations, and ghost text, then redraws.
+#[no_mangle]
+pub extern "C" fn hone_editor_clear(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.clear();
+}
+
+/// Set the caret mode: 0 = insert (line caret), 1 = overwrite (block caret).
+/// Cursors pushed with style -1 take the mode's shape; explicit styles
+/// (0=line, 1=block, 2=underline) always win.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_caret_mode(view: ViewHandle, mode: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_caret_mode(mode);
+}
+
+/// Render a batch of lines in one call. `lines_json` is a JSON array of
+/// `{line_number, text, tokens, y_offset}` objects; `tokens` is an inline
+/// RenderToken array rather than a string.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_lines(view: ViewHandle, lines_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json = unsafe { CStr::from_ptr(lines_json) }.to_str().unwrap_or("[]");
+    view.render_lines(json);
+}
+
+/// Give the view the whole document as a JSON array of `{text, tokens?}`
+/// (line N is index N-1) so it lays out the visible rows itself on every
+/// scroll instead of the host pushing them. Frames keep the rows and only
+/// reset overlays. Pass `null` to return to host-pushed rows.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_content(view: ViewHandle, lines_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json = unsafe { CStr::from_ptr(lines_json) }.to_str().unwrap_or("null");
+    view.set_content(json);
+}
+
+/// Rows laid out past each edge of the viewport in `hone_editor_set_content`
+/// mode (default 2). Larger values render more off-screen rows per scroll
+/// but show fewer blank bands during fast scrolls.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_overscan(view: ViewHandle, lines: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_overscan(lines);
+}
+
+/// Render a single line from length-delimited UTF-8 slices. Neither slice
+/// needs a NUL terminator, so the host can pass views into an existing
+/// buffer instead of allocating a CString per line. Invalid UTF-8 is
+/// replaced with U+FFFD.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_line_ptr(
+    view: ViewHandle,
+    line_number: i32,
+    text_ptr: *const u8,
+    text_len: usize,
+    tokens_ptr: *const u8,
+    tokens_len: usize,
+    y_offset: f64,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text = unsafe { utf8_lossy(text_ptr, text_len) };
+    let tokens = unsafe { utf8_lossy(tokens_ptr, tokens_len) };
+    let tokens = if tokens.is_empty() { "[]" } else { &tokens };
+    view.render_line(line_number, &text, tokens, y_offset);
+}
+
+/// Borrow `len` bytes at `ptr` as UTF-8. Null or empty input yields "".
+unsafe fn utf8_lossy<'a>(ptr: *const u8, len: usize) -> std::borrow::Cow<'a, str> {
+    if ptr.is_null() || len == 0 {
+        return std::borrow::Cow::Borrowed("");
+    }
+    String::from_utf8_lossy(std::slice::from_raw_parts(ptr, len))
+}
+
+/// Measure the advance width of the byte range `[start_byte, end_byte)` of
+/// `text` without the host building prefix strings. Returns 0 for empty or
+/// invalid ranges, including offsets that split a UTF-8 character.
+#[no_mangle]
+pub extern "C" fn hone_editor_measure_range(
+    view: ViewHandle,
+    text: *const c_char,
+    start_byte: usize,
+    end_byte: usize,
+) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    view.measure_range(text_str, start_byte, end_byte)
+}
+
+/// Check that the caller is on the thread that created `view`. Handles only
+/// resolve on their owning thread, so this is false for a foreign thread (or
+/// a destroyed view): exactly the calls the rest of the API ignores.
+#[no_mangle]
+pub extern "C" fn hone_editor_assert_thread(view: ViewHandle) -> bool {
+    view_ref(view).is_some()
+}
+
+/// Apply a built-in color theme: "vscode-dark" (default) or "vscode-light".
+/// Returns false if the name is unknown.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_theme_preset(
+    view: ViewHandle,
+    name: *const c_char,
+) -> bool {
+    let Some(view) = view_mut(view) else {
+        return false;
+    };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap_or("");
+    view.set_theme_preset(name_str)
+}
+
+/// Set the callback invoked after the color theme changes, with "dark",
+/// "light", "high-contrast-dark" or "high-contrast-light", so the host can
+/// re-tokenize or remap token colors and re-push lines. Fires for theme
+/// presets, system appearance changes and high contrast.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_theme_changed_callback(
+    view: ViewHandle,
+    callback: ThemeChangedCallback,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_theme_changed_callback(callback);
+}
+
+/// Byte offsets of the grapheme cluster boundaries in `text`, from 0 through
+/// its length, so hosts move the caret by user-perceived characters (ZWJ
+/// emoji, flags, and combining marks stay whole). The count is written to
+/// `out_len`. The array belongs to the view and stays valid until the next
+/// call; an invalid view returns null with a count of 0.
+#[no_mangle]
+pub extern "C" fn hone_editor_grapheme_boundaries(
+    view: ViewHandle,
+    text: *const c_char,
+    out_len: *mut usize,
+) -> *const u32 {
+    if !out_len.is_null() {
+        unsafe { *out_len = 0 };
+    }
+    let Some(view) = view_mut(view) else {
+        return std::ptr::null();
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let bounds = view.grapheme_boundaries(text_str);
+    if !out_len.is_null() {
+        unsafe { *out_len = bounds.len() };
+    }
+    bounds.as_ptr()
+}
+
+/// Byte range of the word around byte column `col` in `text`, by Unicode word
+/// segmentation, for double-click selection and word-wise caret movement. A
+/// caret just after a word picks that word; otherwise the segment under `col`
+/// is used, so punctuation and whitespace runs select as a unit. `subword`
+/// also splits identifiers at underscores and case changes (`fooBar`).
+/// Returns false, leaving the outputs untouched, for an invalid view.
+#[no_mangle]
+pub extern "C" fn hone_editor_word_range_at(
+    view: ViewHandle,
+    text: *const c_char,
+    col: i32,
+    subword: bool,
+    out_start: *mut i32,
+    out_end: *mut i32,
+) -> bool {
+    if text.is_null() || out_start.is_null() || out_end.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let (start, end) = view.word_range_at(text_str, col.max(0) as usize, subword);
+    unsafe {
+        *out_start = start as i32;
+        *out_end = end as i32;
+    }
+    true
+}
+
+/// Map view point `(x, y)` to the line number and byte column under it,
+/// using the lines pushed this frame and the gutter width. Gutter clicks
+/// give column 0, clicks past the end of a line give its length, and rows
+/// outside the buffered lines clamp to the nearest one.
+/// Returns false, leaving the outputs untouched, when no lines are buffered.
+#[no_mangle]
+pub extern "C" fn hone_editor_point_to_position(
+    view: ViewHandle,
+    x: f64,
+    y: f64,
+    out_line: *mut i32,
+    out_col: *mut i32,
+) -> bool {
+    if out_line.is_null() || out_col.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    match view.point_to_position(x, y) {
+        Some((line, col)) => {
+            unsafe {
+                *out_line = line;
+                *out_col = col as i32;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Byte column of the grapheme boundary in `text` nearest `x`, measured from
+/// the text's left edge with the view's font, so hosts hit-testing their own
+/// text get tabs and mixed-width glyphs right. Returns 0 for an invalid view.
+#[no_mangle]
+pub extern "C" fn hone_editor_column_at_x(view: ViewHandle, text: *const c_char, x: f64) -> i32 {
+    if text.is_null() {
+        return 0;
+    }
+    let Some(view) = view_ref(view) else {
+        return 0;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    view.column_at_x(text_str, x) as i32
+}
+
+/// Write the primary caret's rect in view coordinates to `out[0..4]` as
+/// x, y, width, height: the last `set_cursor` position this frame, the caret
+/// width (a cell for block and underline carets) and the line height. For
+/// anchoring host popups. Returns false, leaving `out` untouched, when no
+/// caret is set. `out` must hold 4 f64s.
+#[no_mangle]
+pub extern "C" fn hone_editor_caret_rect(view: ViewHandle, out: *mut f64) -> bool {
+    if out.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    match view.caret_rect() {
+        Some((x, y, w, h)) => {
+            unsafe {
+                *out = x;
+                *out.add(1) = y;
+                *out.add(2) = w;
+                *out.add(3) = h;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Supply the full document text and caret position (UTF-16 offset) that
+/// the keyboard reads through UITextInput for autocorrect, predictive text
+/// and selection handles. Hosts should call this whenever the document or
+/// caret changes.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_accessible_text(
+    view: ViewHandle,
+    full_text: *const c_char,
+    cursor_offset: usize,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text = unsafe { CStr::from_ptr(full_text) }.to_str().unwrap_or("");
+    view.set_accessible_text(text, cursor_offset);
+}
+
+/// Set the selection anchor (UTF-16 offset) after `set_accessible_text`; the
+/// selection runs from it to the caret. Selection handles that move it emit
+/// `setSelectedRange:<location>,<length>` through the action callback.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_accessible_selection(
+    view: ViewHandle,
+    anchor_offset: usize,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_accessible_selection(anchor_offset);
+}
+
+/// Set the callback invoked when the view becomes or stops being first
+/// responder, so hosts can stop the caret blink while unfocused.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_focus_callback(view: ViewHandle, callback: FocusCallback) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_focus_callback(callback);
+}
+
+/// Set the line caret width and underline caret thickness (default 2.0).
+#[no_mangle]
+pub extern "C" fn hone_editor_set_caret_width(view: ViewHandle, px: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_caret_width(px);
+}
+
+// === Pointer Compatibility ===
+//
+// Hosts written against the original pointer-based contract keep working
+// while they migrate: `hone_editor_create` and `hone_editor_destroy` keep
+// their pointer signatures, and `hone_editor_handle_for_view` turns such a
+// pointer into the handle every other entry point takes. Pointers are looked
+// up in this thread's handle table, never dereferenced, so one used after
+// destroy is ignored unless a newer view was allocated at the same address.
+// Only handles rule that out.
+
+/// Create a new editor view and return its address. Deprecated: use
+/// `hone_editor_create_handle`.
+#[no_mangle]
+pub extern "C" fn hone_editor_create(width: f64, height: f64) -> *mut EditorView {
+    hone_editor_view_for_handle(hone_editor_create_handle(width, height))
+}
+
+/// Destroy a view created with `hone_editor_create`. Pointers that aren't a
+/// live view on this thread are ignored. Deprecated: use
+/// `hone_editor_destroy_handle`.
+#[no_mangle]
+pub extern "C" fn hone_editor_destroy(view: *mut EditorView) {
+    hone_editor_destroy_handle(hone_editor_handle_for_view(view));
+}
+
+/// The handle of a view created with `hone_editor_create`, or 0 if the
+/// pointer isn't a live view on this thread.
+#[no_mangle]
+pub extern "C" fn hone_editor_handle_for_view(view: *mut EditorView) -> ViewHandle {
+    VIEWS.with(|views| views.borrow().find(view)).unwrap_or(0)
+}
+
+/// The address of the view behind a handle, for code that still takes a
+/// pointer, or null if the handle doesn't resolve on this thread.
+#[no_mangle]
+pub extern "C" fn hone_editor_view_for_handle(view: ViewHandle) -> *mut EditorView {
+    VIEWS.with(|views| views.borrow().get(view)).unwrap_or(std::ptr::null_mut())
+}
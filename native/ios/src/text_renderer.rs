@@ -29,7 +29,7 @@ extern "C" {
 }
 
 /// Token data from the TypeScript layer.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct RenderToken {
     /// Start column.
     pub s: usize,
@@ -69,8 +69,9 @@ impl FontSet {
         let leading = normal.leading();
         let line_height = (ascent + descent + leading).ceil();
 
-        // Measure the advance width of 'M' for monospace char width
-        let char_width = measure_string_width(&normal, "M");
+        // Advance of '0', the CSS `ch` unit: the cell width for monospace
+        // fonts and a fair average for proportional ones
+        let char_width = measure_string_width(&normal, "0");
 
         FontSet {
             normal,
@@ -2,15 +2,17 @@
 //!
 //! Registers `HoneEditorView` as a subclass of UIView via the objc runtime.
 //! UIView already uses a top-left origin, so no isFlipped is needed.
-//! Keyboard input uses UIKeyInput protocol (insertText:, deleteBackward).
+//! Keyboard input uses the UITextInput protocol (UIKeyInput plus ranges,
+//! marked text and geometry), backed by the host's document text so
+//! selection handles, the magnifier and predictive text work.
 //! Touch events replace mouse events for cursor positioning and scrolling.
 
 use core_graphics::geometry::{CGPoint, CGRect, CGSize};
 use objc::declare::ClassDecl;
-use objc::runtime::{Class, Object, Sel, BOOL, YES};
+use objc::runtime::{Class, Object, Protocol, Sel, BOOL, NO, YES};
 use objc::Encode;
 use objc::Encoding;
-use std::ffi::{c_void, CStr};
+use std::ffi::{c_void, CStr, CString};
 use std::ptr::null_mut;
 use std::sync::Once;
 
@@ -31,6 +33,27 @@ const EDITOR_STATE_IVAR: &str = "honeEditorState";
 const PREV_TOUCH_X_IVAR: &str = "honePrevTouchX";
 const PREV_TOUCH_Y_IVAR: &str = "honePrevTouchY";
 
+/// Ivar names for the UITextInput delegate (not retained) and the lazily
+/// created tokenizer (retained).
+const INPUT_DELEGATE_IVAR: &str = "honeInputDelegate";
+const TOKENIZER_IVAR: &str = "honeTokenizer";
+
+/// Ivar names on the UITextPosition / UITextRange / UITextSelectionRect
+/// subclasses: UTF-16 offsets into the document and a rect.
+const OFFSET_IVAR: &str = "honeOffset";
+const START_IVAR: &str = "honeStart";
+const END_IVAR: &str = "honeEnd";
+const RECT_IVAR: &str = "honeRect";
+const CONTAINS_START_IVAR: &str = "honeContainsStart";
+const CONTAINS_END_IVAR: &str = "honeContainsEnd";
+
+/// UITextLayoutDirection / UITextStorageDirection values.
+const UI_TEXT_LAYOUT_DIRECTION_RIGHT: i64 = 2;
+const UI_TEXT_LAYOUT_DIRECTION_LEFT: i64 = 3;
+const UI_TEXT_LAYOUT_DIRECTION_UP: i64 = 4;
+const UI_TEXT_LAYOUT_DIRECTION_DOWN: i64 = 5;
+const UI_TEXT_STORAGE_DIRECTION_BACKWARD: i64 = 1;
+
 // -- ObjC-compatible rect type -----------------------------------------------
 // core_graphics::CGRect doesn't implement objc::Encode, so we define a
 // layout-compatible #[repr(C)] wrapper that does.
@@ -75,6 +98,13 @@ unsafe impl Encode for ObjCRect {
 }
 
 impl ObjCRect {
+    fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        ObjCRect {
+            origin: ObjCPoint { x, y },
+            size: ObjCSize { width, height },
+        }
+    }
+
     fn to_cg_rect(self) -> CGRect {
         CGRect::new(
             &CGPoint::new(self.origin.x, self.origin.y),
@@ -83,9 +113,24 @@ impl ObjCRect {
     }
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct ObjCRange {
+    location: usize,
+    length: usize,
+}
+
+unsafe impl Encode for ObjCRange {
+    fn encode() -> Encoding {
+        unsafe { Encoding::from_str("{_NSRange=QQ}") }
+    }
+}
+
 /// Register the HoneEditorView class (idempotent).
 fn ensure_class_registered() {
     REGISTER_CLASS.call_once(|| {
+        register_text_input_classes();
+
         let superclass = Class::get("UIView").expect("UIView class not found");
         let mut decl = ClassDecl::new("HoneEditorView", superclass)
             .expect("Failed to create HoneEditorView class");
@@ -94,6 +139,8 @@ fn ensure_class_registered() {
         decl.add_ivar::<*mut c_void>(EDITOR_STATE_IVAR);
         decl.add_ivar::<f64>(PREV_TOUCH_X_IVAR);
         decl.add_ivar::<f64>(PREV_TOUCH_Y_IVAR);
+        decl.add_ivar::<Id>(INPUT_DELEGATE_IVAR);
+        decl.add_ivar::<Id>(TOKENIZER_IVAR);
 
         unsafe {
             // -- Drawing --
@@ -107,6 +154,14 @@ fn ensure_class_registered() {
                 objc::sel!(canBecomeFirstResponder),
                 can_become_first_responder as extern "C" fn(&Object, Sel) -> BOOL,
             );
+            decl.add_method(
+                objc::sel!(becomeFirstResponder),
+                become_first_responder as extern "C" fn(&Object, Sel) -> BOOL,
+            );
+            decl.add_method(
+                objc::sel!(resignFirstResponder),
+                resign_first_responder as extern "C" fn(&Object, Sel) -> BOOL,
+            );
 
             // -- Touch handling --
             decl.add_method(
@@ -140,6 +195,129 @@ fn ensure_class_registered() {
                 delete_backward as extern "C" fn(&Object, Sel),
             );
 
+            // -- UITextInput protocol --
+            decl.add_method(
+                objc::sel!(textInRange:),
+                text_in_range as extern "C" fn(&Object, Sel, Id) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(replaceRange:withText:),
+                replace_range as extern "C" fn(&Object, Sel, Id, Id),
+            );
+            decl.add_method(
+                objc::sel!(selectedTextRange),
+                selected_text_range as extern "C" fn(&Object, Sel) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(setSelectedTextRange:),
+                set_selected_text_range as extern "C" fn(&Object, Sel, Id),
+            );
+            decl.add_method(
+                objc::sel!(markedTextRange),
+                marked_text_range as extern "C" fn(&Object, Sel) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(markedTextStyle),
+                marked_text_style as extern "C" fn(&Object, Sel) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(setMarkedTextStyle:),
+                set_marked_text_style as extern "C" fn(&Object, Sel, Id),
+            );
+            decl.add_method(
+                objc::sel!(setMarkedText:selectedRange:),
+                set_marked_text as extern "C" fn(&Object, Sel, Id, ObjCRange),
+            );
+            decl.add_method(
+                objc::sel!(unmarkText),
+                unmark_text as extern "C" fn(&Object, Sel),
+            );
+            decl.add_method(
+                objc::sel!(beginningOfDocument),
+                beginning_of_document as extern "C" fn(&Object, Sel) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(endOfDocument),
+                end_of_document as extern "C" fn(&Object, Sel) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(textRangeFromPosition:toPosition:),
+                text_range_from_position as extern "C" fn(&Object, Sel, Id, Id) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(positionFromPosition:offset:),
+                position_from_position as extern "C" fn(&Object, Sel, Id, isize) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(positionFromPosition:inDirection:offset:),
+                position_in_direction as extern "C" fn(&Object, Sel, Id, i64, isize) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(comparePosition:toPosition:),
+                compare_position as extern "C" fn(&Object, Sel, Id, Id) -> i64,
+            );
+            decl.add_method(
+                objc::sel!(offsetFromPosition:toPosition:),
+                offset_from_position as extern "C" fn(&Object, Sel, Id, Id) -> isize,
+            );
+            decl.add_method(
+                objc::sel!(inputDelegate),
+                input_delegate as extern "C" fn(&Object, Sel) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(setInputDelegate:),
+                set_input_delegate as extern "C" fn(&Object, Sel, Id),
+            );
+            decl.add_method(
+                objc::sel!(tokenizer),
+                tokenizer as extern "C" fn(&Object, Sel) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(positionWithinRange:farthestInDirection:),
+                position_within_range as extern "C" fn(&Object, Sel, Id, i64) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(characterRangeByExtendingPosition:inDirection:),
+                character_range_by_extending as extern "C" fn(&Object, Sel, Id, i64) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(baseWritingDirectionForPosition:inDirection:),
+                base_writing_direction as extern "C" fn(&Object, Sel, Id, i64) -> i64,
+            );
+            decl.add_method(
+                objc::sel!(setBaseWritingDirection:forRange:),
+                set_base_writing_direction as extern "C" fn(&Object, Sel, i64, Id),
+            );
+            decl.add_method(
+                objc::sel!(firstRectForRange:),
+                first_rect_for_range as extern "C" fn(&Object, Sel, Id) -> ObjCRect,
+            );
+            decl.add_method(
+                objc::sel!(caretRectForPosition:),
+                caret_rect_for_position as extern "C" fn(&Object, Sel, Id) -> ObjCRect,
+            );
+            decl.add_method(
+                objc::sel!(selectionRectsForRange:),
+                selection_rects_for_range as extern "C" fn(&Object, Sel, Id) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(closestPositionToPoint:),
+                closest_position_to_point as extern "C" fn(&Object, Sel, ObjCPoint) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(closestPositionToPoint:withinRange:),
+                closest_position_within_range
+                    as extern "C" fn(&Object, Sel, ObjCPoint, Id) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(characterRangeAtPoint:),
+                character_range_at_point as extern "C" fn(&Object, Sel, ObjCPoint) -> Id,
+            );
+            decl.add_method(
+                objc::sel!(dealloc),
+                dealloc as extern "C" fn(&Object, Sel),
+            );
+
             // -- UITextInputTraits --
             decl.add_method(
                 objc::sel!(keyboardType),
@@ -167,10 +345,76 @@ fn ensure_class_registered() {
             );
         }
 
+        if let Some(protocol) = Protocol::get("UITextInput") {
+            decl.add_protocol(protocol);
+        }
+
         decl.register();
     });
 }
 
+/// Register the UITextPosition, UITextRange and UITextSelectionRect
+/// subclasses that carry document offsets for UITextInput.
+fn register_text_input_classes() {
+    let superclass = Class::get("UITextPosition").expect("UITextPosition class not found");
+    let mut decl = ClassDecl::new("HoneTextPosition", superclass)
+        .expect("Failed to create HoneTextPosition class");
+    decl.add_ivar::<usize>(OFFSET_IVAR);
+    decl.register();
+
+    let superclass = Class::get("UITextRange").expect("UITextRange class not found");
+    let mut decl = ClassDecl::new("HoneTextRange", superclass)
+        .expect("Failed to create HoneTextRange class");
+    decl.add_ivar::<usize>(START_IVAR);
+    decl.add_ivar::<usize>(END_IVAR);
+    unsafe {
+        decl.add_method(
+            objc::sel!(start),
+            text_range_start as extern "C" fn(&Object, Sel) -> Id,
+        );
+        decl.add_method(
+            objc::sel!(end),
+            text_range_end as extern "C" fn(&Object, Sel) -> Id,
+        );
+        decl.add_method(
+            objc::sel!(isEmpty),
+            text_range_is_empty as extern "C" fn(&Object, Sel) -> BOOL,
+        );
+    }
+    decl.register();
+
+    let superclass =
+        Class::get("UITextSelectionRect").expect("UITextSelectionRect class not found");
+    let mut decl = ClassDecl::new("HoneTextSelectionRect", superclass)
+        .expect("Failed to create HoneTextSelectionRect class");
+    decl.add_ivar::<ObjCRect>(RECT_IVAR);
+    decl.add_ivar::<BOOL>(CONTAINS_START_IVAR);
+    decl.add_ivar::<BOOL>(CONTAINS_END_IVAR);
+    unsafe {
+        decl.add_method(
+            objc::sel!(rect),
+            selection_rect_rect as extern "C" fn(&Object, Sel) -> ObjCRect,
+        );
+        decl.add_method(
+            objc::sel!(writingDirection),
+            selection_rect_writing_direction as extern "C" fn(&Object, Sel) -> i64,
+        );
+        decl.add_method(
+            objc::sel!(containsStart),
+            selection_rect_contains_start as extern "C" fn(&Object, Sel) -> BOOL,
+        );
+        decl.add_method(
+            objc::sel!(containsEnd),
+            selection_rect_contains_end as extern "C" fn(&Object, Sel) -> BOOL,
+        );
+        decl.add_method(
+            objc::sel!(isVertical),
+            selection_rect_is_vertical as extern "C" fn(&Object, Sel) -> BOOL,
+        );
+    }
+    decl.register();
+}
+
 // -- Drawing -----------------------------------------------------------------
 
 extern "C" fn draw_rect(this: &Object, _sel: Sel, dirty_rect: ObjCRect) {
@@ -202,6 +446,35 @@ extern "C" fn can_become_first_responder(_this: &Object, _sel: Sel) -> BOOL {
     YES
 }
 
+extern "C" fn become_first_responder(this: &Object, _sel: Sel) -> BOOL {
+    unsafe {
+        let accepted: BOOL = msg_send![super(this, class!(UIView)), becomeFirstResponder];
+        if accepted == YES {
+            notify_focus(this, true);
+        }
+        accepted
+    }
+}
+
+extern "C" fn resign_first_responder(this: &Object, _sel: Sel) -> BOOL {
+    unsafe {
+        let accepted: BOOL = msg_send![super(this, class!(UIView)), resignFirstResponder];
+        if accepted == YES {
+            notify_focus(this, false);
+        }
+        accepted
+    }
+}
+
+/// Forward a first-responder change to the EditorView.
+unsafe fn notify_focus(this: &Object, focused: bool) {
+    let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+    if !state_ptr.is_null() {
+        let editor_view = &mut *(state_ptr as *mut EditorView);
+        editor_view.on_focus_changed(focused);
+    }
+}
+
 // -- Touch handling ----------------------------------------------------------
 
 /// Extract the first touch point from an NSSet of UITouches, in view coordinates.
@@ -277,39 +550,468 @@ extern "C" fn has_text(_this: &Object, _sel: Sel) -> BOOL {
 }
 
 extern "C" fn insert_text(this: &Object, _sel: Sel, string: Id) {
+    unsafe {
+        if let Some(editor_view) = editor_state(this) {
+            editor_view.insert_text(&string_text(string));
+        }
+    }
+}
+
+extern "C" fn delete_backward(this: &Object, _sel: Sel) {
     unsafe {
         let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
         if state_ptr.is_null() {
             return;
         }
         let editor_view = &mut *(state_ptr as *mut EditorView);
+        editor_view.delete_backward();
+    }
+}
 
-        let utf8: *const i8 = msg_send![string, UTF8String];
-        if utf8.is_null() {
-            return;
+// -- UITextInput protocol ----------------------------------------------------
+// Positions and ranges are UTF-16 offsets into the host's document text.
+
+extern "C" fn text_in_range(this: &Object, _sel: Sel, range: Id) -> Id {
+    unsafe {
+        match (editor_state(this), range_offsets(range)) {
+            (Some(editor_view), Some((start, end))) => {
+                autoreleased_string(&editor_view.document_substring(start, end - start))
+            }
+            _ => NIL,
         }
-        let text = CStr::from_ptr(utf8).to_str().unwrap_or("");
+    }
+}
 
-        // Newline characters route through action callback
-        if text == "\n" || text == "\r" {
-            editor_view.on_action("insertNewline:");
-        } else if !text.is_empty() {
-            editor_view.on_text_input(text);
+extern "C" fn replace_range(this: &Object, _sel: Sel, range: Id, text: Id) {
+    unsafe {
+        let editor_and_range = (editor_state(this), range_offsets(range));
+        if let (Some(editor_view), Some((start, end))) = editor_and_range {
+            editor_view.replace_range(start, end - start, &string_text(text));
         }
     }
 }
 
-extern "C" fn delete_backward(this: &Object, _sel: Sel) {
+extern "C" fn selected_text_range(this: &Object, _sel: Sel) -> Id {
     unsafe {
-        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
-        if state_ptr.is_null() {
-            return;
+        match editor_state(this) {
+            Some(editor_view) => {
+                let (location, length) = editor_view.selected_range();
+                text_range(location, location + length)
+            }
+            None => NIL,
         }
-        let editor_view = &mut *(state_ptr as *mut EditorView);
-        editor_view.on_action("deleteBackward:");
     }
 }
 
+extern "C" fn set_selected_text_range(this: &Object, _sel: Sel, range: Id) {
+    unsafe {
+        let editor_and_range = (editor_state(this), range_offsets(range));
+        if let (Some(editor_view), Some((start, end))) = editor_and_range {
+            editor_view.select_range(start, end - start);
+        }
+    }
+}
+
+extern "C" fn marked_text_range(this: &Object, _sel: Sel) -> Id {
+    unsafe {
+        match editor_state(this).and_then(|v| v.marked_range()) {
+            Some((location, length)) => text_range(location, location + length),
+            None => NIL,
+        }
+    }
+}
+
+/// Marked text is always drawn underlined in the text color.
+extern "C" fn marked_text_style(_this: &Object, _sel: Sel) -> Id {
+    NIL
+}
+
+extern "C" fn set_marked_text_style(_this: &Object, _sel: Sel, _style: Id) {}
+
+extern "C" fn set_marked_text(this: &Object, _sel: Sel, text: Id, selected: ObjCRange) {
+    unsafe {
+        if let Some(editor_view) = editor_state(this) {
+            let text = if text == NIL { String::new() } else { string_text(text) };
+            editor_view.set_marked_text(&text, (selected.location, selected.length));
+        }
+    }
+}
+
+extern "C" fn unmark_text(this: &Object, _sel: Sel) {
+    unsafe {
+        if let Some(editor_view) = editor_state(this) {
+            editor_view.unmark_text();
+        }
+    }
+}
+
+extern "C" fn beginning_of_document(_this: &Object, _sel: Sel) -> Id {
+    unsafe { text_position(0) }
+}
+
+extern "C" fn end_of_document(this: &Object, _sel: Sel) -> Id {
+    unsafe { text_position(editor_state(this).map_or(0, |v| v.document_length())) }
+}
+
+extern "C" fn text_range_from_position(_this: &Object, _sel: Sel, from: Id, to: Id) -> Id {
+    unsafe {
+        match (position_offset(from), position_offset(to)) {
+            (Some(a), Some(b)) => text_range(a.min(b), a.max(b)),
+            _ => NIL,
+        }
+    }
+}
+
+extern "C" fn position_from_position(this: &Object, _sel: Sel, position: Id, offset: isize) -> Id {
+    unsafe {
+        let (Some(editor_view), Some(start)) = (editor_state(this), position_offset(position))
+        else {
+            return NIL;
+        };
+        match start.checked_add_signed(offset) {
+            Some(target) if target <= editor_view.document_length() => text_position(target),
+            _ => NIL,
+        }
+    }
+}
+
+/// Left/right move by characters; up/down move by rows through the
+/// drawn lines, keeping the x position.
+extern "C" fn position_in_direction(
+    this: &Object,
+    sel: Sel,
+    position: Id,
+    direction: i64,
+    offset: isize,
+) -> Id {
+    unsafe {
+        match direction {
+            UI_TEXT_LAYOUT_DIRECTION_RIGHT => position_from_position(this, sel, position, offset),
+            UI_TEXT_LAYOUT_DIRECTION_LEFT => position_from_position(this, sel, position, -offset),
+            UI_TEXT_LAYOUT_DIRECTION_UP | UI_TEXT_LAYOUT_DIRECTION_DOWN => {
+                let (Some(editor_view), Some(start)) =
+                    (editor_state(this), position_offset(position))
+                else {
+                    return NIL;
+                };
+                let Some((x, y, _, h)) = editor_view.position_rect(start) else {
+                    return NIL;
+                };
+                let rows = if direction == UI_TEXT_LAYOUT_DIRECTION_UP { -offset } else { offset };
+                let target_y = y + h / 2.0 + rows as f64 * h;
+                match editor_view.character_index_at(x, target_y) {
+                    Some(target) => text_position(target),
+                    None => NIL,
+                }
+            }
+            _ => NIL,
+        }
+    }
+}
+
+/// NSComparisonResult: -1 ascending, 0 same, 1 descending.
+extern "C" fn compare_position(_this: &Object, _sel: Sel, position: Id, other: Id) -> i64 {
+    unsafe {
+        match (position_offset(position), position_offset(other)) {
+            (Some(a), Some(b)) => match a.cmp(&b) {
+                std::cmp::Ordering::Less => -1,
+                std::cmp::Ordering::Equal => 0,
+                std::cmp::Ordering::Greater => 1,
+            },
+            _ => 0,
+        }
+    }
+}
+
+extern "C" fn offset_from_position(_this: &Object, _sel: Sel, from: Id, to: Id) -> isize {
+    unsafe {
+        match (position_offset(from), position_offset(to)) {
+            (Some(a), Some(b)) => b as isize - a as isize,
+            _ => 0,
+        }
+    }
+}
+
+extern "C" fn input_delegate(this: &Object, _sel: Sel) -> Id {
+    unsafe { *this.get_ivar(INPUT_DELEGATE_IVAR) }
+}
+
+/// The delegate is not retained, per the UITextInput contract.
+extern "C" fn set_input_delegate(this: &Object, _sel: Sel, delegate: Id) {
+    unsafe {
+        let this_mut = this as *const Object as *mut Object;
+        (*this_mut).set_ivar::<Id>(INPUT_DELEGATE_IVAR, delegate);
+    }
+}
+
+extern "C" fn tokenizer(this: &Object, _sel: Sel) -> Id {
+    unsafe {
+        let existing: Id = *this.get_ivar(TOKENIZER_IVAR);
+        if existing != NIL {
+            return existing;
+        }
+        let tokenizer: Id = msg_send![class!(UITextInputStringTokenizer), alloc];
+        let tokenizer: Id =
+            msg_send![tokenizer, initWithTextInput: this as *const Object as Id];
+        let this_mut = this as *const Object as *mut Object;
+        (*this_mut).set_ivar::<Id>(TOKENIZER_IVAR, tokenizer);
+        tokenizer
+    }
+}
+
+extern "C" fn position_within_range(_this: &Object, _sel: Sel, range: Id, direction: i64) -> Id {
+    unsafe {
+        match range_offsets(range) {
+            Some((start, _)) if is_backward(direction) => text_position(start),
+            Some((_, end)) => text_position(end),
+            None => NIL,
+        }
+    }
+}
+
+extern "C" fn character_range_by_extending(
+    this: &Object,
+    _sel: Sel,
+    position: Id,
+    direction: i64,
+) -> Id {
+    unsafe {
+        let (Some(editor_view), Some(offset)) = (editor_state(this), position_offset(position))
+        else {
+            return NIL;
+        };
+        if is_backward(direction) {
+            text_range(offset.saturating_sub(1), offset)
+        } else {
+            text_range(offset, (offset + 1).min(editor_view.document_length()))
+        }
+    }
+}
+
+/// NSWritingDirectionLeftToRight; the editor lays out left to right only.
+extern "C" fn base_writing_direction(_this: &Object, _sel: Sel, _pos: Id, _dir: i64) -> i64 {
+    0
+}
+
+extern "C" fn set_base_writing_direction(_this: &Object, _sel: Sel, _dir: i64, _range: Id) {}
+
+/// The first row of the range, or the caret when it is off screen.
+extern "C" fn first_rect_for_range(this: &Object, _sel: Sel, range: Id) -> ObjCRect {
+    unsafe {
+        let Some(editor_view) = editor_state(this) else {
+            return ObjCRect::new(0.0, 0.0, 0.0, 0.0);
+        };
+        let first = range_offsets(range)
+            .and_then(|(start, end)| editor_view.range_rects(start, end).into_iter().next())
+            .or_else(|| editor_view.caret_rect());
+        match first {
+            Some((x, y, w, h)) => ObjCRect::new(x, y, w, h),
+            None => ObjCRect::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+extern "C" fn caret_rect_for_position(this: &Object, _sel: Sel, position: Id) -> ObjCRect {
+    unsafe {
+        let rect = editor_state(this).and_then(|editor_view| {
+            position_offset(position)
+                .and_then(|offset| editor_view.position_rect(offset))
+                .or_else(|| editor_view.caret_rect())
+        });
+        match rect {
+            Some((x, y, w, h)) => ObjCRect::new(x, y, w, h),
+            None => ObjCRect::new(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+extern "C" fn selection_rects_for_range(this: &Object, _sel: Sel, range: Id) -> Id {
+    unsafe {
+        let array: Id = msg_send![class!(NSMutableArray), array];
+        let (Some(editor_view), Some((start, end))) = (editor_state(this), range_offsets(range))
+        else {
+            return array;
+        };
+        let rects = editor_view.range_rects(start, end);
+        let last = rects.len().saturating_sub(1);
+        let cls =
+            Class::get("HoneTextSelectionRect").expect("HoneTextSelectionRect not registered");
+        for (i, (x, y, w, h)) in rects.into_iter().enumerate() {
+            let item: Id = msg_send![cls, new];
+            (*item).set_ivar(RECT_IVAR, ObjCRect::new(x, y, w, h));
+            (*item).set_ivar::<BOOL>(CONTAINS_START_IVAR, if i == 0 { YES } else { NO });
+            (*item).set_ivar::<BOOL>(CONTAINS_END_IVAR, if i == last { YES } else { NO });
+            let _: () = msg_send![array, addObject: item];
+            let _: () = msg_send![item, release];
+        }
+        array
+    }
+}
+
+extern "C" fn closest_position_to_point(this: &Object, _sel: Sel, point: ObjCPoint) -> Id {
+    unsafe {
+        match editor_state(this).and_then(|v| v.character_index_at(point.x, point.y)) {
+            Some(offset) => text_position(offset),
+            None => NIL,
+        }
+    }
+}
+
+extern "C" fn closest_position_within_range(
+    this: &Object,
+    _sel: Sel,
+    point: ObjCPoint,
+    range: Id,
+) -> Id {
+    unsafe {
+        let offset = editor_state(this).and_then(|v| v.character_index_at(point.x, point.y));
+        match (offset, range_offsets(range)) {
+            (Some(offset), Some((start, end))) => text_position(offset.clamp(start, end)),
+            _ => NIL,
+        }
+    }
+}
+
+extern "C" fn character_range_at_point(this: &Object, _sel: Sel, point: ObjCPoint) -> Id {
+    unsafe {
+        let Some(editor_view) = editor_state(this) else {
+            return NIL;
+        };
+        match editor_view.character_index_at(point.x, point.y) {
+            Some(offset) => text_range(offset, (offset + 1).min(editor_view.document_length())),
+            None => NIL,
+        }
+    }
+}
+
+extern "C" fn dealloc(this: &Object, _sel: Sel) {
+    unsafe {
+        let tokenizer: Id = *this.get_ivar(TOKENIZER_IVAR);
+        if tokenizer != NIL {
+            let _: () = msg_send![tokenizer, release];
+        }
+        let _: () = msg_send![super(this, class!(UIView)), dealloc];
+    }
+}
+
+// -- UITextPosition / UITextRange / UITextSelectionRect subclasses -----------
+
+extern "C" fn text_range_start(this: &Object, _sel: Sel) -> Id {
+    unsafe { text_position(*this.get_ivar(START_IVAR)) }
+}
+
+extern "C" fn text_range_end(this: &Object, _sel: Sel) -> Id {
+    unsafe { text_position(*this.get_ivar(END_IVAR)) }
+}
+
+extern "C" fn text_range_is_empty(this: &Object, _sel: Sel) -> BOOL {
+    unsafe {
+        let start: usize = *this.get_ivar(START_IVAR);
+        let end: usize = *this.get_ivar(END_IVAR);
+        if start == end {
+            YES
+        } else {
+            NO
+        }
+    }
+}
+
+extern "C" fn selection_rect_rect(this: &Object, _sel: Sel) -> ObjCRect {
+    unsafe { *this.get_ivar(RECT_IVAR) }
+}
+
+/// NSWritingDirectionLeftToRight.
+extern "C" fn selection_rect_writing_direction(_this: &Object, _sel: Sel) -> i64 {
+    0
+}
+
+extern "C" fn selection_rect_contains_start(this: &Object, _sel: Sel) -> BOOL {
+    unsafe { *this.get_ivar(CONTAINS_START_IVAR) }
+}
+
+extern "C" fn selection_rect_contains_end(this: &Object, _sel: Sel) -> BOOL {
+    unsafe { *this.get_ivar(CONTAINS_END_IVAR) }
+}
+
+extern "C" fn selection_rect_is_vertical(_this: &Object, _sel: Sel) -> BOOL {
+    NO
+}
+
+// -- UITextInput helpers -----------------------------------------------------
+
+/// Whether a UITextDirection (layout or storage) points toward the start.
+fn is_backward(direction: i64) -> bool {
+    matches!(
+        direction,
+        UI_TEXT_LAYOUT_DIRECTION_LEFT
+            | UI_TEXT_LAYOUT_DIRECTION_UP
+            | UI_TEXT_STORAGE_DIRECTION_BACKWARD
+    )
+}
+
+/// The EditorView behind the `honeEditorState` ivar, if set.
+unsafe fn editor_state(this: &Object) -> Option<&'static mut EditorView> {
+    let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+    if state_ptr.is_null() {
+        None
+    } else {
+        Some(&mut *(state_ptr as *mut EditorView))
+    }
+}
+
+/// A new autoreleased HoneTextPosition at UTF-16 `offset`.
+unsafe fn text_position(offset: usize) -> Id {
+    let cls = Class::get("HoneTextPosition").expect("HoneTextPosition not registered");
+    let position: Id = msg_send![cls, new];
+    (*position).set_ivar(OFFSET_IVAR, offset);
+    msg_send![position, autorelease]
+}
+
+/// A new autoreleased HoneTextRange over UTF-16 `start..end`.
+unsafe fn text_range(start: usize, end: usize) -> Id {
+    let cls = Class::get("HoneTextRange").expect("HoneTextRange not registered");
+    let range: Id = msg_send![cls, new];
+    (*range).set_ivar(START_IVAR, start);
+    (*range).set_ivar(END_IVAR, end);
+    msg_send![range, autorelease]
+}
+
+/// Offset of a HoneTextPosition; `None` for nil or foreign positions.
+unsafe fn position_offset(position: Id) -> Option<usize> {
+    let cls = Class::get("HoneTextPosition")?;
+    if position == NIL {
+        return None;
+    }
+    let is_ours: BOOL = msg_send![position, isKindOfClass: cls];
+    (is_ours == YES).then(|| *(*position).get_ivar::<usize>(OFFSET_IVAR))
+}
+
+/// `(start, end)` offsets of a HoneTextRange; `None` for nil or foreign ranges.
+unsafe fn range_offsets(range: Id) -> Option<(usize, usize)> {
+    let cls = Class::get("HoneTextRange")?;
+    if range == NIL {
+        return None;
+    }
+    let is_ours: BOOL = msg_send![range, isKindOfClass: cls];
+    (is_ours == YES).then(|| (*(*range).get_ivar(START_IVAR), *(*range).get_ivar(END_IVAR)))
+}
+
+/// Contents of an NSString (or the plain text of an NSAttributedString).
+unsafe fn string_text(string: Id) -> String {
+    let attributed: BOOL = msg_send![string, isKindOfClass: class!(NSAttributedString)];
+    let plain: Id = if attributed == YES { msg_send![string, string] } else { string };
+    let utf8: *const i8 = msg_send![plain, UTF8String];
+    if utf8.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(utf8).to_str().unwrap_or("").to_string()
+}
+
+unsafe fn autoreleased_string(s: &str) -> Id {
+    let c_str = CString::new(s).unwrap_or_default();
+    msg_send![class!(NSString), stringWithUTF8String: c_str.as_ptr()]
+}
+
 // -- UITextInputTraits -------------------------------------------------------
 
 /// UIKeyboardTypeDefault = 0
@@ -384,6 +1086,37 @@ pub fn invalidate_view(uiview: Id) {
     }
 }
 
+/// Tell the keyboard the host is about to replace the document text, so it
+/// drops autocorrect and predictive state built on the old text.
+pub fn text_will_change(uiview: Id) {
+    notify_input_delegate(uiview, objc::sel!(textWillChange:));
+}
+
+pub fn text_did_change(uiview: Id) {
+    notify_input_delegate(uiview, objc::sel!(textDidChange:));
+}
+
+/// Tell the keyboard the host is about to move the selection.
+pub fn selection_will_change(uiview: Id) {
+    notify_input_delegate(uiview, objc::sel!(selectionWillChange:));
+}
+
+pub fn selection_did_change(uiview: Id) {
+    notify_input_delegate(uiview, objc::sel!(selectionDidChange:));
+}
+
+fn notify_input_delegate(uiview: Id, sel: Sel) {
+    if uiview == NIL {
+        return;
+    }
+    unsafe {
+        let delegate: Id = *(*uiview).get_ivar(INPUT_DELEGATE_IVAR);
+        if delegate != NIL {
+            let _: Id = msg_send![delegate, performSelector: sel withObject: uiview];
+        }
+    }
+}
+
 /// Update the ivar pointer (used if EditorView is moved/recreated).
 pub fn set_editor_state(uiview: Id, state: *mut EditorView) {
     if uiview != NIL {
@@ -7,16 +7,20 @@
 
 use std::ffi::{c_char, CStr, CString};
 
+use unicode_segmentation::UnicodeSegmentation;
+
 use gtk4::prelude::*;
 use gtk4::{Application, ApplicationWindow};
 
+use hone_editor_linux::ViewHandle;
 use hone_editor_linux::{
-    hone_editor_add_context_menu_item, hone_editor_begin_frame, hone_editor_create,
-    hone_editor_end_frame, hone_editor_measure_text, hone_editor_widget,
+    hone_editor_add_context_menu_item, hone_editor_begin_frame, hone_editor_create_handle,
+    hone_editor_end_frame, hone_editor_measure_text, hone_editor_point_to_position,
+    hone_editor_widget,
     hone_editor_render_line, hone_editor_set_action_callback, hone_editor_set_cursor,
     hone_editor_set_font, hone_editor_set_mouse_down_callback,
     hone_editor_set_scroll_callback, hone_editor_set_selection,
-    hone_editor_set_text_input_callback,
+    hone_editor_set_selection_full_line, hone_editor_set_text_input_callback,
 };
 
 // ── DemoEditor state ────────────────────────────────────────────
@@ -31,7 +35,7 @@ struct DemoEditor {
     sel_anchor: Option<(usize, usize)>,
     scroll_y: f64,
     view_height: f64,
-    editor_ptr: *mut u8,
+    editor: ViewHandle,
     char_width: f64,
     line_height: f64,
 }
@@ -218,7 +222,7 @@ fn validate_tokens_json(tokens_json: &str, orig_text: &str, curr_text: &str) ->
 static mut DEMO: Option<DemoEditor> = None;
 
 impl DemoEditor {
-    fn new(editor_ptr: *mut u8, char_width: f64, line_height: f64, view_height: f64) -> Self {
+    fn new(editor: ViewHandle, char_width: f64, line_height: f64, view_height: f64) -> Self {
         let content = initial_content();
         let lines: Vec<String> = content.iter().map(|(t, _)| t.clone()).collect();
         let line_origins = (0..lines.len()).collect();
@@ -231,7 +235,7 @@ impl DemoEditor {
             sel_anchor: None,
             scroll_y: 0.0,
             view_height,
-            editor_ptr,
+            editor,
             char_width,
             line_height,
         }
@@ -248,38 +252,13 @@ impl DemoEditor {
     }
 
     fn click_to_cursor(&mut self, x: f64, y: f64) {
-        let editor = self.editor_ptr as *mut hone_editor_linux::EditorView;
-        let gutter_w = self.gutter_width();
-
-        let line = ((y + self.scroll_y) / self.line_height).floor() as usize;
-        let line = line.min(self.lines.len().saturating_sub(1));
-
-        let text_x = x - gutter_w;
-        let col = if text_x <= 0.0 {
-            0
-        } else {
-            let line_str = &self.lines[line];
-            let mut best_col = 0;
-            let mut best_dist = text_x;
-            for (byte_idx, _) in line_str.char_indices() {
-                let end = byte_idx + line_str[byte_idx..].chars().next().unwrap().len_utf8();
-                let prefix = &line_str[..end];
-                let c_prefix = CString::new(prefix).unwrap_or_default();
-                let px = hone_editor_measure_text(editor, c_prefix.as_ptr());
-                let dist = (text_x - px).abs();
-                if dist < best_dist {
-                    best_dist = dist;
-                    best_col = end;
-                }
-                if px > text_x + self.char_width {
-                    break;
-                }
-            }
-            best_col
-        };
-
-        self.cursor_line = line;
-        self.cursor_col = col;
+        let editor = self.editor;
+        let (mut line, mut col) = (0, 0);
+        if !hone_editor_point_to_position(editor, x, y, &mut line, &mut col) {
+            return;
+        }
+        self.cursor_line = (line - 1).max(0) as usize;
+        self.cursor_col = col.max(0) as usize;
         self.sel_anchor = None;
     }
 
@@ -488,7 +467,7 @@ impl DemoEditor {
         if self.cursor_col > 0 {
             let line = &self.lines[self.cursor_line];
             self.cursor_col = line[..self.cursor_col]
-                .char_indices()
+                .grapheme_indices(true)
                 .next_back()
                 .map(|(i, _)| i)
                 .unwrap_or(0);
@@ -517,7 +496,7 @@ impl DemoEditor {
         if self.cursor_col < line_len {
             let line = &self.lines[self.cursor_line];
             self.cursor_col = line[self.cursor_col..]
-                .char_indices()
+                .grapheme_indices(true)
                 .nth(1)
                 .map(|(i, _)| self.cursor_col + i)
                 .unwrap_or(line_len);
@@ -641,7 +620,7 @@ impl DemoEditor {
     // ── Rendering ───────────────────────────────────────────────
 
     fn render(&self) {
-        let editor = self.editor_ptr as *mut hone_editor_linux::EditorView;
+        let editor = self.editor;
         let gutter_w = self.gutter_width();
 
         hone_editor_begin_frame(editor);
@@ -704,10 +683,12 @@ impl DemoEditor {
 
                     let y = line_idx as f64 * self.line_height - self.scroll_y;
                     let w = (x_end - x_start).max(0.0);
-                    if w > 0.0 {
+                    // Every row but the last also selects its newline
+                    let eol = line_idx < el;
+                    if w > 0.0 || eol {
                         rects.push(format!(
-                            r#"{{"x":{},"y":{},"w":{},"h":{}}}"#,
-                            x_start, y, w, self.line_height
+                            r#"{{"x":{},"y":{},"w":{},"h":{},"eol":{}}}"#,
+                            x_start, y, w, self.line_height, eol
                         ));
                     }
                 }
@@ -724,7 +705,7 @@ impl DemoEditor {
 // ── Callbacks ───────────────────────────────────────────────────
 
 extern "C" fn on_text_input(
-    _view: *mut hone_editor_linux::EditorView,
+    _view: ViewHandle,
     text: *const c_char,
 ) {
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
@@ -740,7 +721,7 @@ extern "C" fn on_text_input(
 }
 
 extern "C" fn on_action(
-    _view: *mut hone_editor_linux::EditorView,
+    _view: ViewHandle,
     selector: *const c_char,
 ) {
     let sel_str = unsafe { CStr::from_ptr(selector) }.to_str().unwrap_or("");
@@ -806,7 +787,7 @@ extern "C" fn on_action(
 }
 
 extern "C" fn on_mouse_down(
-    _view: *mut hone_editor_linux::EditorView,
+    _view: ViewHandle,
     x: f64,
     y: f64,
 ) {
@@ -819,7 +800,7 @@ extern "C" fn on_mouse_down(
 }
 
 extern "C" fn on_scroll(
-    _view: *mut hone_editor_linux::EditorView,
+    _view: ViewHandle,
     _dx: f64,
     dy: f64,
 ) {
@@ -844,10 +825,11 @@ fn main() {
         let view_width = 900.0;
         let view_height = 650.0;
 
-        let editor = hone_editor_create(view_width, view_height);
+        let editor = hone_editor_create_handle(view_width, view_height);
 
         let font_family = CString::new("monospace").unwrap();
         hone_editor_set_font(editor, font_family.as_ptr(), 14.0);
+        hone_editor_set_selection_full_line(editor, true);
 
         let m_char = CString::new("M").unwrap();
         let char_width = hone_editor_measure_text(editor, m_char.as_ptr());
@@ -855,7 +837,7 @@ fn main() {
 
         unsafe {
             DEMO = Some(DemoEditor::new(
-                editor as *mut u8,
+                editor,
                 char_width,
                 line_height,
                 view_height,
@@ -4,70 +4,274 @@
 //! to measure and draw text with per-token syntax coloring via Pango layouts.
 
 use pango::prelude::*;
-use serde::Deserialize;
-
-/// Token data from the TypeScript layer.
-#[derive(Debug, Deserialize)]
-pub struct RenderToken {
-    /// Start column (byte offset).
-    pub s: usize,
-    /// End column (byte offset).
-    pub e: usize,
-    /// Hex color string (e.g., "#569cd6").
-    pub c: String,
-    /// Font style: "normal", "italic", or "bold".
-    pub st: String,
-}
-
-/// A set of font variants (normal, bold, italic) with cached metrics.
-pub struct FontSet {
+use hone_editor_common::font_cache::FontCache;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[link(name = "fontconfig")]
+extern "C" {
+    fn FcConfigAppFontAddFile(config: *mut c_void, file: *const c_char) -> i32;
+    fn FcConfigAppFontClear(config: *mut c_void);
+    fn FcFreeTypeQuery(
+        file: *const c_char,
+        id: i32,
+        blanks: *mut c_void,
+        count: *mut i32,
+    ) -> *mut c_void;
+    fn FcPatternGetString(
+        pattern: *mut c_void,
+        object: *const c_char,
+        n: i32,
+        s: *mut *const c_char,
+    ) -> i32;
+    fn FcPatternDestroy(pattern: *mut c_void);
+}
+
+#[link(name = "pangoft2-1.0")]
+extern "C" {
+    fn pango_fc_font_map_config_changed(fontmap: *mut c_void);
+}
+
+pub use hone_editor_common::tokens::RenderToken;
+
+/// Font variants (normal, bold, italic descriptions) for one family and
+/// size, with the metrics measured from them. Shared by every view using
+/// that font.
+pub struct Fonts {
     pub normal: pango::FontDescription,
     pub bold: pango::FontDescription,
     pub italic: pango::FontDescription,
+    /// Family as requested; with the size and style, the key in `FONTS`.
+    family: String,
+    /// Weight (100–900) and slant of `normal`.
+    weight: u16,
+    slanted: bool,
+    char_width: f64,
+    ascent: f64,
+    descent: f64,
+}
+
+thread_local! {
+    /// Live `Fonts` by family and point size. Views are created on the GTK
+    /// thread; entries are weak, so fonts are freed with their last view.
+    static FONTS: RefCell<FontCache<Fonts>> = RefCell::new(FontCache::new());
+}
+
+thread_local! {
+    /// Fonts registered from memory: family names by the name passed to
+    /// `register_font`, and the temp files fontconfig reads them from.
+    static PRIVATE_FONTS: RefCell<PrivateFonts> = RefCell::new(PrivateFonts::default());
+}
+
+#[derive(Default)]
+struct PrivateFonts {
+    families: HashMap<String, String>,
+    files: Vec<PathBuf>,
+    /// Live `RegisteredFont`s.
+    live: usize,
+}
+
+/// A font registered with `register_font`. Fontconfig can only drop all
+/// application fonts at once, so they are removed (and their files
+/// deleted) when the last registration is dropped.
+pub struct RegisteredFont {
+    name: String,
+    family: String,
+}
+
+impl Drop for RegisteredFont {
+    fn drop(&mut self) {
+        PRIVATE_FONTS.with(|fonts| {
+            let mut fonts = fonts.borrow_mut();
+            if fonts.families.get(&self.name) == Some(&self.family) {
+                fonts.families.remove(&self.name);
+            }
+            fonts.live -= 1;
+            if fonts.live == 0 {
+                unsafe { FcConfigAppFontClear(std::ptr::null_mut()) };
+                for file in fonts.files.drain(..) {
+                    let _ = std::fs::remove_file(file);
+                }
+                font_map_changed();
+            }
+        });
+    }
+}
+
+/// Register a TrueType/OpenType font from `bytes` as a fontconfig
+/// application font and make it available to `FontSet::new` as `name` as
+/// well as its own family name. Fontconfig only loads fonts from files, so
+/// the bytes are written to a temp file first. Returns `None` if the bytes
+/// are not a font.
+pub fn register_font(name: &str, bytes: &[u8]) -> Option<RegisteredFont> {
+    static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "hone-font-{}-{}",
+        std::process::id(),
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&path, bytes).ok()?;
+    let family = CString::new(path.to_str()?)
+        .ok()
+        .and_then(|file| unsafe { add_font_file(&file) });
+    let Some(family) = family else {
+        let _ = std::fs::remove_file(&path);
+        return None;
+    };
+    font_map_changed();
+    PRIVATE_FONTS.with(|fonts| {
+        let mut fonts = fonts.borrow_mut();
+        fonts.families.insert(name.to_string(), family.clone());
+        fonts.files.push(path);
+        fonts.live += 1;
+    });
+    Some(RegisteredFont { name: name.to_string(), family })
+}
+
+/// Add `file` to fontconfig's application fonts; returns its family name.
+unsafe fn add_font_file(file: &CStr) -> Option<String> {
+    let mut count = 0;
+    let pattern = FcFreeTypeQuery(file.as_ptr(), 0, std::ptr::null_mut(), &mut count);
+    if pattern.is_null() {
+        return None;
+    }
+    let mut family = std::ptr::null();
+    let object = b"family\0".as_ptr() as *const c_char;
+    let found = FcPatternGetString(pattern, object, 0, &mut family) == 0;
+    let family = found.then(|| CStr::from_ptr(family).to_string_lossy().into_owned());
+    FcPatternDestroy(pattern);
+    if FcConfigAppFontAddFile(std::ptr::null_mut(), file.as_ptr()) == 0 {
+        return None;
+    }
+    family
+}
+
+/// Make Pango's default font map see a change to the application fonts.
+fn font_map_changed() {
+    let font_map = pangocairo::FontMap::default();
+    unsafe { pango_fc_font_map_config_changed(font_map.as_ptr() as *mut c_void) };
+}
+
+impl Fonts {
+    /// The `Fonts` another view already holds for `family` at `size` in
+    /// this weight and slant, or the result of `load`, registered for the
+    /// next view to reuse.
+    fn shared(
+        family: &str,
+        size: f64,
+        weight: u16,
+        italic: bool,
+        load: impl FnOnce() -> Fonts,
+    ) -> Rc<Fonts> {
+        FONTS.with(|fonts| fonts.borrow_mut().shared(family, size, weight, italic, load))
+    }
+
+    fn load(
+        family: &str,
+        size: f64,
+        weight: u16,
+        italic: bool,
+        pango_context: &pango::Context,
+    ) -> Fonts {
+        // A font registered from memory under this name wins over system fonts
+        let registered = PRIVATE_FONTS.with(|fonts| fonts.borrow().families.get(family).cloned());
+        let mut normal = pango::FontDescription::new();
+        normal.set_family(registered.as_deref().unwrap_or(family));
+        normal.set_size((size * pango::SCALE as f64) as i32);
+        normal.set_weight(pango_weight(weight));
+        normal.set_style(if italic { pango::Style::Italic } else { pango::Style::Normal });
+
+        let mut bold = normal.clone();
+        bold.set_weight(pango_weight(bold_weight(weight)));
+
+        let mut italic_desc = normal.clone();
+        italic_desc.set_style(pango::Style::Italic);
+
+        // Extract font metrics
+        let metrics = pango_context.metrics(Some(&normal), None);
+
+        Fonts {
+            family: family.to_string(),
+            // Width of '0', the CSS `ch` unit: the cell width for monospace
+            // fonts and a fair average for proportional ones
+            char_width: measure_text_width(pango_context, &normal, "0"),
+            ascent: metrics.ascent() as f64 / pango::SCALE as f64,
+            descent: metrics.descent() as f64 / pango::SCALE as f64,
+            normal,
+            bold,
+            italic: italic_desc,
+            weight,
+            slanted: italic,
+        }
+    }
+}
+
+/// A view's font: shared `Fonts` (the descriptions are reached through
+/// `Deref`), the view's own Pango context, whose antialiasing is set per
+/// view, and the metrics, which line spacing and a metrics override adjust.
+pub struct FontSet {
+    fonts: Rc<Fonts>,
     pub pango_context: pango::Context,
     pub char_width: f64,
     pub ascent: f64,
     pub descent: f64,
     pub line_height: f64,
+    /// Line height from font metrics alone, before any line spacing.
+    pub natural_line_height: f64,
+    /// Font-derived (char_width, ascent, natural_line_height), saved while a
+    /// metrics override is active.
+    saved_metrics: Option<(f64, f64, f64)>,
 }
 
-impl FontSet {
-    /// Create a new FontSet from a font family name and size.
-    pub fn new(family: &str, size: f64) -> Self {
-        let mut normal = pango::FontDescription::new();
-        normal.set_family(family);
-        normal.set_size((size * pango::SCALE as f64) as i32);
-        normal.set_weight(pango::Weight::Normal);
-        normal.set_style(pango::Style::Normal);
-
-        let mut bold = normal.clone();
-        bold.set_weight(pango::Weight::Bold);
+impl Deref for FontSet {
+    type Target = Fonts;
 
-        let mut italic = normal.clone();
-        italic.set_style(pango::Style::Italic);
+    fn deref(&self) -> &Fonts {
+        &self.fonts
+    }
+}
 
+impl FontSet {
+    /// Create a FontSet from a font family name, size, weight (100–900,
+    /// 400 is regular) and slant, sharing the fonts of any other view
+    /// already using them.
+    pub fn new(family: &str, size: f64, weight: u16, italic: bool) -> Self {
         // Create a Pango context from the default font map
         let font_map = pangocairo::FontMap::default();
         let pango_context = font_map.create_context();
+        let fonts = Fonts::shared(family, size, weight, italic, || {
+            Fonts::load(family, size, weight, italic, &pango_context)
+        });
+        Self::with_fonts(fonts, pango_context)
+    }
 
-        // Extract font metrics
-        let metrics = pango_context.metrics(Some(&normal), None);
-        let ascent = metrics.ascent() as f64 / pango::SCALE as f64;
-        let descent = metrics.descent() as f64 / pango::SCALE as f64;
-        let line_height = (ascent + descent).ceil();
-
-        // Measure 'M' for monospace character width
-        let char_width = measure_text_width(&pango_context, &normal, "M");
+    /// Same family and style at a new point size. The Pango context (and
+    /// its antialiasing options) is shared with `self`.
+    pub fn resized(&self, size: f64) -> Self {
+        let pango_context = self.pango_context.clone();
+        let (weight, italic) = (self.weight, self.slanted);
+        let fonts = Fonts::shared(&self.family, size, weight, italic, || {
+            Fonts::load(&self.family, size, weight, italic, &pango_context)
+        });
+        Self::with_fonts(fonts, pango_context)
+    }
 
+    fn with_fonts(fonts: Rc<Fonts>, pango_context: pango::Context) -> Self {
+        let line_height = (fonts.ascent + fonts.descent).ceil();
         FontSet {
-            normal,
-            bold,
-            italic,
             pango_context,
-            char_width,
-            ascent,
-            descent,
+            char_width: fonts.char_width,
+            ascent: fonts.ascent,
+            descent: fonts.descent,
             line_height,
+            natural_line_height: line_height,
+            saved_metrics: None,
+            fonts,
         }
     }
 
@@ -76,9 +280,67 @@ impl FontSet {
         if text.is_empty() {
             return 0.0;
         }
+        if self.saved_metrics.is_some() {
+            return text.chars().count() as f64 * self.char_width;
+        }
         measure_text_width(&self.pango_context, &self.normal, text)
     }
 
+    /// Whether every character of `text` draws as a real glyph, from the
+    /// view's font or the fontconfig fallback Pango picks, rather than a hex
+    /// box. Control characters are not drawn and always pass.
+    pub fn has_glyphs(&self, text: &str) -> bool {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        let layout = pango::Layout::new(&self.pango_context);
+        layout.set_font_description(Some(&self.normal));
+        layout.set_text(&text);
+        layout.unknown_glyphs_count() == 0
+    }
+
+    /// Scale `line_height` to `multiplier` times the font's natural height.
+    /// Glyph widths (and therefore `measure_text`) are unaffected.
+    pub fn set_line_spacing(&mut self, multiplier: f64) {
+        self.line_height = (self.natural_line_height * multiplier.max(0.5)).ceil();
+    }
+
+    /// Force fixed monospace metrics, for tests and headless runs where real
+    /// font loading is unavailable or varies. While set, `measure_text` is
+    /// `char_count * char_width`. `None` restores the font's own metrics.
+    pub fn set_metrics_override(&mut self, metrics: Option<(f64, f64, f64)>) {
+        match metrics {
+            Some((char_width, line_height, ascent)) => {
+                if self.saved_metrics.is_none() {
+                    self.saved_metrics =
+                        Some((self.char_width, self.ascent, self.natural_line_height));
+                }
+                self.char_width = char_width;
+                self.ascent = ascent;
+                self.natural_line_height = line_height;
+            }
+            None => {
+                if let Some((char_width, ascent, natural)) = self.saved_metrics.take() {
+                    self.char_width = char_width;
+                    self.ascent = ascent;
+                    self.natural_line_height = natural;
+                }
+            }
+        }
+        self.line_height = self.natural_line_height;
+    }
+
+    /// Space above the glyphs that centers them vertically in a spaced line.
+    pub fn half_leading(&self) -> f64 {
+        ((self.line_height - self.natural_line_height) / 2.0).floor()
+    }
+
+    /// Set the antialiasing used for text laid out with this font set.
+    pub fn set_antialias(&self, antialias: cairo::Antialias) {
+        if let Ok(mut options) = cairo::FontOptions::new() {
+            options.set_antialias(antialias);
+            pangocairo::functions::context_set_font_options(&self.pango_context, Some(&options));
+        }
+    }
+
     /// Get the font description for a given style string.
     pub fn font_desc_for_style(&self, style: &str) -> &pango::FontDescription {
         match style {
@@ -98,6 +360,27 @@ fn measure_text_width(ctx: &pango::Context, font_desc: &pango::FontDescription,
     width as f64
 }
 
+/// Weight for bold runs over a base of `weight`: at least bold, and
+/// heavier than the base.
+fn bold_weight(weight: u16) -> u16 {
+    (weight + 300).clamp(700, 900)
+}
+
+/// Pango weight for a CSS-style weight, rounded to the nearest named one.
+fn pango_weight(weight: u16) -> pango::Weight {
+    match weight {
+        ..=150 => pango::Weight::Thin,
+        ..=250 => pango::Weight::Ultralight,
+        ..=350 => pango::Weight::Light,
+        ..=450 => pango::Weight::Normal,
+        ..=550 => pango::Weight::Medium,
+        ..=650 => pango::Weight::Semibold,
+        ..=750 => pango::Weight::Bold,
+        ..=850 => pango::Weight::Ultrabold,
+        _ => pango::Weight::Heavy,
+    }
+}
+
 /// Parse a "#rrggbb" hex color string to (r, g, b) floats in [0, 1].
 pub fn parse_hex_color(hex: &str) -> (f64, f64, f64) {
     let hex = hex.trim_start_matches('#');
@@ -175,7 +458,7 @@ pub fn draw_line(
         // Set font style if not normal
         match token.st.as_str() {
             "bold" => {
-                let mut weight_attr = pango::AttrInt::new_weight(pango::Weight::Bold);
+                let mut weight_attr = pango::AttrInt::new_weight(font_set.bold.weight());
                 weight_attr.set_start_index(start);
                 weight_attr.set_end_index(end);
                 attr_list.insert(weight_attr);
@@ -188,11 +471,23 @@ pub fn draw_line(
             }
             _ => {}
         }
+
+        // Set font size if overridden
+        if let Some(size) = token.sz {
+            let mut size_attr = pango::AttrSize::new((size * pango::SCALE as f64) as i32);
+            size_attr.set_start_index(start);
+            size_attr.set_end_index(end);
+            attr_list.insert(size_attr);
+        }
     }
 
     layout.set_attributes(Some(&attr_list));
 
-    cr.move_to(x, y);
+    // Pango aligns all runs on one baseline, but places it at the tallest
+    // run's ascent. Pin it to the line font's ascent so sized runs never
+    // shift the line vertically.
+    let baseline = layout.baseline() as f64 / pango::SCALE as f64;
+    cr.move_to(x, y + font_set.ascent - baseline);
     pangocairo::functions::show_layout(cr, &layout);
 }
 
@@ -229,3 +524,16 @@ pub fn draw_text(
     cr.move_to(x, y);
     pangocairo::functions::show_layout(cr, &layout);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ten_views_share_one_fonts_object() {
+        let views: Vec<FontSet> =
+            (0..10).map(|_| FontSet::new("monospace", 13.0, 400, false)).collect();
+        assert!(views.iter().all(|v| Rc::ptr_eq(&v.fonts, &views[0].fonts)));
+        assert_eq!(Rc::strong_count(&views[0].fonts), 10);
+    }
+}
@@ -1,13 +1,22 @@
 //! GTK4 DrawingArea widget with event controllers for the Hone editor.
 //!
-//! Creates a DrawingArea that delegates drawing to EditorView::draw()
-//! and routes keyboard, mouse, and scroll events through callbacks.
+//! Creates a DrawingArea that delegates drawing to EditorView::paint()
+//! and routes keyboard, mouse, and scroll events through callbacks. Text
+//! input goes through a GtkIMMulticontext for dead keys and input methods.
 
 use gdk4::Key;
+use glib::translate::IntoGlib;
 use gtk4::prelude::*;
-use gtk4::{DrawingArea, EventControllerKey, EventControllerScroll, EventControllerScrollFlags, GestureClick};
+use gtk4::{
+    DrawingArea, EventControllerFocus, EventControllerKey, EventControllerMotion,
+    EventControllerScroll, EventControllerScrollFlags, GestureClick, IMMulticontext,
+    PropagationPhase,
+};
 
-use crate::editor_view::EditorView;
+use crate::editor_view::{
+    CursorKind, EditorView, KEY_MODIFIER_ALT, KEY_MODIFIER_COMMAND, KEY_MODIFIER_CONTROL,
+    KEY_MODIFIER_SHIFT,
+};
 
 /// Create a GTK4 DrawingArea widget wired to the given EditorView.
 ///
@@ -27,10 +36,18 @@ pub fn create_editor_widget(
     let cursor = gdk4::Cursor::from_name("text", None);
     area.set_cursor(cursor.as_ref());
 
+    // Dead keys, compose sequences and input methods commit through this
+    let im_context = IMMulticontext::new();
+    im_context.set_client_widget(Some(&area));
+    setup_im_handler(&im_context, state);
+
     setup_draw_handler(&area, state);
-    setup_key_handler(&area, state);
-    setup_click_handler(&area, state);
+    setup_host_key_handler(&area, state);
+    setup_key_handler(&area, &im_context, state);
+    setup_click_handler(&area, &im_context, state);
     setup_scroll_handler(&area, state);
+    setup_focus_handler(&area, &im_context, state);
+    setup_motion_handler(&area, state);
 
     // Convert to raw pointer — caller must ensure the widget stays alive
     let widget_obj = area.upcast::<gtk4::Widget>();
@@ -44,20 +61,162 @@ pub fn create_editor_widget(
     ptr
 }
 
-/// Set up the draw function that delegates to EditorView::draw().
+/// A `notify` handler on the global GtkSettings, disconnected on drop so
+/// it never outlives the EditorView it points at.
+pub struct SettingsWatch {
+    settings: gtk4::Settings,
+    handler: Option<glib::SignalHandlerId>,
+}
+
+impl Drop for SettingsWatch {
+    fn drop(&mut self) {
+        if let Some(handler) = self.handler.take() {
+            self.settings.disconnect(handler);
+        }
+    }
+}
+
+/// Whether GTK is set to prefer a dark theme.
+pub fn prefers_dark_theme() -> bool {
+    gtk4::Settings::default().is_some_and(|s| s.is_gtk_application_prefer_dark_theme())
+}
+
+/// Re-sync the view's theme whenever gtk-application-prefer-dark-theme changes.
+pub fn watch_dark_theme(state: *mut EditorView) -> Option<SettingsWatch> {
+    let settings = gtk4::Settings::default()?;
+    let state_ptr = state as usize;
+    let handler = settings.connect_gtk_application_prefer_dark_theme_notify(move |_| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        editor_view.sync_system_appearance();
+    });
+    Some(SettingsWatch { settings, handler: Some(handler) })
+}
+
+/// Whether the GTK theme is a high-contrast one (HighContrast or
+/// HighContrastInverse).
+pub fn prefers_high_contrast() -> bool {
+    gtk4::Settings::default()
+        .and_then(|s| s.gtk_theme_name())
+        .is_some_and(|name| name.contains("HighContrast"))
+}
+
+/// Re-sync the view's theme whenever gtk-theme-name changes, to follow
+/// high-contrast themes.
+pub fn watch_theme_name(state: *mut EditorView) -> Option<SettingsWatch> {
+    let settings = gtk4::Settings::default()?;
+    let state_ptr = state as usize;
+    let handler = settings.connect_gtk_theme_name_notify(move |_| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        editor_view.sync_system_appearance();
+    });
+    Some(SettingsWatch { settings, handler: Some(handler) })
+}
+
+/// Set up the draw function that delegates to EditorView::paint().
 fn setup_draw_handler(area: &DrawingArea, state: *mut EditorView) {
     let state_ptr = state as usize; // usize is Send + Copy
     area.set_draw_func(move |_area, cr, w, h| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        editor_view.paint(cr, w as f64, h as f64);
+    });
+}
+
+/// Report keyboard focus changes to the EditorView and the input method.
+/// Losing focus abandons a composition in progress.
+fn setup_focus_handler(area: &DrawingArea, im_context: &IMMulticontext, state: *mut EditorView) {
+    let controller = EventControllerFocus::new();
+    let state_ptr = state as usize;
+
+    let im = im_context.clone();
+    controller.connect_enter(move |_| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        update_im_cursor_location(&im, editor_view);
+        im.focus_in();
+        editor_view.on_focus_changed(true);
+    });
+    let im = im_context.clone();
+    controller.connect_leave(move |_| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        im.focus_out();
+        im.reset();
+        editor_view.set_marked_text("");
+        editor_view.on_focus_changed(false);
+    });
+
+    area.add_controller(controller);
+}
+
+/// Forward the input method's committed text and preedit to the EditorView.
+fn setup_im_handler(im_context: &IMMulticontext, state: *mut EditorView) {
+    let state_ptr = state as usize;
+
+    im_context.connect_commit(move |_, text| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        editor_view.insert_text(text);
+    });
+    im_context.connect_preedit_start(move |im| {
         let editor_view = unsafe { &*(state_ptr as *const EditorView) };
-        editor_view.draw(cr, w as f64, h as f64);
+        update_im_cursor_location(im, editor_view);
+    });
+    im_context.connect_preedit_changed(move |im| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        let (text, _, _) = im.preedit_string();
+        editor_view.set_marked_text(&text);
+    });
+    im_context.connect_preedit_end(move |_| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        editor_view.set_marked_text("");
+    });
+}
+
+/// Place the input method's candidate window at the caret.
+fn update_im_cursor_location(im_context: &IMMulticontext, editor_view: &EditorView) {
+    if let Some((x, y, w, h)) = editor_view.caret_rect() {
+        let rect = gdk4::Rectangle::new(x as i32, y as i32, w.ceil() as i32, h.ceil() as i32);
+        im_context.set_cursor_location(&rect);
+    }
+}
+
+/// Give the host first refusal on every key.
+///
+/// Runs in the capture phase, ahead of the key controller that feeds the
+/// input method, so the host sees printable keys too and can claim them
+/// before they are composed into text.
+fn setup_host_key_handler(area: &DrawingArea, state: *mut EditorView) {
+    let controller = EventControllerKey::new();
+    controller.set_propagation_phase(PropagationPhase::Capture);
+    let state_ptr = state as usize;
+
+    controller.connect_key_pressed(move |_controller, keyval, _keycode, modifier| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        let mut modifiers = 0;
+        for (mask, bit) in [
+            (gdk4::ModifierType::SHIFT_MASK, KEY_MODIFIER_SHIFT),
+            (gdk4::ModifierType::CONTROL_MASK, KEY_MODIFIER_CONTROL),
+            (gdk4::ModifierType::ALT_MASK, KEY_MODIFIER_ALT),
+            (gdk4::ModifierType::SUPER_MASK, KEY_MODIFIER_COMMAND),
+        ] {
+            if modifier.contains(mask) {
+                modifiers |= bit;
+            }
+        }
+        if editor_view.on_key(keyval.into_glib(), modifiers) {
+            glib::Propagation::Stop
+        } else {
+            glib::Propagation::Proceed
+        }
     });
+
+    area.add_controller(controller);
 }
 
 /// Set up keyboard event handling.
 ///
-/// Maps GTK key events to macOS-style selector names for cross-platform parity.
-fn setup_key_handler(area: &DrawingArea, state: *mut EditorView) {
+/// Key events pass through the input method first; keys it does not consume
+/// are mapped to macOS-style selector names for cross-platform parity.
+fn setup_key_handler(area: &DrawingArea, im_context: &IMMulticontext, state: *mut EditorView) {
     let controller = EventControllerKey::new();
+    controller.set_im_context(Some(im_context));
     let state_ptr = state as usize;
 
     controller.connect_key_pressed(move |_controller, keyval, _keycode, modifier| {
@@ -80,6 +239,14 @@ fn setup_key_handler(area: &DrawingArea, state: *mut EditorView) {
 
         // Navigation and editing keys
         let selector = match keyval {
+            Key::Left if ctrl && shift => "moveWordLeftAndModifySelection:",
+            Key::Right if ctrl && shift => "moveWordRightAndModifySelection:",
+            Key::Home if ctrl && shift => "moveToBeginningOfDocumentAndModifySelection:",
+            Key::End if ctrl && shift => "moveToEndOfDocumentAndModifySelection:",
+            Key::Left if ctrl => "moveWordLeft:",
+            Key::Right if ctrl => "moveWordRight:",
+            Key::Home if ctrl => "moveToBeginningOfDocument:",
+            Key::End if ctrl => "moveToEndOfDocument:",
             Key::Left if shift => "moveLeftAndModifySelection:",
             Key::Right if shift => "moveRightAndModifySelection:",
             Key::Up if shift => "moveUpAndModifySelection:",
@@ -92,26 +259,21 @@ fn setup_key_handler(area: &DrawingArea, state: *mut EditorView) {
             Key::Down => "moveDown:",
             Key::Home => "moveToBeginningOfLine:",
             Key::End => "moveToEndOfLine:",
+            Key::Page_Up if shift => "pageUpAndModifySelection:",
+            Key::Page_Down if shift => "pageDownAndModifySelection:",
+            Key::Page_Up => "pageUp:",
+            Key::Page_Down => "pageDown:",
             Key::BackSpace => "deleteBackward:",
             Key::Delete => "deleteForward:",
             Key::Return | Key::KP_Enter => "insertNewline:",
-            Key::Tab if shift => "insertBacktab:",
-            Key::Tab => "insertTab:",
-            Key::Escape => "cancelOperation:",
-            _ => {
-                // Try printable character input
-                if !ctrl {
-                    if let Some(ch) = keyval.to_unicode() {
-                        if !ch.is_control() {
-                            let mut buf = [0u8; 4];
-                            let s = ch.encode_utf8(&mut buf);
-                            editor_view.on_text_input(s);
-                            return glib::Propagation::Stop;
-                        }
-                    }
-                }
-                return glib::Propagation::Proceed;
+            // GTK reports Shift+Tab as ISO_Left_Tab
+            Key::Tab | Key::ISO_Left_Tab => {
+                editor_view.on_tab(shift || keyval == Key::ISO_Left_Tab);
+                return glib::Propagation::Stop;
             }
+            Key::Escape => "cancelOperation:",
+            // Text arrives only through the input method's commit signal
+            _ => return glib::Propagation::Proceed,
         };
 
         editor_view.on_action(selector);
@@ -122,14 +284,21 @@ fn setup_key_handler(area: &DrawingArea, state: *mut EditorView) {
 }
 
 /// Set up mouse click handling.
-fn setup_click_handler(area: &DrawingArea, state: *mut EditorView) {
+fn setup_click_handler(area: &DrawingArea, im_context: &IMMulticontext, state: *mut EditorView) {
     let gesture = GestureClick::new();
     gesture.set_button(1); // Left click only
     let state_ptr = state as usize;
+    let im = im_context.clone();
 
     gesture.connect_pressed(move |gesture, _n_press, x, y| {
         let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
-        editor_view.on_mouse_down(x, y);
+        // Clicking away abandons a composition in progress
+        im.reset();
+        editor_view.set_marked_text("");
+        let ctrl = gesture.current_event_state().contains(gdk4::ModifierType::CONTROL_MASK);
+        if !(ctrl && editor_view.on_link_click(x, y)) {
+            editor_view.on_mouse_down(x, y);
+        }
         // Grab focus on click
         let widget = gesture.widget();
         widget.grab_focus();
@@ -138,6 +307,28 @@ fn setup_click_handler(area: &DrawingArea, state: *mut EditorView) {
     area.add_controller(gesture);
 }
 
+/// Update the pointer shape on hover: arrow over the gutter, pointing hand
+/// over links, host cursor regions, I-beam elsewhere.
+fn setup_motion_handler(area: &DrawingArea, state: *mut EditorView) {
+    let controller = EventControllerMotion::new();
+    let state_ptr = state as usize;
+
+    controller.connect_motion(move |controller, x, y| {
+        let editor_view = unsafe { &*(state_ptr as *const EditorView) };
+        let name = match editor_view.cursor_at(x, y) {
+            CursorKind::Text => "text",
+            CursorKind::Arrow => "default",
+            CursorKind::Hand => "pointer",
+        };
+        let widget = controller.widget();
+        if widget.cursor().and_then(|c| c.name()).as_deref() != Some(name) {
+            widget.set_cursor_from_name(Some(name));
+        }
+    });
+
+    area.add_controller(controller);
+}
+
 /// Set up scroll (mouse wheel / touchpad) handling.
 fn setup_scroll_handler(area: &DrawingArea, state: *mut EditorView) {
     let controller = EventControllerScroll::new(
@@ -145,8 +336,13 @@ fn setup_scroll_handler(area: &DrawingArea, state: *mut EditorView) {
     );
     let state_ptr = state as usize;
 
-    controller.connect_scroll(move |_controller, dx, dy| {
+    controller.connect_scroll(move |controller, dx, dy| {
         let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        // Ctrl+wheel zooms instead of scrolling
+        if controller.current_event_state().contains(gdk4::ModifierType::CONTROL_MASK) {
+            editor_view.on_action(if dy < 0.0 { "zoomIn:" } else { "zoomOut:" });
+            return glib::Propagation::Stop;
+        }
         // Multiply by ~40 for reasonable scroll speed (GTK reports in "steps")
         editor_view.on_scroll(dx * 40.0, dy * 40.0);
         glib::Propagation::Stop
@@ -155,6 +351,21 @@ fn setup_scroll_handler(area: &DrawingArea, state: *mut EditorView) {
     area.add_controller(controller);
 }
 
+/// Call `EditorView::tick_flashes` on every frame clock tick until it
+/// reports that no flash decorations remain.
+pub fn start_flash_tick(ptr: *mut std::ffi::c_void, state: *mut EditorView) {
+    let state_ptr = state as usize;
+    let widget: gtk4::Widget = unsafe { glib::translate::from_glib_none(ptr as *mut _) };
+    widget.add_tick_callback(move |_, _| {
+        let editor_view = unsafe { &mut *(state_ptr as *mut EditorView) };
+        if editor_view.tick_flashes() {
+            glib::ControlFlow::Continue
+        } else {
+            glib::ControlFlow::Break
+        }
+    });
+}
+
 /// Invalidate the widget to trigger a redraw.
 pub fn invalidate_widget(ptr: *mut std::ffi::c_void) {
     if ptr.is_null() {
@@ -21,10 +21,10 @@ use cocoa::foundation::{NSAutoreleasePool, NSPoint, NSRect, NSSize, NSString};
 // Re-export the FFI functions by referencing them — this forces the linker to
 // include them from the staticlib/rlib.
 use hone_editor_macos::{
-    hone_editor_create, hone_editor_set_font, hone_editor_begin_frame,
+    hone_editor_create_handle, hone_editor_set_font, hone_editor_begin_frame,
     hone_editor_render_line, hone_editor_set_cursor, hone_editor_set_selection,
     hone_editor_end_frame, hone_editor_attach_to_view, hone_editor_measure_text,
-    hone_editor_destroy,
+    hone_editor_destroy_handle,
 };
 
 /// Sample TypeScript lines with token data (VS Code dark theme colors).
@@ -81,6 +81,13 @@ fn sample_lines() -> Vec<(&'static str, &'static str)> {
             "}",
             r##"[{"s":0,"e":1,"c":"#d4d4d4","st":"normal"}]"##,
         ),
+        ("", "[]"),
+        // Baseline check: a 14pt line with a trailing 10pt hint. The hint's
+        // glyphs should rest on the same baseline as the code, not float above it.
+        (
+            "const total = sum(items) : number",
+            r##"[{"s":0,"e":5,"c":"#569cd6","st":"normal"},{"s":6,"e":11,"c":"#4fc1ff","st":"normal"},{"s":12,"e":13,"c":"#d4d4d4","st":"normal"},{"s":14,"e":17,"c":"#dcdcaa","st":"normal"},{"s":17,"e":18,"c":"#d4d4d4","st":"normal"},{"s":18,"e":23,"c":"#9cdcfe","st":"normal"},{"s":23,"e":24,"c":"#d4d4d4","st":"normal"},{"s":25,"e":33,"c":"#808080","st":"italic","sz":10}]"##,
+        ),
     ]
 }
 
@@ -109,7 +116,7 @@ fn main() {
         window.setTitle_(title);
 
         // Create the editor view via FFI
-        let editor = hone_editor_create(800.0, 600.0);
+        let editor = hone_editor_create_handle(800.0, 600.0);
 
         // Set font
         let font_family = CString::new("Menlo").unwrap();
@@ -171,6 +178,6 @@ fn main() {
         app.run();
 
         // Cleanup (won't reach here until app quits)
-        hone_editor_destroy(editor);
+        hone_editor_destroy_handle(editor);
     }
 }
@@ -5,31 +5,89 @@
 //! endFrame the NSView is invalidated, and drawRect: calls draw() which
 //! paints everything via Core Graphics / Core Text.
 
+use hone_editor_common::columns::visual_column;
+use hone_editor_common::diagnostics::severity_index;
+use hone_editor_common::graphemes::grapheme_boundaries;
+use hone_editor_common::handles::ViewHandle;
+use hone_editor_common::hit_test::column_at_point;
+use hone_editor_common::selection::{
+    push_stacked, rounded_corners, selection_outlines, SelectionRegion,
+};
+use hone_editor_common::selectors::is_edit_selector;
+use hone_editor_common::tokens::{overlay_colors, slice_tokens};
+use hone_editor_common::whitespace::{indent_level_ends, trailing_whitespace_start};
+use hone_editor_common::words::{subword_selector, word_range_at};
 use cocoa::base::{id, nil};
 use cocoa::foundation::NSRect;
+use core_graphics::base::{
+    kCGBitmapByteOrder32Big, kCGImageAlphaLast, kCGImageAlphaPremultipliedLast,
+    kCGRenderingIntentDefault,
+};
+use core_graphics::color_space::CGColorSpace;
 use core_graphics::context::CGContext;
+use core_graphics::data_provider::CGDataProvider;
 use core_graphics::geometry::{CGPoint, CGRect, CGSize};
+use core_graphics::gradient::{CGGradient, CGGradientDrawingOptions};
+use core_graphics::image::CGImage;
 use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::ffi::{c_char, CString};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::text_renderer::{self, FontSet, RenderToken};
+use crate::theme::{self, Theme};
 use crate::view;
 
 // ── Callback types ──────────────────────────────────────────────
 
 /// Called when the user types printable text. `text` is a null-terminated UTF-8 C string.
-pub type TextInputCallback = extern "C" fn(view: *mut EditorView, text: *const c_char);
+pub type TextInputCallback = extern "C" fn(view: ViewHandle, text: *const c_char);
 
 /// Called when a macOS action selector fires (arrow keys, delete, enter, etc.).
 /// `selector` is the selector name as a null-terminated UTF-8 C string (e.g. "moveLeft:").
-pub type ActionCallback = extern "C" fn(view: *mut EditorView, selector: *const c_char);
+pub type ActionCallback = extern "C" fn(view: ViewHandle, selector: *const c_char);
 
 /// Called when the user clicks in the editor view. `x` and `y` are in view coordinates.
-pub type MouseDownCallback = extern "C" fn(view: *mut EditorView, x: f64, y: f64);
+pub type MouseDownCallback = extern "C" fn(view: ViewHandle, x: f64, y: f64);
 
 /// Called when the user scrolls. `dx`/`dy` are pixel deltas (dy positive = scroll down).
-pub type ScrollCallback = extern "C" fn(view: *mut EditorView, dx: f64, dy: f64);
+pub type ScrollCallback = extern "C" fn(view: ViewHandle, dx: f64, dy: f64);
+
+/// Called after the view changes its own scroll offset (wheel in content mode,
+/// reveal, center) with the final offsets. Never fired for host `scroll` calls.
+pub type DidScrollCallback = extern "C" fn(view: ViewHandle, offset_x: f64, offset_y: f64);
+
+/// Called after each painted frame with the time spent rendering it, in milliseconds.
+pub type FrameCallback = extern "C" fn(view: ViewHandle, elapsed_ms: f64);
+
+/// Called when font metrics change (font, size, line spacing, or metrics
+/// override), so the host can re-lay-out without polling.
+pub type MetricsCallback =
+    extern "C" fn(view: ViewHandle, char_width: f64, line_height: f64, ascent: f64);
+
+/// Called for every key press before default handling; return true to consume
+/// the event. `keycode` is the NSEvent keyCode; `modifiers` is a mask of the `KEY_MODIFIER_*` bits.
+pub type KeyCallback = extern "C" fn(view: ViewHandle, keycode: u32, modifiers: u32) -> bool;
+
+/// Modifier bits passed to `KeyCallback`. `KEY_MODIFIER_COMMAND` is Cmd on
+/// macOS and the Super/Windows key elsewhere.
+pub const KEY_MODIFIER_SHIFT: u32 = 1 << 0;
+pub const KEY_MODIFIER_CONTROL: u32 = 1 << 1;
+pub const KEY_MODIFIER_ALT: u32 = 1 << 2;
+pub const KEY_MODIFIER_COMMAND: u32 = 1 << 3;
+
+/// Called after the color theme changes (a preset or the system appearance),
+/// so the host can recolor tokens. `appearance` is "dark", "light",
+/// "high-contrast-dark" or "high-contrast-light"; high-contrast themes
+/// already draw tokens in the plain text color.
+pub type ThemeChangedCallback = extern "C" fn(view: ViewHandle, appearance: *const c_char);
+
+/// Called when the view gains (`focused` = true) or loses keyboard focus.
+pub type FocusCallback = extern "C" fn(view: ViewHandle, focused: bool);
 
 /// A custom context menu item added by the host application.
 pub struct ContextMenuItem {
@@ -40,18 +98,35 @@ pub struct ContextMenuItem {
 // ── Data structures ──────────────────────────────────────────────
 
 #[derive(Debug, Deserialize)]
-pub struct SelectionRegion {
+pub struct CursorData {
     pub x: f64,
     pub y: f64,
-    pub w: f64,
-    pub h: f64,
+    /// 0 = line, 1 = block, 2 = underline. Set per cursor, so a block primary
+    /// can be drawn alongside line secondaries. Missing means line.
+    #[serde(default)]
+    pub style: i32,
 }
 
+/// A collaborator's caret from `set_remote_cursors`.
 #[derive(Debug, Deserialize)]
-pub struct CursorData {
+pub struct RemoteCursor {
     pub x: f64,
     pub y: f64,
-    pub style: i32,
+    /// "#rrggbb" for the caret and its name tag.
+    pub color: String,
+    /// Name drawn in a tag above the caret; empty for none.
+    #[serde(default)]
+    pub label: String,
+}
+
+/// A vertical band from `set_region_shading`, spanning `start_y..end_y`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadedRegion {
+    pub start_y: f64,
+    pub end_y: f64,
+    /// "#rrggbb", drawn faint.
+    pub color: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -65,11 +140,17 @@ pub struct DecorationOverlay {
     pub kind: String,
 }
 
+#[derive(Deserialize)]
 struct LineRenderData {
     line_number: i32,
     text: String,
+    #[serde(default)]
     tokens: Vec<RenderToken>,
     y_offset: f64,
+    /// Row height for lines taller than the font's (inline diffs, widgets).
+    /// `None` uses `line_height`.
+    #[serde(default)]
+    height: Option<f64>,
 }
 
 struct GhostTextData {
@@ -79,12 +160,202 @@ struct GhostTextData {
     color: (f64, f64, f64),
 }
 
+/// A decoration from `flash_decoration` that fades out over `duration`.
+struct FlashDecoration {
+    decor: DecorationOverlay,
+    start: Instant,
+    duration: Duration,
+}
+
+impl FlashDecoration {
+    /// Opacity at `now`: 1 when flashed, falling linearly to 0.
+    fn alpha(&self, now: Instant) -> f64 {
+        let t = now.duration_since(self.start).as_secs_f64() / self.duration.as_secs_f64();
+        (1.0 - t).clamp(0.0, 1.0)
+    }
+}
+
+/// A collapsed-region badge drawn after column `after_col` of `line`.
+struct FoldPlaceholder {
+    line: i32,
+    after_col: usize,
+}
+
+/// A scope header pinned to the top of the viewport (sticky scroll).
+#[derive(Deserialize)]
+struct StickyLine {
+    line_number: i32,
+    text: String,
+    #[serde(default)]
+    tokens: Vec<RenderToken>,
+}
+
+/// Caret and selection summary from `status_info`, for a status bar's
+/// "Ln 3, Col 12 (5 selected)".
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StatusInfo {
+    /// 1-based line of the primary caret; 0 when no caret is set.
+    pub line: i32,
+    /// 1-based display column of the caret, tabs expanded and wide
+    /// characters counted as two cells.
+    pub column: i32,
+    /// Characters in the logical selection, each line break counting one.
+    pub selection_chars: i32,
+    /// Lines the logical selection touches; 0 with no selection.
+    pub selection_lines: i32,
+}
+
+/// One document line from `set_content`; its line number is its index + 1.
+#[derive(Deserialize)]
+struct ContentLine {
+    text: String,
+    #[serde(default)]
+    tokens: Vec<RenderToken>,
+}
+
+/// A range from `set_selection_logical`: columns are character offsets into
+/// the rendered lines and the end is exclusive.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogicalSelection {
+    start_line: i32,
+    start_col: usize,
+    end_line: i32,
+    end_col: usize,
+    #[serde(default)]
+    color: Option<String>,
+}
+
+/// A recolored span from `set_color_overrides`: columns are character
+/// offsets into the rendered line and the end is exclusive.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ColorOverride {
+    line: i32,
+    start_col: usize,
+    end_col: usize,
+    color: String,
+}
+
+/// A link range from `set_links`: columns are character offsets into the
+/// rendered line.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LinkSpec {
+    line: i32,
+    start_col: usize,
+    end_col: usize,
+    url: String,
+}
+
+/// A diagnostic range from `set_diagnostics`: columns are character offsets
+/// into the rendered line and `severity` is "error", "warning" or "info".
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiagnosticSpec {
+    line: i32,
+    start_col: usize,
+    end_col: usize,
+    severity: String,
+}
+
+/// A bitmap from `render_image`, drawn at `(x, y)` one point per pixel.
+struct InlineImage {
+    x: f64,
+    y: f64,
+    image: CGImage,
+}
+
+/// A label from `render_caret_label`, drawn just above the caret at `(x, y)`.
+struct CaretLabel {
+    x: f64,
+    y: f64,
+    text: String,
+    bg: (f64, f64, f64),
+    fg: (f64, f64, f64),
+}
+
+/// A card from `render_hover_card`, rows already wrapped, top-left at `(x, y)`.
+struct HoverCard {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    rows: Vec<(String, Vec<RenderToken>)>,
+}
+
+/// One line of a hover card as sent by the host.
+#[derive(Deserialize)]
+struct HoverLine {
+    text: String,
+    #[serde(default)]
+    tokens: Vec<RenderToken>,
+}
+
+/// A list from `render_completion_popup`, top-left at `(x, y)`.
+struct CompletionPopup {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    items: Vec<CompletionItem>,
+    selected: Option<usize>,
+    // Offsets of the label and detail columns from `x`
+    label_x: f64,
+    detail_x: f64,
+}
+
+/// One completion item as sent by the host.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionItem {
+    label: String,
+    #[serde(default)]
+    detail: String,
+    /// Kind glyph drawn before the label; empty for none.
+    #[serde(default)]
+    icon: String,
+    /// "#rrggbb" for the glyph; defaults to the gutter foreground.
+    #[serde(default)]
+    icon_color: Option<String>,
+}
+
+/// A link resolved to view coordinates for hit testing.
+struct LinkRegion {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    url: String,
+}
+
+/// Mouse cursor shape shown over a region of the view.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorKind {
+    #[serde(alias = "ibeam")]
+    Text,
+    Arrow,
+    Hand,
+}
+
+/// A host-supplied cursor region from `set_cursor_regions`, in view coordinates.
+#[derive(Deserialize)]
+struct CursorRegion {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    cursor: CursorKind,
+}
+
 // ── EditorView ───────────────────────────────────────────────────
 
 /// Top-level editor view state.
 ///
-/// This is the object behind the opaque `*mut EditorView` pointer
-/// returned by `hone_editor_create()`.
+/// This is the object behind the opaque `ViewHandle`
+/// returned by `hone_editor_create_handle()`.
 pub struct EditorView {
     pub renderer: FontSet,
     nsview: id,
@@ -118,11 +389,154 @@ pub struct EditorView {
     default_text_color: (f64, f64, f64),
     selection_color: (f64, f64, f64, f64),
     cursor_color: (f64, f64, f64),
+
+    // Selection style: 0 = rectangles, 1 = rounded merged outline
+    selection_style: i32,
+
+    // Column rulers (character columns) and their color
+    rulers: Vec<u32>,
+    ruler_color: (f64, f64, f64, f64),
+
+    // Line the caret is on (-1 = unknown); skipped by trailing-whitespace highlighting
+    active_line: i32,
+    highlight_trailing_whitespace: bool,
+    trailing_whitespace_color: (f64, f64, f64),
+
+    // Caret mode: 0 = insert (line caret), 1 = overwrite (block caret).
+    // Supplies the style for cursors pushed with style -1.
+    caret_mode: i32,
+
+    // Text antialiasing: -1 = platform default, 0 = none, 1 = grayscale, 2 = subpixel
+    text_antialiasing: i32,
+
+    // Line height multiplier applied on top of the font's natural line height
+    line_spacing: f64,
+
+    // Fixed (char_width, line_height, ascent) for headless/testing, if set
+    metrics_override: Option<(f64, f64, f64)>,
+
+    // Total document lines (0 = unknown); bounds library-driven scrolling
+    line_count: i32,
+
+    // Lines of context kept between a revealed line and the viewport edge
+    scrolloff: i32,
+    frame_callback: Option<FrameCallback>,
+    selection_full_line: bool,
+    overscroll: i32,
+    // Whole document from `set_content`; `Some` while the view picks its own rows
+    content: Option<Vec<ContentLine>>,
+    // Rows laid out past each viewport edge in content mode
+    overscan: i32,
+    // Handle the host knows this view by; 0 until lib.rs registers it
+    pub(crate) handle: ViewHandle,
+    follow_system_appearance: bool,
+    theme_name: &'static str,
+    theme_changed_callback: Option<ThemeChangedCallback>,
+    key_callback: Option<KeyCallback>,
+    metrics_callback: Option<MetricsCallback>,
+    /// Horizontal scroll offset in pixels; line text slides under the gutter.
+    scroll_x: f64,
+    gutter_shadow: bool,
+    gutter_visible: bool,
+    // Mark wrapped continuation rows in the gutter instead of numbering them
+    wrap_markers: bool,
+    // Input-field mode: one centered row, no gutter, Enter submits
+    single_line: bool,
+    fold_placeholders: Vec<FoldPlaceholder>,
+    sticky_lines: Vec<StickyLine>,
+    grapheme_buf: Vec<u32>,
+    /// Host-supplied document text and caret (UTF-16 offset) for VoiceOver
+    /// and the text input client; the selection runs from the anchor to the caret.
+    accessible_text: Option<String>,
+    accessible_cursor: usize,
+    accessible_anchor: usize,
+    /// In-progress IME composition and its selected UTF-16 `(location, length)`.
+    marked_text: Option<String>,
+    marked_selection: (usize, usize),
+
+    // High contrast: `base_theme` is the preset shown when it is off
+    base_theme: &'static Theme,
+    force_high_contrast: bool,
+    system_high_contrast: bool,
+    flatten_tokens: bool,
+
+    // Focus: dim the content by `inactive_dim` (0 = off) while unfocused
+    focused: bool,
+    focus_callback: Option<FocusCallback>,
+    inactive_dim: f64,
+    hollow_caret_unfocused: bool,
+    links: Vec<LinkRegion>,
+    cursor_regions: Vec<CursorRegion>,
+    flashes: Vec<FlashDecoration>,
+    // When the visual bell last rang; cleared once it has faded
+    bell: Option<Instant>,
+    flash_timer: id,
+    placeholder: Option<(String, (f64, f64, f64))>,
+    caret_width: f64,
+    max_line_width: f64,
+    occurrences: Vec<SelectionRegion>,
+    occurrence_color: (f64, f64, f64),
+    // Tint gutter rows covered by a selection, with an optional color
+    gutter_selection: Option<Option<(f64, f64, f64)>>,
+    // Bitmaps from render_image, cleared on begin_frame
+    images: Vec<InlineImage>,
+    // Keep lines across begin_frame in persistent_lines instead
+    persistent_frame: bool,
+    // Persistent mode's lines by line number, pruned to the viewport
+    persistent_lines: BTreeMap<i32, LineRenderData>,
+    // Collaborators' carets and selections, cleared on begin_frame
+    remote_cursors: Vec<RemoteCursor>,
+    remote_selections: Vec<SelectionRegion>,
+    // Labels from render_caret_label, cleared on begin_frame
+    caret_labels: Vec<CaretLabel>,
+    // Hover card from render_hover_card, cleared on begin_frame
+    hover_card: Option<HoverCard>,
+    // Hover card fill and border, set by the theme
+    widget_bg_color: (f64, f64, f64),
+    widget_border_color: (f64, f64, f64),
+    // Completion list from render_completion_popup, cleared on begin_frame;
+    // its scroll survives frames so the host can re-render it freely
+    completion: Option<CompletionPopup>,
+    completion_open: bool,
+    completion_scroll: f64,
+    completion_selected: Option<usize>,
+    // Indent rainbow colors, cycled per indent level; empty when off
+    indent_rainbow: Vec<(f64, f64, f64)>,
+    tab_width: usize,
+    // Host-shaded vertical bands, cleared on begin_frame
+    shaded_regions: Vec<ShadedRegion>,
+    // Emit moveSubword… selectors and split subwords in word ranges
+    subword_navigation: bool,
+    // Recolored spans over line tokens, cleared on begin_frame
+    color_overrides: Vec<ColorOverride>,
+    // Fonts registered from memory; unregistered when the view is dropped
+    registered_fonts: Vec<text_renderer::RegisteredFont>,
+    // Ordered (start, end) line/char-column ends of the logical selection,
+    // kept for status_info; cleared on begin_frame
+    selection_ranges: Vec<((i32, usize), (i32, usize))>,
+    // Top and bottom colors of a vertical background gradient; None fills
+    // with background_color
+    background_gradient: Option<((f64, f64, f64), (f64, f64, f64))>,
+    // Draw the active line's number bold, with an optional color
+    active_line_gutter: Option<Option<(f64, f64, f64)>>,
+    // Read-only mode, with an optional text-area tint
+    read_only: Option<Option<(f64, f64, f64)>>,
+    did_scroll_callback: Option<DidScrollCallback>,
+    // Set while did_scroll_callback runs, so a scroll it triggers isn't re-reported
+    in_did_scroll: bool,
+    // End-of-file markers: 0 = none, 1 = a tilde on each row past the last
+    // line (vim style), 2 = one end symbol below the last line
+    eof_markers: i32,
+    // Squiggle colors ("#rrggbb") for error, warning and info diagnostics
+    diagnostic_colors: [String; 3],
+    // Gutter numbering: 0 = absolute, 1 = relative to the active line,
+    // 2 = relative with the active line's absolute number
+    line_number_mode: i32,
 }
 
 impl EditorView {
     pub fn new(width: f64, height: f64) -> Self {
-        let renderer = FontSet::new("Menlo", 14.0);
+        let renderer = FontSet::new("Menlo", 14.0, 400, false);
 
         EditorView {
             renderer,
@@ -150,6 +564,94 @@ impl EditorView {
             default_text_color: (0.843, 0.843, 0.843),   // #d7d7d7
             selection_color: (0.153, 0.306, 0.482, 0.4), // #264f7a @ 40%
             cursor_color: (0.918, 0.918, 0.918),          // #eaeaea
+            selection_style: 0,
+            rulers: Vec::new(),
+            ruler_color: (0.353, 0.353, 0.353, 0.5), // #5a5a5a @ 50%
+            active_line: -1,
+            highlight_trailing_whitespace: false,
+            trailing_whitespace_color: (1.0, 0.0, 0.0),
+            caret_mode: 0,
+            text_antialiasing: -1,
+            line_spacing: 1.0,
+            metrics_override: None,
+            line_count: 0,
+            scrolloff: 0,
+            frame_callback: None,
+            selection_full_line: false,
+            overscroll: 0,
+            content: None,
+            overscan: 2,
+            handle: 0,
+            follow_system_appearance: false,
+            theme_name: theme::VSCODE_DARK.name,
+            theme_changed_callback: None,
+            key_callback: None,
+            metrics_callback: None,
+            scroll_x: 0.0,
+            gutter_shadow: true,
+            gutter_visible: true,
+            wrap_markers: true,
+            single_line: false,
+            fold_placeholders: Vec::new(),
+            sticky_lines: Vec::new(),
+            grapheme_buf: Vec::new(),
+            accessible_text: None,
+            accessible_cursor: 0,
+            accessible_anchor: 0,
+            marked_text: None,
+            marked_selection: (0, 0),
+            base_theme: &theme::VSCODE_DARK,
+            force_high_contrast: false,
+            system_high_contrast: false,
+            flatten_tokens: false,
+            focused: false,
+            focus_callback: None,
+            inactive_dim: 0.0,
+            hollow_caret_unfocused: true,
+            links: Vec::new(),
+            cursor_regions: Vec::new(),
+            flashes: Vec::new(),
+            bell: None,
+            flash_timer: nil,
+            placeholder: None,
+            caret_width: 2.0,
+            max_line_width: 0.0,
+            occurrences: Vec::new(),
+            occurrence_color: (0.541, 0.541, 0.541), // #8a8a8a
+            gutter_selection: None,
+            images: Vec::new(),
+            persistent_frame: false,
+            persistent_lines: BTreeMap::new(),
+            remote_cursors: Vec::new(),
+            remote_selections: Vec::new(),
+            caret_labels: Vec::new(),
+            hover_card: None,
+            widget_bg_color: (0.145, 0.145, 0.149), // #252526
+            widget_border_color: (0.271, 0.271, 0.271), // #454545
+            completion: None,
+            completion_open: false,
+            completion_scroll: 0.0,
+            completion_selected: None,
+            indent_rainbow: Vec::new(),
+            tab_width: 4,
+            shaded_regions: Vec::new(),
+            subword_navigation: false,
+            color_overrides: Vec::new(),
+            registered_fonts: Vec::new(),
+            selection_ranges: Vec::new(),
+            background_gradient: None,
+            active_line_gutter: None,
+            read_only: None,
+            did_scroll_callback: None,
+            in_did_scroll: false,
+            eof_markers: 0,
+            diagnostic_colors: [
+                theme::VSCODE_DARK.error,
+                theme::VSCODE_DARK.warning,
+                theme::VSCODE_DARK.info,
+            ]
+            .map(String::from),
+            line_number_mode: 0,
         }
     }
 
@@ -174,33 +676,83 @@ impl EditorView {
 
     /// Called from the NSView's insertText: handler.
     pub fn on_text_input(&mut self, text: &str) {
+        if self.read_only.is_some() {
+            return;
+        }
         if let Some(cb) = self.text_input_callback {
             if let Ok(c_text) = CString::new(text) {
-                let self_ptr = self as *mut EditorView;
-                cb(self_ptr, c_text.as_ptr());
+                cb(self.handle, c_text.as_ptr());
             }
         }
     }
 
     /// Called from the NSView's doCommandBySelector: handler.
     pub fn on_action(&mut self, selector: &str) {
+        let selector = if self.single_line && selector == "insertNewline:" {
+            "submit:"
+        } else if self.subword_navigation {
+            subword_selector(selector)
+        } else {
+            selector
+        };
+        if self.read_only.is_some() && is_edit_selector(selector) {
+            return;
+        }
         if let Some(cb) = self.action_callback {
             if let Ok(c_sel) = CString::new(selector) {
-                let self_ptr = self as *mut EditorView;
-                cb(self_ptr, c_sel.as_ptr());
+                cb(self.handle, c_sel.as_ptr());
             }
         }
     }
 
+    /// Make word movement stop at camelCase humps and snake_case underscores:
+    /// the word-movement selectors are emitted as their `moveSubword…`
+    /// counterparts and `word_range_at` always splits subwords.
+    pub fn set_subword_navigation(&mut self, enabled: bool) {
+        self.subword_navigation = enabled;
+    }
+
+    /// Called for Tab / Shift+Tab. Emits "indent:" / "outdent:" when a
+    /// selection crosses a line break, "insertTab:" / "insertBacktab:"
+    /// otherwise, so multi-cursor selections within lines insert tabs.
+    pub fn on_tab(&mut self, backward: bool) {
+        let multi_line = self.selections.iter().any(|s| s.eol);
+        let selector = match (backward, multi_line) {
+            (false, false) => "insertTab:",
+            (true, false) => "insertBacktab:",
+            (false, true) => "indent:",
+            (true, true) => "outdent:",
+        };
+        self.on_action(selector);
+    }
+
     pub fn set_mouse_down_callback(&mut self, cb: MouseDownCallback) {
         self.mouse_down_callback = Some(cb);
     }
 
     /// Called from the NSView's mouseDown: handler.
     pub fn on_mouse_down(&mut self, x: f64, y: f64) {
+        let sticky_row = (y / self.renderer.line_height).floor();
+        if y >= 0.0 && (sticky_row as usize) < self.sticky_lines.len() {
+            let line = self.sticky_lines[sticky_row as usize].line_number;
+            self.on_action(&format!("revealLine:{line}"));
+            return;
+        }
+        if let Some(popup) = &self.completion {
+            if x >= popup.x && x < popup.x + popup.w && y >= popup.y && y < popup.y + popup.h {
+                let row = (y - popup.y + self.completion_scroll) / self.renderer.line_height;
+                if (row as usize) < popup.items.len() {
+                    self.on_action(&format!("acceptCompletion:{}", row as usize));
+                }
+                return;
+            }
+        }
+        if let Some(line) = self.fold_placeholder_at(x, y) {
+            self.on_action(&format!("unfold:{line}"));
+            return;
+        }
         if let Some(cb) = self.mouse_down_callback {
-            let self_ptr = self as *mut EditorView;
-            cb(self_ptr, x, y);
+            cb(self.handle, x, y);
         }
     }
 
@@ -208,11 +760,93 @@ impl EditorView {
         self.scroll_callback = Some(cb);
     }
 
+    pub fn set_did_scroll_callback(&mut self, cb: DidScrollCallback) {
+        self.did_scroll_callback = Some(cb);
+    }
+
+    pub fn set_frame_callback(&mut self, cb: FrameCallback) {
+        self.frame_callback = Some(cb);
+    }
+
+    pub fn set_metrics_callback(&mut self, cb: MetricsCallback) {
+        self.metrics_callback = Some(cb);
+    }
+
+    /// After a metric change, scroll so the line that was at the top of the
+    /// viewport stays there instead of jumping with the old pixel offset.
+    fn restore_top_line(&mut self, old_line_h: f64) {
+        let line_h = self.renderer.line_height;
+        if old_line_h > 0.0 && line_h != old_line_h {
+            self.scroll_to(self.scroll_offset / old_line_h * line_h);
+        }
+    }
+
+    fn notify_metrics(&mut self) {
+        // Widths measured with the old metrics are stale
+        self.max_line_width = 0.0;
+        self.fill_visible_lines();
+        if let Some(cb) = self.metrics_callback {
+            let (char_width, line_height, ascent) =
+                (self.renderer.char_width, self.renderer.line_height, self.renderer.ascent);
+            cb(self.handle, char_width, line_height, ascent);
+        }
+    }
+
+    pub fn set_key_callback(&mut self, cb: KeyCallback) {
+        self.key_callback = Some(cb);
+    }
+
+    /// Called from the NSView's keyDown: handler before any default handling.
+    /// Returns true if the host consumed the key.
+    pub fn on_key(&mut self, keycode: u32, modifiers: u32) -> bool {
+        match self.key_callback {
+            Some(cb) => cb(self.handle, keycode, modifiers),
+            None => false,
+        }
+    }
+
     /// Called from the NSView's scrollWheel: handler.
     pub fn on_scroll(&mut self, dx: f64, dy: f64) {
+        // An open completion list takes the wheel while it overflows
+        if let Some(popup) = &self.completion {
+            let max = popup.items.len() as f64 * self.renderer.line_height - popup.h;
+            if max > 0.0 {
+                self.completion_scroll = (self.completion_scroll - dy).clamp(0.0, max);
+                self.invalidate();
+                return;
+            }
+        }
+        // A single-line input never scrolls vertically
+        let mut dy = if self.single_line { 0.0 } else { dy };
+        // In content mode the view scrolls itself; the host hears the delta
+        // left after clamping
+        if self.content.is_some() {
+            let offset = self.clamp_scroll(self.scroll_offset - dy);
+            dy = self.scroll_offset - offset;
+            self.scroll_offset = offset;
+            self.fill_visible_lines();
+            self.invalidate();
+        }
+        self.notify_scroll(dx, dy);
+        if self.content.is_some() && dy != 0.0 {
+            self.notify_did_scroll();
+        }
+    }
+
+    fn notify_scroll(&mut self, dx: f64, dy: f64) {
         if let Some(cb) = self.scroll_callback {
-            let self_ptr = self as *mut EditorView;
-            cb(self_ptr, dx, dy);
+            cb(self.handle, dx, dy);
+        }
+    }
+
+    fn notify_did_scroll(&mut self) {
+        if self.in_did_scroll {
+            return;
+        }
+        if let Some(cb) = self.did_scroll_callback {
+            self.in_did_scroll = true;
+            cb(self.handle, self.scroll_x, self.scroll_offset);
+            self.in_did_scroll = false;
         }
     }
 
@@ -232,271 +866,2590 @@ impl EditorView {
     }
 
     pub fn set_font(&mut self, family: &str, size: f64) {
-        self.renderer = FontSet::new(family, size);
+        self.set_font_ex(family, size, 400, false);
+    }
+
+    /// Like `set_font`, with the base face's weight (100–900, 400 is
+    /// regular) and slant. Bold runs use a heavier weight and keep the slant.
+    pub fn set_font_ex(&mut self, family: &str, size: f64, weight: u16, italic: bool) {
+        let old_line_h = self.renderer.line_height;
+        self.renderer = FontSet::new(family, size, weight, italic);
+        self.renderer.set_metrics_override(self.metrics_override);
+        self.renderer.set_line_spacing(self.line_spacing);
         if self.nsview != nil {
             view::invalidate_view(self.nsview);
         }
+        self.restore_top_line(old_line_h);
+        self.notify_metrics();
     }
 
-    pub fn measure_text(&self, text: &str) -> f64 {
-        self.renderer.measure_text(text)
+    /// Register a TrueType/OpenType font from `bytes` so `set_font(name, ..)`
+    /// finds it, for fonts bundled with the app rather than installed. The
+    /// registration lasts until the view is destroyed. Returns false if the
+    /// bytes are not a font.
+    pub fn register_font_bytes(&mut self, name: &str, bytes: &[u8]) -> bool {
+        match text_renderer::register_font(name, bytes) {
+            Some(font) => {
+                self.registered_fonts.push(font);
+                true
+            }
+            None => false,
+        }
     }
 
-    // ── Frame buffer API ─────────────────────────────────────────
-
-    pub fn begin_frame(&mut self) {
-        self.frame_lines.clear();
-        self.cursor = None;
-        self.cursors.clear();
-        self.selections.clear();
-        self.decorations.clear();
-        self.ghost_text = None;
-        self.max_line_number = 0;
+    /// Change the point size but keep the family. Cheaper than `set_font`:
+    /// only size-dependent fonts and metrics are rebuilt.
+    pub fn set_font_size(&mut self, size: f64) {
+        let old_line_h = self.renderer.line_height;
+        self.renderer = self.renderer.resized(size);
+        self.renderer.set_metrics_override(self.metrics_override);
+        self.renderer.set_line_spacing(self.line_spacing);
+        if self.nsview != nil {
+            view::invalidate_view(self.nsview);
+        }
+        self.restore_top_line(old_line_h);
+        self.notify_metrics();
     }
 
-    pub fn render_line(&mut self, line_number: i32, text: &str, tokens_json: &str, y_offset: f64) {
-        let tokens: Vec<RenderToken> = serde_json::from_str(tokens_json).unwrap_or_default();
-        if line_number > self.max_line_number {
-            self.max_line_number = line_number;
+    /// Apply a built-in color preset by name. Returns false (and changes
+    /// nothing) for an unknown name. While high contrast is on, the preset
+    /// is remembered and shown once it turns off.
+    pub fn set_theme_preset(&mut self, name: &str) -> bool {
+        match theme::preset(name) {
+            Some(t) => {
+                self.base_theme = t;
+                if !self.high_contrast() {
+                    self.apply_theme(t);
+                }
+                true
+            }
+            None => false,
         }
-        self.frame_lines.push(LineRenderData {
-            line_number,
-            text: text.to_string(),
-            tokens,
-            y_offset,
-        });
     }
 
-    pub fn set_cursor(&mut self, x: f64, y: f64, style: i32) {
-        self.cursor = Some(CursorData { x, y, style });
+    /// Whether the high-contrast palette is in force, by the host or the OS.
+    fn high_contrast(&self) -> bool {
+        self.force_high_contrast || self.system_high_contrast
     }
 
-    pub fn set_cursors(&mut self, cursors_json: &str) {
-        self.cursors = serde_json::from_str(cursors_json).unwrap_or_default();
+    /// Force the high-contrast palette on, whatever the system setting.
+    pub fn set_force_high_contrast(&mut self, enabled: bool) {
+        self.force_high_contrast = enabled;
+        self.refresh_theme();
     }
 
-    pub fn set_selection(&mut self, regions_json: &str) {
-        self.selections = serde_json::from_str(regions_json).unwrap_or_default();
+    fn apply_theme(&mut self, t: &'static Theme) {
+        let hex = text_renderer::parse_hex_color;
+        self.background_color = hex(t.background);
+        self.gutter_bg_color = hex(t.gutter_background);
+        self.gutter_fg_color = hex(t.gutter_foreground);
+        self.default_text_color = hex(t.text);
+        let (r, g, b) = hex(t.selection);
+        self.selection_color = (r, g, b, t.selection_alpha);
+        self.occurrence_color = hex(t.occurrence);
+        self.diagnostic_colors = [t.error, t.warning, t.info].map(String::from);
+        self.widget_bg_color = hex(t.widget_background);
+        self.widget_border_color = hex(t.widget_border);
+        self.cursor_color = hex(t.cursor);
+        self.flatten_tokens = t.high_contrast;
+        let (r, g, b) = hex(t.ruler);
+        self.ruler_color = (r, g, b, self.ruler_color.3);
+        if self.theme_name != t.name {
+            self.theme_name = t.name;
+            self.on_theme_changed(t);
+        }
     }
 
-    pub fn scroll(&mut self, offset_y: f64) {
-        self.scroll_offset = offset_y;
+    /// Tokens to draw for a line: none under a high-contrast theme, so every
+    /// glyph takes the plain text color.
+    fn visible_tokens<'a>(&self, tokens: &'a [RenderToken]) -> &'a [RenderToken] {
+        if self.flatten_tokens {
+            &[]
+        } else {
+            tokens
+        }
     }
 
-    pub fn render_decorations(&mut self, decorations_json: &str) {
-        let mut decors: Vec<DecorationOverlay> =
-            serde_json::from_str(decorations_json).unwrap_or_default();
-        self.decorations.append(&mut decors);
+    /// Tokens to draw for `line`: its own, with any color overrides for it
+    /// laid on top.
+    fn line_tokens<'a>(&self, line: &'a LineRenderData) -> Cow<'a, [RenderToken]> {
+        let tokens = self.visible_tokens(&line.tokens);
+        if self.flatten_tokens {
+            return Cow::Borrowed(tokens);
+        }
+        // Tokens are UTF-16 columns here
+        let col = |c: usize| line.text.chars().take(c).map(char::len_utf16).sum::<usize>();
+        let spans: Vec<(usize, usize, &str)> = self
+            .color_overrides
+            .iter()
+            .filter(|o| o.line == line.line_number && o.end_col > o.start_col)
+            .map(|o| (col(o.start_col), col(o.end_col), o.color.as_str()))
+            .collect();
+        if spans.is_empty() {
+            return Cow::Borrowed(tokens);
+        }
+        Cow::Owned(overlay_colors(tokens, &spans))
     }
 
-    pub fn render_ghost_text(&mut self, text: &str, x: f64, y: f64, color: &str) {
-        self.ghost_text = Some(GhostTextData {
-            text: text.to_string(),
-            x,
-            y,
-            color: text_renderer::parse_hex_color(color),
-        });
+    pub fn set_focus_callback(&mut self, cb: FocusCallback) {
+        self.focus_callback = Some(cb);
     }
 
-    pub fn end_frame(&mut self) {
-        if self.nsview != nil {
-            view::invalidate_view(self.nsview);
+    /// Called from the native focus events. Repaints (for the inactive dim)
+    /// and notifies the host when the state actually changes.
+    pub fn on_focus_changed(&mut self, focused: bool) {
+        if self.focused == focused {
+            return;
         }
+        self.focused = focused;
+        if let Some(cb) = self.focus_callback {
+            cb(self.handle, focused);
+        }
+        self.invalidate();
     }
 
-    pub fn invalidate(&mut self) {
-        if self.nsview != nil {
-            view::invalidate_view(self.nsview);
+    /// Dim the content while the view is unfocused, so the active pane
+    /// stands out in split layouts. `alpha` is the opacity of a
+    /// background-colored overlay (0 turns it off, 1 hides the content).
+    pub fn set_inactive_dim(&mut self, alpha: f64) {
+        self.inactive_dim = alpha.clamp(0.0, 1.0);
+        self.invalidate();
+    }
+
+    /// Draw carets as a 1px outline instead of filled while the view is
+    /// unfocused (the default). Hosts can opt out to keep them solid.
+    pub fn set_hollow_caret_when_unfocused(&mut self, enabled: bool) {
+        self.hollow_caret_unfocused = enabled;
+        self.invalidate();
+    }
+
+    /// Present non-editable content (diffs, output): carets are hidden, text
+    /// input and IME composition are dropped, and selectors that edit
+    /// (`insert…`, `delete…`, `cut:`, `paste:`, `undo:`, ...) are swallowed.
+    /// Movement, selection, `copy:` and `selectAll:` still reach the host.
+    /// `tint` ("#rrggbb") is drawn faint over the text area; `None` for none.
+    pub fn set_read_only(&mut self, enabled: bool, tint: Option<&str>) {
+        self.read_only = enabled.then(|| tint.map(text_renderer::parse_hex_color));
+        if enabled {
+            self.marked_text = None;
         }
+        self.invalidate();
     }
 
-    pub fn attach_to_parent(&mut self, parent: *mut std::ffi::c_void) {
-        self.parent_view = parent;
-        if self.nsview != nil && !parent.is_null() {
-            unsafe {
-                let parent_view = parent as id;
-                let _: () = msg_send![parent_view, addSubview: self.nsview];
-                let bounds: NSRect = msg_send![parent_view, bounds];
-                let _: () = msg_send![self.nsview, setFrame: bounds];
-            }
+    pub fn set_theme_changed_callback(&mut self, cb: ThemeChangedCallback) {
+        self.theme_changed_callback = Some(cb);
+    }
+
+    fn on_theme_changed(&mut self, t: &'static Theme) {
+        if let Some(cb) = self.theme_changed_callback {
+            let appearance = CString::new(t.appearance()).unwrap();
+            cb(self.handle, appearance.as_ptr());
         }
     }
 
-    // ── Drawing ──────────────────────────────────────────────────
+    /// Track the OS light/dark setting, switching between the vscode-light
+    /// and vscode-dark presets now and whenever it changes.
+    pub fn set_follow_system_appearance(&mut self, enabled: bool) {
+        self.follow_system_appearance = enabled;
+        self.sync_system_appearance();
+    }
 
-    /// Compute gutter width matching the TS GutterRenderer formula:
-    /// max(2, digits) * charWidth + 36  (16px fold + 16px padding + 4px diff)
-    fn gutter_width(&self) -> f64 {
-        let digits = if self.max_line_number <= 0 {
-            2
+    /// Pick up the "Increase contrast" setting and, if following it, the
+    /// light/dark appearance. Called from the NSView when its effective
+    /// appearance changes.
+    pub fn sync_system_appearance(&mut self) {
+        self.system_high_contrast = view::prefers_high_contrast();
+        if self.follow_system_appearance {
+            let dark = view::is_dark_appearance(self.nsview);
+            self.base_theme = if dark { &theme::VSCODE_DARK } else { &theme::VSCODE_LIGHT };
+        }
+        self.refresh_theme();
+    }
+
+    /// Show the high-contrast variant of the base theme while high contrast
+    /// is on, and the base theme otherwise.
+    fn refresh_theme(&mut self) {
+        let t = if self.high_contrast() {
+            theme::high_contrast(self.base_theme.dark)
         } else {
-            let d = (self.max_line_number as f64).log10().floor() as i32 + 1;
-            d.max(2)
+            self.base_theme
         };
-        digits as f64 * self.renderer.char_width + 36.0
+        if t.name != self.theme_name {
+            self.apply_theme(t);
+        }
+        self.invalidate();
     }
 
-    /// Convert raw CGContextRef from drawRect: to a safe wrapper and draw.
-    ///
-    /// # Safety
-    /// Called from the NSView drawRect: handler with a valid CGContextRef.
-    pub fn draw(&self, raw_ctx: core_graphics::sys::CGContextRef, _dirty_rect: NSRect) {
-        let ctx = unsafe { CGContext::from_existing_context_ptr(raw_ctx) };
-        self.draw_with_context(&ctx);
+    pub fn set_text_antialiasing(&mut self, mode: i32) {
+        self.text_antialiasing = mode;
+        self.invalidate();
     }
 
-    fn draw_with_context(&self, ctx: &CGContext) {
-        let bounds = CGRect::new(
-            &CGPoint::new(0.0, 0.0),
-            &CGSize::new(self.width, self.height),
-        );
+    pub fn set_metrics_override(&mut self, metrics: Option<(f64, f64, f64)>) {
+        let old_line_h = self.renderer.line_height;
+        self.metrics_override = metrics;
+        self.renderer.set_metrics_override(metrics);
+        self.renderer.set_line_spacing(self.line_spacing);
+        self.invalidate();
+        self.restore_top_line(old_line_h);
+        self.notify_metrics();
+    }
 
-        // 1. Fill background
-        ctx.set_rgb_fill_color(
-            self.background_color.0,
-            self.background_color.1,
-            self.background_color.2,
-            1.0,
-        );
-        ctx.fill_rect(bounds);
+    pub fn set_line_spacing(&mut self, multiplier: f64) {
+        let old_line_h = self.renderer.line_height;
+        self.line_spacing = multiplier;
+        self.renderer.set_line_spacing(multiplier);
+        self.invalidate();
+        self.restore_top_line(old_line_h);
+        self.notify_metrics();
+    }
 
-        let gutter_w = self.gutter_width();
+    /// Current line height in points (font metrics times line spacing).
+    pub fn line_height(&self) -> f64 {
+        self.renderer.line_height
+    }
 
-        // 2. Draw gutter background
-        ctx.set_rgb_fill_color(
-            self.gutter_bg_color.0,
-            self.gutter_bg_color.1,
-            self.gutter_bg_color.2,
-            1.0,
-        );
-        let gutter_rect = CGRect::new(
-            &CGPoint::new(0.0, 0.0),
-            &CGSize::new(gutter_w, self.height),
-        );
-        ctx.fill_rect(gutter_rect);
+    /// Width of one character cell, as used for the gutter, rulers and block
+    /// caret: the advance of "0" (the metrics override's width while set).
+    pub fn char_width(&self) -> f64 {
+        self.renderer.char_width
+    }
 
-        // 3. Draw each buffered line
-        for line in &self.frame_lines {
-            // Draw line number in gutter (right-aligned)
-            let num_str = format!("{}", line.line_number);
-            let num_width = self.renderer.char_width * num_str.len() as f64;
-            // Right-align: gutter_w - 20px (fold+diff area) - num_width
-            let num_x = gutter_w - 20.0 - num_width;
+    /// Whether every character of `text` can be drawn with a real glyph,
+    /// from the view's font or the system fallback, instead of a missing
+    /// glyph box.
+    pub fn font_has_glyphs(&self, text: &str) -> bool {
+        self.renderer.has_glyphs(text)
+    }
 
-            text_renderer::draw_text(
-                ctx,
-                &num_str,
-                num_x,
-                line.y_offset,
-                &self.renderer.normal,
-                self.renderer.ascent,
-                self.gutter_fg_color,
-            );
+    pub fn measure_text(&self, text: &str) -> f64 {
+        self.renderer.measure_text(text)
+    }
 
-            // Draw text content with tokens starting at gutter_w
-            text_renderer::draw_line(
-                ctx,
-                &line.text,
-                &line.tokens,
-                gutter_w,
-                line.y_offset,
-                &self.renderer,
-                self.default_text_color,
-            );
+    /// Advance width of `text[start..end]` (byte offsets). Returns 0 for an
+    /// empty range or offsets that are out of bounds or not on a char boundary.
+    pub fn measure_range(&self, text: &str, start: usize, end: usize) -> f64 {
+        match text.get(start..end) {
+            Some(span) if !span.is_empty() => self.measure_text(span),
+            _ => 0.0,
         }
+    }
 
-        // 4. Draw decorations (underlines, backgrounds)
-        for decor in &self.decorations {
-            let (r, g, b) = text_renderer::parse_hex_color(&decor.color);
-            match decor.kind.as_str() {
-                "background" => {
-                    ctx.set_rgb_fill_color(r, g, b, 0.3);
-                    let rect = CGRect::new(
-                        &CGPoint::new(decor.x, decor.y),
-                        &CGSize::new(decor.w, decor.h),
-                    );
-                    ctx.fill_rect(rect);
-                }
-                "underline" => {
-                    ctx.set_rgb_stroke_color(r, g, b, 1.0);
-                    ctx.set_line_width(1.0);
-                    let y_bottom = decor.y + decor.h - 1.0;
-                    ctx.move_to_point(decor.x, y_bottom);
-                    ctx.add_line_to_point(decor.x + decor.w, y_bottom);
-                    ctx.stroke_path();
+    /// Byte offsets of the extended grapheme cluster boundaries in `text`,
+    /// from 0 through `text.len()`. Valid until the next call.
+    pub fn grapheme_boundaries(&mut self, text: &str) -> &[u32] {
+        self.grapheme_buf.clear();
+        self.grapheme_buf.extend(grapheme_boundaries(text).map(|i| i as u32));
+        &self.grapheme_buf
+    }
+
+    /// Line, column and selection size for a status bar, from this frame's
+    /// caret (`set_cursor`) and logical selection (`set_selection_logical`;
+    /// pixel regions from `set_selection` aren't counted). Line text comes
+    /// from `set_content` or the lines pushed this frame; a selected line
+    /// that is in neither counts only its line break.
+    pub fn status_info(&self) -> StatusInfo {
+        let mut info = StatusInfo::default();
+        // The caret's line and byte column, hit-tested at the middle of its row
+        let caret =
+            self.caret_rect().and_then(|(x, y, _, h)| self.point_to_position(x, y + h / 2.0));
+        if let Some((line, byte)) = caret {
+            info.line = line;
+            info.column = self.visual_column(self.line_text(line).unwrap_or(""), byte) as i32 + 1;
+        }
+        let chars = |line: i32| self.line_text(line).map_or(0, |t| t.chars().count());
+        for &((start_line, start_col), (end_line, end_col)) in &self.selection_ranges {
+            if (start_line, start_col) == (end_line, end_col) {
+                continue;
+            }
+            let selected = if start_line == end_line {
+                end_col - start_col
+            } else {
+                let middle: usize = (start_line + 1..end_line).map(|l| chars(l) + 1).sum();
+                chars(start_line).saturating_sub(start_col) + 1 + middle + end_col
+            };
+            info.selection_chars += selected as i32;
+            info.selection_lines += end_line - start_line + 1;
+        }
+        info
+    }
+
+    /// Text of line `line_number` from `set_content`, or as pushed this frame.
+    fn line_text(&self, line_number: i32) -> Option<&str> {
+        if let Some(content) = &self.content {
+            let index = usize::try_from(line_number - 1).ok()?;
+            return content.get(index).map(|l| l.text.as_str());
+        }
+        self.buffered_line(line_number).map(|l| l.text.as_str())
+    }
+
+    /// Display column of byte offset `byte_offset` in `text`: tabs expand to
+    /// the next tab stop and wide (CJK, fullwidth, emoji) graphemes take two
+    /// cells. For "Col N" status text and aligning on a character grid,
+    /// where byte and char offsets are wrong.
+    pub fn visual_column(&self, text: &str, byte_offset: usize) -> usize {
+        visual_column(text, byte_offset, self.tab_width)
+    }
+
+    /// Byte range of the word around byte column `col` in `text`, by Unicode
+    /// word segmentation. A caret just after a word picks that word;
+    /// otherwise the segment under `col` is used, so a run of punctuation or
+    /// whitespace selects as a unit. With `subword` (or subword navigation
+    /// on), identifiers also split at underscores and case changes (`fooBar`
+    /// gives `foo` and `Bar`).
+    pub fn word_range_at(&self, text: &str, col: usize, subword: bool) -> (usize, usize) {
+        word_range_at(text, col, subword || self.subword_navigation)
+    }
+
+    // ── Frame buffer API ─────────────────────────────────────────
+
+    pub fn begin_frame(&mut self) {
+        if !self.persistent_frame && self.content.is_none() {
+            self.frame_lines.clear();
+            self.max_line_number = 0;
+        }
+        self.cursor = None;
+        self.cursors.clear();
+        self.selections.clear();
+        self.selection_ranges.clear();
+        self.occurrences.clear();
+        self.decorations.clear();
+        self.ghost_text = None;
+        self.fold_placeholders.clear();
+        self.links.clear();
+        self.color_overrides.clear();
+        self.images.clear();
+        self.remote_cursors.clear();
+        self.remote_selections.clear();
+        self.caret_labels.clear();
+        self.hover_card = None;
+        self.shaded_regions.clear();
+        self.completion_open = self.completion.take().is_some();
+    }
+
+    pub fn render_line(&mut self, line_number: i32, text: &str, tokens_json: &str, y_offset: f64) {
+        self.push_line(line_number, text, tokens_json, y_offset, None);
+    }
+
+    /// Like `render_line`, but the row is `height` tall so it can reserve
+    /// space below the text. The host's `y_offset`s must account for it.
+    pub fn render_line_with_height(
+        &mut self,
+        line_number: i32,
+        text: &str,
+        tokens_json: &str,
+        y_offset: f64,
+        height: f64,
+    ) {
+        let height = (height > 0.0).then_some(height);
+        self.push_line(line_number, text, tokens_json, y_offset, height);
+    }
+
+    fn push_line(
+        &mut self,
+        line_number: i32,
+        text: &str,
+        tokens_json: &str,
+        y_offset: f64,
+        height: Option<f64>,
+    ) {
+        let tokens: Vec<RenderToken> = serde_json::from_str(tokens_json).unwrap_or_default();
+        let line = LineRenderData {
+            line_number,
+            text: text.to_string(),
+            tokens,
+            y_offset,
+            height,
+        };
+        self.store_line(line, false);
+    }
+
+    /// Queue a whole viewport in one call. `lines_json` is an array of
+    /// `{line_number, text, tokens, y_offset, height?}` objects, where
+    /// `tokens` is the same token array `render_line` takes as a string and
+    /// `height` is the row height `render_line_with_height` takes.
+    pub fn render_lines(&mut self, lines_json: &str) {
+        let lines: Vec<LineRenderData> = serde_json::from_str(lines_json).unwrap_or_default();
+        for line in lines {
+            self.store_line(line, false);
+        }
+    }
+
+    /// Add a line to the frame buffer. In persistent mode, or with `replace`,
+    /// a line with the same number is overwritten instead of duplicated.
+    fn store_line(&mut self, line: LineRenderData, replace: bool) {
+        if line.line_number > self.max_line_number {
+            self.max_line_number = line.line_number;
+        }
+        self.note_line_width(&line.text);
+        if self.persistent_frame && self.content.is_none() {
+            self.persistent_lines.insert(line.line_number, line);
+            return;
+        }
+        if replace {
+            let slot = self.frame_lines.iter_mut().find(|l| l.line_number == line.line_number);
+            if let Some(slot) = slot {
+                *slot = line;
+                return;
+            }
+        }
+        self.frame_lines.push(line);
+    }
+
+    /// Buffered rows: this frame's, or those kept in persistent mode.
+    fn lines(&self) -> impl Iterator<Item = &LineRenderData> {
+        self.frame_lines.iter().chain(self.persistent_lines.values())
+    }
+
+    /// The buffered row for `line_number` (the first one, for wrapped lines).
+    fn buffered_line(&self, line_number: i32) -> Option<&LineRenderData> {
+        self.persistent_lines
+            .get(&line_number)
+            .or_else(|| self.frame_lines.iter().find(|l| l.line_number == line_number))
+    }
+
+    /// Hand the whole document to the view, as a JSON array of
+    /// `{text, tokens?}` (line numbers are 1-based indexes), and let it pick
+    /// the rows to lay out from the scroll offset instead of the host
+    /// pushing them each frame. Also sets the line count. Frames keep these
+    /// rows; `begin_frame` only resets overlays. `null` hands rows back to
+    /// the host.
+    pub fn set_content(&mut self, lines_json: &str) {
+        let lines: Option<Vec<ContentLine>> = serde_json::from_str(lines_json).unwrap_or_default();
+        self.frame_lines.clear();
+        self.persistent_lines.clear();
+        self.max_line_number = 0;
+        if let Some(lines) = &lines {
+            self.line_count = lines.len() as i32;
+        }
+        self.content = lines;
+        self.fill_visible_lines();
+        self.invalidate();
+    }
+
+    /// Rows laid out above and below the viewport in `set_content` mode
+    /// (default 2). More rows cost layout and memory on every scroll but
+    /// leave fewer blank bands when the view moves before the rows are
+    /// rebuilt; 0 lays out only what is on screen.
+    pub fn set_overscan(&mut self, lines: i32) {
+        self.overscan = lines.max(0);
+        self.fill_visible_lines();
+        self.invalidate();
+    }
+
+    /// In content mode, rebuild the frame buffer from the document: the
+    /// rows in view plus `overscan` on each side, placed for the current
+    /// scroll offset.
+    fn fill_visible_lines(&mut self) {
+        let Some(content) = self.content.take() else {
+            return;
+        };
+        self.frame_lines.clear();
+        self.max_line_number = 0;
+        let line_h = self.renderer.line_height;
+        if line_h > 0.0 {
+            let overscan = self.overscan as f64;
+            let first = ((self.scroll_offset / line_h).floor() - overscan).max(0.0) as usize;
+            let last = ((self.scroll_offset + self.height) / line_h).ceil() + overscan;
+            for (i, line) in content.iter().enumerate().take(last.max(0.0) as usize).skip(first) {
+                let row = LineRenderData {
+                    line_number: i as i32 + 1,
+                    text: line.text.clone(),
+                    tokens: line.tokens.clone(),
+                    y_offset: i as f64 * line_h - self.scroll_offset,
+                    height: None,
+                };
+                self.store_line(row, false);
+            }
+        }
+        self.content = Some(content);
+    }
+
+    /// Keep buffered lines across frames so hosts can push only the lines
+    /// that changed (`render_line` or `update_line` replace by line number).
+    /// Cursors, selections and other overlays are still per-frame.
+    /// `end_frame` drops lines more than `overscan` rows out of view; call
+    /// `clear` after deleting lines.
+    pub fn set_persistent_frame(&mut self, enabled: bool) {
+        self.persistent_frame = enabled;
+        if enabled {
+            for line in std::mem::take(&mut self.frame_lines) {
+                self.persistent_lines.insert(line.line_number, line);
+            }
+        } else {
+            self.frame_lines.extend(std::mem::take(&mut self.persistent_lines).into_values());
+        }
+    }
+
+    /// Replace (or add) one buffered line outside a `begin_frame`/`end_frame`
+    /// cycle and redraw just its row. Meant for persistent mode, where the
+    /// rest of the frame is still valid.
+    pub fn update_line(&mut self, line_number: i32, text: &str, tokens_json: &str, y_offset: f64) {
+        let tokens: Vec<RenderToken> = serde_json::from_str(tokens_json).unwrap_or_default();
+        let old = self.buffered_line(line_number);
+        let old_row = old.map(|l| (l.y_offset, self.row_height(l)));
+        let height = old.and_then(|l| l.height);
+        let line = LineRenderData {
+            line_number,
+            text: text.to_string(),
+            tokens,
+            y_offset,
+            height,
+        };
+        let new_h = height.unwrap_or(self.renderer.line_height);
+        let (mut top, mut bottom) = (y_offset, y_offset + new_h);
+        if let Some((y, h)) = old_row {
+            top = top.min(y);
+            bottom = bottom.max(y + h);
+        }
+        // A new widest line number widens the gutter and shifts every row.
+        let gutter_w = self.gutter_width();
+        self.store_line(line, true);
+        if self.gutter_width() != gutter_w {
+            view::invalidate_view(self.nsview);
+        } else {
+            view::invalidate_rect(self.nsview, 0.0, top, self.width, bottom - top);
+        }
+    }
+
+    /// Draw a `width` x `height` bitmap at view point `(x, y)` this frame.
+    /// `rgba` is tightly packed, top row first, 8-bit straight (not
+    /// premultiplied) RGBA. Cleared on `begin_frame`; hosts reserve the
+    /// space with `render_line_with_height`.
+    pub fn render_image(&mut self, rgba: &[u8], width: u32, height: u32, x: f64, y: f64) {
+        let (w, h) = (width as usize, height as usize);
+        let provider = CGDataProvider::from_buffer(Arc::new(rgba.to_vec()));
+        let image = CGImage::new(
+            w,
+            h,
+            8,
+            32,
+            w * 4,
+            &CGColorSpace::create_device_rgb(),
+            kCGImageAlphaLast | kCGBitmapByteOrder32Big,
+            &provider,
+            true,
+            kCGRenderingIntentDefault,
+        );
+        self.images.push(InlineImage { x, y, image });
+    }
+
+    pub fn set_caret_mode(&mut self, mode: i32) {
+        self.caret_mode = mode;
+    }
+
+    /// Mark where the document ends: 0 = off, 1 = tildes on rows past the
+    /// last line, 2 = an end symbol below it. Uses `set_line_count`.
+    pub fn set_eof_markers(&mut self, mode: i32) {
+        self.eof_markers = mode;
+        self.invalidate();
+    }
+
+    /// Resolve style -1 ("use mode default") to the caret mode's shape.
+    /// Explicit styles (0, 1, 2) are kept as-is.
+    fn resolve_cursor_style(&self, style: i32) -> i32 {
+        match style {
+            -1 if self.caret_mode == 1 => 1,
+            -1 => 0,
+            s => s,
+        }
+    }
+
+    /// Width of the line caret and thickness of the underline caret, in
+    /// points (default 2). Clamped to at least 1.
+    pub fn set_caret_width(&mut self, px: f64) {
+        self.caret_width = if px.is_finite() { px.max(1.0) } else { 2.0 };
+        self.invalidate();
+    }
+
+    pub fn set_cursor(&mut self, x: f64, y: f64, style: i32) {
+        let style = self.resolve_cursor_style(style);
+        self.cursor = Some(CursorData { x, y, style });
+    }
+
+    /// The primary caret's rect in view coordinates as `(x, y, w, h)`: the
+    /// position from `set_cursor`, the drawn caret width (a cell for block
+    /// and underline carets) and the row height. `None` when no caret was
+    /// set this frame.
+    pub fn caret_rect(&self) -> Option<(f64, f64, f64, f64)> {
+        let c = self.cursor.as_ref()?;
+        let w = if c.style == 0 { self.caret_width } else { self.renderer.char_width };
+        let y = c.y + self.single_line_offset();
+        Some((c.x, y, w, self.row_height_at(c.y)))
+    }
+
+    /// Replace the multi-cursor list for this frame. `begin_frame` clears it;
+    /// calling this again within a frame overwrites rather than appends.
+    pub fn set_cursors(&mut self, cursors_json: &str) {
+        let mut cursors: Vec<CursorData> = serde_json::from_str(cursors_json).unwrap_or_default();
+        for c in &mut cursors {
+            c.style = self.resolve_cursor_style(c.style);
+        }
+        self.cursors = cursors;
+    }
+
+    pub fn set_selection(&mut self, regions_json: &str) {
+        self.selections = serde_json::from_str(regions_json).unwrap_or_default();
+        self.selection_ranges.clear();
+    }
+
+    /// Replace the selection with ranges (`[{startLine, startCol, endLine,
+    /// endCol, color?}]`, character columns, either end first) and measure
+    /// the regions here instead of in the host. Rows a range continues past
+    /// are marked `eol`, so empty lines inside it still show. The lines must
+    /// already be rendered this frame; rows outside them are skipped.
+    pub fn set_selection_logical(&mut self, ranges_json: &str) {
+        let ranges: Vec<LogicalSelection> = serde_json::from_str(ranges_json).unwrap_or_default();
+        let text_x = self.gutter_width() - self.scroll_x;
+        let mut regions = Vec::new();
+        self.selection_ranges.clear();
+        for range in ranges {
+            let (mut start, mut end) =
+                ((range.start_line, range.start_col), (range.end_line, range.end_col));
+            if end < start {
+                std::mem::swap(&mut start, &mut end);
+            }
+            self.selection_ranges.push((start, end));
+            let rows = start.0..=end.0;
+            for line in self.lines().filter(|l| rows.contains(&l.line_number)) {
+                let byte = |col: usize| {
+                    line.text.char_indices().nth(col).map_or(line.text.len(), |(i, _)| i)
+                };
+                let from = if line.line_number == start.0 { byte(start.1) } else { 0 };
+                let to = if line.line_number == end.0 { byte(end.1) } else { line.text.len() };
+                let eol = line.line_number < end.0;
+                let w = self.renderer.measure_text(&line.text[from..to.max(from)]);
+                if w <= 0.0 && !eol {
+                    continue;
+                }
+                let region = SelectionRegion {
+                    x: text_x + self.renderer.measure_text(&line.text[..from]),
+                    y: line.y_offset,
+                    w,
+                    h: self.row_height(line),
+                    color: range.color.clone(),
+                    eol,
+                };
+                push_stacked(&mut regions, region);
+            }
+        }
+        self.selections = regions;
+    }
+
+    /// Outline other occurrences of the selected text. Same geometry as
+    /// `set_selection`; a region's `color` overrides the theme occurrence
+    /// color. Drawn under the selection and cleared on `begin_frame`.
+    pub fn set_occurrence_highlights(&mut self, regions_json: &str) {
+        self.occurrences = serde_json::from_str(regions_json).unwrap_or_default();
+    }
+
+    pub fn set_occurrence_color(&mut self, color: &str) {
+        self.occurrence_color = text_renderer::parse_hex_color(color);
+    }
+
+    /// Override the squiggle color for one severity ("error", "warning" or
+    /// "info") until the next theme change. Returns false for an unknown
+    /// severity.
+    pub fn set_diagnostic_color(&mut self, severity: &str, color: &str) -> bool {
+        match severity_index(severity) {
+            Some(i) => {
+                self.diagnostic_colors[i] = color.to_string();
+                self.invalidate();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Tint the gutter behind lines touched by a selection region. `color`
+    /// is "#rrggbb" (drawn at the selection alpha); `None` uses the theme
+    /// selection color.
+    pub fn set_gutter_selection_highlight(&mut self, enabled: bool, color: Option<&str>) {
+        self.gutter_selection = enabled.then(|| color.map(text_renderer::parse_hex_color));
+    }
+
+    /// Shade vertical spans across the text area (`[{startY, endY, color}]`),
+    /// such as the block under a hovered fold chevron. Drawn faint under the
+    /// text; cleared on `begin_frame`.
+    pub fn set_region_shading(&mut self, regions_json: &str) {
+        self.shaded_regions = serde_json::from_str(regions_json).unwrap_or_default();
+    }
+
+    /// Collaborators' carets as `[{x, y, color, label}]`, drawn with a name
+    /// tag above each. Cleared on `begin_frame`.
+    pub fn set_remote_cursors(&mut self, cursors_json: &str) {
+        self.remote_cursors = serde_json::from_str(cursors_json).unwrap_or_default();
+    }
+
+    /// Collaborators' selections, same shape as `set_selection`; each
+    /// region's `color` is its owner's. Drawn over the local selection and
+    /// under the local caret. Cleared on `begin_frame`.
+    pub fn set_remote_selections(&mut self, regions_json: &str) {
+        self.remote_selections = serde_json::from_str(regions_json).unwrap_or_default();
+    }
+
+    /// Draw a rounded `text` label just above the caret at `(x, y)` this
+    /// frame (presence, active signature parameter). Colors are "#rrggbb".
+    pub fn render_caret_label(&mut self, x: f64, y: f64, text: &str, bg: &str, fg: &str) {
+        self.caret_labels.push(CaretLabel {
+            x,
+            y,
+            text: text.to_string(),
+            bg: text_renderer::parse_hex_color(bg),
+            fg: text_renderer::parse_hex_color(fg),
+        });
+    }
+
+    /// Show a bordered card of `lines_json` (`[{ text, tokens? }]`, tokens as
+    /// in `render_line`) below the caret row at `(x, y)`, wrapped to
+    /// `max_width`. It flips above the row when it would run off the bottom.
+    /// Drawn over everything else; cleared on `begin_frame`.
+    pub fn render_hover_card(&mut self, x: f64, y: f64, lines_json: &str, max_width: f64) {
+        let lines: Vec<HoverLine> = serde_json::from_str(lines_json).unwrap_or_default();
+        let text_max = (max_width - CARD_PADDING * 2.0).max(self.renderer.char_width);
+        let mut rows = Vec::new();
+        let mut text_w: f64 = 0.0;
+        for line in &lines {
+            for (start, end) in self.wrap_ranges(&line.text, text_max) {
+                let text = line.text[start..end].trim_end();
+                text_w = text_w.max(self.measure_text(text));
+                // Token columns are the UTF-16 offsets draw_line expects.
+                let col = |b: usize| line.text[..b].encode_utf16().count();
+                let tokens = slice_tokens(&line.tokens, col(start), col(end));
+                rows.push((text.to_string(), tokens));
+            }
+        }
+        if rows.is_empty() {
+            self.hover_card = None;
+            return;
+        }
+        let w = text_w + CARD_PADDING * 2.0;
+        let h = rows.len() as f64 * self.renderer.line_height + CARD_PADDING * 2.0;
+        let (x, y) = self.popup_origin(x, y, w, h);
+        self.hover_card = Some(HoverCard {
+            x,
+            y,
+            w,
+            h,
+            rows,
+        });
+    }
+
+    /// Byte ranges of `text` wrapped to `max_w`: after whitespace where
+    /// possible, otherwise between graphemes.
+    fn wrap_ranges(&self, text: &str, max_w: f64) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        let mut last_space = None;
+        for (i, g) in text.grapheme_indices(true) {
+            let end = i + g.len();
+            if i > start && self.measure_range(text, start, end) > max_w {
+                let brk = last_space.filter(|&b| b > start).unwrap_or(i);
+                ranges.push((start, brk));
+                start = brk;
+            }
+            if g.chars().all(char::is_whitespace) {
+                last_space = Some(end);
+            }
+        }
+        ranges.push((start, text.len()));
+        ranges
+    }
+
+    /// Show a completion list of `items_json` (`[{ label, detail?, icon?,
+    /// iconColor? }]`) below the caret row at `(x, y)`, flipped above it near
+    /// the bottom edge, with `selected_index` highlighted (negative for none).
+    /// At most `COMPLETION_MAX_ROWS` rows show; the rest scroll with the wheel,
+    /// and a newly selected item is scrolled into view. Clicking an item emits
+    /// `"acceptCompletion:<index>"`; arrow keys reach the host through the key
+    /// callback as usual. Drawn over everything else; cleared on `begin_frame`.
+    pub fn render_completion_popup(
+        &mut self,
+        x: f64,
+        y: f64,
+        items_json: &str,
+        selected_index: i32,
+    ) {
+        let items: Vec<CompletionItem> = serde_json::from_str(items_json).unwrap_or_default();
+        if items.is_empty() {
+            self.completion = None;
+            return;
+        }
+        let row_h = self.renderer.line_height;
+        let char_w = self.renderer.char_width;
+        let widest = |text: fn(&CompletionItem) -> &str| {
+            items.iter().map(|i| self.measure_text(text(i))).fold(0.0, f64::max)
+        };
+        let label_x = CARD_PADDING
+            + if items.iter().any(|i| !i.icon.is_empty()) { char_w * 2.0 } else { 0.0 };
+        let detail_x = label_x + widest(|i| &i.label) + char_w * 2.0;
+        let detail_w = widest(|i| &i.detail);
+        let w = if detail_w > 0.0 { detail_x + detail_w } else { detail_x - char_w * 2.0 };
+        let w = (w + CARD_PADDING).min(self.width);
+        let h = items.len().min(COMPLETION_MAX_ROWS) as f64 * row_h;
+
+        let selected = usize::try_from(selected_index).ok().filter(|&i| i < items.len());
+        if !self.completion_open {
+            self.completion_scroll = 0.0;
+        }
+        let reveal = !self.completion_open || selected != self.completion_selected;
+        if let Some(i) = selected.filter(|_| reveal) {
+            let top = i as f64 * row_h;
+            if top < self.completion_scroll {
+                self.completion_scroll = top;
+            } else if top + row_h > self.completion_scroll + h {
+                self.completion_scroll = top + row_h - h;
+            }
+        }
+        let max = items.len() as f64 * row_h - h;
+        self.completion_scroll = self.completion_scroll.clamp(0.0, max.max(0.0));
+        self.completion_selected = selected;
+
+        let (x, y) = self.popup_origin(x, y, w, h);
+        self.completion = Some(CompletionPopup {
+            x,
+            y,
+            w,
+            h,
+            items,
+            selected,
+            label_x,
+            detail_x,
+        });
+    }
+
+    /// Top-left for a `w` x `h` popup anchored below the caret row at
+    /// `(x, y)`: flipped above the row when it would run off the bottom and
+    /// kept inside the view horizontally.
+    fn popup_origin(&self, x: f64, y: f64, w: f64, h: f64) -> (f64, f64) {
+        let below = y + self.row_height_at(y);
+        let top = if below + h > self.height && y >= h { y - h } else { below };
+        (x.min(self.width - w).max(0.0), top)
+    }
+
+    pub fn set_selection_style(&mut self, style: i32) {
+        self.selection_style = style;
+    }
+
+    pub fn set_selection_full_line(&mut self, enabled: bool) {
+        self.selection_full_line = enabled;
+    }
+
+    /// Width added past line end for regions whose newline is selected.
+    fn eol_selection_width(&self) -> f64 {
+        if self.selection_full_line {
+            self.renderer.char_width
+        } else {
+            0.0
+        }
+    }
+
+    pub fn set_rulers(&mut self, columns_json: &str) {
+        self.rulers = serde_json::from_str(columns_json).unwrap_or_default();
+    }
+
+    /// Tint each indent level of a line's leading whitespace with the next
+    /// of `colors_json` (`["#rrggbb", ...]`), cycling, drawn faint under the
+    /// text and selection. An empty array turns it off.
+    pub fn set_indent_rainbow(&mut self, colors_json: &str) {
+        let colors: Vec<String> = serde_json::from_str(colors_json).unwrap_or_default();
+        self.indent_rainbow = colors.iter().map(|c| text_renderer::parse_hex_color(c)).collect();
+    }
+
+    /// Columns per indent level and per tab stop, for the indent rainbow.
+    pub fn set_tab_width(&mut self, width: u32) {
+        self.tab_width = (width as usize).max(1);
+    }
+
+    pub fn set_ruler_color(&mut self, color: &str) {
+        let (r, g, b) = text_renderer::parse_hex_color(color);
+        self.ruler_color = (r, g, b, self.ruler_color.3);
+    }
+
+    /// Fill the text area with a vertical gradient from `top` to `bottom`
+    /// ("#rrggbb") instead of the flat theme background. The gutter keeps its
+    /// own color. `enabled = false` goes back to the flat fill (the default).
+    pub fn set_background_gradient(&mut self, enabled: bool, top: &str, bottom: &str) {
+        self.background_gradient = enabled
+            .then(|| (text_renderer::parse_hex_color(top), text_renderer::parse_hex_color(bottom)));
+    }
+
+    /// Supply the authoritative document text and caret position (a UTF-16
+    /// offset into `text`) for VoiceOver and the text input client. Until set,
+    /// accessibility queries fall back to the lines in the current frame.
+    /// Collapses the selection to the caret.
+    pub fn set_accessible_text(&mut self, text: &str, cursor_offset: usize) {
+        self.accessible_text = Some(text.to_string());
+        self.accessible_cursor = cursor_offset;
+        self.accessible_anchor = cursor_offset;
+        view::post_accessibility_changed(self.nsview);
+    }
+
+    /// Extend the selection from `anchor_offset` (UTF-16) to the caret from
+    /// the last `set_accessible_text`.
+    pub fn set_accessible_selection(&mut self, anchor_offset: usize) {
+        self.accessible_anchor = anchor_offset;
+        view::post_accessibility_changed(self.nsview);
+    }
+
+    /// The selection as a UTF-16 `(location, length)` into the accessible
+    /// text. While composing, the IME's selection within the marked text.
+    pub fn selected_range(&self) -> (usize, usize) {
+        let start = self.accessible_anchor.min(self.accessible_cursor);
+        match self.marked_text {
+            Some(_) => (start + self.marked_selection.0, self.marked_selection.1),
+            None => (start, self.accessible_anchor.max(self.accessible_cursor) - start),
+        }
+    }
+
+    /// UTF-16 `(location, length)` of the marked text, which stands in for
+    /// the selection until it is committed.
+    pub fn marked_range(&self) -> Option<(usize, usize)> {
+        let text = self.marked_text.as_ref()?;
+        let start = self.accessible_anchor.min(self.accessible_cursor);
+        Some((start, text.encode_utf16().count()))
+    }
+
+    /// Show `text` as an uncommitted composition at the caret, with the IME's
+    /// `selection` (UTF-16, relative to `text`). Empty text cancels it.
+    pub fn set_marked_text(&mut self, text: &str, selection: (usize, usize)) {
+        let text = if self.read_only.is_some() { "" } else { text };
+        self.marked_text = (!text.is_empty()).then(|| text.to_string());
+        self.marked_selection = selection;
+        view::invalidate_view(self.nsview);
+    }
+
+    /// Commit any marked text as typed input.
+    pub fn unmark_text(&mut self) {
+        if let Some(text) = self.marked_text.take() {
+            view::invalidate_view(self.nsview);
+            self.on_text_input(&text);
+        }
+    }
+
+    /// Commit `text` from the input method, replacing any composition.
+    pub fn insert_text(&mut self, text: &str) {
+        if self.marked_text.take().is_some() {
+            view::invalidate_view(self.nsview);
+        }
+        if !text.is_empty() {
+            self.on_text_input(text);
+        }
+    }
+
+    /// The accessible text in UTF-16 `(location, length)`, clamped to the
+    /// text, with the clamped range. `None` when the range is empty.
+    pub fn accessible_substring(
+        &self,
+        location: usize,
+        length: usize,
+    ) -> Option<(String, usize, usize)> {
+        let units: Vec<u16> = self.accessibility_value().encode_utf16().collect();
+        let start = location.min(units.len());
+        let end = location.saturating_add(length).min(units.len());
+        if start == end {
+            return None;
+        }
+        Some((String::from_utf16_lossy(&units[start..end]), start, end - start))
+    }
+
+    /// UTF-16 offset into the accessible text of the character under view
+    /// point `(x, y)`, via `point_to_position`.
+    pub fn character_index_at(&self, x: f64, y: f64) -> Option<usize> {
+        let (line_number, col) = self.point_to_position(x, y)?;
+        // Without host text the value is the frame's lines joined in order
+        let row = match self.accessible_text {
+            Some(_) => usize::try_from(line_number - 1).ok()?,
+            None => self.lines().position(|l| l.line_number == line_number)?,
+        };
+        let text = self.accessibility_value();
+        let mut offset = 0;
+        for (i, line) in text.split('\n').enumerate() {
+            if i == row {
+                let prefix = line.get(..col).unwrap_or(line);
+                return Some(offset + prefix.encode_utf16().count());
+            }
+            offset += line.encode_utf16().count() + 1;
+        }
+        None
+    }
+
+    /// Text exposed as the NSAccessibility value.
+    pub fn accessibility_value(&self) -> String {
+        match self.accessible_text {
+            Some(ref text) => text.clone(),
+            None => self
+                .lines()
+                .map(|l| l.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Zero-based line holding the caret, for accessibilityInsertionPointLineNumber.
+    pub fn accessibility_line_number(&self) -> usize {
+        match self.accessible_text {
+            Some(ref text) => text
+                .encode_utf16()
+                .take(self.accessible_cursor)
+                .filter(|&u| u == u16::from(b'\n'))
+                .count(),
+            None => (self.active_line - 1).max(0) as usize,
+        }
+    }
+
+    pub fn set_active_line(&mut self, line_number: i32) {
+        self.active_line = line_number;
+    }
+
+    /// Draw the active line's number (see `set_active_line`) bold and in
+    /// `color` ("#rrggbb"), so the caret's row stands out in the gutter
+    /// without a full-line highlight. `None` uses the theme text color.
+    pub fn set_active_line_gutter(&mut self, enabled: bool, color: Option<&str>) {
+        self.active_line_gutter = enabled.then(|| color.map(text_renderer::parse_hex_color));
+    }
+
+    /// Number gutter rows 0 = absolutely, 1 = by distance from the active
+    /// line (which shows 0), or 2 = by distance, with the active line's own
+    /// number. In mode 2 the relative numbers stay right-aligned while the
+    /// absolute one is left-aligned in the number column, so its width
+    /// never shifts them. Without an active line rows are numbered
+    /// absolutely.
+    pub fn set_line_number_mode(&mut self, mode: i32) {
+        self.line_number_mode = mode;
+        self.invalidate();
+    }
+
+    pub fn set_highlight_trailing_whitespace(&mut self, enabled: bool, color: &str) {
+        self.highlight_trailing_whitespace = enabled;
+        self.trailing_whitespace_color = text_renderer::parse_hex_color(color);
+    }
+
+    pub fn set_line_count(&mut self, count: i32) {
+        self.line_count = count;
+    }
+
+    /// Allow scrolling `lines` lines past the point where the last line sits
+    /// at the bottom of the view.
+    pub fn set_overscroll(&mut self, lines: i32) {
+        self.overscroll = lines.max(0);
+    }
+
+    /// Number of whole lines that fit in the viewport, for page movement.
+    /// Always at least 1 so paging makes progress in tiny views.
+    pub fn page_line_count(&self) -> i32 {
+        let line_h = self.line_height();
+        if line_h <= 0.0 {
+            return 1;
+        }
+        ((self.height / line_h).floor() as i32).max(1)
+    }
+
+    /// Largest valid scroll offset, or `None` while the line count is unknown.
+    pub fn max_scroll(&self) -> Option<f64> {
+        (self.line_count > 0).then(|| {
+            let line_h = self.renderer.line_height;
+            let content = (self.line_count + self.overscroll) as f64 * line_h;
+            (content - self.height).max(0.0)
+        })
+    }
+
+    fn clamp_scroll(&self, offset: f64) -> f64 {
+        let offset = offset.max(0.0);
+        match self.max_scroll() {
+            Some(max) => offset.min(max),
+            None => offset,
+        }
+    }
+
+    pub fn set_scrolloff(&mut self, lines: i32) {
+        self.scrolloff = lines.max(0);
+    }
+
+    /// Scroll so the line at content-space `y_offset` is visible.
+    /// Mode 0 moves to the nearest edge (no-op if already visible), keeping
+    /// `scrolloff` lines of context; mode 1 centers it.
+    pub fn reveal(&mut self, y_offset: f64, mode: i32) {
+        let line_h = self.renderer.line_height;
+        if mode == 1 {
+            self.center_on(y_offset);
+            return;
+        }
+        // Never ask for more margin than fits around a single line.
+        let margin = (self.scrolloff as f64 * line_h).min(((self.height - line_h) / 2.0).max(0.0));
+        let target = if y_offset - margin < self.scroll_offset {
+            y_offset - margin
+        } else if y_offset + line_h + margin > self.scroll_offset + self.height {
+            y_offset + line_h + margin - self.height
+        } else {
+            self.scroll_offset
+        };
+        self.scroll_to(target);
+    }
+
+    /// Scroll so the line at content-space `y_offset` is vertically centered
+    /// (clamped to the content bounds).
+    pub fn center_on(&mut self, y_offset: f64) {
+        let line_h = self.renderer.line_height;
+        self.scroll_to(y_offset + line_h / 2.0 - self.height / 2.0);
+    }
+
+    /// Move to a clamped scroll offset, report it to the host through the
+    /// scroll callback (as a wheel-style delta) and redraw.
+    fn scroll_to(&mut self, offset: f64) {
+        let offset = self.clamp_scroll(offset);
+        if offset == self.scroll_offset {
+            return;
+        }
+        // Wheel convention here: negative dy scrolls down.
+        let delta = self.scroll_offset - offset;
+        self.scroll_offset = offset;
+        self.fill_visible_lines();
+        self.notify_scroll(0.0, delta);
+        self.notify_did_scroll();
+        self.invalidate();
+    }
+
+    /// Set the scroll offset. Clamped to the document in content mode, where
+    /// the view owns scrolling; host-pushed frames take it as given.
+    pub fn scroll(&mut self, offset_y: f64) {
+        self.scroll_offset = if self.content.is_some() {
+            self.clamp_scroll(offset_y)
+        } else {
+            offset_y
+        };
+        self.fill_visible_lines();
+    }
+
+    /// Scroll line text horizontally by `offset_x` pixels (clamped at 0).
+    /// The gutter stays fixed; host-positioned overlays are unaffected.
+    pub fn set_scroll_x(&mut self, offset_x: f64) {
+        self.scroll_x = offset_x.max(0.0);
+        self.invalidate();
+    }
+
+    /// Toggle the shadow drawn at the gutter edge while scrolled horizontally.
+    pub fn set_gutter_shadow(&mut self, enabled: bool) {
+        self.gutter_shadow = enabled;
+        self.invalidate();
+    }
+
+    /// Show or hide the whole gutter. Hidden, it takes no width (no line
+    /// numbers, fold/diff area or background) and text starts at x = 0, for
+    /// inline snippets and single-line inputs.
+    pub fn set_gutter_visible(&mut self, visible: bool) {
+        self.gutter_visible = visible;
+        self.invalidate();
+    }
+
+    /// Draw a "↪" in the gutter for rows that continue a wrapped line (on by
+    /// default). Off, every row shows its line number.
+    pub fn set_wrap_markers(&mut self, enabled: bool) {
+        self.wrap_markers = enabled;
+        self.invalidate();
+    }
+
+    /// One-line input mode for search boxes and inline rename: the gutter is
+    /// hidden, `insertNewline:` is emitted as `submit:`, vertical scrolling
+    /// is dropped, and the first row is drawn centered in the view and
+    /// clipped to one row.
+    pub fn set_single_line(&mut self, enabled: bool) {
+        self.single_line = enabled;
+        self.invalidate();
+    }
+
+    /// Replace the sticky scroll headers: a JSON array of
+    /// `{line_number, text, tokens}` pinned to the top rows, in order.
+    /// An empty array (or invalid JSON) removes them.
+    pub fn set_sticky_lines(&mut self, lines_json: &str) {
+        self.sticky_lines = serde_json::from_str(lines_json).unwrap_or_default();
+        self.invalidate();
+    }
+
+    /// Show a "⋯" badge after column `after_col` of `line` (a folded region).
+    /// The line must also be rendered this frame. Cleared on `begin_frame`.
+    pub fn render_fold_placeholder(&mut self, line: i32, after_col: i32) {
+        self.fold_placeholders.push(FoldPlaceholder {
+            line,
+            after_col: after_col.max(0) as usize,
+        });
+    }
+
+    /// Left edge, line top, and width of a placeholder pill, or `None` when
+    /// its line isn't in the current frame.
+    fn fold_placeholder_rect(&self, fold: &FoldPlaceholder) -> Option<(f64, f64, f64)> {
+        let line = self.buffered_line(fold.line)?;
+        let end = line
+            .text
+            .char_indices()
+            .nth(fold.after_col)
+            .map_or(line.text.len(), |(i, _)| i);
+        let cw = self.renderer.char_width;
+        let x = self.gutter_width() - self.scroll_x + self.renderer.measure_text(&line.text[..end]);
+        let w = self.renderer.measure_text(FOLD_PLACEHOLDER) + cw;
+        Some((x + cw / 2.0, line.y_offset, w))
+    }
+
+    /// Line number of the fold placeholder under view point `(x, y)`, if any.
+    fn fold_placeholder_at(&self, x: f64, y: f64) -> Option<i32> {
+        let line_h = self.renderer.line_height;
+        self.fold_placeholders.iter().find_map(|fold| {
+            let (px, py, w) = self.fold_placeholder_rect(fold)?;
+            (x >= px && x < px + w && y >= py && y < py + line_h).then_some(fold.line)
+        })
+    }
+
+    /// Underline link ranges (`[{line, startCol, endCol, url}]`) and make
+    /// them Ctrl/Cmd+clickable, emitting `"openLink:<url>"`. The lines must
+    /// already be rendered this frame. Cleared on `begin_frame`.
+    pub fn set_links(&mut self, links_json: &str) {
+        let specs: Vec<LinkSpec> = serde_json::from_str(links_json).unwrap_or_default();
+        let text_x = self.gutter_width() - self.scroll_x;
+        let line_h = self.renderer.line_height;
+        for spec in specs {
+            let Some(line) = self.buffered_line(spec.line) else {
+                continue;
+            };
+            let byte = |col: usize| {
+                line.text.char_indices().nth(col).map_or(line.text.len(), |(i, _)| i)
+            };
+            let (start, end) = (byte(spec.start_col), byte(spec.end_col.max(spec.start_col)));
+            let x = text_x + self.renderer.measure_text(&line.text[..start]);
+            let w = self.renderer.measure_text(&line.text[start..end]);
+            let y = line.y_offset;
+            // Drawn by the decoration pass like any other underline
+            self.decorations.push(DecorationOverlay {
+                x,
+                y,
+                w,
+                h: line_h,
+                color: LINK_COLOR.to_string(),
+                kind: "underline".to_string(),
+            });
+            self.links.push(LinkRegion { x, y, w, h: line_h, url: spec.url });
+        }
+    }
+
+    /// Squiggle diagnostic ranges (`[{line, startCol, endCol, severity}]`)
+    /// in the theme's color for each severity, measured here so they line
+    /// up with the glyphs. An empty range marks one character cell, for
+    /// diagnostics at the end of a line. The lines must already be rendered
+    /// this frame; unknown severities are skipped. Cleared on `begin_frame`.
+    pub fn set_diagnostics(&mut self, diagnostics_json: &str) {
+        let specs: Vec<DiagnosticSpec> =
+            serde_json::from_str(diagnostics_json).unwrap_or_default();
+        let text_x = self.gutter_width() - self.scroll_x;
+        let line_h = self.renderer.line_height;
+        for spec in specs {
+            let Some(severity) = severity_index(&spec.severity) else {
+                continue;
+            };
+            let Some(line) = self.buffered_line(spec.line) else {
+                continue;
+            };
+            let byte = |col: usize| {
+                line.text.char_indices().nth(col).map_or(line.text.len(), |(i, _)| i)
+            };
+            let (start, end) = (byte(spec.start_col), byte(spec.end_col.max(spec.start_col)));
+            let x = text_x + self.renderer.measure_text(&line.text[..start]);
+            let w = self.renderer.measure_text(&line.text[start..end]);
+            self.decorations.push(DecorationOverlay {
+                x,
+                y: line.y_offset,
+                w: w.max(self.renderer.char_width),
+                h: line_h,
+                color: self.diagnostic_colors[severity].clone(),
+                kind: "underline-wavy".to_string(),
+            });
+        }
+    }
+
+    /// Recolor spans of already rendered lines (`[{line, startCol, endCol,
+    /// color}]`) on top of their tokens, e.g. LSP semantic tokens over
+    /// TextMate ones. Only the color changes; style and size stay with the
+    /// underlying token. Later entries win where they overlap. Cleared on
+    /// `begin_frame`.
+    pub fn set_color_overrides(&mut self, overrides_json: &str) {
+        self.color_overrides = serde_json::from_str(overrides_json).unwrap_or_default();
+    }
+
+    /// Buffered line number and byte column under view point `(x, y)`.
+    /// Rows above or below the pushed lines clamp to the nearest one, points
+    /// in the gutter map to column 0 and points past the end of a line to
+    /// its length; otherwise the column snaps to the nearest grapheme
+    /// boundary. `None` when no lines were pushed this frame.
+    pub fn point_to_position(&self, x: f64, y: f64) -> Option<(i32, usize)> {
+        let y = y - self.single_line_offset();
+        let row_dist = |l: &&LineRenderData| {
+            if y < l.y_offset {
+                l.y_offset - y
+            } else {
+                (y - (l.y_offset + self.row_height(l))).max(0.0)
+            }
+        };
+        let line = self
+            .lines()
+            .min_by(|a, b| row_dist(a).total_cmp(&row_dist(b)))?;
+        let gutter_w = self.gutter_width();
+        let text_x = gutter_w - self.scroll_x;
+        let col = column_at_point(&line.text, x, gutter_w, text_x, |p| self.measure_text(p));
+        Some((line.line_number, col))
+    }
+
+    /// Byte span and `0xRRGGBB` color of the token covering byte column
+    /// `col` on buffered line `line`, as the line was pushed and drawn.
+    /// Tokens here are in UTF-16 columns (Core Text string indices), so the
+    /// lookup converts both ways. `None` when the line isn't buffered or no
+    /// token covers `col`.
+    pub fn token_at(&self, line: i32, col: usize) -> Option<(usize, usize, u32)> {
+        let data = self.buffered_line(line)?;
+        let text = &data.text;
+        let col: usize = text
+            .char_indices()
+            .take_while(|&(i, _)| i < col)
+            .map(|(_, c)| c.len_utf16())
+            .sum();
+        let token = data.tokens.iter().find(|t| t.s <= col && col < t.e)?;
+        let byte = |units: usize| {
+            let mut seen = 0;
+            text.char_indices()
+                .find(|&(_, c)| {
+                    seen += c.len_utf16();
+                    seen > units
+                })
+                .map_or(text.len(), |(i, _)| i)
+        };
+        let hex = token.c.trim_start_matches('#');
+        let color = hex.get(..6).and_then(|h| u32::from_str_radix(h, 16).ok()).unwrap_or(0);
+        Some((byte(token.s), byte(token.e), color))
+    }
+
+    /// URL of the link under view point `(x, y)`, if any.
+    pub fn link_at(&self, x: f64, y: f64) -> Option<&str> {
+        self.links
+            .iter()
+            .find(|l| x >= l.x && x < l.x + l.w && y >= l.y && y < l.y + l.h)
+            .map(|l| l.url.as_str())
+    }
+
+    /// Cursor rects `(x, y, w, h, kind)` covering the view, lowest priority
+    /// first: I-beam over everything, arrow over the gutter, hand over links,
+    /// then the host regions.
+    pub fn cursor_rects(&self) -> Vec<(f64, f64, f64, f64, CursorKind)> {
+        let mut rects = vec![
+            (0.0, 0.0, self.width, self.height, CursorKind::Text),
+            (0.0, 0.0, self.gutter_width(), self.height, CursorKind::Arrow),
+        ];
+        rects.extend(self.links.iter().map(|l| (l.x, l.y, l.w, l.h, CursorKind::Hand)));
+        rects.extend(self.cursor_regions.iter().map(|r| (r.x, r.y, r.w, r.h, r.cursor)));
+        rects
+    }
+
+    /// Replace the host cursor regions (`[{x, y, w, h, cursor}]`, cursor one of
+    /// `"text"`, `"arrow"`, `"hand"`). They take precedence over the built-in
+    /// arrow over the gutter and hand over links; everything else is an I-beam.
+    pub fn set_cursor_regions(&mut self, regions_json: &str) {
+        self.cursor_regions = serde_json::from_str(regions_json).unwrap_or_default();
+        if self.nsview != nil {
+            view::invalidate_cursor_rects(self.nsview);
+        }
+    }
+
+    /// Cursor shape for view point `(x, y)`.
+    pub fn cursor_at(&self, x: f64, y: f64) -> CursorKind {
+        let hit = |rx: f64, ry: f64, rw: f64, rh: f64| {
+            x >= rx && x < rx + rw && y >= ry && y < ry + rh
+        };
+        if let Some(r) = self.cursor_regions.iter().rev().find(|r| hit(r.x, r.y, r.w, r.h)) {
+            r.cursor
+        } else if self.link_at(x, y).is_some() {
+            CursorKind::Hand
+        } else if x < self.gutter_width() {
+            CursorKind::Arrow
+        } else {
+            CursorKind::Text
+        }
+    }
+
+    /// Handle a Ctrl/Cmd+click: emits `"openLink:<url>"` and returns true
+    /// when `(x, y)` is on a link.
+    pub fn on_link_click(&mut self, x: f64, y: f64) -> bool {
+        match self.link_at(x, y).map(str::to_string) {
+            Some(url) => {
+                self.on_action(&format!("openLink:{url}"));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Draw one decoration (`DecorationOverlay` JSON) and fade it out over
+    /// `duration_ms`. Unlike `render_decorations` it survives `begin_frame`;
+    /// a platform timer redraws until it has faded, then it is removed.
+    pub fn flash_decoration(&mut self, decoration_json: &str, duration_ms: u32) {
+        let Ok(decor) = serde_json::from_str::<DecorationOverlay>(decoration_json) else {
+            return;
+        };
+        self.flashes.push(FlashDecoration {
+            decor,
+            start: Instant::now(),
+            duration: Duration::from_millis(duration_ms.max(1) as u64),
+        });
+        self.start_flash_timer();
+        self.invalidate();
+    }
+
+    /// Visual bell: wash the whole view in the theme's text color and fade it
+    /// out over `VISUAL_BELL_DURATION`, driven by the flash timer. Frame
+    /// state is untouched; ringing again restarts the fade.
+    pub fn visual_bell(&mut self) {
+        self.bell = Some(Instant::now());
+        self.start_flash_timer();
+        self.invalidate();
+    }
+
+    /// Opacity of the bell wash at `now`, falling from `VISUAL_BELL_ALPHA`
+    /// to 0; 0 when the bell isn't ringing.
+    fn bell_alpha(&self, now: Instant) -> f64 {
+        let Some(start) = self.bell else {
+            return 0.0;
+        };
+        let t = now.duration_since(start).as_secs_f64() / VISUAL_BELL_DURATION.as_secs_f64();
+        VISUAL_BELL_ALPHA * (1.0 - t).clamp(0.0, 1.0)
+    }
+
+    /// Advance flash animations and the visual bell from the platform timer:
+    /// drop finished ones and redraw. Stops the timer and returns false once
+    /// none remain.
+    pub fn tick_flashes(&mut self) -> bool {
+        let now = Instant::now();
+        self.flashes.retain(|f| f.alpha(now) > 0.0);
+        if self.bell_alpha(now) <= 0.0 {
+            self.bell = None;
+        }
+        self.invalidate();
+        if self.flashes.is_empty() && self.bell.is_none() {
+            self.stop_flash_timer();
+            return false;
+        }
+        true
+    }
+
+    /// Start the ~60 Hz flash animation timer unless it is already running.
+    fn start_flash_timer(&mut self) {
+        if self.flash_timer == nil && self.nsview != nil {
+            self.flash_timer = view::start_flash_timer(self.nsview);
+        }
+    }
+
+    fn stop_flash_timer(&mut self) {
+        if self.flash_timer != nil {
+            view::stop_timer(self.flash_timer);
+            self.flash_timer = nil;
+        }
+    }
+
+    pub fn render_decorations(&mut self, decorations_json: &str) {
+        let mut decors: Vec<DecorationOverlay> =
+            serde_json::from_str(decorations_json).unwrap_or_default();
+        self.decorations.append(&mut decors);
+    }
+
+    /// Faint text shown on the first row while the document is empty (no
+    /// lines, or one empty line). It is not part of the frame, so it can't be
+    /// selected or measured. An empty `text` removes it.
+    pub fn set_placeholder(&mut self, text: &str, color: &str) {
+        self.placeholder = if text.is_empty() {
+            None
+        } else {
+            Some((text.to_string(), text_renderer::parse_hex_color(color)))
+        };
+        self.invalidate();
+    }
+
+    /// Top of the row to draw the placeholder on, or `None` when there is no
+    /// placeholder or the document isn't empty.
+    fn placeholder_y(&self) -> Option<f64> {
+        self.placeholder.as_ref()?;
+        let mut lines = self.lines();
+        match (lines.next(), lines.next()) {
+            (None, _) => Some(0.0),
+            (Some(line), None) if line.text.is_empty() => Some(line.y_offset),
+            _ => None,
+        }
+    }
+
+    pub fn render_ghost_text(&mut self, text: &str, x: f64, y: f64, color: &str) {
+        self.ghost_text = Some(GhostTextData {
+            text: text.to_string(),
+            x,
+            y,
+            color: text_renderer::parse_hex_color(color),
+        });
+    }
+
+    pub fn end_frame(&mut self) {
+        self.prune_persistent_lines();
+        if self.nsview != nil {
+            view::invalidate_view(self.nsview);
+            // Gutter width and links may have changed
+            view::invalidate_cursor_rects(self.nsview);
+        }
+    }
+
+    /// Drop persistent-mode lines more than `overscan` rows above or below
+    /// the viewport, so the buffer stays about one screen long.
+    fn prune_persistent_lines(&mut self) {
+        let line_h = self.renderer.line_height;
+        let margin = self.overscan as f64 * line_h;
+        let (top, bottom) = (-margin, self.height + margin);
+        self.persistent_lines
+            .retain(|_, l| l.y_offset + l.height.unwrap_or(line_h) > top && l.y_offset < bottom);
+    }
+
+    /// Blank the view outside a frame (e.g. when the file closes): drops all
+    /// frame content, as an empty `begin_frame`/`end_frame` pair would, and
+    /// redraws.
+    pub fn clear(&mut self) {
+        self.max_line_width = 0.0;
+        self.content = None;
+        self.frame_lines.clear();
+        self.persistent_lines.clear();
+        self.max_line_number = 0;
+        self.begin_frame();
+        self.end_frame();
+    }
+
+    /// Grow the widest-line width reported by `content_size`.
+    fn note_line_width(&mut self, text: &str) {
+        let w = self.renderer.measure_text(text);
+        if w > self.max_line_width {
+            self.max_line_width = w;
+        }
+    }
+
+    /// Scrollable content size as `(width, height)`: the widest line pushed
+    /// since the last `clear` or metrics change (text only, gutter excluded)
+    /// and `(line_count + overscroll) * line_height`, which is 0 until
+    /// `set_line_count` is called.
+    pub fn content_size(&self) -> (f64, f64) {
+        if self.single_line {
+            return (self.max_line_width, self.renderer.line_height);
+        }
+        let lines = if self.line_count > 0 { self.line_count + self.overscroll } else { 0 };
+        (self.max_line_width, lines as f64 * self.renderer.line_height)
+    }
+
+    pub fn invalidate(&mut self) {
+        if self.nsview != nil {
+            view::invalidate_view(self.nsview);
+        }
+    }
+
+    /// Repaint only the `(x, y, w, h)` rect, in view coordinates, clamped to
+    /// the view. For hosts that know a small region changed (a flashed
+    /// decoration, say). A rect that is empty or entirely outside the view
+    /// does nothing.
+    pub fn invalidate_rect(&mut self, x: f64, y: f64, w: f64, h: f64) {
+        let (left, top) = (x.max(0.0), y.max(0.0));
+        let (right, bottom) = ((x + w).min(self.width), (y + h).min(self.height));
+        // Also rejects NaN, which fails every comparison
+        if !(right > left && bottom > top) {
+            return;
+        }
+        view::invalidate_rect(self.nsview, left, top, right - left, bottom - top);
+    }
+
+    pub fn attach_to_parent(&mut self, parent: *mut std::ffi::c_void) {
+        self.parent_view = parent;
+        if self.nsview != nil && !parent.is_null() {
+            unsafe {
+                let parent_view = parent as id;
+                let _: () = msg_send![parent_view, addSubview: self.nsview];
+                let bounds: NSRect = msg_send![parent_view, bounds];
+                let _: () = msg_send![self.nsview, setFrame: bounds];
+            }
+        }
+    }
+
+    // ── Drawing ──────────────────────────────────────────────────
+
+    /// Whether `line` is a visual row continuing a wrapped line: hosts that
+    /// wrap push one row per visual line, all with the same line number, so
+    /// any row below another with its number continues it.
+    fn is_continuation_row(&self, line: &LineRenderData) -> bool {
+        self.lines()
+            .any(|l| l.line_number == line.line_number && l.y_offset < line.y_offset)
+    }
+
+    /// Bottom of the document's last line when it is buffered and EOF
+    /// markers are on; None while the end is out of view.
+    fn eof_y(&self) -> Option<f64> {
+        if self.eof_markers == 0 || self.line_count <= 0 {
+            return None;
+        }
+        self.lines()
+            .filter(|l| l.line_number == self.line_count)
+            .map(|l| l.y_offset + self.row_height(l))
+            .reduce(f64::max)
+    }
+
+    fn gutter_shown(&self) -> bool {
+        self.gutter_visible && !self.single_line
+    }
+
+    /// How far single-line mode shifts the row down to center it; 0 otherwise.
+    fn single_line_offset(&self) -> f64 {
+        if !self.single_line {
+            return 0.0;
+        }
+        ((self.height - self.renderer.line_height) / 2.0).max(0.0).round()
+    }
+
+    /// Compute gutter width matching the TS GutterRenderer formula:
+    /// max(2, digits) * charWidth + 36  (16px fold + 16px padding + 4px diff)
+    fn gutter_width(&self) -> f64 {
+        if !self.gutter_shown() {
+            return 0.0;
+        }
+        self.gutter_digits() as f64 * self.renderer.char_width + 36.0
+    }
+
+    /// Digits the gutter reserves for line numbers: those of the largest
+    /// number pushed so far, at least 2.
+    fn gutter_digits(&self) -> i32 {
+        if self.max_line_number <= 0 {
+            2
+        } else {
+            let d = (self.max_line_number as f64).log10().floor() as i32 + 1;
+            d.max(2)
+        }
+    }
+
+    /// Gutter label for `line_number` under the line-number mode.
+    fn line_number_label(&self, line_number: i32) -> String {
+        if self.line_number_mode == 0 || self.active_line <= 0 {
+            return line_number.to_string();
+        }
+        match (line_number - self.active_line).abs() {
+            0 if self.line_number_mode == 2 => line_number.to_string(),
+            distance => distance.to_string(),
+        }
+    }
+
+    /// Whether `line_number` is drawn as the hybrid mode's absolute number,
+    /// left-aligned in the number column.
+    fn is_hybrid_anchor(&self, line_number: i32) -> bool {
+        self.line_number_mode == 2 && self.active_line > 0 && line_number == self.active_line
+    }
+
+    /// Called from the NSView drawRect: handler — draw the frame and report
+    /// the render time to the frame callback, if one is set. The callback
+    /// runs after drawing, from `&mut self`, so it may call back into the FFI.
+    pub fn paint(&mut self, raw_ctx: core_graphics::sys::CGContextRef, dirty_rect: NSRect) {
+        let start = self.frame_callback.map(|cb| (cb, Instant::now()));
+        self.draw(raw_ctx, dirty_rect);
+        if let Some((cb, start)) = start {
+            cb(self.handle, start.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+
+    /// Convert raw CGContextRef from drawRect: to a safe wrapper and draw.
+    ///
+    /// # Safety
+    /// Called from the NSView drawRect: handler with a valid CGContextRef.
+    pub fn draw(&self, raw_ctx: core_graphics::sys::CGContextRef, _dirty_rect: NSRect) {
+        let ctx = unsafe { CGContext::from_existing_context_ptr(raw_ctx) };
+        self.draw_with_context(&ctx);
+    }
+
+    /// Render the current frame buffer into `out` (RGBA, premultiplied,
+    /// top row first, `width * 4` bytes per row) through a CGBitmapContext.
+    /// No NSView or window is required.
+    pub fn render_to_buffer(&self, width: u32, height: u32, out: &mut [u8]) -> bool {
+        let color_space = CGColorSpace::create_device_rgb();
+        let ctx = CGContext::create_bitmap_context(
+            Some(out.as_mut_ptr() as *mut std::ffi::c_void),
+            width as usize,
+            height as usize,
+            8,
+            width as usize * 4,
+            &color_space,
+            kCGImageAlphaPremultipliedLast | kCGBitmapByteOrder32Big,
+        );
+        // Match the flipped NSView coordinate system draw() expects.
+        ctx.translate(0.0, height as f64);
+        ctx.scale(1.0, -1.0);
+        self.draw_with_context(&ctx);
+        ctx.flush();
+        true
+    }
+
+    fn draw_with_context(&self, ctx: &CGContext) {
+        match self.text_antialiasing {
+            0 => ctx.set_should_antialias(false),
+            1 => {
+                ctx.set_should_antialias(true);
+                ctx.set_should_smooth_fonts(false);
+            }
+            2 => {
+                ctx.set_should_antialias(true);
+                ctx.set_should_smooth_fonts(true);
+            }
+            _ => {}
+        }
+
+        let bounds = CGRect::new(
+            &CGPoint::new(0.0, 0.0),
+            &CGSize::new(self.width, self.height),
+        );
+
+        // 1. Fill background
+        self.fill_background(ctx, bounds);
+
+        // 1a. Single-line mode: center the row and clip drawing to it
+        if self.single_line {
+            ctx.save();
+            ctx.translate(0.0, self.single_line_offset());
+            ctx.clip_to_rect(CGRect::new(
+                &CGPoint::new(0.0, 0.0),
+                &CGSize::new(self.width, self.renderer.line_height),
+            ));
+        }
+
+        let gutter_w = self.gutter_width();
+        let text_x = gutter_w - self.scroll_x;
+
+        // 2. Draw gutter background
+        ctx.set_rgb_fill_color(
+            self.gutter_bg_color.0,
+            self.gutter_bg_color.1,
+            self.gutter_bg_color.2,
+            1.0,
+        );
+        let gutter_rect = CGRect::new(
+            &CGPoint::new(0.0, 0.0),
+            &CGSize::new(gutter_w, self.height),
+        );
+        ctx.fill_rect(gutter_rect);
+
+        // 2a. Draw column rulers
+        ctx.set_rgb_fill_color(
+            self.ruler_color.0,
+            self.ruler_color.1,
+            self.ruler_color.2,
+            self.ruler_color.3,
+        );
+        for &col in &self.rulers {
+            let x = (text_x + col as f64 * self.renderer.char_width).floor();
+            if x < gutter_w {
+                continue;
+            }
+            ctx.fill_rect(CGRect::new(
+                &CGPoint::new(x, 0.0),
+                &CGSize::new(1.0, self.height),
+            ));
+        }
+
+        // 2b. Tint the gutter rows of selected lines
+        if let Some(color) = self.gutter_selection {
+            let (c0, c1, c2, _) = self.selection_color;
+            let (r, g, b) = color.unwrap_or((c0, c1, c2));
+            for line in self.lines() {
+                let line_h = self.row_height(line);
+                if !self.row_selected(line.y_offset, line_h) {
+                    continue;
                 }
-                "underline-wavy" => {
-                    ctx.set_rgb_stroke_color(r, g, b, 1.0);
-                    ctx.set_line_width(1.0);
-                    let y_base = decor.y + decor.h - 1.0;
-                    let wave_height = 2.0;
-                    let wave_len = 4.0;
-                    let mut x = decor.x;
-                    ctx.move_to_point(x, y_base);
-                    let mut up = true;
-                    while x < decor.x + decor.w {
-                        let y_target = if up { y_base - wave_height } else { y_base };
-                        x += wave_len;
-                        ctx.add_line_to_point(x, y_target);
-                        up = !up;
+                ctx.set_rgb_fill_color(r, g, b, self.selection_color.3);
+                ctx.fill_rect(CGRect::new(
+                    &CGPoint::new(0.0, line.y_offset),
+                    &CGSize::new(gutter_w, line_h),
+                ));
+            }
+        }
+
+        // Read-only tint across the text area, under the shading below
+        if let Some(Some((r, g, b))) = self.read_only {
+            ctx.set_rgb_fill_color(r, g, b, REGION_SHADING_ALPHA);
+            ctx.fill_rect(CGRect::new(
+                &CGPoint::new(gutter_w, 0.0),
+                &CGSize::new(self.width - gutter_w, self.height),
+            ));
+        }
+
+        // 2c. Shade host regions (e.g. a hovered fold range) across the
+        // text area, under the text
+        for region in &self.shaded_regions {
+            let (r, g, b) = text_renderer::parse_hex_color(&region.color);
+            ctx.set_rgb_fill_color(r, g, b, REGION_SHADING_ALPHA);
+            ctx.fill_rect(CGRect::new(
+                &CGPoint::new(gutter_w, region.start_y),
+                &CGSize::new(self.width - gutter_w, region.end_y - region.start_y),
+            ));
+        }
+
+        // 3. Draw each buffered line's number in the gutter, when shown
+        let lead = self.renderer.half_leading();
+        if self.gutter_shown() {
+            for line in self.lines() {
+                let text_y = line.y_offset + lead;
+
+                // Draw line number in gutter (right-aligned); rows continuing
+                // a wrapped line get a marker instead
+                let marker = self.wrap_markers && self.is_continuation_row(line);
+                let num_str = if marker {
+                    WRAP_MARKER.to_string()
+                } else {
+                    self.line_number_label(line.line_number)
+                };
+                let num_width = self.renderer.char_width * num_str.chars().count() as f64;
+                // Right-align: gutter_w - 20px (fold+diff area) - num_width.
+                // The hybrid mode's absolute number starts the column instead
+                let num_x = if !marker && self.is_hybrid_anchor(line.line_number) {
+                    gutter_w - 20.0 - self.gutter_digits() as f64 * self.renderer.char_width
+                } else {
+                    gutter_w - 20.0 - num_width
+                };
+                let (font, color) = match self.active_line_gutter {
+                    Some(color) if line.line_number == self.active_line => {
+                        (&self.renderer.bold, color.unwrap_or(self.default_text_color))
                     }
-                    ctx.stroke_path();
-                }
-                _ => {}
+                    _ => (&self.renderer.normal, self.gutter_fg_color),
+                };
+
+                text_renderer::draw_text(
+                    ctx,
+                    &num_str,
+                    num_x,
+                    text_y,
+                    font,
+                    self.renderer.ascent,
+                    color,
+                );
             }
         }
 
-        // 5. Draw selection rectangles
-        for sel in &self.selections {
-            ctx.set_rgb_fill_color(
-                self.selection_color.0,
-                self.selection_color.1,
-                self.selection_color.2,
-                self.selection_color.3,
+        // 3a. Draw line text, shifted by the horizontal scroll and clipped
+        // to the text area so it slides under the gutter
+        ctx.save();
+        ctx.clip_to_rect(CGRect::new(
+            &CGPoint::new(gutter_w, 0.0),
+            &CGSize::new((self.width - gutter_w).max(0.0), self.height),
+        ));
+        // Indent rainbow bands, under the text
+        if !self.indent_rainbow.is_empty() {
+            self.draw_indent_rainbow(ctx, text_x);
+        }
+        for line in self.lines() {
+            let text_y = line.y_offset + lead;
+            text_renderer::draw_line(
+                ctx,
+                &line.text,
+                &self.line_tokens(line),
+                text_x,
+                text_y,
+                &self.renderer,
+                self.default_text_color,
             );
-            let rect = CGRect::new(
-                &CGPoint::new(sel.x, sel.y),
-                &CGSize::new(sel.w, sel.h),
+        }
+
+        // Inline images, under the same clip. Flip each one locally since
+        // the view's y axis points down.
+        for img in &self.images {
+            let (w, h) = (img.image.width() as f64, img.image.height() as f64);
+            ctx.save();
+            ctx.translate(img.x, img.y + h);
+            ctx.scale(1.0, -1.0);
+            ctx.draw_image(
+                CGRect::new(&CGPoint::new(0.0, 0.0), &CGSize::new(w, h)),
+                &img.image,
             );
-            ctx.fill_rect(rect);
+            ctx.restore();
+        }
+        ctx.restore();
+
+        // 3b. Placeholder for an empty document
+        if let (Some((text, color)), Some(y)) = (&self.placeholder, self.placeholder_y()) {
+            text_renderer::draw_text(
+                ctx,
+                text,
+                text_x,
+                y + lead,
+                &self.renderer.italic,
+                self.renderer.ascent,
+                *color,
+            );
+        }
+
+        // 3c. End-of-file markers in the gutter, below the last line
+        if let Some(eof) = self.eof_y().filter(|_| self.gutter_shown()) {
+            let (marker, rows) = match self.eof_markers {
+                1 => ("~", usize::MAX),
+                _ => (EOF_SYMBOL, 1),
+            };
+            let x = gutter_w - 20.0 - self.renderer.char_width;
+            let line_h = self.renderer.line_height;
+            let ys = (0..rows).map(|i| eof + i as f64 * line_h);
+            for y in ys.take_while(|&y| y < self.height) {
+                text_renderer::draw_text(
+                    ctx,
+                    marker,
+                    x,
+                    y + lead,
+                    &self.renderer.normal,
+                    self.renderer.ascent,
+                    self.gutter_fg_color,
+                );
+            }
+        }
+
+        // 4. Draw decorations (underlines, backgrounds), then fading flashes
+        for decor in &self.decorations {
+            self.draw_decoration(ctx, decor, gutter_w, 1.0);
+        }
+        let now = Instant::now();
+        for flash in &self.flashes {
+            self.draw_decoration(ctx, &flash.decor, gutter_w, flash.alpha(now));
+        }
+
+        // 4a. Highlight trailing whitespace
+        if self.highlight_trailing_whitespace {
+            let (r, g, b) = self.trailing_whitespace_color;
+            ctx.set_rgb_fill_color(r, g, b, 0.3);
+            for line in self.lines() {
+                if line.line_number == self.active_line {
+                    continue;
+                }
+                let Some(start) = trailing_whitespace_start(&line.text) else {
+                    continue;
+                };
+                let x = (text_x + self.renderer.measure_text(&line.text[..start])).max(gutter_w);
+                let w = text_x + self.renderer.measure_text(&line.text) - x;
+                if w <= 0.0 {
+                    continue;
+                }
+                ctx.fill_rect(CGRect::new(
+                    &CGPoint::new(x, line.y_offset),
+                    &CGSize::new(w, self.row_height(line)),
+                ));
+            }
+        }
+
+        // 4b. Draw fold placeholders
+        for fold in &self.fold_placeholders {
+            let Some((x, line_y, w)) = self.fold_placeholder_rect(fold) else {
+                continue;
+            };
+            if x < gutter_w {
+                continue;
+            }
+            let (y, h) = (line_y + 2.0, self.renderer.line_height - 4.0);
+            let (r, g, b) = self.gutter_fg_color;
+            ctx.set_rgb_fill_color(r, g, b, 0.2);
+            let pill = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+            let corners = rounded_corners(&pill, h / 2.0);
+            let last = &corners[corners.len() - 1];
+            ctx.move_to_point(last.end.0, last.end.1);
+            for c in &corners {
+                ctx.add_line_to_point(c.start.0, c.start.1);
+                ctx.add_quad_curve_to_point(c.ctrl.0, c.ctrl.1, c.end.0, c.end.1);
+            }
+            ctx.close_path();
+            ctx.fill_path();
+            text_renderer::draw_text(
+                ctx,
+                FOLD_PLACEHOLDER,
+                x + self.renderer.char_width / 2.0,
+                line_y + lead,
+                &self.renderer.normal,
+                self.renderer.ascent,
+                self.gutter_fg_color,
+            );
+        }
+
+        // 4c. Occurrence highlights: outlined, under the selection
+        ctx.set_line_width(1.0);
+        for occ in &self.occurrences {
+            let (r, g, b) = occ
+                .color
+                .as_deref()
+                .map_or(self.occurrence_color, text_renderer::parse_hex_color);
+            ctx.set_rgb_stroke_color(r, g, b, 1.0);
+            ctx.stroke_rect(CGRect::new(
+                &CGPoint::new(occ.x + 0.5, occ.y + 0.5),
+                &CGSize::new((occ.w - 1.0).max(0.0), (occ.h - 1.0).max(0.0)),
+            ));
         }
 
+        // 5. Draw selection rectangles
+        if self.selection_style == 1 {
+            self.draw_rounded_selections(ctx);
+        } else {
+            let eol_w = self.eol_selection_width();
+            for sel in self.sized_selections().iter() {
+                let w = if sel.eol { sel.w + eol_w } else { sel.w };
+                let (r, g, b) = self.selection_rgb(sel);
+                ctx.set_rgb_fill_color(r, g, b, self.selection_color.3);
+                let rect = CGRect::new(
+                    &CGPoint::new(sel.x, sel.y),
+                    &CGSize::new(w, sel.h),
+                );
+                ctx.fill_rect(rect);
+            }
+        }
+
+        // 5a. Collaborators' selections and carets, under the local caret
+        self.draw_remote_presence(ctx, lead);
+
         // 6. Draw ghost text
         if let Some(ref ghost) = self.ghost_text {
             text_renderer::draw_text(
                 ctx,
                 &ghost.text,
                 ghost.x,
-                ghost.y,
+                ghost.y + lead,
                 &self.renderer.normal,
                 self.renderer.ascent,
                 ghost.color,
             );
         }
 
+        // 6a. IME marked text over the caret, underlined until committed
+        if let Some(ref text) = self.marked_text {
+            self.draw_marked_text(ctx, text, lead);
+        }
+
         // 7. Draw cursors
         self.draw_cursors(ctx);
+
+        // 7a. Caret labels, above their carets
+        for label in &self.caret_labels {
+            let y = self.label_y(label.y, self.row_height_at(label.y));
+            self.draw_label(ctx, label.x, y, &label.text, label.bg, label.fg, lead);
+        }
+
+        // 8. Sticky scroll headers
+        if !self.sticky_lines.is_empty() {
+            self.draw_sticky_lines(ctx, gutter_w, text_x);
+        }
+
+        // 9. Gutter shadow while horizontally scrolled
+        if self.gutter_shown() && self.gutter_shadow && self.scroll_x > 0.0 {
+            self.draw_gutter_shadow(ctx, gutter_w);
+        }
+
+        if self.single_line {
+            ctx.restore();
+        }
+
+        // 10. Inactive dim overlay
+        if !self.focused && self.inactive_dim > 0.0 {
+            ctx.set_rgb_fill_color(
+                self.background_color.0,
+                self.background_color.1,
+                self.background_color.2,
+                self.inactive_dim,
+            );
+            ctx.fill_rect(bounds);
+        }
+
+        // 10a. Visual bell, fading out over everything but popups
+        let bell = self.bell_alpha(now);
+        if bell > 0.0 {
+            let (r, g, b) = self.default_text_color;
+            ctx.set_rgb_fill_color(r, g, b, bell);
+            ctx.fill_rect(bounds);
+        }
+
+        // 11. Completion list, then the hover card, over everything else
+        if let Some(ref popup) = self.completion {
+            self.draw_completion_popup(ctx, popup, lead);
+        }
+        if let Some(ref card) = self.hover_card {
+            self.draw_hover_card(ctx, card, lead);
+        }
+    }
+
+    /// Fill each indent level of every line's leading whitespace with the
+    /// next rainbow color. Widths are measured, so tabs match the text.
+    fn draw_indent_rainbow(&self, ctx: &CGContext, text_x: f64) {
+        for line in self.lines() {
+            let line_h = self.row_height(line);
+            let mut x = text_x;
+            let ends = indent_level_ends(&line.text, self.tab_width);
+            for (level, end) in ends.into_iter().enumerate() {
+                let end_x = text_x + self.renderer.measure_text(&line.text[..end]);
+                let (r, g, b) = self.indent_rainbow[level % self.indent_rainbow.len()];
+                ctx.set_rgb_fill_color(r, g, b, INDENT_RAINBOW_ALPHA);
+                ctx.fill_rect(CGRect::new(
+                    &CGPoint::new(x, line.y_offset),
+                    &CGSize::new(end_x - x, line_h),
+                ));
+                x = end_x;
+            }
+        }
+    }
+
+    /// Fill `rect` with the editor background: the gradient from
+    /// `set_background_gradient`, spanning the whole view height, or the
+    /// flat background color.
+    fn fill_background(&self, ctx: &CGContext, rect: CGRect) {
+        let Some((top, bottom)) = self.background_gradient else {
+            let (r, g, b) = self.background_color;
+            ctx.set_rgb_fill_color(r, g, b, 1.0);
+            ctx.fill_rect(rect);
+            return;
+        };
+        let gradient = CGGradient::create_with_color_components(
+            &CGColorSpace::create_device_rgb(),
+            &[top.0, top.1, top.2, 1.0, bottom.0, bottom.1, bottom.2, 1.0],
+            &[0.0, 1.0],
+            2,
+        );
+        ctx.save();
+        ctx.clip_to_rect(rect);
+        ctx.draw_linear_gradient(
+            &gradient,
+            CGPoint::new(0.0, 0.0),
+            CGPoint::new(0.0, self.height),
+            CGGradientDrawingOptions::empty(),
+        );
+        ctx.restore();
+    }
+
+    /// Soft shadow just right of the gutter, signalling that text is
+    /// scrolled underneath it.
+    fn draw_gutter_shadow(&self, ctx: &CGContext, gutter_w: f64) {
+        let shadow_w = 6.0;
+        let gradient = CGGradient::create_with_color_components(
+            &CGColorSpace::create_device_rgb(),
+            &[0.0, 0.0, 0.0, 0.25, 0.0, 0.0, 0.0, 0.0],
+            &[0.0, 1.0],
+            2,
+        );
+        ctx.save();
+        ctx.clip_to_rect(CGRect::new(
+            &CGPoint::new(gutter_w, 0.0),
+            &CGSize::new(shadow_w, self.height),
+        ));
+        ctx.draw_linear_gradient(
+            &gradient,
+            CGPoint::new(gutter_w, 0.0),
+            CGPoint::new(gutter_w + shadow_w, 0.0),
+            CGGradientDrawingOptions::empty(),
+        );
+        ctx.restore();
+    }
+
+    /// Segment length and height of the `underline-wavy` zigzag. Both scale
+    /// with the font (half a cell wide, half that high; about 4px by 2px at
+    /// 13pt) so the squiggle keeps its shape from small to large sizes.
+    fn squiggle_wave(&self) -> (f64, f64) {
+        let wave_len = (self.renderer.char_width / 2.0).max(2.0);
+        (wave_len, (wave_len / 2.0).max(1.0))
+    }
+
+    /// Draw one decoration overlay, with its opacity scaled by `alpha`.
+    fn draw_decoration(
+        &self,
+        ctx: &CGContext,
+        decor: &DecorationOverlay,
+        gutter_w: f64,
+        alpha: f64,
+    ) {
+        let (r, g, b) = text_renderer::parse_hex_color(&decor.color);
+        match decor.kind.as_str() {
+            "background" => {
+                ctx.set_rgb_fill_color(r, g, b, 0.3 * alpha);
+                let rect = CGRect::new(
+                    &CGPoint::new(decor.x, decor.y),
+                    &CGSize::new(decor.w, decor.h),
+                );
+                ctx.fill_rect(rect);
+            }
+            "underline" => {
+                ctx.set_rgb_stroke_color(r, g, b, alpha);
+                ctx.set_line_width(1.0);
+                let y_bottom = decor.y + decor.h - 1.0;
+                ctx.move_to_point(decor.x, y_bottom);
+                ctx.add_line_to_point(decor.x + decor.w, y_bottom);
+                ctx.stroke_path();
+            }
+            "underline-wavy" => {
+                ctx.set_rgb_stroke_color(r, g, b, alpha);
+                ctx.set_line_width(1.0);
+                let y_base = decor.y + decor.h - 1.0;
+                let (wave_len, wave_height) = self.squiggle_wave();
+                let mut x = decor.x;
+                ctx.move_to_point(x, y_base);
+                let mut up = true;
+                while x < decor.x + decor.w {
+                    let y_target = if up { y_base - wave_height } else { y_base };
+                    x += wave_len;
+                    ctx.add_line_to_point(x, y_target);
+                    up = !up;
+                }
+                ctx.stroke_path();
+            }
+            "line-accent" => {
+                // Thin bar at the content's left edge, ignoring scroll_x
+                ctx.set_rgb_fill_color(r, g, b, alpha);
+                let rect = CGRect::new(
+                    &CGPoint::new(gutter_w, decor.y),
+                    &CGSize::new(LINE_ACCENT_WIDTH, decor.h),
+                );
+                ctx.fill_rect(rect);
+            }
+            _ => {}
+        }
+    }
+
+    /// Pin the sticky scope headers over the top rows, on top of the normal
+    /// lines, selections, and cursors.
+    fn draw_sticky_lines(&self, ctx: &CGContext, gutter_w: f64, text_x: f64) {
+        let line_h = self.renderer.line_height;
+        let lead = self.renderer.half_leading();
+        let bottom = self.sticky_lines.len() as f64 * line_h;
+        self.fill_background(
+            ctx,
+            CGRect::new(&CGPoint::new(0.0, 0.0), &CGSize::new(self.width, bottom)),
+        );
+        let (r, g, b) = self.gutter_bg_color;
+        ctx.set_rgb_fill_color(r, g, b, 1.0);
+        ctx.fill_rect(CGRect::new(&CGPoint::new(0.0, 0.0), &CGSize::new(gutter_w, bottom)));
+
+        if self.gutter_shown() {
+            for (i, sticky) in self.sticky_lines.iter().enumerate() {
+                let num_str = format!("{}", sticky.line_number);
+                let num_x = gutter_w - 20.0 - self.renderer.char_width * num_str.len() as f64;
+                text_renderer::draw_text(
+                    ctx,
+                    &num_str,
+                    num_x,
+                    i as f64 * line_h + lead,
+                    &self.renderer.normal,
+                    self.renderer.ascent,
+                    self.gutter_fg_color,
+                );
+            }
+        }
+
+        ctx.save();
+        ctx.clip_to_rect(CGRect::new(
+            &CGPoint::new(gutter_w, 0.0),
+            &CGSize::new((self.width - gutter_w).max(0.0), bottom),
+        ));
+        for (i, sticky) in self.sticky_lines.iter().enumerate() {
+            text_renderer::draw_line(
+                ctx,
+                &sticky.text,
+                self.visible_tokens(&sticky.tokens),
+                text_x,
+                i as f64 * line_h + lead,
+                &self.renderer,
+                self.default_text_color,
+            );
+        }
+        ctx.restore();
+
+        // Bottom border separating the pinned rows from scrolling content
+        let (r, g, b) = self.gutter_fg_color;
+        ctx.set_rgb_fill_color(r, g, b, 0.4);
+        ctx.fill_rect(CGRect::new(&CGPoint::new(0.0, bottom), &CGSize::new(self.width, 1.0)));
+    }
+
+    /// Fill color for a selection region. Unfocused views draw it as the
+    /// gray of equal luminance, so the live pane's selection stands out.
+    fn selection_rgb(&self, sel: &SelectionRegion) -> (f64, f64, f64) {
+        let (r, g, b) = match sel.color {
+            Some(ref hex) => text_renderer::parse_hex_color(hex),
+            None => (
+                self.selection_color.0,
+                self.selection_color.1,
+                self.selection_color.2,
+            ),
+        };
+        if self.focused {
+            return (r, g, b);
+        }
+        let l = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        (l, l, l)
+    }
+
+    /// Height of a buffered line's row.
+    fn row_height(&self, line: &LineRenderData) -> f64 {
+        line.height.unwrap_or(self.renderer.line_height)
+    }
+
+    /// The buffered line whose row contains `y`.
+    fn row_at(&self, y: f64) -> Option<&LineRenderData> {
+        self.lines()
+            .find(|l| y >= l.y_offset && y < l.y_offset + self.row_height(l))
+    }
+
+    /// Height of the buffered row containing `y`, or `line_height` if none.
+    fn row_height_at(&self, y: f64) -> f64 {
+        self.row_at(y).map_or(self.renderer.line_height, |l| self.row_height(l))
+    }
+
+    /// Selection regions with each row stretched to its line's height, so a
+    /// region sent at the default line height covers a taller line.
+    fn sized_selections(&self) -> Cow<'_, [SelectionRegion]> {
+        if self.lines().all(|l| l.height.is_none()) {
+            return Cow::Borrowed(&self.selections);
+        }
+        let stretch = |s: &SelectionRegion| match self.row_at(s.y).and_then(|l| l.height) {
+            Some(h) => SelectionRegion { h: s.h.max(h), ..s.clone() },
+            None => s.clone(),
+        };
+        Cow::Owned(self.selections.iter().map(stretch).collect())
+    }
+
+    /// True when any selection region overlaps the row `[y, y + h)`.
+    fn row_selected(&self, y: f64, h: f64) -> bool {
+        self.selections.iter().any(|s| s.y < y + h && s.y + s.h > y)
+    }
+
+    /// Fill each run of contiguous selection rows as one outline with rounded corners.
+    fn draw_rounded_selections(&self, ctx: &CGContext) {
+        let selections = self.sized_selections();
+        for (outline, first) in selection_outlines(&selections, self.eol_selection_width()) {
+            if outline.len() < 3 {
+                continue;
+            }
+            let (r, g, b) = self.selection_rgb(&selections[first]);
+            ctx.set_rgb_fill_color(r, g, b, self.selection_color.3);
+
+            let corners = rounded_corners(&outline, SELECTION_CORNER_RADIUS);
+            let last = &corners[corners.len() - 1];
+            ctx.move_to_point(last.end.0, last.end.1);
+            for c in &corners {
+                ctx.add_line_to_point(c.start.0, c.start.1);
+                ctx.add_quad_curve_to_point(c.ctrl.0, c.ctrl.1, c.end.0, c.end.1);
+            }
+            ctx.close_path();
+            ctx.fill_path();
+        }
+    }
+
+    /// The hover card: fill, a one-point border, then its rows.
+    fn draw_hover_card(&self, ctx: &CGContext, card: &HoverCard, lead: f64) {
+        let (r, g, b) = self.widget_bg_color;
+        ctx.set_rgb_fill_color(r, g, b, 1.0);
+        ctx.fill_rect(CGRect::new(&CGPoint::new(card.x, card.y), &CGSize::new(card.w, card.h)));
+        let (r, g, b) = self.widget_border_color;
+        ctx.set_rgb_stroke_color(r, g, b, 1.0);
+        ctx.set_line_width(1.0);
+        ctx.stroke_rect(CGRect::new(
+            &CGPoint::new(card.x + 0.5, card.y + 0.5),
+            &CGSize::new(card.w - 1.0, card.h - 1.0),
+        ));
+        let line_h = self.renderer.line_height;
+        for (i, (text, tokens)) in card.rows.iter().enumerate() {
+            text_renderer::draw_line(
+                ctx,
+                text,
+                self.visible_tokens(tokens),
+                card.x + CARD_PADDING,
+                card.y + CARD_PADDING + i as f64 * line_h + lead,
+                &self.renderer,
+                self.default_text_color,
+            );
+        }
+    }
+
+    /// The completion list: fill, the visible rows (selected one highlighted,
+    /// then glyph, label and dimmed detail), a scroll thumb when it overflows,
+    /// and a one-point border.
+    fn draw_completion_popup(&self, ctx: &CGContext, popup: &CompletionPopup, lead: f64) {
+        let row_h = self.renderer.line_height;
+        let bounds = CGRect::new(&CGPoint::new(popup.x, popup.y), &CGSize::new(popup.w, popup.h));
+        let (r, g, b) = self.widget_bg_color;
+        ctx.set_rgb_fill_color(r, g, b, 1.0);
+        ctx.fill_rect(bounds);
+        ctx.save();
+        ctx.clip_to_rect(bounds);
+        let first = (self.completion_scroll / row_h) as usize;
+        for (i, item) in popup.items.iter().enumerate().skip(first) {
+            let row_y = popup.y + i as f64 * row_h - self.completion_scroll;
+            if row_y >= popup.y + popup.h {
+                break;
+            }
+            if popup.selected == Some(i) {
+                let (r, g, b, _) = self.selection_color;
+                ctx.set_rgb_fill_color(r, g, b, 1.0);
+                ctx.fill_rect(CGRect::new(
+                    &CGPoint::new(popup.x, row_y),
+                    &CGSize::new(popup.w, row_h),
+                ));
+            }
+            let text = |s: &str, x: f64, color: (f64, f64, f64)| {
+                let (font, ascent) = (&self.renderer.normal, self.renderer.ascent);
+                text_renderer::draw_text(ctx, s, x, row_y + lead, font, ascent, color);
+            };
+            let icon_color = item
+                .icon_color
+                .as_deref()
+                .map_or(self.gutter_fg_color, text_renderer::parse_hex_color);
+            text(&item.icon, popup.x + CARD_PADDING, icon_color);
+            text(&item.label, popup.x + popup.label_x, self.default_text_color);
+            text(&item.detail, popup.x + popup.detail_x, self.gutter_fg_color);
+        }
+        let content_h = popup.items.len() as f64 * row_h;
+        if content_h > popup.h {
+            let (r, g, b) = self.widget_border_color;
+            ctx.set_rgb_fill_color(r, g, b, 1.0);
+            ctx.fill_rect(CGRect::new(
+                &CGPoint::new(
+                    popup.x + popup.w - SCROLL_THUMB_WIDTH - 1.0,
+                    popup.y + self.completion_scroll / content_h * popup.h,
+                ),
+                &CGSize::new(SCROLL_THUMB_WIDTH, popup.h * popup.h / content_h),
+            ));
+        }
+        ctx.restore();
+        let (r, g, b) = self.widget_border_color;
+        ctx.set_rgb_stroke_color(r, g, b, 1.0);
+        ctx.set_line_width(1.0);
+        ctx.stroke_rect(CGRect::new(
+            &CGPoint::new(popup.x + 0.5, popup.y + 0.5),
+            &CGSize::new(popup.w - 1.0, popup.h - 1.0),
+        ));
+    }
+
+    /// Top of a label for a caret row at `y`: above the row, or below it
+    /// when the row is at the top edge.
+    fn label_y(&self, y: f64, line_h: f64) -> f64 {
+        let h = self.renderer.line_height;
+        if y >= h { y - h } else { y + line_h }
+    }
+
+    /// A rounded label with its top-left at `(x, y)`, sized to `text`.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_label(
+        &self,
+        ctx: &CGContext,
+        x: f64,
+        y: f64,
+        text: &str,
+        bg: (f64, f64, f64),
+        fg: (f64, f64, f64),
+        lead: f64,
+    ) {
+        let h = self.renderer.line_height;
+        let w = self.renderer.measure_text(text) + LABEL_PADDING * 2.0;
+        ctx.set_rgb_fill_color(bg.0, bg.1, bg.2, 1.0);
+        let rect = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+        let corners = rounded_corners(&rect, LABEL_CORNER_RADIUS);
+        let last = &corners[corners.len() - 1];
+        ctx.move_to_point(last.end.0, last.end.1);
+        for c in &corners {
+            ctx.add_line_to_point(c.start.0, c.start.1);
+            ctx.add_quad_curve_to_point(c.ctrl.0, c.ctrl.1, c.end.0, c.end.1);
+        }
+        ctx.close_path();
+        ctx.fill_path();
+        text_renderer::draw_text(
+            ctx,
+            text,
+            x + LABEL_PADDING,
+            y + lead,
+            &self.renderer.normal,
+            self.renderer.ascent,
+            fg,
+        );
+    }
+
+    /// Collaborators' selections, then their carets with name tags.
+    fn draw_remote_presence(&self, ctx: &CGContext, lead: f64) {
+        for sel in &self.remote_selections {
+            let (r, g, b) = match sel.color {
+                Some(ref hex) => text_renderer::parse_hex_color(hex),
+                None => (self.selection_color.0, self.selection_color.1, self.selection_color.2),
+            };
+            ctx.set_rgb_fill_color(r, g, b, self.selection_color.3);
+            ctx.fill_rect(CGRect::new(
+                &CGPoint::new(sel.x, sel.y),
+                &CGSize::new(sel.w, sel.h),
+            ));
+        }
+        for cursor in &self.remote_cursors {
+            let line_h = self.row_height_at(cursor.y);
+            let (r, g, b) = text_renderer::parse_hex_color(&cursor.color);
+            ctx.set_rgb_fill_color(r, g, b, 1.0);
+            ctx.fill_rect(CGRect::new(
+                &CGPoint::new(cursor.x, cursor.y),
+                &CGSize::new(self.caret_width, line_h),
+            ));
+            if cursor.label.is_empty() {
+                continue;
+            }
+            let tag_y = self.label_y(cursor.y, line_h);
+            self.draw_label(
+                ctx,
+                cursor.x,
+                tag_y,
+                &cursor.label,
+                (r, g, b),
+                self.background_color,
+                lead,
+            );
+        }
+    }
+
+    fn draw_marked_text(&self, ctx: &CGContext, text: &str, lead: f64) {
+        let Some((x, y, _, h)) = self.caret_rect() else {
+            return;
+        };
+        let w = self.measure_text(text);
+        let (r, g, b) = self.background_color;
+        ctx.set_rgb_fill_color(r, g, b, 1.0);
+        ctx.fill_rect(CGRect::new(&CGPoint::new(x, y), &CGSize::new(w, h)));
+        text_renderer::draw_text(
+            ctx,
+            text,
+            x,
+            y + lead,
+            &self.renderer.normal,
+            self.renderer.ascent,
+            self.default_text_color,
+        );
+        let (r, g, b) = self.default_text_color;
+        ctx.set_rgb_fill_color(r, g, b, 1.0);
+        ctx.fill_rect(CGRect::new(&CGPoint::new(x, y + h - 1.0), &CGSize::new(w, 1.0)));
     }
 
     fn draw_cursors(&self, ctx: &CGContext) {
+        // Read-only content has no caret to blink
+        if self.read_only.is_some() {
+            return;
+        }
+        let hollow = self.hollow_caret_unfocused && !self.focused;
         let draw_one = |cursor: &CursorData| {
+            let line_h = self.row_height_at(cursor.y);
             let (w, h) = match cursor.style {
-                0 => (2.0, self.renderer.line_height), // Line cursor
-                1 => (self.renderer.char_width, self.renderer.line_height), // Block cursor
-                2 => (self.renderer.char_width, 2.0),  // Underline cursor
-                _ => (2.0, self.renderer.line_height),
+                0 => (self.caret_width, line_h), // Line cursor
+                1 => (self.renderer.char_width, line_h), // Block cursor
+                2 => (self.renderer.char_width, self.caret_width), // Underline cursor
+                _ => (self.caret_width, line_h),
             };
             let y = if cursor.style == 2 {
-                cursor.y + self.renderer.line_height - 2.0
+                cursor.y + line_h - self.caret_width
             } else {
                 cursor.y
             };
-            ctx.set_rgb_fill_color(
-                self.cursor_color.0,
-                self.cursor_color.1,
-                self.cursor_color.2,
-                1.0,
-            );
+            let (r, g, b) = self.cursor_color;
+            if hollow {
+                // Stroke inside the caret's bounds so it covers the same pixels
+                ctx.set_rgb_stroke_color(r, g, b, 1.0);
+                ctx.set_line_width(1.0);
+                ctx.stroke_rect(CGRect::new(
+                    &CGPoint::new(cursor.x + 0.5, y + 0.5),
+                    &CGSize::new((w - 1.0).max(0.0), (h - 1.0).max(0.0)),
+                ));
+                return;
+            }
+            ctx.set_rgb_fill_color(r, g, b, 1.0);
             let rect = CGRect::new(
                 &CGPoint::new(cursor.x, y),
                 &CGSize::new(w, h),
@@ -516,8 +3469,55 @@ impl EditorView {
     }
 }
 
+// ── Selection geometry ───────────────────────────────────────────
+
+/// Corner radius used by the rounded selection style.
+const SELECTION_CORNER_RADIUS: f64 = 3.0;
+
+/// Width of the `"line-accent"` decoration bar.
+const LINE_ACCENT_WIDTH: f64 = 2.0;
+
+/// Underline color for links from `set_links`.
+const LINK_COLOR: &str = "#3794ff";
+
+/// Horizontal padding inside caret labels and remote name tags.
+const LABEL_PADDING: f64 = 4.0;
+
+/// Corner radius of caret labels and remote name tags.
+const LABEL_CORNER_RADIUS: f64 = 3.0;
+
+/// Space between a hover card's border and its text.
+const CARD_PADDING: f64 = 6.0;
+
+/// Rows a completion list shows before it scrolls.
+const COMPLETION_MAX_ROWS: usize = 12;
+
+/// Width of the scroll thumb in an overflowing completion list.
+const SCROLL_THUMB_WIDTH: f64 = 4.0;
+
+/// Opacity of indent rainbow bands over the background.
+const INDENT_RAINBOW_ALPHA: f64 = 0.12;
+
+/// Opacity of host-shaded regions over the background.
+const REGION_SHADING_ALPHA: f64 = 0.1;
+
+/// Peak opacity of the visual bell wash and how long it takes to fade.
+const VISUAL_BELL_ALPHA: f64 = 0.25;
+const VISUAL_BELL_DURATION: Duration = Duration::from_millis(150);
+
+/// Text drawn inside a fold placeholder pill.
+const FOLD_PLACEHOLDER: &str = "\u{22EF}";
+
+/// Gutter marker below the last line in end-symbol EOF mode.
+const EOF_SYMBOL: &str = "\u{220E}";
+
+/// Gutter marker for rows that continue a wrapped line.
+const WRAP_MARKER: &str = "\u{21AA}";
+
 impl Drop for EditorView {
     fn drop(&mut self) {
+        // The timer retains the NSView and would call back into freed state
+        self.stop_flash_timer();
         if self.nsview != nil {
             unsafe {
                 let _: () = msg_send![self.nsview, removeFromSuperview];
@@ -525,3 +3525,187 @@ impl Drop for EditorView {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT_W: u32 = 320;
+    const SNAPSHOT_H: u32 = 80;
+
+    /// A view at `size` points showing `text` as line 1.
+    fn snapshot_view(size: f64, text: &str, tokens: &serde_json::Value) -> EditorView {
+        let mut view = EditorView::new(SNAPSHOT_W as f64, SNAPSHOT_H as f64);
+        view.set_font("Menlo", size);
+        view.begin_frame();
+        view.render_line(1, text, &tokens.to_string(), 20.0);
+        view.end_frame();
+        view
+    }
+
+    /// Render `view` offscreen and return every pixel `pick` accepts as
+    /// `(x, y)`, so tests compare where ink lands instead of golden images.
+    fn ink(view: &EditorView, pick: fn(&[u8]) -> bool) -> Vec<(usize, usize)> {
+        let mut rgba = vec![0u8; (SNAPSHOT_W * SNAPSHOT_H * 4) as usize];
+        assert!(view.render_to_buffer(SNAPSHOT_W, SNAPSHOT_H, &mut rgba));
+        let width = SNAPSHOT_W as usize;
+        rgba.chunks_exact(4)
+            .enumerate()
+            .filter(|(_, px)| pick(px))
+            .map(|(i, _)| (i % width, i / width))
+            .collect()
+    }
+
+    fn red(px: &[u8]) -> bool {
+        px[0] > px[1].saturating_add(60) && px[0] > px[2].saturating_add(60)
+    }
+
+    fn green(px: &[u8]) -> bool {
+        px[1] > px[0].saturating_add(60) && px[1] > px[2].saturating_add(60)
+    }
+
+    /// Top and bottom rows the pixels span.
+    fn rows(pixels: &[(usize, usize)]) -> (usize, usize) {
+        let top = pixels.iter().map(|&(_, y)| y).min().expect("no ink");
+        let bottom = pixels.iter().map(|&(_, y)| y).max().expect("no ink");
+        (top, bottom)
+    }
+
+    #[test]
+    fn a_smaller_run_shares_the_line_baseline() {
+        // A 14pt line with a 10pt trailing hint. "m" and "n" sit flat on the
+        // baseline, so each run's bottom ink row is where its baseline fell.
+        let tokens = |hint_size: Option<f64>| {
+            serde_json::json!([
+                {"s": 0, "e": 3, "c": "#ff0000", "st": "normal"},
+                {"s": 4, "e": 7, "c": "#00ff00", "st": "normal", "sz": hint_size},
+            ])
+        };
+        let view = snapshot_view(14.0, "mmm nnn", &tokens(Some(10.0)));
+        let (code_top, code_bottom) = rows(&ink(&view, red));
+        let (hint_top, hint_bottom) = rows(&ink(&view, green));
+        assert!(hint_bottom.abs_diff(code_bottom) <= 1, "{hint_bottom} vs {code_bottom}");
+        assert!(hint_bottom - hint_top < code_bottom - code_top, "hint was not smaller");
+        // The hint leaves the 14pt run where it is without one.
+        let plain = snapshot_view(14.0, "mmm nnn", &tokens(None));
+        assert_eq!(rows(&ink(&plain, red)), (code_top, code_bottom));
+    }
+
+    #[test]
+    fn a_diagnostic_squiggles_in_its_severity_color() {
+        let mut view = EditorView::new(SNAPSHOT_W as f64, SNAPSHOT_H as f64);
+        view.set_font("Menlo", 14.0);
+        assert!(view.set_diagnostic_color("warning", "#00ff00"));
+        assert!(!view.set_diagnostic_color("hint", "#ff0000"));
+        view.begin_frame();
+        view.render_line(1, "let value = 1;", "[]", 20.0);
+        let diagnostic = serde_json::json!([
+            {"line": 1, "startCol": 4, "endCol": 9, "severity": "warning"},
+        ]);
+        view.set_diagnostics(&diagnostic.to_string());
+        view.end_frame();
+        let pixels = ink(&view, green);
+        assert!(!pixels.is_empty(), "no squiggle in the warning color");
+        // The squiggle stays under "value".
+        let start = view.gutter_width() + 4.0 * view.char_width();
+        let end = start + 5.0 * view.char_width();
+        assert!(pixels.iter().all(|&(x, _)| x as f64 >= start - 1.0 && x as f64 <= end + 1.0));
+        assert!(ink(&view, red).is_empty(), "the unknown severity was applied");
+    }
+
+    #[test]
+    fn each_cursor_style_inks_its_own_footprint() {
+        let mut view = EditorView::new(SNAPSHOT_W as f64, SNAPSHOT_H as f64);
+        view.set_font("Menlo", 14.0);
+        view.on_focus_changed(true);
+        view.cursor_color = (1.0, 0.0, 0.0);
+        // Line, block and underline carets 60px apart, clear of the gutter.
+        let x = view.gutter_width().ceil() as usize + 20;
+        let [line, block, underline] = [x, x + 60, x + 120];
+        view.begin_frame();
+        let cursors = serde_json::json!([
+            {"x": line, "y": 20, "style": 0},
+            {"x": block, "y": 20, "style": 1},
+            {"x": underline, "y": 20, "style": 2},
+        ]);
+        view.set_cursors(&cursors.to_string());
+        view.end_frame();
+        let pixels = ink(&view, red);
+        // Width and top/bottom rows of the ink in the 40px from `x`.
+        let footprint = |x: usize| {
+            let caret: Vec<_> =
+                pixels.iter().copied().filter(|&(px, _)| (x..x + 40).contains(&px)).collect();
+            let left = caret.iter().map(|&(px, _)| px).min().expect("no caret");
+            let right = caret.iter().map(|&(px, _)| px).max().expect("no caret");
+            assert_eq!(left, x);
+            (right - left + 1, rows(&caret))
+        };
+        let (cw, line_h) = (view.char_width(), view.line_height());
+        let near = |px: usize, expected: f64| (px as f64 - expected).abs() <= 1.0;
+        let (w, (top, bottom)) = footprint(line);
+        assert!(w == 2 && top == 20 && near(bottom - top + 1, line_h), "line: {w} {top}..{bottom}");
+        let (w, (top, bottom)) = footprint(block);
+        assert!(near(w, cw) && top == 20 && near(bottom - top + 1, line_h), "block: {w}");
+        let (w, (top, bottom)) = footprint(underline);
+        assert!(near(w, cw) && near(bottom - top + 1, 2.0), "underline: {w} {top}..{bottom}");
+        assert!(near(bottom + 1, 20.0 + line_h), "underline sits at {bottom}");
+    }
+
+    #[test]
+    fn rulers_are_drawn_at_their_columns() {
+        let mut view = EditorView::new(SNAPSHOT_W as f64, SNAPSHOT_H as f64);
+        view.set_font("Menlo", 14.0);
+        view.set_ruler_color("#00ff00");
+        view.set_rulers("[4,10]");
+        view.begin_frame();
+        view.end_frame();
+        let pixels = ink(&view, green);
+        let mut xs: Vec<usize> = pixels.iter().map(|&(x, _)| x).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        let expected: Vec<usize> = [4.0, 10.0]
+            .iter()
+            .map(|col| (view.gutter_width() + col * view.char_width()).floor() as usize)
+            .collect();
+        assert_eq!(xs, expected);
+        // Each ruler is one pixel wide and spans the whole view.
+        assert_eq!(pixels.len(), 2 * SNAPSHOT_H as usize);
+    }
+
+    /// Draw a 120px wavy underline at `size` points and return the wave the
+    /// view chose, the rows the ink spans, and how many crests it has.
+    fn squiggle(size: f64) -> ((f64, f64), usize, usize) {
+        let mut view = EditorView::new(SNAPSHOT_W as f64, SNAPSHOT_H as f64);
+        view.set_font("Menlo", size);
+        view.begin_frame();
+        let decor = serde_json::json!([
+            {"x": 160, "y": 20, "w": 120, "h": 20, "color": "#ff0000", "type": "underline-wavy"},
+        ]);
+        view.render_decorations(&decor.to_string());
+        view.end_frame();
+        let pixels = ink(&view, red);
+        let (top, bottom) = rows(&pixels);
+        // Each crest leaves a separate run of ink along the topmost row.
+        let mut crest_xs: Vec<usize> =
+            pixels.iter().filter(|&&(_, y)| y == top).map(|&(x, _)| x).collect();
+        crest_xs.sort_unstable();
+        let crests = 1 + crest_xs.windows(2).filter(|pair| pair[1] > pair[0] + 1).count();
+        (view.squiggle_wave(), bottom - top + 1, crests)
+    }
+
+    #[test]
+    fn wavy_underline_scales_with_the_font() {
+        let mut drawn = Vec::new();
+        for size in [10.0, 24.0] {
+            let ((wave_len, wave_height), height, crests) = squiggle(size);
+            // One crest per two segments; the 1px stroke adds a row.
+            let expected_crests = 120.0 / (2.0 * wave_len);
+            assert!((crests as f64 - expected_crests).abs() <= 2.0, "{size}pt: {crests} crests");
+            assert!((height as f64 - (wave_height + 1.0)).abs() <= 2.0, "{size}pt: {height}px");
+            drawn.push((height, crests));
+        }
+        let (small, large) = (drawn[0], drawn[1]);
+        assert!(small.0 < large.0, "24pt wave is not taller: {small:?} vs {large:?}");
+        assert!(small.1 > large.1, "24pt wave is not longer: {small:?} vs {large:?}");
+    }
+}
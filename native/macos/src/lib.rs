@@ -7,124 +7,291 @@
 extern crate objc;
 
 mod text_renderer;
+mod theme;
 mod view;
 mod editor_view;
 mod metal_blitter;
 
-pub use editor_view::EditorView;
+pub use editor_view::{EditorView, StatusInfo};
+pub use hone_editor_common::handles::ViewHandle;
 
-use editor_view::{ActionCallback, MouseDownCallback, ScrollCallback, TextInputCallback};
+use editor_view::{
+    ActionCallback, DidScrollCallback, FocusCallback, FrameCallback, KeyCallback,
+    MetricsCallback, MouseDownCallback, ScrollCallback, TextInputCallback,
+    ThemeChangedCallback,
+};
+use hone_editor_common::handles::HandleTable;
+use std::cell::RefCell;
 use std::ffi::{c_char, CStr};
 
+thread_local! {
+    /// Views created on this thread. Views are single-threaded, so a handle
+    /// only resolves on the thread that created it.
+    static VIEWS: RefCell<HandleTable<EditorView>> = const { RefCell::new(HandleTable::new()) };
+}
+
+/// Resolve a handle from the host to its live view. Returns None for 0, for
+/// a destroyed view's handle, and on any thread other than the view's own,
+/// so such calls are ignored (mutating ones panic in debug builds).
+fn view_ref<'a>(view: ViewHandle) -> Option<&'a EditorView> {
+    VIEWS.with(|views| views.borrow().get(view)).map(|view| unsafe { &*view })
+}
+
+/// `view_ref` for calls that mutate the view. Views are single-threaded, so
+/// debug builds panic if the handle belongs to a view on another thread.
+fn view_mut<'a>(view: ViewHandle) -> Option<&'a mut EditorView> {
+    let found = VIEWS.with(|views| views.borrow().get(view));
+    #[cfg(debug_assertions)]
+    if found.is_none() {
+        hone_editor_common::handles::owners::assert_not_foreign(view);
+    }
+    found.map(|view| unsafe { &mut *view })
+}
+
+/// Move a new view into this thread's table and return its handle, or 0 if
+/// the table is full (the view is dropped).
+fn register(view: Box<EditorView>) -> ViewHandle {
+    VIEWS.with(|views| {
+        let mut views = views.borrow_mut();
+        let Some(handle) = views.insert(view) else {
+            return 0;
+        };
+        if let Some(view) = views.get(handle) {
+            unsafe { (*view).handle = handle };
+        }
+        #[cfg(debug_assertions)]
+        hone_editor_common::handles::owners::claim(handle);
+        handle
+    })
+}
+
 // === FFI Contract Implementation ===
 
-/// Create a new editor view with the given dimensions.
+/// Create a new editor view with the given dimensions and return its handle.
 #[no_mangle]
-pub extern "C" fn hone_editor_create(width: f64, height: f64) -> *mut EditorView {
+pub extern "C" fn hone_editor_create_handle(width: f64, height: f64) -> ViewHandle {
     let mut ev = Box::new(EditorView::new(width, height));
     ev.init_nsview();
-    Box::into_raw(ev)
+    register(ev)
 }
 
 /// Attach the editor view to a parent NSView.
 #[no_mangle]
-pub extern "C" fn hone_editor_attach_to_view(view: *mut EditorView, parent_view: i64) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_attach_to_view(view: ViewHandle, parent_view: i64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.attach_to_parent(parent_view as *mut std::ffi::c_void);
 }
 
-/// Destroy an editor view and free all resources.
+/// Destroy an editor view and free all resources. Stale handles are ignored.
 #[no_mangle]
-pub extern "C" fn hone_editor_destroy(view: *mut EditorView) {
-    if !view.is_null() {
-        unsafe { drop(Box::from_raw(view)); }
-    }
+pub extern "C" fn hone_editor_destroy_handle(view: ViewHandle) {
+    // Drop outside the table borrow so teardown can't observe it.
+    let Some(removed) = VIEWS.with(|views| views.borrow_mut().remove(view)) else {
+        return;
+    };
+    #[cfg(debug_assertions)]
+    hone_editor_common::handles::owners::release(view);
+    drop(removed);
 }
 
 /// Set the editor font family and size.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_font(
-    view: *mut EditorView,
+    view: ViewHandle,
     family: *const c_char,
     size: f64,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let family_str = unsafe { CStr::from_ptr(family) }.to_str().unwrap_or("Menlo");
     view.set_font(family_str, size);
 }
 
+/// Set the editor font with a base weight (100–900, 400 is regular; other
+/// values are clamped) and slant, e.g. a Medium UI font. Bold token runs
+/// use a heavier weight and keep the slant.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_font_ex(
+    view: ViewHandle,
+    family: *const c_char,
+    size: f64,
+    weight: i32,
+    italic: bool,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let family_str = unsafe { CStr::from_ptr(family) }.to_str().unwrap_or("Menlo");
+    view.set_font_ex(family_str, size, weight.clamp(100, 900) as u16, italic);
+}
+
+/// Register a TrueType/OpenType font from `len` bytes at `bytes` under
+/// `name`, so a later `hone_editor_set_font(view, name, ..)` uses it without
+/// the font being installed. The bytes are copied. The font stays
+/// registered until the view is destroyed; returns false if the data is
+/// not a usable font.
+#[no_mangle]
+pub extern "C" fn hone_editor_register_font_bytes(
+    view: ViewHandle,
+    name: *const c_char,
+    bytes: *const u8,
+    len: usize,
+) -> bool {
+    if name.is_null() || bytes.is_null() || len == 0 {
+        return false;
+    }
+    let Some(view) = view_mut(view) else {
+        return false;
+    };
+    let Ok(name_str) = unsafe { CStr::from_ptr(name) }.to_str() else {
+        return false;
+    };
+    let data = unsafe { std::slice::from_raw_parts(bytes, len) };
+    view.register_font_bytes(name_str, data)
+}
+
 /// Render a single line of text with syntax coloring.
 #[no_mangle]
 pub extern "C" fn hone_editor_render_line(
-    view: *mut EditorView,
+    view: ViewHandle,
     line_number: i32,
     text: *const c_char,
     tokens_json: *const c_char,
     y_offset: f64,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     let tokens_str = unsafe { CStr::from_ptr(tokens_json) }.to_str().unwrap_or("[]");
     view.render_line(line_number, text_str, tokens_str, y_offset);
 }
 
+/// Render a single line in a row `height` tall, reserving space below the
+/// text (inline diffs, widgets). Cursors and selections on the line span the
+/// full row; the host's `y_offset`s must account for the extra height.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_line_with_height(
+    view: ViewHandle,
+    line_number: i32,
+    text: *const c_char,
+    tokens_json: *const c_char,
+    y_offset: f64,
+    height: f64,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let tokens_str = unsafe { CStr::from_ptr(tokens_json) }.to_str().unwrap_or("[]");
+    view.render_line_with_height(line_number, text_str, tokens_str, y_offset, height);
+}
+
 /// Set the cursor position and style.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_cursor(
-    view: *mut EditorView,
+    view: ViewHandle,
     x: f64,
     y: f64,
     style: i32,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_cursor(x, y, style);
 }
 
 /// Set selection highlight regions.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_selection(
-    view: *mut EditorView,
+    view: ViewHandle,
     regions_json: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(regions_json) }.to_str().unwrap_or("[]");
     view.set_selection(json_str);
 }
 
+/// Set the selection from `[{startLine, startCol, endLine, endCol}]` ranges
+/// (character columns) over the lines rendered this frame; the view measures
+/// the per-line regions. An alternative to `hone_editor_set_selection`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_selection_logical(
+    view: ViewHandle,
+    ranges_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(ranges_json) }.to_str().unwrap_or("[]");
+    view.set_selection_logical(json_str);
+}
+
 /// Set the vertical scroll offset.
 #[no_mangle]
-pub extern "C" fn hone_editor_scroll(view: *mut EditorView, offset_y: f64) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_scroll(view: ViewHandle, offset_y: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.scroll(offset_y);
 }
 
 /// Measure the width of a text string in the current font.
 #[no_mangle]
 pub extern "C" fn hone_editor_measure_text(
-    view: *mut EditorView,
+    view: ViewHandle,
     text: *const c_char,
 ) -> f64 {
-    let view = unsafe { &*view };
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     view.measure_text(text_str)
 }
 
 /// Invalidate the view, triggering a redraw on the next frame.
 #[no_mangle]
-pub extern "C" fn hone_editor_invalidate(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_invalidate(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.invalidate();
 }
 
+/// Repaint only the `(x, y, w, h)` rect in view coordinates, clamped to the
+/// view, when the host knows just that region changed. Maps to
+/// `setNeedsDisplayInRect:` / `InvalidateRect`; GTK 4 redraws the whole
+/// widget. An empty or off-view rect does nothing.
+#[no_mangle]
+pub extern "C" fn hone_editor_invalidate_rect(
+    view: ViewHandle,
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.invalidate_rect(x, y, w, h);
+}
+
 // === Optional Extended FFI ===
 
 /// Render decorations (underlines, backgrounds) for a line.
 #[no_mangle]
 pub extern "C" fn hone_editor_render_decorations(
-    view: *mut EditorView,
+    view: ViewHandle,
     decorations_json: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(decorations_json) }.to_str().unwrap_or("[]");
     view.render_decorations(json_str);
 }
@@ -132,25 +299,31 @@ pub extern "C" fn hone_editor_render_decorations(
 /// Render ghost text (semi-transparent inline completion).
 #[no_mangle]
 pub extern "C" fn hone_editor_render_ghost_text(
-    view: *mut EditorView,
+    view: ViewHandle,
     text: *const c_char,
     x: f64,
     y: f64,
     color: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
     let color_str = unsafe { CStr::from_ptr(color) }.to_str().unwrap_or("#808080");
     view.render_ghost_text(text_str, x, y, color_str);
 }
 
-/// Set multiple cursor positions.
+/// Set multiple cursor positions. `cursors_json` is a JSON array of
+/// `{x, y, style}`; each entry's style (0=line, 1=block, 2=underline) is drawn
+/// independently. Replaces any cursors set earlier in the frame.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_cursors(
-    view: *mut EditorView,
+    view: ViewHandle,
     cursors_json: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let json_str = unsafe { CStr::from_ptr(cursors_json) }.to_str().unwrap_or("[]");
     view.set_cursors(json_str);
 }
@@ -158,52 +331,74 @@ pub extern "C" fn hone_editor_set_cursors(
 /// Set the callback for text input (printable characters).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_text_input_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: TextInputCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_text_input_callback(callback);
 }
 
 /// Set the callback for action selectors (arrows, delete, enter, etc.).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_action_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: ActionCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_action_callback(callback);
 }
 
 /// Set the callback for mouse-down events (click to position cursor).
 #[no_mangle]
 pub extern "C" fn hone_editor_set_mouse_down_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: MouseDownCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_mouse_down_callback(callback);
 }
 
 /// Set the callback for scroll wheel events.
 #[no_mangle]
 pub extern "C" fn hone_editor_set_scroll_callback(
-    view: *mut EditorView,
+    view: ViewHandle,
     callback: ScrollCallback,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_scroll_callback(callback);
 }
 
+/// Set the callback fired with the final offsets after the view scrolls itself.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_did_scroll_callback(
+    view: ViewHandle,
+    callback: DidScrollCallback,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_did_scroll_callback(callback);
+}
+
 /// Add a custom item to the editor's right-click context menu.
 /// The `action_id` is dispatched through the action callback when the item is clicked.
 #[no_mangle]
 pub extern "C" fn hone_editor_add_context_menu_item(
-    view: *mut EditorView,
+    view: ViewHandle,
     title: *const c_char,
     action_id: *const c_char,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     let title_str = unsafe { CStr::from_ptr(title) }.to_str().unwrap_or("");
     let action_str = unsafe { CStr::from_ptr(action_id) }.to_str().unwrap_or("");
     view.add_context_menu_item(title_str, action_str);
@@ -211,28 +406,1403 @@ pub extern "C" fn hone_editor_add_context_menu_item(
 
 /// Remove all custom context menu items.
 #[no_mangle]
-pub extern "C" fn hone_editor_clear_context_menu_items(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_clear_context_menu_items(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.clear_context_menu_items();
 }
 
 /// Get the NSView handle for the editor view (as a raw pointer).
 #[no_mangle]
-pub extern "C" fn hone_editor_nsview(view: *mut EditorView) -> *mut std::ffi::c_void {
-    let view = unsafe { &*view };
+pub extern "C" fn hone_editor_nsview(view: ViewHandle) -> *mut std::ffi::c_void {
+    let Some(view) = view_ref(view) else {
+        return std::ptr::null_mut();
+    };
     view.nsview() as *mut std::ffi::c_void
 }
 
 /// Begin a frame batch.
 #[no_mangle]
-pub extern "C" fn hone_editor_begin_frame(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_begin_frame(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.begin_frame();
 }
 
 /// End a frame batch.
 #[no_mangle]
-pub extern "C" fn hone_editor_end_frame(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub extern "C" fn hone_editor_end_frame(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.end_frame();
 }
+
+/// Blank the view outside a frame: clears lines, cursors, selections,
+/// decorations, and ghost text, then redraws.
+#[no_mangle]
+pub extern "C" fn hone_editor_clear(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.clear();
+}
+
+/// Set the selection drawing style: 0 = per-line rectangles (default),
+/// 1 = contiguous rows merged into a single outline with rounded corners.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_selection_style(view: ViewHandle, style: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_selection_style(style);
+}
+
+/// Set vertical column rulers. `columns_json` is a JSON array of character
+/// columns (e.g. `[80, 120]`); an empty array removes all rulers.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_rulers(view: ViewHandle, columns_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(columns_json) }.to_str().unwrap_or("[]");
+    view.set_rulers(json_str);
+}
+
+/// Set the ruler color as a "#rrggbb" hex string (drawn at 50% opacity).
+#[no_mangle]
+pub extern "C" fn hone_editor_set_ruler_color(view: ViewHandle, color: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let color_str = unsafe { CStr::from_ptr(color) }.to_str().unwrap_or("#5a5a5a");
+    view.set_ruler_color(color_str);
+}
+
+/// Tint each indent level of a line's leading whitespace, cycling through
+/// `colors_json` (`["#rrggbb", ...]`, drawn faint) under the text and
+/// selection. An empty array turns the rainbow off.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_indent_rainbow(
+    view: ViewHandle,
+    colors_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(colors_json) }.to_str().unwrap_or("[]");
+    view.set_indent_rainbow(json_str);
+}
+
+/// Fill the text area with a vertical gradient from `top_color` to
+/// `bottom_color` ("#rrggbb") instead of the flat theme background; the
+/// gutter keeps its color. `enabled = false` restores the flat fill.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_background_gradient(
+    view: ViewHandle,
+    enabled: bool,
+    top_color: *const c_char,
+    bottom_color: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let color = |c: *const c_char| {
+        if c.is_null() {
+            ""
+        } else {
+            unsafe { CStr::from_ptr(c) }.to_str().unwrap_or("")
+        }
+    };
+    view.set_background_gradient(enabled, color(top_color), color(bottom_color));
+}
+
+/// Columns per indent level and per tab stop (default 4).
+#[no_mangle]
+pub extern "C" fn hone_editor_set_tab_width(view: ViewHandle, width: u32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_tab_width(width);
+}
+
+/// Draw the active line's number (see `hone_editor_set_active_line`) bold
+/// and in `color` ("#rrggbb"), marking the caret's row without a full-line
+/// band. A null or empty `color` uses the theme text color.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_active_line_gutter(
+    view: ViewHandle,
+    enabled: bool,
+    color: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let color_str = if color.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(color) }.to_str().ok().filter(|s| !s.is_empty())
+    };
+    view.set_active_line_gutter(enabled, color_str);
+}
+
+/// Number the gutter 0 = absolutely (default), 1 = relative to the active
+/// line (see `hone_editor_set_active_line`), or 2 = relative with the active
+/// line's absolute number. In mode 2 relative numbers are right-aligned and
+/// the absolute number is left-aligned in the number column, so its extra
+/// digits don't make the column jitter as the caret moves.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_line_number_mode(view: ViewHandle, mode: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_line_number_mode(mode);
+}
+
+/// Hint which line (1-based line number) the caret is on; -1 clears the hint.
+/// Used to skip per-line effects that would flicker while typing.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_active_line(view: ViewHandle, line_number: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_active_line(line_number);
+}
+
+/// Enable or disable highlighting of trailing spaces/tabs, painted with the
+/// given "#rrggbb" color at 30% opacity. The active line is never highlighted.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_highlight_trailing_whitespace(
+    view: ViewHandle,
+    enabled: bool,
+    color: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let color_str = unsafe { CStr::from_ptr(color) }.to_str().unwrap_or("#ff0000");
+    view.set_highlight_trailing_whitespace(enabled, color_str);
+}
+/// Mark the end of the document: 0 = none, 1 = faint tildes in the gutter
+/// on rows past the last line (vim style), 2 = an end symbol below it. The
+/// last line comes from `hone_editor_set_line_count`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_eof_markers(view: ViewHandle, mode: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_eof_markers(mode);
+}
+
+/// Set the caret mode: 0 = insert (line caret), 1 = overwrite (block caret).
+/// Cursors pushed with style -1 take the mode's shape; explicit styles
+/// (0=line, 1=block, 2=underline) always win.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_caret_mode(view: ViewHandle, mode: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_caret_mode(mode);
+}
+
+/// Set text antialiasing: 0 = none, 1 = grayscale, 2 = subpixel (LCD).
+/// Any other value restores the platform default. Triggers a redraw.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_text_antialiasing(view: ViewHandle, mode: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_text_antialiasing(mode);
+}
+
+/// Set the line height multiplier (e.g. 1.5). Line height becomes the font's
+/// natural height times `multiplier`; glyphs are centered vertically in the
+/// extra space and text widths are unchanged. Triggers a redraw.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_line_spacing(view: ViewHandle, multiplier: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_line_spacing(multiplier);
+}
+
+/// Get the current line height in points, including line spacing.
+#[no_mangle]
+pub extern "C" fn hone_editor_get_line_height(view: ViewHandle) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
+    view.line_height()
+}
+
+/// Width of one character cell in points: the advance of "0" (the
+/// CSS `ch` unit), the same value the renderer uses for the gutter, rulers
+/// and block caret. Exact for monospace fonts; for proportional fonts it is
+/// an approximation, and hosts should measure text for precise layout.
+#[no_mangle]
+pub extern "C" fn hone_editor_char_width(view: ViewHandle) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
+    view.char_width()
+}
+
+/// Whether every character of `text` (UTF-8) has a glyph in the view's font
+/// or the platform fallback fonts, so hosts can warn about missing glyphs
+/// before showing content. Control characters always pass. Returns false
+/// for invalid UTF-8.
+#[no_mangle]
+pub extern "C" fn hone_editor_font_has_glyphs(view: ViewHandle, text: *const c_char) -> bool {
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    let Ok(text_str) = unsafe { CStr::from_ptr(text) }.to_str() else {
+        return false;
+    };
+    view.font_has_glyphs(text_str)
+}
+
+/// Render the current frame buffer offscreen into `rgba_out`, which must hold
+/// `width * height * 4` bytes. Pixels are premultiplied RGBA, top row first.
+/// Works without a window, so tests and CI can hash or diff the output.
+/// Returns false if the buffer is null, the size is zero, or rendering fails.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_to_buffer(
+    view: ViewHandle,
+    width: u32,
+    height: u32,
+    rgba_out: *mut u8,
+) -> bool {
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    if rgba_out.is_null() || width == 0 || height == 0 {
+        return false;
+    }
+    let out = unsafe { std::slice::from_raw_parts_mut(rgba_out, width as usize * height as usize * 4) };
+    view.render_to_buffer(width, height, out)
+}
+
+/// Force fixed monospace metrics, intended for tests and headless/CI runs
+/// where font loading is unavailable or differs between machines. While set,
+/// `measure_text` returns `char_count * char_width`, and layout uses the given
+/// line height and ascent. Pass `char_width <= 0` to restore real font metrics.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_metrics_override(
+    view: ViewHandle,
+    char_width: f64,
+    line_height: f64,
+    ascent: f64,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let metrics = (char_width > 0.0).then_some((char_width, line_height, ascent));
+    view.set_metrics_override(metrics);
+}
+
+/// Set the total number of lines in the document. Used to clamp scrolling
+/// that the library drives itself (e.g. `hone_editor_reveal`); 0 = unknown.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_line_count(view: ViewHandle, count: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_line_count(count);
+}
+
+/// Scroll so the line at content-space `y_offset` (line index * line height)
+/// is visible. `mode` 0 scrolls to the nearest edge (honoring the scrolloff
+/// margin), 1 centers the line.
+/// The new offset is reported through the scroll callback and the view redraws.
+#[no_mangle]
+pub extern "C" fn hone_editor_reveal(view: ViewHandle, y_offset: f64, mode: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.reveal(y_offset, mode);
+}
+
+/// Scroll so the line at content-space `y_offset` is vertically centered
+/// (Vim's "zz"). The new offset is reported through the scroll callback.
+#[no_mangle]
+pub extern "C" fn hone_editor_center_on(view: ViewHandle, y_offset: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.center_on(y_offset);
+}
+
+/// Keep at least `lines` lines of context between a revealed line and the
+/// viewport edge when revealing in nearest-edge mode (Vim's 'scrolloff').
+#[no_mangle]
+pub extern "C" fn hone_editor_set_scrolloff(view: ViewHandle, lines: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_scrolloff(lines);
+}
+
+/// Set the callback invoked after each painted frame with its render time
+/// in milliseconds. Nothing is timed while no callback is set.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_frame_callback(
+    view: ViewHandle,
+    callback: FrameCallback,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_frame_callback(callback);
+}
+
+/// Render a batch of lines in one call. `lines_json` is a JSON array of
+/// `{line_number, text, tokens, y_offset, height?}` objects; `tokens` is an
+/// inline RenderToken array rather than a string, and `height` an optional
+/// row height as in `hone_editor_render_line_with_height`.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_lines(view: ViewHandle, lines_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json = unsafe { CStr::from_ptr(lines_json) }.to_str().unwrap_or("[]");
+    view.render_lines(json);
+}
+
+/// Keep buffered lines across `begin_frame` so the host can push only
+/// changed lines; `render_line` then replaces a line with the same number.
+/// Cursors, selections and overlays are still per-frame. Call
+/// `hone_editor_clear` after removing lines, since none are dropped.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_persistent_frame(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_persistent_frame(enabled);
+}
+
+/// Replace (or add) one line outside a frame and redraw just its row,
+/// without a `begin_frame`/`end_frame` cycle. For persistent-frame mode.
+#[no_mangle]
+pub extern "C" fn hone_editor_update_line(
+    view: ViewHandle,
+    line_number: i32,
+    text: *const c_char,
+    tokens_json: *const c_char,
+    y_offset: f64,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let tokens_str = unsafe { CStr::from_ptr(tokens_json) }.to_str().unwrap_or("[]");
+    view.update_line(line_number, text_str, tokens_str, y_offset);
+}
+
+/// Draw a bitmap at view point `(x, y)` this frame, clipped to the text area
+/// and cleared on `begin_frame`. `rgba` points to `width * height * 4` bytes:
+/// tightly packed rows, top row first, each pixel 8-bit R, G, B, A with
+/// straight (not premultiplied) alpha. The pixels are copied, so the buffer
+/// may be freed after the call. Reserve the space with taller line rows.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_image(
+    view: ViewHandle,
+    rgba: *const u8,
+    width: u32,
+    height: u32,
+    x: f64,
+    y: f64,
+) {
+    if rgba.is_null() || width == 0 || height == 0 {
+        return;
+    }
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let len = width as usize * height as usize * 4;
+    let pixels = unsafe { std::slice::from_raw_parts(rgba, len) };
+    view.render_image(pixels, width, height, x, y);
+}
+
+/// Render a single line from length-delimited UTF-8 slices. Neither slice
+/// needs a NUL terminator, so the host can pass views into an existing
+/// buffer instead of allocating a CString per line. Invalid UTF-8 is
+/// replaced with U+FFFD.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_line_ptr(
+    view: ViewHandle,
+    line_number: i32,
+    text_ptr: *const u8,
+    text_len: usize,
+    tokens_ptr: *const u8,
+    tokens_len: usize,
+    y_offset: f64,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text = unsafe { utf8_lossy(text_ptr, text_len) };
+    let tokens = unsafe { utf8_lossy(tokens_ptr, tokens_len) };
+    let tokens = if tokens.is_empty() { "[]" } else { &tokens };
+    view.render_line(line_number, &text, tokens, y_offset);
+}
+
+/// Borrow `len` bytes at `ptr` as UTF-8. Null or empty input yields "".
+unsafe fn utf8_lossy<'a>(ptr: *const u8, len: usize) -> std::borrow::Cow<'a, str> {
+    if ptr.is_null() || len == 0 {
+        return std::borrow::Cow::Borrowed("");
+    }
+    String::from_utf8_lossy(std::slice::from_raw_parts(ptr, len))
+}
+
+/// Extend selected lines past their end to show the newline is included.
+/// Applies to regions sent with `"eol": true`, including empty lines.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_selection_full_line(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_selection_full_line(enabled);
+}
+
+/// Let the view scroll `lines` lines past the end of the document, so the
+/// last line can move up from the bottom edge. 0 disables it.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_overscroll(view: ViewHandle, lines: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_overscroll(lines);
+}
+
+/// Give the view the whole document as a JSON array of `{text, tokens?}`
+/// (line N is index N-1) so it lays out the visible rows itself on every
+/// scroll instead of the host pushing them. Sets the line count; frames
+/// keep the rows and only reset overlays. Pass `null` to return to
+/// host-pushed rows.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_content(view: ViewHandle, lines_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json = unsafe { CStr::from_ptr(lines_json) }.to_str().unwrap_or("null");
+    view.set_content(json);
+}
+
+/// Rows laid out past each edge of the viewport in `hone_editor_set_content`
+/// mode (default 2). Larger values render more off-screen rows per scroll
+/// but show fewer blank bands during fast scrolls.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_overscan(view: ViewHandle, lines: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_overscan(lines);
+}
+
+/// Largest scroll offset the view allows, including overscroll. Returns -1
+/// until the document length is known via `hone_editor_set_line_count`.
+#[no_mangle]
+pub extern "C" fn hone_editor_max_scroll(view: ViewHandle) -> f64 {
+    let Some(view) = view_mut(view) else {
+        return -1.0;
+    };
+    view.max_scroll().unwrap_or(-1.0)
+}
+
+/// Measure the advance width of the byte range `[start_byte, end_byte)` of
+/// `text` without the host building prefix strings. Returns 0 for empty or
+/// invalid ranges, including offsets that split a UTF-8 character.
+#[no_mangle]
+pub extern "C" fn hone_editor_measure_range(
+    view: ViewHandle,
+    text: *const c_char,
+    start_byte: usize,
+    end_byte: usize,
+) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    view.measure_range(text_str, start_byte, end_byte)
+}
+
+/// Check that the caller is on the thread that created `view`. Handles only
+/// resolve on their owning thread, so this is false for a foreign thread (or
+/// a destroyed view): exactly the calls the rest of the API ignores.
+#[no_mangle]
+pub extern "C" fn hone_editor_assert_thread(view: ViewHandle) -> bool {
+    view_ref(view).is_some()
+}
+
+/// Apply a built-in color theme: "vscode-dark" (default) or "vscode-light".
+/// Returns false if the name is unknown.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_theme_preset(
+    view: ViewHandle,
+    name: *const c_char,
+) -> bool {
+    let Some(view) = view_mut(view) else {
+        return false;
+    };
+    let name_str = unsafe { CStr::from_ptr(name) }.to_str().unwrap_or("");
+    view.set_theme_preset(name_str)
+}
+
+/// Follow the OS light/dark setting, switching between the vscode-dark and
+/// vscode-light presets as it changes. Disabling keeps the current colors.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_follow_system_appearance(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_follow_system_appearance(enabled);
+}
+
+/// Set the callback invoked after the color theme changes, with "dark",
+/// "light", "high-contrast-dark" or "high-contrast-light", so the host can
+/// re-tokenize or remap token colors and re-push lines. Fires for theme
+/// presets, system appearance changes and high contrast.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_theme_changed_callback(
+    view: ViewHandle,
+    callback: ThemeChangedCallback,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_theme_changed_callback(callback);
+}
+
+/// Set the callback invoked for every key press before default handling.
+/// Returning true from the callback consumes the key, so hosts can bind
+/// shortcuts (toggle comment, add selection, find) that have no standard selector.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_key_callback(view: ViewHandle, callback: KeyCallback) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_key_callback(callback);
+}
+
+/// Number of whole lines visible in the viewport, from the real view height
+/// and current line height. Hosts use it for `pageUp:`/`pageDown:` instead
+/// of guessing. Returns 0 for an invalid view.
+#[no_mangle]
+pub extern "C" fn hone_editor_page_line_count(view: ViewHandle) -> i32 {
+    let Some(view) = view_ref(view) else {
+        return 0;
+    };
+    view.page_line_count()
+}
+
+/// Change the font size without changing the family. Only size-dependent
+/// metrics are rebuilt; the metrics callback fires so the host can re-lay-out.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_font_size(view: ViewHandle, size: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_font_size(size);
+}
+
+/// Set the callback invoked with `(char_width, line_height, ascent)` whenever
+/// font metrics change.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_metrics_callback(
+    view: ViewHandle,
+    callback: MetricsCallback,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_metrics_callback(callback);
+}
+
+/// Scroll line text horizontally by `offset_x` pixels. The gutter stays
+/// fixed and the text slides under it.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_scroll_x(view: ViewHandle, offset_x: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_scroll_x(offset_x);
+}
+
+/// Enable or disable the soft shadow at the gutter edge shown while text
+/// is scrolled horizontally (on by default).
+#[no_mangle]
+pub extern "C" fn hone_editor_set_gutter_shadow(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_gutter_shadow(enabled);
+}
+
+/// Show or hide the whole gutter (shown by default). Hidden, it takes no
+/// width: no line numbers, fold/diff area or background, and text starts at
+/// x = 0. Carets, selections and decorations follow the text origin.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_gutter_visible(view: ViewHandle, visible: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_gutter_visible(visible);
+}
+
+/// Draw a "↪" marker in the gutter, instead of a repeated line number, for
+/// rows that continue a wrapped line (on by default). A row continues a line
+/// when an earlier row this frame has the same line number, as when a host
+/// wraps lines itself and pushes one row per visual line.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_wrap_markers(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_wrap_markers(enabled);
+}
+
+/// One-line input mode for search boxes and inline rename. While enabled the
+/// gutter is hidden, Enter emits `"submit:"` instead of `"insertNewline:"`,
+/// vertical scroll deltas are dropped, and the first row is drawn centered in
+/// the view and clipped to one row (`caret_rect` and `point_to_position`
+/// account for the offset).
+#[no_mangle]
+pub extern "C" fn hone_editor_set_single_line(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_single_line(enabled);
+}
+
+/// Draw a "⋯" badge after column `after_col` of `line` to mark a folded
+/// region. Clicking it emits the action `"unfold:<line>"`. Cleared on
+/// `begin_frame`; the host still decides which lines to render.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_fold_placeholder(
+    view: ViewHandle,
+    line: i32,
+    after_col: i32,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.render_fold_placeholder(line, after_col);
+}
+
+/// Set the sticky scroll headers pinned to the top of the viewport.
+/// `lines_json` is a JSON array of `{line_number, text, tokens}`; each entry
+/// takes one row from the top. Clicking a row emits `"revealLine:<n>"`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_sticky_lines(view: ViewHandle, lines_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json = unsafe { CStr::from_ptr(lines_json) }.to_str().unwrap_or("[]");
+    view.set_sticky_lines(json);
+}
+
+/// Byte offsets of the grapheme cluster boundaries in `text`, from 0 through
+/// its length, so hosts move the caret by user-perceived characters (ZWJ
+/// emoji, flags, and combining marks stay whole). The count is written to
+/// `out_len`. The array belongs to the view and stays valid until the next
+/// call; an invalid view returns null with a count of 0.
+#[no_mangle]
+pub extern "C" fn hone_editor_grapheme_boundaries(
+    view: ViewHandle,
+    text: *const c_char,
+    out_len: *mut usize,
+) -> *const u32 {
+    if !out_len.is_null() {
+        unsafe { *out_len = 0 };
+    }
+    let Some(view) = view_mut(view) else {
+        return std::ptr::null();
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let bounds = view.grapheme_boundaries(text_str);
+    if !out_len.is_null() {
+        unsafe { *out_len = bounds.len() };
+    }
+    bounds.as_ptr()
+}
+
+/// Fill `out` with the caret's 1-based line and display column (tabs and
+/// wide characters accounted for, as `hone_editor_visual_column`) and the
+/// size of the logical selection in characters and lines, so hosts report
+/// "Ln/Col/Sel" the same way. Uses this frame's `set_cursor` and
+/// `set_selection_logical`; line is 0 with no caret. Returns false, leaving
+/// `out` untouched, for an invalid view or null `out`.
+#[no_mangle]
+pub extern "C" fn hone_editor_status_info(view: ViewHandle, out: *mut StatusInfo) -> bool {
+    if out.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    unsafe { *out = view.status_info() };
+    true
+}
+
+/// Display column of byte offset `byte_offset` in `text`, counting a tab as
+/// reaching the next tab stop (see `hone_editor_set_tab_width`) and an East
+/// Asian Wide or Fullwidth character as two cells. An offset inside a
+/// character gives that character's column. Returns 0 for an invalid view.
+#[no_mangle]
+pub extern "C" fn hone_editor_visual_column(
+    view: ViewHandle,
+    text: *const c_char,
+    byte_offset: i32,
+) -> i32 {
+    if text.is_null() {
+        return 0;
+    }
+    let Some(view) = view_ref(view) else {
+        return 0;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    view.visual_column(text_str, byte_offset.max(0) as usize) as i32
+}
+
+/// Byte range of the word around byte column `col` in `text`, by Unicode word
+/// segmentation, for double-click selection and word-wise caret movement. A
+/// caret just after a word picks that word; otherwise the segment under `col`
+/// is used, so punctuation and whitespace runs select as a unit. `subword`
+/// (or subword navigation on) also splits identifiers at underscores and case
+/// changes (`fooBar`).
+/// Returns false, leaving the outputs untouched, for an invalid view.
+#[no_mangle]
+pub extern "C" fn hone_editor_word_range_at(
+    view: ViewHandle,
+    text: *const c_char,
+    col: i32,
+    subword: bool,
+    out_start: *mut i32,
+    out_end: *mut i32,
+) -> bool {
+    if text.is_null() || out_start.is_null() || out_end.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let (start, end) = view.word_range_at(text_str, col.max(0) as usize, subword);
+    unsafe {
+        *out_start = start as i32;
+        *out_end = end as i32;
+    }
+    true
+}
+
+/// Make Alt/Ctrl+arrow stop at camelCase humps and snake_case underscores.
+/// While enabled the word-movement selectors are emitted as
+/// `"moveSubwordLeft:"` / `"moveSubwordRight:"` (and their
+/// `AndModifySelection:` forms), and word ranges always split subwords.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_subword_navigation(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_subword_navigation(enabled);
+}
+
+/// Map view point `(x, y)` to the line number and byte column under it,
+/// using the lines pushed this frame, the gutter width and the horizontal
+/// scroll. Gutter clicks give column 0, clicks past the end of a line give
+/// its length, and rows outside the buffered lines clamp to the nearest one.
+/// Returns false, leaving the outputs untouched, when no lines are buffered.
+#[no_mangle]
+pub extern "C" fn hone_editor_point_to_position(
+    view: ViewHandle,
+    x: f64,
+    y: f64,
+    out_line: *mut i32,
+    out_col: *mut i32,
+) -> bool {
+    if out_line.is_null() || out_col.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    match view.point_to_position(x, y) {
+        Some((line, col)) => {
+            unsafe {
+                *out_line = line;
+                *out_col = col as i32;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Write the byte span and `0xRRGGBB` color of the syntax token covering byte
+/// column `col` on `line`, from the lines pushed this frame (or the persisted
+/// frame), so go-to-definition and hover use exactly the rendered span.
+/// Returns false, leaving the outputs untouched, when the line isn't buffered
+/// or no token covers `col`.
+#[no_mangle]
+pub extern "C" fn hone_editor_token_at(
+    view: ViewHandle,
+    line: i32,
+    col: i32,
+    out_start: *mut i32,
+    out_end: *mut i32,
+    out_color: *mut u32,
+) -> bool {
+    if out_start.is_null() || out_end.is_null() || out_color.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    match view.token_at(line, col.max(0) as usize) {
+        Some((start, end, color)) => {
+            unsafe {
+                *out_start = start as i32;
+                *out_end = end as i32;
+                *out_color = color;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Write the primary caret's rect in view coordinates to `out[0..4]` as
+/// x, y, width, height: the last `set_cursor` position this frame, the caret
+/// width (a cell for block and underline carets) and the line height. For
+/// anchoring host popups. Returns false, leaving `out` untouched, when no
+/// caret is set. `out` must hold 4 f64s.
+#[no_mangle]
+pub extern "C" fn hone_editor_caret_rect(view: ViewHandle, out: *mut f64) -> bool {
+    if out.is_null() {
+        return false;
+    }
+    let Some(view) = view_ref(view) else {
+        return false;
+    };
+    match view.caret_rect() {
+        Some((x, y, w, h)) => {
+            unsafe {
+                *out = x;
+                *out.add(1) = y;
+                *out.add(2) = w;
+                *out.add(3) = h;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Supply the full document text and caret position (UTF-16 offset) that
+/// VoiceOver reads. The frame buffer only holds visible lines, so hosts
+/// should call this whenever the document or caret changes.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_accessible_text(
+    view: ViewHandle,
+    full_text: *const c_char,
+    cursor_offset: usize,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text = unsafe { CStr::from_ptr(full_text) }.to_str().unwrap_or("");
+    view.set_accessible_text(text, cursor_offset);
+}
+
+/// Set the selection anchor (UTF-16 offset) after `set_accessible_text`; the
+/// selection runs from it to the caret. Input methods and Services read it.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_accessible_selection(
+    view: ViewHandle,
+    anchor_offset: usize,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_accessible_selection(anchor_offset);
+}
+
+/// Force the high-contrast palette (pure background and foreground, token
+/// colors flattened to the text color) regardless of the system setting.
+/// Disabling returns to the system setting and the last chosen preset.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_force_high_contrast(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_force_high_contrast(enabled);
+}
+
+/// Set the callback invoked when the view gains or loses keyboard focus.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_focus_callback(view: ViewHandle, callback: FocusCallback) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_focus_callback(callback);
+}
+
+/// Dim the content with a background-colored overlay of opacity `alpha`
+/// while the view is unfocused (0 = off), so the active pane stands out.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_inactive_dim(view: ViewHandle, alpha: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_inactive_dim(alpha);
+}
+
+/// Draw carets as a 1px outline while the view is unfocused (on by default);
+/// pass false to keep them solid.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_hollow_caret_when_unfocused(
+    view: ViewHandle,
+    enabled: bool,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_hollow_caret_when_unfocused(enabled);
+}
+
+/// Read-only mode for non-editable content (diffs, output panes): carets are
+/// hidden and text input and editing selectors (`insert…`, `delete…`, `cut:`,
+/// `paste:`, `undo:`, ...) are dropped, while movement, selection, `copy:`
+/// and `selectAll:` still reach the host. `tint` ("#rrggbb") is drawn faint
+/// over the text area; null or empty for none.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_read_only(
+    view: ViewHandle,
+    enabled: bool,
+    tint: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let tint_str = if tint.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(tint) }.to_str().ok().filter(|s| !s.is_empty())
+    };
+    view.set_read_only(enabled, tint_str);
+}
+
+/// Underline link ranges on rendered lines. `links_json` is a JSON array of
+/// `{line, startCol, endCol, url}`; Ctrl/Cmd+click emits `"openLink:<url>"`.
+/// Call after `render_line`; cleared on `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_links(view: ViewHandle, links_json: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(links_json) }.to_str().unwrap_or("[]");
+    view.set_links(json_str);
+}
+
+/// Draw wavy underlines under diagnostic ranges on rendered lines.
+/// `diagnostics_json` is a JSON array of `{line, startCol, endCol, severity}`
+/// with character columns and severity "error", "warning" or "info"; the
+/// color comes from the theme (see `hone_editor_set_diagnostic_color`). Call
+/// after `render_line`; cleared on `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_diagnostics(
+    view: ViewHandle,
+    diagnostics_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(diagnostics_json) }.to_str().unwrap_or("[]");
+    view.set_diagnostics(json_str);
+}
+
+/// Recolor spans of rendered lines on top of their syntax tokens, for LSP
+/// semantic tokens layered over TextMate highlighting. `overrides_json` is a
+/// JSON array of `{line, startCol, endCol, color}` with character columns and
+/// `"#rrggbb"` colors; later entries win where they overlap. Only the glyph
+/// color changes, so advances match the base tokens. Cleared on
+/// `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_color_overrides(
+    view: ViewHandle,
+    overrides_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(overrides_json) }.to_str().unwrap_or("[]");
+    view.set_color_overrides(json_str);
+}
+
+/// Set mouse cursor regions. `regions_json` is a JSON array of
+/// `{x, y, w, h, cursor}` with cursor `"text"`, `"arrow"`, or `"hand"`; they
+/// override the default arrow over the gutter and hand over links.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_cursor_regions(
+    view: ViewHandle,
+    regions_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(regions_json) }.to_str().unwrap_or("[]");
+    view.set_cursor_regions(json_str);
+}
+
+/// Flash one decoration (a `DecorationOverlay` JSON object) and fade it out
+/// over `duration_ms`. It survives `begin_frame` and is removed once faded.
+#[no_mangle]
+pub extern "C" fn hone_editor_flash_decoration(
+    view: ViewHandle,
+    decoration_json: *const c_char,
+    duration_ms: u32,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(decoration_json) }.to_str().unwrap_or("");
+    view.flash_decoration(json_str, duration_ms);
+}
+
+/// Visual bell: wash the whole view in the theme's text color, fading out
+/// over ~150ms, for "invalid command" feedback without audio. Buffered lines
+/// and other frame state are left alone.
+#[no_mangle]
+pub extern "C" fn hone_editor_visual_bell(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.visual_bell();
+}
+
+/// Show faint placeholder text (e.g. "Start typing...") on the first row
+/// while the document is empty. Pass an empty string to remove it.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_placeholder(
+    view: ViewHandle,
+    text: *const c_char,
+    color: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let color_str = unsafe { CStr::from_ptr(color) }.to_str().unwrap_or("#808080");
+    view.set_placeholder(text_str, color_str);
+}
+
+/// Set the line caret width and underline caret thickness (default 2.0).
+#[no_mangle]
+pub extern "C" fn hone_editor_set_caret_width(view: ViewHandle, px: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_caret_width(px);
+}
+
+/// Write the scrollable content size to `out[0]` (widest line pushed so far,
+/// excluding the gutter) and `out[1]` (`(line_count + overscroll) *
+/// line_height`), for sizing a host scroll container. `out` must hold 2 f64s.
+#[no_mangle]
+pub extern "C" fn hone_editor_content_size(view: ViewHandle, out: *mut f64) {
+    if out.is_null() {
+        return;
+    }
+    let Some(view) = view_ref(view) else {
+        return;
+    };
+    let (width, height) = view.content_size();
+    unsafe {
+        *out = width;
+        *out.add(1) = height;
+    }
+}
+
+/// Outline other occurrences of the selected text. `regions_json` has the
+/// same `{x, y, w, h, color?}` shape as `set_selection`; drawn under the
+/// selection and cleared on `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_occurrence_highlights(
+    view: ViewHandle,
+    regions_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(regions_json) }.to_str().unwrap_or("[]");
+    view.set_occurrence_highlights(json_str);
+}
+
+/// Set the occurrence outline color as a "#rrggbb" hex string, overriding
+/// the theme until the next theme change.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_occurrence_color(view: ViewHandle, color: *const c_char) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let color_str = unsafe { CStr::from_ptr(color) }.to_str().unwrap_or("#8a8a8a");
+    view.set_occurrence_color(color_str);
+}
+
+/// Set the squiggle color for a diagnostic severity ("error", "warning" or
+/// "info") as a "#rrggbb" hex string, overriding the theme until the next
+/// theme change. Returns false for an unknown severity.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_diagnostic_color(
+    view: ViewHandle,
+    severity: *const c_char,
+    color: *const c_char,
+) -> bool {
+    let Some(view) = view_mut(view) else {
+        return false;
+    };
+    let severity_str = unsafe { CStr::from_ptr(severity) }.to_str().unwrap_or("");
+    let color_str = unsafe { CStr::from_ptr(color) }.to_str().unwrap_or("");
+    view.set_diagnostic_color(severity_str, color_str)
+}
+
+/// Tint the gutter behind selected lines. `color` is a "#rrggbb" hex string;
+/// null or empty uses the theme selection color.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_gutter_selection_highlight(
+    view: ViewHandle,
+    enabled: bool,
+    color: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let color_str = if color.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(color) }.to_str().ok().filter(|s| !s.is_empty())
+    };
+    view.set_gutter_selection_highlight(enabled, color_str);
+}
+
+/// Shade vertical spans across the text area, given as a JSON array of
+/// `{startY, endY, color}` with "#rrggbb" colors drawn faint under the text.
+/// The host decides which ranges to shade (e.g. the fold under the pointer).
+/// Cleared on `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_region_shading(
+    view: ViewHandle,
+    regions_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(regions_json) }.to_str().unwrap_or("[]");
+    view.set_region_shading(json_str);
+}
+
+/// Collaborators' carets as a JSON array of `{x, y, color, label}`, drawn
+/// in `color` with a name tag above each. Cleared on `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_remote_cursors(
+    view: ViewHandle,
+    cursors_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(cursors_json) }.to_str().unwrap_or("[]");
+    view.set_remote_cursors(json_str);
+}
+
+/// Collaborators' selections with the `{x, y, w, h, color}` shape of
+/// `set_selection`, drawn over the local selection and under the local
+/// caret. Cleared on `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_set_remote_selections(
+    view: ViewHandle,
+    regions_json: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(regions_json) }.to_str().unwrap_or("[]");
+    view.set_remote_selections(json_str);
+}
+
+/// Draw a rounded `text` label just above the caret at `(x, y)` this frame,
+/// sized to the measured text (or below the line at the top edge). Colors
+/// are "#rrggbb" hex strings. Cleared on `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_caret_label(
+    view: ViewHandle,
+    x: f64,
+    y: f64,
+    text: *const c_char,
+    bg_color: *const c_char,
+    fg_color: *const c_char,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
+    let bg_str = unsafe { CStr::from_ptr(bg_color) }.to_str().unwrap_or("#000000");
+    let fg_str = unsafe { CStr::from_ptr(fg_color) }.to_str().unwrap_or("#ffffff");
+    view.render_caret_label(x, y, text_str, bg_str, fg_str);
+}
+
+/// Show a bordered hover card below the caret row at `(x, y)`, flipped above
+/// it near the bottom edge. `lines_json` is `[{ text, tokens? }]` with tokens
+/// as in `hone_editor_render_line`; lines wrap to `max_width`. Drawn over
+/// everything else and cleared on `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_hover_card(
+    view: ViewHandle,
+    x: f64,
+    y: f64,
+    lines_json: *const c_char,
+    max_width: f64,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(lines_json) }.to_str().unwrap_or("[]");
+    view.render_hover_card(x, y, json_str, max_width);
+}
+
+/// Show a completion list below the caret row at `(x, y)`, flipped above it
+/// near the bottom edge. `items_json` is `[{ label, detail?, icon?, iconColor? }]`;
+/// `selected_index` is highlighted and scrolled into view (negative for none).
+/// Long lists scroll inside the popup. Clicking an item emits
+/// `"acceptCompletion:<index>"` through the action callback. Cleared on
+/// `begin_frame`.
+#[no_mangle]
+pub extern "C" fn hone_editor_render_completion_popup(
+    view: ViewHandle,
+    x: f64,
+    y: f64,
+    items_json: *const c_char,
+    selected_index: i32,
+) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    let json_str = unsafe { CStr::from_ptr(items_json) }.to_str().unwrap_or("[]");
+    view.render_completion_popup(x, y, json_str, selected_index);
+}
+
+// === Pointer Compatibility ===
+//
+// Hosts written against the original pointer-based contract keep working
+// while they migrate: `hone_editor_create` and `hone_editor_destroy` keep
+// their pointer signatures, and `hone_editor_handle_for_view` turns such a
+// pointer into the handle every other entry point takes. Pointers are looked
+// up in this thread's handle table, never dereferenced, so one used after
+// destroy is ignored unless a newer view was allocated at the same address.
+// Only handles rule that out.
+
+/// Create a new editor view and return its address. Deprecated: use
+/// `hone_editor_create_handle`.
+#[no_mangle]
+pub extern "C" fn hone_editor_create(width: f64, height: f64) -> *mut EditorView {
+    hone_editor_view_for_handle(hone_editor_create_handle(width, height))
+}
+
+/// Destroy a view created with `hone_editor_create`. Pointers that aren't a
+/// live view on this thread are ignored. Deprecated: use
+/// `hone_editor_destroy_handle`.
+#[no_mangle]
+pub extern "C" fn hone_editor_destroy(view: *mut EditorView) {
+    hone_editor_destroy_handle(hone_editor_handle_for_view(view));
+}
+
+/// The handle of a view created with `hone_editor_create`, or 0 if the
+/// pointer isn't a live view on this thread.
+#[no_mangle]
+pub extern "C" fn hone_editor_handle_for_view(view: *mut EditorView) -> ViewHandle {
+    VIEWS.with(|views| views.borrow().find(view)).unwrap_or(0)
+}
+
+/// The address of the view behind a handle, for code that still takes a
+/// pointer, or null if the handle doesn't resolve on this thread.
+#[no_mangle]
+pub extern "C" fn hone_editor_view_for_handle(view: ViewHandle) -> *mut EditorView {
+    VIEWS.with(|views| views.borrow().get(view)).unwrap_or(std::ptr::null_mut())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    const W: u32 = 200;
+    const H: u32 = 40;
+
+    fn create() -> ViewHandle {
+        let view = hone_editor_create_handle(W as f64, H as f64);
+        let family = CString::new("Menlo").unwrap();
+        hone_editor_set_font(view, family.as_ptr(), 14.0);
+        view
+    }
+
+    /// The color `view` paints in the bottom-right corner, clear of any text.
+    fn background(view: ViewHandle) -> [u8; 3] {
+        let mut rgba = vec![0u8; (W * H * 4) as usize];
+        assert!(hone_editor_render_to_buffer(view, W, H, rgba.as_mut_ptr()));
+        let px = &rgba[(((H - 4) * W + W - 4) * 4) as usize..];
+        [px[0], px[1], px[2]]
+    }
+
+    /// Whether `view` paints any strongly red pixel.
+    fn draws_red(view: ViewHandle) -> bool {
+        let mut rgba = vec![0u8; (W * H * 4) as usize];
+        assert!(hone_editor_render_to_buffer(view, W, H, rgba.as_mut_ptr()));
+        rgba.chunks_exact(4).any(|px| px[0] > 200 && px[1] < 60 && px[2] < 60)
+    }
+
+    fn set_preset(view: ViewHandle, name: &str) -> bool {
+        let name = CString::new(name).unwrap();
+        hone_editor_set_theme_preset(view, name.as_ptr())
+    }
+
+    #[test]
+    fn theme_preset_repaints_the_background() {
+        let view = create();
+        assert_eq!(background(view), [0x1e, 0x1e, 0x1e]);
+        assert!(set_preset(view, "vscode-light"));
+        assert_eq!(background(view), [0xff, 0xff, 0xff]);
+        // An unknown name is refused and leaves the colors alone.
+        assert!(!set_preset(view, "solarized"));
+        assert_eq!(background(view), [0xff, 0xff, 0xff]);
+        assert!(set_preset(view, "vscode-dark"));
+        assert_eq!(background(view), [0x1e, 0x1e, 0x1e]);
+        hone_editor_destroy_handle(view);
+    }
+
+    #[test]
+    fn high_contrast_applies_its_preset_colors() {
+        let view = create();
+        let text = CString::new("mmm").unwrap();
+        let tokens = CString::new(r##"[{"s":0,"e":3,"c":"#ff0000","st":"normal"}]"##).unwrap();
+        hone_editor_begin_frame(view);
+        hone_editor_render_line(view, 1, text.as_ptr(), tokens.as_ptr(), 0.0);
+        hone_editor_end_frame(view);
+        assert!(draws_red(view));
+        // Forced on a dark preset: black background, tokens flattened to
+        // the plain text color.
+        hone_editor_set_force_high_contrast(view, true);
+        assert_eq!(background(view), [0x00, 0x00, 0x00]);
+        assert!(!draws_red(view));
+        hone_editor_set_force_high_contrast(view, false);
+        assert_eq!(background(view), [0x1e, 0x1e, 0x1e]);
+        assert!(draws_red(view));
+        // The high-contrast presets can also be chosen directly.
+        assert!(set_preset(view, "high-contrast-light"));
+        assert_eq!(background(view), [0xff, 0xff, 0xff]);
+        assert!(!draws_red(view));
+        hone_editor_destroy_handle(view);
+    }
+}
@@ -5,13 +5,24 @@
 
 use core_foundation::attributed_string::CFMutableAttributedString;
 use core_foundation::base::TCFType;
-use core_foundation::string::CFString;
+use core_foundation::data::CFData;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::number::CFNumber;
+use core_foundation::string::{CFString, CFStringRef};
 use core_graphics::color::CGColor;
 use core_graphics::context::CGContext;
 use core_graphics::geometry::CGAffineTransform;
 use core_text::font::{self as ct_font, CTFont};
+use core_text::font_descriptor::{
+    self, kCTFontFamilyNameAttribute, kCTFontTraitsAttribute, kCTFontWeightTrait,
+};
 use core_text::line::CTLine;
-use serde::Deserialize;
+use hone_editor_common::font_cache::FontCache;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::ops::Deref;
+use std::rc::Rc;
 
 // Core Text symbolic traits for creating bold/italic variants
 const K_CT_FONT_BOLD_TRAIT: u32 = 1 << 1;
@@ -25,61 +36,206 @@ extern "C" {
         sym_trait_value: u32,
         sym_trait_mask: u32,
     ) -> core_text::font::CTFontRef;
+    fn CGDataProviderCreateWithCFData(data: core_foundation::data::CFDataRef) -> *mut c_void;
+    fn CGDataProviderRelease(provider: *mut c_void);
+    fn CGFontCreateWithDataProvider(provider: *mut c_void) -> *mut c_void;
+    fn CGFontCopyPostScriptName(font: *mut c_void) -> CFStringRef;
+    fn CGFontRelease(font: *mut c_void);
+    fn CTFontManagerRegisterGraphicsFont(font: *mut c_void, error: *mut *const c_void) -> bool;
+    fn CTFontManagerUnregisterGraphicsFont(font: *mut c_void, error: *mut *const c_void) -> bool;
+    fn CTFontCreateForString(
+        current_font: core_text::font::CTFontRef,
+        string: CFStringRef,
+        range: core_foundation::base::CFRange,
+    ) -> core_text::font::CTFontRef;
 }
 
-/// Token data from the TypeScript layer.
-#[derive(Debug, Deserialize)]
-pub struct RenderToken {
-    /// Start column.
-    pub s: usize,
-    /// End column.
-    pub e: usize,
-    /// Hex color string (e.g., "#569cd6").
-    pub c: String,
-    /// Font style: "normal", "italic", or "bold".
-    pub st: String,
-}
+pub use hone_editor_common::tokens::RenderToken;
 
-/// A set of font variants (normal, bold, italic) with cached metrics.
-pub struct FontSet {
+/// Font variants (normal, bold, italic) for one family and size, with the
+/// metrics measured from them. Shared by every view using that font.
+pub struct Fonts {
     pub normal: CTFont,
     pub bold: CTFont,
     pub italic: CTFont,
+    /// Family as requested; with the size and style, the key in `FONTS`.
+    family: String,
+    /// Weight (100–900) and slant of `normal`.
+    weight: u16,
+    slanted: bool,
+    char_width: f64,
+    ascent: f64,
+    descent: f64,
+    leading: f64,
+}
+
+thread_local! {
+    /// Live `Fonts` by family and point size. Views are created on the main
+    /// thread; entries are weak, so fonts are freed with their last view.
+    static FONTS: RefCell<FontCache<Fonts>> = RefCell::new(FontCache::new());
+}
+
+thread_local! {
+    /// PostScript names of fonts registered from memory, by the name passed
+    /// to `register_font`.
+    static PRIVATE_FONTS: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+/// A font registered with `register_font`: a process-wide Core Text
+/// registration, removed on drop. Fonts already created from it keep
+/// their glyph data.
+pub struct RegisteredFont {
+    name: String,
+    postscript: String,
+    /// Retained CGFontRef.
+    font: *mut c_void,
+}
+
+impl Drop for RegisteredFont {
+    fn drop(&mut self) {
+        PRIVATE_FONTS.with(|fonts| {
+            let mut fonts = fonts.borrow_mut();
+            if fonts.get(&self.name) == Some(&self.postscript) {
+                fonts.remove(&self.name);
+            }
+        });
+        unsafe {
+            CTFontManagerUnregisterGraphicsFont(self.font, std::ptr::null_mut());
+            CGFontRelease(self.font);
+        }
+    }
+}
+
+/// Register a TrueType/OpenType font from `bytes` (copied) with
+/// CTFontManagerRegisterGraphicsFont and make it available to
+/// `FontSet::new` as `name` as well as its own family name. Returns `None`
+/// if the bytes are not a font or Core Text refuses it (e.g. a font with
+/// that PostScript name is already registered).
+pub fn register_font(name: &str, bytes: &[u8]) -> Option<RegisteredFont> {
+    let data = CFData::from_buffer(bytes);
+    unsafe {
+        let provider = CGDataProviderCreateWithCFData(data.as_concrete_TypeRef());
+        if provider.is_null() {
+            return None;
+        }
+        let font = CGFontCreateWithDataProvider(provider);
+        CGDataProviderRelease(provider);
+        if font.is_null() {
+            return None;
+        }
+        if !CTFontManagerRegisterGraphicsFont(font, std::ptr::null_mut()) {
+            CGFontRelease(font);
+            return None;
+        }
+        let postscript =
+            CFString::wrap_under_create_rule(CGFontCopyPostScriptName(font)).to_string();
+        PRIVATE_FONTS.with(|fonts| fonts.borrow_mut().insert(name.to_string(), postscript.clone()));
+        Some(RegisteredFont { name: name.to_string(), postscript, font })
+    }
+}
+
+impl Fonts {
+    /// The `Fonts` another view already holds for `family` at `size` in
+    /// this weight and slant, or the result of `load`, registered for the
+    /// next view to reuse.
+    fn shared(
+        family: &str,
+        size: f64,
+        weight: u16,
+        italic: bool,
+        load: impl FnOnce() -> Fonts,
+    ) -> Rc<Fonts> {
+        FONTS.with(|fonts| fonts.borrow_mut().shared(family, size, weight, italic, load))
+    }
+
+    /// Variants of `normal`, which already has `weight` and `italic` applied.
+    /// Bold is heavier than `normal` and keeps its slant.
+    fn from_font(family: &str, normal: CTFont, size: f64, weight: u16, italic: bool) -> Fonts {
+        let bold = if weight == 400 {
+            create_variant(&normal, size, K_CT_FONT_BOLD_TRAIT)
+        } else {
+            styled(&normal, size, bold_weight(weight), italic)
+        };
+        let italic_face = create_variant(&normal, size, K_CT_FONT_ITALIC_TRAIT);
+        Fonts {
+            // Advance of '0', the CSS `ch` unit: the cell width for
+            // monospace fonts and a fair average for proportional ones
+            char_width: measure_string_width(&normal, "0"),
+            ascent: normal.ascent(),
+            descent: normal.descent(),
+            leading: normal.leading(),
+            normal,
+            bold,
+            italic: italic_face,
+            family: family.to_string(),
+            weight,
+            slanted: italic,
+        }
+    }
+}
+
+/// A view's font: shared `Fonts` (the variants are reached through `Deref`)
+/// plus the metrics, which line spacing and a metrics override adjust per
+/// view.
+pub struct FontSet {
+    fonts: Rc<Fonts>,
     pub char_width: f64,
     pub ascent: f64,
     pub descent: f64,
     pub leading: f64,
     pub line_height: f64,
+    /// Line height from font metrics alone, before any line spacing.
+    pub natural_line_height: f64,
+    /// Font-derived (char_width, ascent, natural_line_height), saved while a
+    /// metrics override is active.
+    saved_metrics: Option<(f64, f64, f64)>,
 }
 
-impl FontSet {
-    /// Create a new FontSet from a font family name and size.
-    pub fn new(family: &str, size: f64) -> Self {
-        let normal = ct_font::new_from_name(family, size)
-            .or(ct_font::new_from_name("Menlo", size))
-            .or(ct_font::new_from_name("Monaco", size))
-            .expect("No monospace font available");
+impl Deref for FontSet {
+    type Target = Fonts;
 
-        let bold = create_variant(&normal, size, K_CT_FONT_BOLD_TRAIT);
-        let italic = create_variant(&normal, size, K_CT_FONT_ITALIC_TRAIT);
+    fn deref(&self) -> &Fonts {
+        &self.fonts
+    }
+}
 
-        let ascent = normal.ascent();
-        let descent = normal.descent();
-        let leading = normal.leading();
-        let line_height = (ascent + descent + leading).ceil();
+impl FontSet {
+    /// Create a FontSet from a font family name, size, weight (100–900,
+    /// 400 is regular) and slant, sharing the fonts of any other view
+    /// already using them.
+    pub fn new(family: &str, size: f64, weight: u16, italic: bool) -> Self {
+        Self::with_fonts(Fonts::shared(family, size, weight, italic, || {
+            // A font registered from memory under this name wins over system fonts
+            let registered = PRIVATE_FONTS.with(|fonts| fonts.borrow().get(family).cloned());
+            let base = ct_font::new_from_name(registered.as_deref().unwrap_or(family), size)
+                .or(ct_font::new_from_name("Menlo", size))
+                .or(ct_font::new_from_name("Monaco", size))
+                .expect("No monospace font available");
+            let normal = styled(&base, size, weight, italic);
+            Fonts::from_font(family, normal, size, weight, italic)
+        }))
+    }
 
-        // Measure the advance width of 'M' for monospace char width
-        let char_width = measure_string_width(&normal, "M");
+    /// Same family and style at a new point size, skipping the family lookup.
+    pub fn resized(&self, size: f64) -> Self {
+        let (weight, italic) = (self.weight, self.slanted);
+        Self::with_fonts(Fonts::shared(&self.family, size, weight, italic, || {
+            let normal = self.normal.clone_with_font_size(size);
+            Fonts::from_font(&self.family, normal, size, weight, italic)
+        }))
+    }
 
+    fn with_fonts(fonts: Rc<Fonts>) -> Self {
+        let line_height = (fonts.ascent + fonts.descent + fonts.leading).ceil();
         FontSet {
-            normal,
-            bold,
-            italic,
-            char_width,
-            ascent,
-            descent,
-            leading,
+            char_width: fonts.char_width,
+            ascent: fonts.ascent,
+            descent: fonts.descent,
+            leading: fonts.leading,
             line_height,
+            natural_line_height: line_height,
+            saved_metrics: None,
+            fonts,
         }
     }
 
@@ -88,9 +244,73 @@ impl FontSet {
         if text.is_empty() {
             return 0.0;
         }
+        if self.saved_metrics.is_some() {
+            return text.chars().count() as f64 * self.char_width;
+        }
         measure_string_width(&self.normal, text)
     }
 
+    /// Whether every character of `text` has a glyph in the view's font or
+    /// the font Core Text falls back to for it. The LastResort font only
+    /// draws placeholder boxes, so it doesn't count. Control characters are
+    /// not drawn and always pass.
+    pub fn has_glyphs(&self, text: &str) -> bool {
+        text.chars().filter(|c| !c.is_control()).all(|c| {
+            let mut buf = [0u16; 2];
+            let units = c.encode_utf16(&mut buf);
+            let count = units.len() as isize;
+            let cf_str = CFString::new(c.encode_utf8(&mut [0; 4]));
+            let font = unsafe {
+                CTFont::wrap_under_create_rule(CTFontCreateForString(
+                    self.normal.as_concrete_TypeRef(),
+                    cf_str.as_concrete_TypeRef(),
+                    core_foundation::base::CFRange::init(0, count),
+                ))
+            };
+            let mut glyphs = [0u16; 2];
+            font.postscript_name() != "LastResort"
+                && unsafe {
+                    font.get_glyphs_for_characters(units.as_ptr(), glyphs.as_mut_ptr(), count)
+                }
+        })
+    }
+
+    /// Scale `line_height` to `multiplier` times the font's natural height.
+    /// Glyph widths (and therefore `measure_text`) are unaffected.
+    pub fn set_line_spacing(&mut self, multiplier: f64) {
+        self.line_height = (self.natural_line_height * multiplier.max(0.5)).ceil();
+    }
+
+    /// Force fixed monospace metrics, for tests and headless runs where real
+    /// font loading is unavailable or varies. While set, `measure_text` is
+    /// `char_count * char_width`. `None` restores the font's own metrics.
+    pub fn set_metrics_override(&mut self, metrics: Option<(f64, f64, f64)>) {
+        match metrics {
+            Some((char_width, line_height, ascent)) => {
+                if self.saved_metrics.is_none() {
+                    self.saved_metrics =
+                        Some((self.char_width, self.ascent, self.natural_line_height));
+                }
+                self.char_width = char_width;
+                self.ascent = ascent;
+                self.natural_line_height = line_height;
+            }
+            None => {
+                if let Some((char_width, ascent, natural)) = self.saved_metrics.take() {
+                    self.char_width = char_width;
+                    self.ascent = ascent;
+                    self.natural_line_height = natural;
+                }
+            }
+        }
+        self.line_height = self.natural_line_height;
+    }
+
+    /// Space above the glyphs that centers them vertically in a spaced line.
+    pub fn half_leading(&self) -> f64 {
+        ((self.line_height - self.natural_line_height) / 2.0).floor()
+    }
+
     /// Get the font variant for a given style string.
     pub fn font_for_style(&self, style: &str) -> &CTFont {
         match style {
@@ -101,6 +321,64 @@ impl FontSet {
     }
 }
 
+/// Weight for bold runs over a base of `weight`: at least bold, and
+/// heavier than the base.
+fn bold_weight(weight: u16) -> u16 {
+    (weight + 300).clamp(700, 900)
+}
+
+/// Core Text weight trait (NSFontWeight) for a CSS-style weight.
+fn ct_weight(weight: u16) -> f64 {
+    match weight {
+        ..=150 => -0.8,
+        ..=250 => -0.6,
+        ..=350 => -0.4,
+        ..=450 => 0.0,
+        ..=550 => 0.23,
+        ..=650 => 0.3,
+        ..=750 => 0.4,
+        ..=850 => 0.56,
+        _ => 0.62,
+    }
+}
+
+/// `base` at `weight`, slanted when `italic`. Regular weight keeps the face
+/// as loaded; other weights pick the closest face of the same family.
+fn styled(base: &CTFont, size: f64, weight: u16, italic: bool) -> CTFont {
+    let font = if weight == 400 { base.clone() } else { with_weight(base, size, weight) };
+    if italic {
+        create_variant(&font, size, K_CT_FONT_ITALIC_TRAIT)
+    } else {
+        font
+    }
+}
+
+/// The face of `base`'s family closest to `weight`. Falls back to `base`
+/// when Core Text can only match another family.
+fn with_weight(base: &CTFont, size: f64, weight: u16) -> CTFont {
+    let family = base.family_name();
+    let (family_key, traits_key, weight_key) = unsafe {
+        (
+            CFString::wrap_under_get_rule(kCTFontFamilyNameAttribute),
+            CFString::wrap_under_get_rule(kCTFontTraitsAttribute),
+            CFString::wrap_under_get_rule(kCTFontWeightTrait),
+        )
+    };
+    let weight_value = CFNumber::from(ct_weight(weight));
+    let traits = CFDictionary::from_CFType_pairs(&[(weight_key, weight_value.as_CFType())]);
+    let attributes = CFDictionary::from_CFType_pairs(&[
+        (family_key, CFString::new(&family).as_CFType()),
+        (traits_key, traits.as_CFType()),
+    ]);
+    let descriptor = font_descriptor::new_from_attributes(&attributes);
+    let font = ct_font::new_from_descriptor(&descriptor, size);
+    if font.family_name() == family {
+        font
+    } else {
+        base.clone()
+    }
+}
+
 /// Create a bold or italic variant of a font. Falls back to the original if
 /// the variant doesn't exist.
 fn create_variant(base: &CTFont, size: f64, trait_mask: u32) -> CTFont {
@@ -216,14 +494,17 @@ pub fn draw_line(
         let color = parse_hex_color(&token.c);
         set_foreground_color(&mut attr_str, token_range, color);
 
-        // Set font style if not normal
-        if token.st != "normal" {
-            let font = font_set.font_for_style(&token.st);
+        // Set font style / size if not the line default
+        if token.st != "normal" || token.sz.is_some() {
+            let mut font = font_set.font_for_style(&token.st).clone();
+            if let Some(size) = token.sz {
+                font = font.clone_with_font_size(size);
+            }
             unsafe {
                 attr_str.set_attribute(
                     token_range,
                     core_text::string_attributes::kCTFontAttributeName,
-                    font,
+                    &font,
                 );
             }
         }
@@ -235,12 +516,18 @@ pub fn draw_line(
     // Set identity text matrix (Core Text expects this)
     ctx.set_text_matrix(&FLIPPED_TEXT_MATRIX);
     // In a flipped coordinate system, y is the top of the line.
-    // Core Text draws from the baseline, so offset by ascent.
+    // Core Text draws from the baseline, so offset by the line font's ascent.
+    // Every run in the CTLine (including smaller `sz` runs) sits on this
+    // baseline; using a run's own ascent here would lift it off the line.
     ctx.set_text_position(x, y + font_set.ascent);
     line.draw(ctx);
 }
 
 /// Draw simple single-color text (used for line numbers in the gutter).
+///
+/// `ascent` is the ascent of the *line's* font, not `font`'s: the baseline is
+/// `y + ascent`, so text in a smaller or larger font lines up with the rest of
+/// the line.
 pub fn draw_text(
     ctx: &CGContext,
     text: &str,
@@ -292,3 +579,16 @@ fn set_foreground_color(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ten_views_share_one_fonts_object() {
+        let views: Vec<FontSet> =
+            (0..10).map(|_| FontSet::new("Menlo", 13.0, 400, false)).collect();
+        assert!(views.iter().all(|v| Rc::ptr_eq(&v.fonts, &views[0].fonts)));
+        assert_eq!(Rc::strong_count(&views[0].fonts), 10);
+    }
+}
@@ -5,22 +5,51 @@
 //! Rust EditorView's draw() method.
 
 use cocoa::base::{id, nil, YES};
-use cocoa::foundation::{NSRect, NSString};
+use cocoa::foundation::{NSPoint, NSRect, NSSize, NSString};
 use objc::declare::ClassDecl;
-use objc::runtime::{Class, Object, Sel, BOOL};
+use objc::runtime::{Class, Object, Protocol, Sel, BOOL, NO};
 use std::ffi::{c_void, CStr, CString};
 use std::sync::Once;
 
-use crate::editor_view::EditorView;
+use crate::editor_view::{
+    CursorKind, EditorView, KEY_MODIFIER_ALT, KEY_MODIFIER_COMMAND, KEY_MODIFIER_CONTROL,
+    KEY_MODIFIER_SHIFT,
+};
 
 static REGISTER_CLASS: Once = Once::new();
 
 /// Ivar name for the pointer back to the Rust EditorView.
 const EDITOR_STATE_IVAR: &str = "honeEditorState";
 
-/// NSEventModifierFlagCommand
+/// NSEventModifierFlag masks
+const NS_SHIFT_KEY_MASK: u64 = 1 << 17;
+const NS_CONTROL_KEY_MASK: u64 = 1 << 18;
+const NS_ALTERNATE_KEY_MASK: u64 = 1 << 19;
 const NS_COMMAND_KEY_MASK: u64 = 1 << 20;
 
+/// `NSNotFound`, the location of an empty `NSRange`.
+const NS_NOT_FOUND: usize = isize::MAX as usize;
+
+/// `NSRange`, with the objc encoding `add_method` needs.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TextRange {
+    location: usize,
+    length: usize,
+}
+
+unsafe impl objc::Encode for TextRange {
+    fn encode() -> objc::Encoding {
+        unsafe { objc::Encoding::from_str("{_NSRange=QQ}") }
+    }
+}
+
+impl TextRange {
+    fn not_found() -> Self {
+        TextRange { location: NS_NOT_FOUND, length: 0 }
+    }
+}
+
 /// Register the HoneEditorView class (idempotent).
 fn ensure_class_registered() {
     REGISTER_CLASS.call_once(|| {
@@ -40,6 +69,14 @@ fn ensure_class_registered() {
                 objc::sel!(acceptsFirstResponder),
                 accepts_first_responder as extern "C" fn(&Object, Sel) -> BOOL,
             );
+            decl.add_method(
+                objc::sel!(becomeFirstResponder),
+                become_first_responder as extern "C" fn(&Object, Sel) -> BOOL,
+            );
+            decl.add_method(
+                objc::sel!(resignFirstResponder),
+                resign_first_responder as extern "C" fn(&Object, Sel) -> BOOL,
+            );
             decl.add_method(
                 objc::sel!(drawRect:),
                 draw_rect as extern "C" fn(&Object, Sel, NSRect),
@@ -56,6 +93,49 @@ fn ensure_class_registered() {
                 objc::sel!(doCommandBySelector:),
                 do_command_by_selector as extern "C" fn(&Object, Sel, Sel),
             );
+            // NSTextInputClient: IME composition, dictation and Services
+            decl.add_method(
+                objc::sel!(insertText:replacementRange:),
+                insert_text_replacement as extern "C" fn(&Object, Sel, id, TextRange),
+            );
+            decl.add_method(
+                objc::sel!(setMarkedText:selectedRange:replacementRange:),
+                set_marked_text as extern "C" fn(&Object, Sel, id, TextRange, TextRange),
+            );
+            decl.add_method(
+                objc::sel!(unmarkText),
+                unmark_text as extern "C" fn(&Object, Sel),
+            );
+            decl.add_method(
+                objc::sel!(hasMarkedText),
+                has_marked_text as extern "C" fn(&Object, Sel) -> BOOL,
+            );
+            decl.add_method(
+                objc::sel!(markedRange),
+                marked_range as extern "C" fn(&Object, Sel) -> TextRange,
+            );
+            decl.add_method(
+                objc::sel!(selectedRange),
+                selected_range as extern "C" fn(&Object, Sel) -> TextRange,
+            );
+            decl.add_method(
+                objc::sel!(validAttributesForMarkedText),
+                valid_attributes_for_marked_text as extern "C" fn(&Object, Sel) -> id,
+            );
+            decl.add_method(
+                objc::sel!(attributedSubstringForProposedRange:actualRange:),
+                attributed_substring
+                    as extern "C" fn(&Object, Sel, TextRange, *mut c_void) -> id,
+            );
+            decl.add_method(
+                objc::sel!(firstRectForCharacterRange:actualRange:),
+                first_rect_for_character_range
+                    as extern "C" fn(&Object, Sel, TextRange, *mut c_void) -> NSRect,
+            );
+            decl.add_method(
+                objc::sel!(characterIndexForPoint:),
+                character_index_for_point as extern "C" fn(&Object, Sel, NSPoint) -> usize,
+            );
             decl.add_method(
                 objc::sel!(mouseDown:),
                 mouse_down as extern "C" fn(&Object, Sel, id),
@@ -64,6 +144,10 @@ fn ensure_class_registered() {
                 objc::sel!(resetCursorRects),
                 reset_cursor_rects as extern "C" fn(&Object, Sel),
             );
+            decl.add_method(
+                objc::sel!(flashTick:),
+                flash_tick as extern "C" fn(&Object, Sel, id),
+            );
             // Responder-chain actions (also used by context menu)
             decl.add_method(
                 objc::sel!(copy:),
@@ -93,6 +177,35 @@ fn ensure_class_registered() {
                 objc::sel!(contextMenuItemClicked:),
                 context_menu_item_clicked as extern "C" fn(&Object, Sel, id),
             );
+            decl.add_method(
+                objc::sel!(viewDidChangeEffectiveAppearance),
+                view_did_change_effective_appearance as extern "C" fn(&Object, Sel),
+            );
+            // NSAccessibility: expose the text area to VoiceOver
+            decl.add_method(
+                objc::sel!(isAccessibilityElement),
+                is_accessibility_element as extern "C" fn(&Object, Sel) -> BOOL,
+            );
+            decl.add_method(
+                objc::sel!(accessibilityRole),
+                accessibility_role as extern "C" fn(&Object, Sel) -> id,
+            );
+            decl.add_method(
+                objc::sel!(accessibilityValue),
+                accessibility_value as extern "C" fn(&Object, Sel) -> id,
+            );
+            decl.add_method(
+                objc::sel!(accessibilityNumberOfCharacters),
+                accessibility_number_of_characters as extern "C" fn(&Object, Sel) -> isize,
+            );
+            decl.add_method(
+                objc::sel!(accessibilityInsertionPointLineNumber),
+                accessibility_insertion_point_line_number as extern "C" fn(&Object, Sel) -> isize,
+            );
+        }
+
+        if let Some(protocol) = Protocol::get("NSTextInputClient") {
+            decl.add_protocol(protocol);
         }
 
         decl.register();
@@ -107,13 +220,42 @@ extern "C" fn accepts_first_responder(_this: &Object, _sel: Sel) -> BOOL {
     YES
 }
 
+extern "C" fn become_first_responder(this: &Object, _sel: Sel) -> BOOL {
+    unsafe {
+        let accepted: BOOL = msg_send![super(this, class!(NSView)), becomeFirstResponder];
+        if accepted == YES {
+            notify_focus(this, true);
+        }
+        accepted
+    }
+}
+
+extern "C" fn resign_first_responder(this: &Object, _sel: Sel) -> BOOL {
+    unsafe {
+        let accepted: BOOL = msg_send![super(this, class!(NSView)), resignFirstResponder];
+        if accepted == YES {
+            notify_focus(this, false);
+        }
+        accepted
+    }
+}
+
+/// Forward a first-responder change to the EditorView.
+unsafe fn notify_focus(this: &Object, focused: bool) {
+    let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+    if !state_ptr.is_null() {
+        let editor_view = &mut *(state_ptr as *mut EditorView);
+        editor_view.on_focus_changed(focused);
+    }
+}
+
 extern "C" fn draw_rect(this: &Object, _sel: Sel, dirty_rect: NSRect) {
     unsafe {
         let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
         if state_ptr.is_null() {
             return;
         }
-        let editor_view = &*(state_ptr as *const EditorView);
+        let editor_view = &mut *(state_ptr as *mut EditorView);
 
         let gfx_ctx: id = msg_send![class!(NSGraphicsContext), currentContext];
         if gfx_ctx == nil {
@@ -124,7 +266,7 @@ extern "C" fn draw_rect(this: &Object, _sel: Sel, dirty_rect: NSRect) {
             return;
         }
 
-        editor_view.draw(cg_ctx, dirty_rect);
+        editor_view.paint(cg_ctx, dirty_rect);
     }
 }
 
@@ -132,6 +274,27 @@ extern "C" fn key_down(this: &Object, _sel: Sel, event: id) {
     unsafe {
         let flags: u64 = msg_send![event, modifierFlags];
 
+        // Give the host first refusal on every key
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if !state_ptr.is_null() {
+            let editor_view = &mut *(state_ptr as *mut EditorView);
+            let keycode: u16 = msg_send![event, keyCode];
+            let mut modifiers = 0;
+            for (mask, bit) in [
+                (NS_SHIFT_KEY_MASK, KEY_MODIFIER_SHIFT),
+                (NS_CONTROL_KEY_MASK, KEY_MODIFIER_CONTROL),
+                (NS_ALTERNATE_KEY_MASK, KEY_MODIFIER_ALT),
+                (NS_COMMAND_KEY_MASK, KEY_MODIFIER_COMMAND),
+            ] {
+                if flags & mask != 0 {
+                    modifiers |= bit;
+                }
+            }
+            if editor_view.on_key(keycode as u32, modifiers) {
+                return;
+            }
+        }
+
         // Intercept Cmd+key shortcuts (without a menu bar these don't route
         // through the responder chain automatically)
         if flags & NS_COMMAND_KEY_MASK != 0 {
@@ -169,15 +332,191 @@ extern "C" fn insert_text(this: &Object, _sel: Sel, string: id) {
             return;
         }
         let editor_view = &mut *(state_ptr as *mut EditorView);
+        editor_view.insert_text(&input_string(string));
+    }
+}
 
-        let utf8: *const i8 = msg_send![string, UTF8String];
-        if utf8.is_null() {
+/// The host owns the document, so the replacement range is left to it: the
+/// text replaces the selection or the marked text.
+extern "C" fn insert_text_replacement(this: &Object, sel: Sel, string: id, _range: TextRange) {
+    insert_text(this, sel, string);
+}
+
+extern "C" fn set_marked_text(
+    this: &Object,
+    _sel: Sel,
+    string: id,
+    selected: TextRange,
+    _replacement: TextRange,
+) {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return;
+        }
+        let editor_view = &mut *(state_ptr as *mut EditorView);
+        let text = input_string(string);
+        editor_view.set_marked_text(&text, (selected.location, selected.length));
+    }
+}
+
+extern "C" fn unmark_text(this: &Object, _sel: Sel) {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
             return;
         }
-        let text = CStr::from_ptr(utf8).to_str().unwrap_or("");
-        if !text.is_empty() {
-            editor_view.on_text_input(text);
+        let editor_view = &mut *(state_ptr as *mut EditorView);
+        editor_view.unmark_text();
+    }
+}
+
+extern "C" fn has_marked_text(this: &Object, _sel: Sel) -> BOOL {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return NO;
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        if editor_view.marked_range().is_some() {
+            YES
+        } else {
+            NO
+        }
+    }
+}
+
+extern "C" fn marked_range(this: &Object, _sel: Sel) -> TextRange {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return TextRange::not_found();
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        match editor_view.marked_range() {
+            Some((location, length)) => TextRange { location, length },
+            None => TextRange::not_found(),
+        }
+    }
+}
+
+extern "C" fn selected_range(this: &Object, _sel: Sel) -> TextRange {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return TextRange::not_found();
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        let (location, length) = editor_view.selected_range();
+        TextRange { location, length }
+    }
+}
+
+extern "C" fn valid_attributes_for_marked_text(_this: &Object, _sel: Sel) -> id {
+    unsafe { msg_send![class!(NSArray), array] }
+}
+
+extern "C" fn attributed_substring(
+    this: &Object,
+    _sel: Sel,
+    range: TextRange,
+    actual_range: *mut c_void,
+) -> id {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return nil;
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        let Some((text, location, length)) =
+            editor_view.accessible_substring(range.location, range.length)
+        else {
+            return nil;
+        };
+        if !actual_range.is_null() {
+            *(actual_range as *mut TextRange) = TextRange { location, length };
+        }
+        let string: id = msg_send![class!(NSAttributedString), alloc];
+        let string: id = msg_send![string, initWithString: autoreleased_string(&text)];
+        msg_send![string, autorelease]
+    }
+}
+
+/// Screen rect for the IME candidate window: the caret, since only the
+/// caret's position is known to the view.
+extern "C" fn first_rect_for_character_range(
+    this: &Object,
+    _sel: Sel,
+    range: TextRange,
+    actual_range: *mut c_void,
+) -> NSRect {
+    let empty = NSRect::new(NSPoint::new(0.0, 0.0), NSSize::new(0.0, 0.0));
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return empty;
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        let Some((x, y, w, h)) = editor_view.caret_rect() else {
+            return empty;
+        };
+        if !actual_range.is_null() {
+            *(actual_range as *mut TextRange) = range;
+        }
+        let window: id = msg_send![this, window];
+        if window == nil {
+            return empty;
         }
+        let rect = NSRect::new(NSPoint::new(x, y), NSSize::new(w, h));
+        let in_window: NSRect = msg_send![this, convertRect: rect toView: nil];
+        msg_send![window, convertRectToScreen: in_window]
+    }
+}
+
+extern "C" fn character_index_for_point(this: &Object, _sel: Sel, point: NSPoint) -> usize {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return NS_NOT_FOUND;
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        let window: id = msg_send![this, window];
+        if window == nil {
+            return NS_NOT_FOUND;
+        }
+        let screen_rect = NSRect::new(point, NSSize::new(0.0, 0.0));
+        let in_window: NSRect = msg_send![window, convertRectFromScreen: screen_rect];
+        let view_point: NSPoint =
+            msg_send![this, convertPoint: in_window.origin fromView: nil];
+        editor_view
+            .character_index_at(view_point.x, view_point.y)
+            .unwrap_or(NS_NOT_FOUND)
+    }
+}
+
+/// Plain text of an `NSString` or `NSAttributedString` from the input method.
+unsafe fn input_string(string: id) -> String {
+    let attributed: BOOL = msg_send![string, isKindOfClass: class!(NSAttributedString)];
+    let plain: id = if attributed == YES { msg_send![string, string] } else { string };
+    let utf8: *const i8 = msg_send![plain, UTF8String];
+    if utf8.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(utf8).to_str().unwrap_or("").to_string()
+}
+
+/// Map the AppKit-specific selectors that interpretKeyEvents: produces for
+/// Fn/Cmd+arrow keys onto the names the other platforms emit. Word and
+/// document movement (Option/Cmd+arrows) already use the shared names.
+fn normalize_selector(sel_name: &str) -> &str {
+    match sel_name {
+        "scrollPageUp:" => "pageUp:",
+        "scrollPageDown:" => "pageDown:",
+        "moveToLeftEndOfLine:" => "moveToBeginningOfLine:",
+        "moveToRightEndOfLine:" => "moveToEndOfLine:",
+        "moveToLeftEndOfLineAndModifySelection:" => "moveToBeginningOfLineAndModifySelection:",
+        "moveToRightEndOfLineAndModifySelection:" => "moveToEndOfLineAndModifySelection:",
+        other => other,
     }
 }
 
@@ -189,8 +528,11 @@ extern "C" fn do_command_by_selector(this: &Object, _sel: Sel, action: Sel) {
         }
         let editor_view = &mut *(state_ptr as *mut EditorView);
 
-        let sel_name = action.name();
-        editor_view.on_action(sel_name);
+        match action.name() {
+            "insertTab:" => editor_view.on_tab(false),
+            "insertBacktab:" => editor_view.on_tab(true),
+            sel_name => editor_view.on_action(normalize_selector(sel_name)),
+        }
     }
 }
 
@@ -206,16 +548,62 @@ extern "C" fn mouse_down(this: &Object, _sel: Sel, event: id) {
         let view_point: cocoa::foundation::NSPoint =
             msg_send![this, convertPoint: window_point fromView: nil];
 
+        // Clicking away commits a composition in progress
+        if editor_view.marked_range().is_some() {
+            editor_view.unmark_text();
+            let input_context: id = msg_send![this, inputContext];
+            if input_context != nil {
+                let _: () = msg_send![input_context, discardMarkedText];
+            }
+        }
+
+        let flags: u64 = msg_send![event, modifierFlags];
+        if flags & NS_COMMAND_KEY_MASK != 0
+            && editor_view.on_link_click(view_point.x, view_point.y)
+        {
+            return;
+        }
+
         editor_view.on_mouse_down(view_point.x, view_point.y);
     }
 }
 
-/// Set the I-beam cursor for the entire view.
+/// Install cursor rects: I-beam over text, arrow over the gutter, pointing
+/// hand over links, plus any host regions. Later rects take precedence.
 extern "C" fn reset_cursor_rects(this: &Object, _sel: Sel) {
     unsafe {
-        let bounds: NSRect = msg_send![this, bounds];
-        let ibeam: id = msg_send![class!(NSCursor), IBeamCursor];
-        let _: () = msg_send![this, addCursorRect: bounds cursor: ibeam];
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            let bounds: NSRect = msg_send![this, bounds];
+            let ibeam: id = msg_send![class!(NSCursor), IBeamCursor];
+            let _: () = msg_send![this, addCursorRect: bounds cursor: ibeam];
+            return;
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        for (x, y, w, h, kind) in editor_view.cursor_rects() {
+            let cursor: id = match kind {
+                CursorKind::Text => msg_send![class!(NSCursor), IBeamCursor],
+                CursorKind::Arrow => msg_send![class!(NSCursor), arrowCursor],
+                CursorKind::Hand => msg_send![class!(NSCursor), pointingHandCursor],
+            };
+            let rect = NSRect::new(
+                cocoa::foundation::NSPoint::new(x, y),
+                cocoa::foundation::NSSize::new(w, h),
+            );
+            let _: () = msg_send![this, addCursorRect: rect cursor: cursor];
+        }
+    }
+}
+
+/// NSTimer target for flash decoration animation.
+extern "C" fn flash_tick(this: &Object, _sel: Sel, _timer: id) {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return;
+        }
+        let editor_view = &mut *(state_ptr as *mut EditorView);
+        editor_view.tick_flashes();
     }
 }
 
@@ -242,6 +630,15 @@ extern "C" fn scroll_wheel(this: &Object, _sel: Sel, event: id) {
 
         let dx: f64 = msg_send![event, scrollingDeltaX];
         let dy: f64 = msg_send![event, scrollingDeltaY];
+
+        // Cmd+wheel zooms instead of scrolling
+        let flags: u64 = msg_send![event, modifierFlags];
+        if flags & NS_COMMAND_KEY_MASK != 0 {
+            if dy != 0.0 {
+                editor_view.on_action(if dy > 0.0 { "zoomIn:" } else { "zoomOut:" });
+            }
+            return;
+        }
         let precise: BOOL = msg_send![event, hasPreciseScrollingDeltas];
         let (dx, dy) = if precise == YES {
             (dx, dy)
@@ -253,6 +650,119 @@ extern "C" fn scroll_wheel(this: &Object, _sel: Sel, event: id) {
     }
 }
 
+/// The system (or an ancestor view) switched between light and dark mode.
+extern "C" fn view_did_change_effective_appearance(this: &Object, _sel: Sel) {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return;
+        }
+        let editor_view = &mut *(state_ptr as *mut EditorView);
+        editor_view.sync_system_appearance();
+    }
+}
+
+extern "C" fn is_accessibility_element(_this: &Object, _sel: Sel) -> BOOL {
+    YES
+}
+
+extern "C" fn accessibility_role(_this: &Object, _sel: Sel) -> id {
+    unsafe { autoreleased_string("AXTextArea") }
+}
+
+extern "C" fn accessibility_value(this: &Object, _sel: Sel) -> id {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return autoreleased_string("");
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        autoreleased_string(&editor_view.accessibility_value())
+    }
+}
+
+extern "C" fn accessibility_number_of_characters(this: &Object, _sel: Sel) -> isize {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return 0;
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        editor_view.accessibility_value().encode_utf16().count() as isize
+    }
+}
+
+extern "C" fn accessibility_insertion_point_line_number(this: &Object, _sel: Sel) -> isize {
+    unsafe {
+        let state_ptr: *mut c_void = *this.get_ivar(EDITOR_STATE_IVAR);
+        if state_ptr.is_null() {
+            return 0;
+        }
+        let editor_view = &*(state_ptr as *const EditorView);
+        editor_view.accessibility_line_number() as isize
+    }
+}
+
+unsafe fn autoreleased_string(s: &str) -> id {
+    let ns = NSString::alloc(nil).init_str(s);
+    msg_send![ns, autorelease]
+}
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    fn NSAccessibilityPostNotification(element: id, notification: id);
+}
+
+/// Tell assistive apps that the view's text and caret changed.
+pub fn post_accessibility_changed(nsview: id) {
+    if nsview == nil {
+        return;
+    }
+    unsafe {
+        for name in ["AXValueChanged", "AXSelectedTextChanged"] {
+            NSAccessibilityPostNotification(nsview, autoreleased_string(name));
+        }
+    }
+}
+
+/// Whether `nsview`'s effective appearance is Dark Aqua. Without a view,
+/// the application's appearance is used.
+pub fn is_dark_appearance(nsview: id) -> bool {
+    unsafe {
+        let appearance: id = if nsview != nil {
+            msg_send![nsview, effectiveAppearance]
+        } else {
+            let app: id = msg_send![class!(NSApplication), sharedApplication];
+            msg_send![app, effectiveAppearance]
+        };
+        if appearance == nil {
+            return false;
+        }
+        let aqua = NSString::alloc(nil).init_str("NSAppearanceNameAqua");
+        let dark_aqua = NSString::alloc(nil).init_str("NSAppearanceNameDarkAqua");
+        let objects = [aqua, dark_aqua];
+        let names: id = msg_send![class!(NSArray), arrayWithObjects: objects.as_ptr() count: 2usize];
+        let best: id = msg_send![appearance, bestMatchFromAppearancesWithNames: names];
+        let is_dark: BOOL = if best != nil {
+            msg_send![best, isEqualToString: dark_aqua]
+        } else {
+            objc::runtime::NO
+        };
+        let _: () = msg_send![aqua, release];
+        let _: () = msg_send![dark_aqua, release];
+        is_dark == YES
+    }
+}
+
+/// Whether "Increase contrast" is on in the Accessibility display settings.
+pub fn prefers_high_contrast() -> bool {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let increase: BOOL = msg_send![workspace, accessibilityDisplayShouldIncreaseContrast];
+        increase == YES
+    }
+}
+
 /// Build a context menu on right-click.
 ///
 /// Includes default items (Cut, Copy, Paste, Select All) plus any custom
@@ -392,6 +902,52 @@ pub fn invalidate_view(nsview: id) {
     }
 }
 
+/// Redraw only the `(x, y, w, h)` rect on the next display cycle.
+pub fn invalidate_rect(nsview: id, x: f64, y: f64, w: f64, h: f64) {
+    if nsview != nil {
+        let rect = NSRect::new(
+            cocoa::foundation::NSPoint::new(x, y),
+            cocoa::foundation::NSSize::new(w, h),
+        );
+        unsafe {
+            let _: () = msg_send![nsview, setNeedsDisplayInRect: rect];
+        }
+    }
+}
+
+/// Ask AppKit to rebuild the view's cursor rects (e.g. after links or
+/// cursor regions change).
+pub fn invalidate_cursor_rects(nsview: id) {
+    if nsview != nil {
+        unsafe {
+            let window: id = msg_send![nsview, window];
+            if window != nil {
+                let _: () = msg_send![window, invalidateCursorRectsForView: nsview];
+            }
+        }
+    }
+}
+
+/// Schedule a repeating ~60 Hz timer that sends `flashTick:` to the view.
+/// The run loop owns the returned timer until `stop_timer`.
+pub fn start_flash_timer(nsview: id) -> id {
+    unsafe {
+        msg_send![class!(NSTimer),
+            scheduledTimerWithTimeInterval: 1.0 / 60.0
+            target: nsview
+            selector: objc::sel!(flashTick:)
+            userInfo: nil
+            repeats: YES]
+    }
+}
+
+/// Invalidate a timer from `start_flash_timer`.
+pub fn stop_timer(timer: id) {
+    unsafe {
+        let _: () = msg_send![timer, invalidate];
+    }
+}
+
 /// Update the ivar pointer (used if EditorView is moved/recreated).
 pub fn set_editor_state(nsview: id, state: *mut EditorView) {
     if nsview != nil {
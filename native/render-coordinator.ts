@@ -20,6 +20,11 @@ export interface RenderCoordinatorConfig {
   fontSize: number;
   /** Line height multiplier. */
   lineHeight: number;
+  /**
+   * Send all dirty lines through one `renderLines` call per frame instead of
+   * one `renderLine` call each, when the FFI supports it.
+   */
+  batchLines?: boolean;
 }
 
 /**
@@ -39,6 +44,7 @@ export class NativeRenderCoordinator {
   private _lastScrollTop: number = -1;
   private _lastCursorKey: string = '';
   private _lastSelectionKey: string = '';
+  private _lastLineCount: number = -1;
 
   constructor(ffi: NativeEditorFFI, config: RenderCoordinatorConfig) {
     this._ffi = ffi;
@@ -64,9 +70,7 @@ export class NativeRenderCoordinator {
 
     this._handle = this._ffi.create(width, height);
     this._ffi.setFont(this._handle, this._config.fontFamily, this._config.fontSize);
-
-    // Measure a reference character to get char width
-    this._charWidth = this._ffi.measureText(this._handle, 'M');
+    this._charWidth = this.nativeCharWidth(this._handle);
 
     return this._handle;
   }
@@ -134,9 +138,17 @@ export class NativeRenderCoordinator {
       this._lastScrollTop = scroll.scrollTop;
     }
 
+    // Keep the native document length current for library-driven scrolling
+    const lineCount = vm.document.buffer.getLineCount();
+    if (lineCount !== this._lastLineCount) {
+      this._ffi.setLineCount?.(handle, lineCount);
+      this._lastLineCount = lineCount;
+    }
+
     // 2. Render visible lines (with dirty tracking)
     const visibleLines = vm.visibleLines;
     const currentLineNumbers = new Set<number>();
+    const batch: string[] | null = this._config.batchLines && this._ffi.renderLines ? [] : null;
 
     for (const line of visibleLines) {
       currentLineNumbers.add(line.lineNumber);
@@ -146,13 +158,20 @@ export class NativeRenderCoordinator {
         const tokensJson = this.serializeTokens(line.tokens);
         const yOffset = this.computeYOffset(line.lineNumber, scroll.scrollTop);
 
-        this._ffi.renderLine(
-          handle,
-          line.lineNumber + 1, // 1-based display
-          line.content,
-          tokensJson,
-          yOffset,
-        );
+        if (batch) {
+          batch.push(
+            `{"line_number":${line.lineNumber + 1},"text":${JSON.stringify(line.content)},` +
+            `"tokens":${tokensJson},"y_offset":${yOffset}}`,
+          );
+        } else {
+          this._ffi.renderLine(
+            handle,
+            line.lineNumber + 1, // 1-based display
+            line.content,
+            tokensJson,
+            yOffset,
+          );
+        }
 
         // Render decorations if supported
         if (this._ffi.renderDecorations && line.decorations.length > 0) {
@@ -164,6 +183,10 @@ export class NativeRenderCoordinator {
       }
     }
 
+    if (batch && batch.length > 0) {
+      this._ffi.renderLines!(handle, `[${batch.join(',')}]`);
+    }
+
     // Clean up stale line entries
     for (const lineNum of this._renderedLines.keys()) {
       if (!currentLineNumbers.has(lineNum)) {
@@ -191,6 +214,7 @@ export class NativeRenderCoordinator {
     this._renderedLines.clear();
     this._lastCursorKey = '';
     this._lastSelectionKey = '';
+    this._lastLineCount = -1;
     if (this._handle) {
       this._ffi.invalidate(this._handle);
     }
@@ -207,7 +231,7 @@ export class NativeRenderCoordinator {
 
     if (this._handle) {
       this._ffi.setFont(this._handle, family, size);
-      this._charWidth = this._ffi.measureText(this._handle, 'M');
+      this._charWidth = this.nativeCharWidth(this._handle);
 
       if (this._viewModel) {
         this._viewModel.setCharWidth(this._charWidth);
@@ -245,6 +269,9 @@ export class NativeRenderCoordinator {
     if (cursorKey === this._lastCursorKey) return;
     this._lastCursorKey = cursorKey;
 
+    // Tell the native layer which line the caret is editing (1-based)
+    this._ffi.setActiveLine?.(handle, cursors[0].line + 1);
+
     if (cursors.length === 1 || !this._ffi.setCursors) {
       // Single cursor or multi-cursor not supported
       const primary = cursors[0];
@@ -289,7 +316,9 @@ export class NativeRenderCoordinator {
         const w = this.measureTextWidth(handle, lineContent.substring(startCol, endCol));
         const y = this.computeYOffset(line, scroll.scrollTop);
 
-        regions.push({ x, y, w, h: this._lineHeightPx });
+        const region: SelectionRegion = { x, y, w, h: this._lineHeightPx };
+        if (line < sel.endLine) region.eol = true;
+        regions.push(region);
       }
     }
 
@@ -322,6 +351,11 @@ export class NativeRenderCoordinator {
     return this.measureTextWidth(handle, textBeforeCursor) + vm.gutterWidth;
   }
 
+  /** The native cell width, or the width of 'M' where charWidth is missing. */
+  private nativeCharWidth(handle: NativeViewHandle): number {
+    return this._ffi.charWidth?.(handle) ?? this._ffi.measureText(handle, 'M');
+  }
+
   private measureTextWidth(handle: NativeViewHandle, text: string): number {
     if (text.length === 0) return 0;
     return this._ffi.measureText(handle, text);
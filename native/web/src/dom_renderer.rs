@@ -42,6 +42,7 @@ pub fn generate_editor_css(config: &DomRendererConfig) -> String {
     position: relative;
     overflow: hidden;
     {containment}
+    line-height: 1.5;
     font-variant-ligatures: contextual;
     -webkit-font-smoothing: antialiased;
     -moz-osx-font-smoothing: grayscale;
@@ -52,6 +53,15 @@ pub fn generate_editor_css(config: &DomRendererConfig) -> String {
     right: 0;
     white-space: pre;
     pointer-events: none;
+    user-select: text;
+}}
+.{prefix}-placeholder {{
+    position: absolute;
+    left: 0;
+    white-space: pre;
+    font-style: italic;
+    pointer-events: none;
+    user-select: none;
 }}
 .{prefix}-cursor {{
     position: absolute;
@@ -67,6 +77,97 @@ pub fn generate_editor_css(config: &DomRendererConfig) -> String {
     pointer-events: none;
     opacity: 0.3;
 }}
+@media (forced-colors: active) {{
+    .{prefix}-line span {{ color: CanvasText; }}
+    .{prefix}-cursor {{ background: CanvasText; }}
+    .{prefix}-selection {{ background: Highlight; opacity: 1; }}
+}}
+.{prefix}-high-contrast {{ background: Canvas; color: CanvasText; }}
+.{prefix}-high-contrast .{prefix}-line span {{ color: CanvasText; }}
+.{prefix}-high-contrast .{prefix}-cursor {{ background: CanvasText; }}
+.{prefix}-high-contrast .{prefix}-selection {{ background: Highlight; opacity: 0.5; }}
 "#
     )
 }
+
+/// ARIA attributes for the editor container, so assistive technology
+/// announces it as a multi-line text field named `label`.
+pub fn container_aria_attributes(label: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("role", "textbox".to_string()),
+        ("aria-multiline", "true".to_string()),
+        ("aria-label", label.to_string()),
+    ]
+}
+
+/// ARIA attributes for a line `<div>`. The line's text itself lives in its
+/// token `<span>`s as real text nodes (never CSS `content`), which is what
+/// browser Find and screen readers read.
+pub fn line_aria_attributes(line_number: i32) -> Vec<(&'static str, String)> {
+    vec![
+        ("role", "group".to_string()),
+        ("aria-roledescription", "line".to_string()),
+        ("aria-label", format!("Line {line_number}")),
+    ]
+}
+
+/// Line `<div>`s kept across frames and keyed by visible row, so scrolling
+/// rewrites existing elements (text, color, transform) instead of creating
+/// new ones. Elements are only created or removed when the number of
+/// visible rows changes.
+pub struct LinePool<E> {
+    rows: Vec<E>,
+    created: u64,
+    removed: u64,
+}
+
+impl<E> LinePool<E> {
+    pub fn new() -> Self {
+        Self {
+            rows: Vec::new(),
+            created: 0,
+            removed: 0,
+        }
+    }
+
+    /// The element for visible row `row`, creating rows up to it with
+    /// `create` if the pool is shorter.
+    pub fn acquire(&mut self, row: usize, mut create: impl FnMut(usize) -> E) -> &mut E {
+        while self.rows.len() <= row {
+            self.rows.push(create(self.rows.len()));
+            self.created += 1;
+        }
+        &mut self.rows[row]
+    }
+
+    /// Drop the rows from `count` on, handing each to `remove` to detach it.
+    pub fn truncate(&mut self, count: usize, mut remove: impl FnMut(E)) {
+        while self.rows.len() > count {
+            if let Some(el) = self.rows.pop() {
+                remove(el);
+                self.removed += 1;
+            }
+        }
+    }
+
+    /// The element for visible row `row`, if the pool has one.
+    pub fn get(&self, row: usize) -> Option<&E> {
+        self.rows.get(row)
+    }
+
+    /// Total elements created since the pool was made.
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+
+    /// Total elements removed since the pool was made.
+    pub fn removed(&self) -> u64 {
+        self.removed
+    }
+}
+
+impl<E> Default for LinePool<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
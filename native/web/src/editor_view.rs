@@ -1,14 +1,27 @@
 //! Web EditorView: DOM-based rendering.
 //!
 //! Production implementation:
-//! - Each visible line is a <div> with position:absolute and top set by y_offset
-//! - Each token is a <span> with color set to the token color
+//! - Each visible line is a <div> with position:absolute and top set by y_offset;
+//!   the <div>s are pooled by visible row and reused across frames
+//! - Each token is a <span> with color set to the token color, holding its
+//!   text as a text node so browser Find and screen readers see it
+//! - The container has role="textbox" and an aria-label; lines are labelled
+//!   groups (see `dom_renderer::container_aria_attributes`)
 //! - Cursor is a <div> with CSS animation for blinking
 //! - Selection is rendered via <div> overlays with semi-transparent background
 
+use hone_editor_common::graphemes::grapheme_boundaries_utf16;
+use hone_editor_common::hit_test::{byte_at_utf16, column_at_x};
+use hone_editor_common::words::word_range_at;
 use serde::Deserialize;
+use wasm_bindgen::prelude::*;
 
-#[derive(Debug, Deserialize)]
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::dom_renderer::{container_aria_attributes, line_aria_attributes, LinePool};
+
+#[derive(Debug, Hash, Deserialize)]
 pub struct RenderToken {
     pub s: usize,
     pub e: usize,
@@ -16,6 +29,43 @@ pub struct RenderToken {
     pub st: String,
 }
 
+/// Caret position nearest a point on a line, returned to JS by
+/// `hone_editor_hit_test_str`. `col` is a UTF-16 offset on a grapheme
+/// boundary and `x` the caret's left edge at that column.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct HitTestResult {
+    pub line: i32,
+    pub col: u32,
+    pub x: f64,
+}
+
+#[derive(Deserialize)]
+struct LineRenderData {
+    line_number: i32,
+    text: String,
+    #[serde(default)]
+    tokens: Vec<RenderToken>,
+    y_offset: f64,
+}
+
+/// A pooled line `<div>` and the state last written to it.
+#[derive(Default)]
+struct LineElement {
+    line_number: i32,
+    top: f64,
+    /// Hash of the text and tokens last written into the spans; `None` for a
+    /// freshly created row.
+    content_key: Option<u64>,
+    /// ARIA attributes set on the `<div>` for its current line.
+    attributes: Vec<(&'static str, String)>,
+    // In production: the HtmlDivElement and its token <span>s
+}
+
+/// Line height as a multiple of the font size; matches the container's CSS
+/// `line-height` from `dom_renderer::generate_editor_css`.
+const LINE_HEIGHT_FACTOR: f64 = 1.5;
+
 pub struct EditorView {
     font_family: String,
     font_size: f64,
@@ -25,7 +75,21 @@ pub struct EditorView {
     needs_display: bool,
     /// The DOM element ID of the parent container this editor is attached to.
     pub parent_element_id: Option<String>,
-    // In production: references to DOM container element, line pool, etc.
+    /// ARIA attributes set on the container, naming its role="textbox".
+    container_attributes: Vec<(&'static str, String)>,
+    /// Placeholder text and CSS color shown while the document is empty.
+    placeholder: Option<(String, String)>,
+    /// Line <div>s reused across frames, one per visible row.
+    line_pool: LinePool<LineElement>,
+    /// Next pool row `render_line` writes to this frame.
+    frame_row: usize,
+    /// Line DOM mutations (transform or span rewrites) since creation.
+    dom_writes: u64,
+    /// Total document lines from `set_line_count` (0 = unknown).
+    line_count: i32,
+    /// Widest line pushed since the last `clear` or font change.
+    max_line_width: f64,
+    // In production: references to DOM container element, cursor, etc.
 }
 
 impl EditorView {
@@ -38,22 +102,108 @@ impl EditorView {
             scroll_offset_y: 0.0,
             needs_display: true,
             parent_element_id: None,
+            container_attributes: container_aria_attributes("Code editor"),
+            placeholder: None,
+            line_pool: LinePool::new(),
+            frame_row: 0,
+            dom_writes: 0,
+            line_count: 0,
+            max_line_width: 0.0,
         }
     }
 
     pub fn set_font(&mut self, family: &str, size: f64) {
         self.font_family = family.to_string();
         self.font_size = size;
+        self.max_line_width = 0.0;
         self.needs_display = true;
         // Production: update CSS font-family and font-size on container
     }
 
-    pub fn render_line(&mut self, _line_number: i32, _text: &str, _tokens_json: &str, _y_offset: f64) {
-        // Production:
-        // 1. Get or create a <div> for this line from the pool
-        // 2. Clear existing <span> children
-        // 3. For each token, create <span> with style="color: {token.c}"
-        // 4. Set div.style.top = y_offset + "px"
+    pub fn render_line(&mut self, line_number: i32, text: &str, tokens_json: &str, y_offset: f64) {
+        let tokens: Vec<RenderToken> = serde_json::from_str(tokens_json).unwrap_or_default();
+        self.place_line(line_number, text, &tokens, y_offset);
+    }
+
+    pub fn render_lines(&mut self, lines_json: &str) {
+        let lines: Vec<LineRenderData> = serde_json::from_str(lines_json).unwrap_or_default();
+        for line in &lines {
+            self.place_line(line.line_number, &line.text, &line.tokens, line.y_offset);
+        }
+        // Production: rows the pool had to create this call are built into a
+        // DocumentFragment and attached once, so the viewport costs a single
+        // layout pass.
+    }
+
+    /// Write a line into the next pooled row, creating a <div> only when the
+    /// pool has fewer rows than this frame needs. A row already showing the
+    /// same line, text, and tokens is left alone, so re-pushing an unchanged
+    /// viewport every frame touches no DOM.
+    fn place_line(
+        &mut self,
+        line_number: i32,
+        text: &str,
+        tokens: &[RenderToken],
+        y_offset: f64,
+    ) {
+        let row = self.frame_row;
+        self.frame_row += 1;
+        self.max_line_width = self.max_line_width.max(self.measure_text(text));
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        tokens.hash(&mut hasher);
+        let key = hasher.finish();
+
+        // Production: the create closure makes the <div> and appends it to
+        // the container
+        let el = self.line_pool.acquire(row, |_| LineElement::default());
+        if el.top != y_offset || el.content_key.is_none() {
+            el.top = y_offset;
+            self.dom_writes += 1;
+            // Production: div.style.transform = translateY(y_offset px)
+        }
+        if el.content_key != Some(key) || el.line_number != line_number {
+            if el.line_number != line_number || el.attributes.is_empty() {
+                el.attributes = line_aria_attributes(line_number);
+            }
+            el.line_number = line_number;
+            el.content_key = Some(key);
+            self.dom_writes += 1;
+            // Production:
+            // 1. Reuse existing <span> children, adding or removing only the
+            //    difference in token count
+            // 2. For each token, set style="color: {token.c}" and
+            //    span.textContent = the token's text (a real text node, never
+            //    CSS `content`, so Find and screen readers see it)
+            // 3. div.setAttribute for each of el.attributes
+        }
+    }
+
+    /// Line elements created and removed, and line DOM writes, since the view
+    /// was made. Scrolling with a steady viewport height creates no elements,
+    /// and re-rendering identical content writes nothing.
+    pub fn dom_node_stats(&self) -> (u64, u64, u64) {
+        (self.line_pool.created(), self.line_pool.removed(), self.dom_writes)
+    }
+
+    /// ARIA attributes the DOM layer sets on the container (`row` None) or
+    /// on the pooled line `<div>` for visible `row`, or None past the pool.
+    pub fn aria_attributes(&self, row: Option<usize>) -> Option<&[(&'static str, String)]> {
+        match row {
+            None => Some(&self.container_attributes),
+            Some(row) => self.line_pool.get(row).map(|el| el.attributes.as_slice()),
+        }
+    }
+
+    pub fn set_line_count(&mut self, count: i32) {
+        self.line_count = count;
+    }
+
+    /// Scrollable content size as `(width, height)`: the widest line pushed
+    /// so far and `line_count * line_height`.
+    pub fn content_size(&self) -> (f64, f64) {
+        let line_h = self.font_size * LINE_HEIGHT_FACTOR;
+        (self.max_line_width, self.line_count.max(0) as f64 * line_h)
     }
 
     pub fn set_cursor(&mut self, _x: f64, _y: f64, _style: i32) {
@@ -78,6 +228,90 @@ impl EditorView {
         text.len() as f64 * self.font_size * 0.6
     }
 
+    /// Advance width of `text[start..end]` (byte offsets). Returns 0 for an
+    /// empty range or offsets that are out of bounds or not on a char boundary.
+    pub fn measure_range(&self, text: &str, start: usize, end: usize) -> f64 {
+        match text.get(start..end) {
+            Some(span) if !span.is_empty() => self.measure_text(span),
+            _ => 0.0,
+        }
+    }
+
+    /// Width of the first `col` UTF-16 code units of `text`, so JS can pass
+    /// its own string indices. A `col` inside a surrogate pair rounds down;
+    /// one past the end measures the whole string.
+    pub fn measure_prefix(&self, text: &str, col: u32) -> f64 {
+        self.measure_text(&text[..byte_at_utf16(text, col)])
+    }
+
+    /// Caret column nearest `x` (relative to the text's left edge) on `line`,
+    /// as a UTF-16 column and the x of that caret position. Never splits a
+    /// grapheme cluster; see `column_at_x`.
+    pub fn hit_test(&self, line: i32, text: &str, x: f64) -> HitTestResult {
+        let prefix = &text[..column_at_x(text, x, |p| self.measure_text(p))];
+        HitTestResult {
+            line,
+            col: prefix.encode_utf16().count() as u32,
+            x: self.measure_text(prefix),
+        }
+    }
+
+    /// UTF-16 offsets of the extended grapheme cluster boundaries in `text`,
+    /// from 0 through its UTF-16 length, matching JavaScript string indices.
+    pub fn grapheme_boundaries(&self, text: &str) -> Vec<u32> {
+        grapheme_boundaries_utf16(text).collect()
+    }
+
+    /// UTF-16 range of the word around UTF-16 column `col` in `text`, by
+    /// Unicode word segmentation. A caret just after a word picks that word;
+    /// otherwise the segment under `col` is used, so a run of punctuation or
+    /// whitespace selects as a unit. With `subword`, identifiers also split
+    /// at underscores and case changes (`fooBar` gives `foo` and `Bar`).
+    pub fn word_range_at(&self, text: &str, col: u32, subword: bool) -> (u32, u32) {
+        let mut units = 0u32;
+        let mut byte_col = text.len();
+        for (i, ch) in text.char_indices() {
+            if units >= col {
+                byte_col = i;
+                break;
+            }
+            units += ch.len_utf16() as u32;
+        }
+        let to_units = |b: usize| text[..b].encode_utf16().count() as u32;
+        let range = word_range_at(text, byte_col, subword);
+        (to_units(range.0), to_units(range.1))
+    }
+
+    pub fn set_follow_system_appearance(&mut self, _enabled: bool) {
+        self.needs_display = true;
+        // Production: window.matchMedia('(prefers-color-scheme: dark)');
+        // apply the dark/light preset from .matches now and from its
+        // "change" listener, removing the listener when disabled
+    }
+
+    pub fn set_force_high_contrast(&mut self, _enabled: bool) {
+        self.needs_display = true;
+        // Production: toggle the "-high-contrast" class on the container;
+        // generate_editor_css gives it the same system colors the
+        // (forced-colors: active) media query applies
+    }
+
+    /// Faint text shown while the document is empty. An empty `text` removes
+    /// it.
+    pub fn set_placeholder(&mut self, text: &str, color: &str) {
+        self.placeholder = (!text.is_empty()).then(|| (text.to_string(), color.to_string()));
+        self.needs_display = true;
+        // Production: a "-placeholder" <div> (pointer-events: none,
+        // user-select: none, aria-hidden) at the first row, shown from
+        // end_frame only when the frame had no lines or one empty line
+    }
+
+    /// Set the accessible name screen readers announce for the editor.
+    pub fn set_aria_label(&mut self, label: &str) {
+        self.container_attributes = container_aria_attributes(label);
+        // Production: container.setAttribute for each of container_attributes
+    }
+
     pub fn invalidate(&mut self) {
         self.needs_display = true;
         // Production: requestAnimationFrame for next repaint
@@ -85,10 +319,142 @@ impl EditorView {
 
     pub fn begin_frame(&mut self) {
         self.needs_display = false;
+        self.frame_row = 0;
         // Production: batch DOM mutations
     }
 
     pub fn end_frame(&mut self) {
+        // Production: size the scroll spacer from content_size() so the
+        // container's scrollWidth/scrollHeight give native scrollbars
+        // Rows this frame didn't use are surplus: the viewport shrank
+        self.line_pool.truncate(self.frame_row, |_el| {
+            // Production: el.div.remove()
+        });
         // Production: flush batched DOM mutations
     }
+
+    /// Blank the view outside a frame. The empty frame removes every pooled
+    /// line element, so no stale DOM is left behind.
+    pub fn clear(&mut self) {
+        self.max_line_width = 0.0;
+        self.begin_frame();
+        self.end_frame();
+        self.needs_display = true;
+        // Production: also hide the cursor and remove selection overlays
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute<'a>(attributes: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+        attributes.iter().find(|(n, _)| *n == name).map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn container_is_a_labelled_multiline_textbox() {
+        let mut view = EditorView::new(800.0, 600.0);
+        let attributes = view.aria_attributes(None).unwrap();
+        assert_eq!(attribute(attributes, "role"), Some("textbox"));
+        assert_eq!(attribute(attributes, "aria-multiline"), Some("true"));
+        assert_eq!(attribute(attributes, "aria-label"), Some("Code editor"));
+
+        view.set_aria_label("main.rs");
+        let attributes = view.aria_attributes(None).unwrap();
+        assert_eq!(attribute(attributes, "aria-label"), Some("main.rs"));
+    }
+
+    #[test]
+    fn line_rows_are_labelled_with_their_line_number() {
+        let mut view = EditorView::new(800.0, 600.0);
+        view.begin_frame();
+        view.render_line(5, "fn main() {}", "[]", 0.0);
+        view.render_line(6, "", "[]", 21.0);
+        view.end_frame();
+        let row = view.aria_attributes(Some(0)).unwrap();
+        assert_eq!(attribute(row, "aria-roledescription"), Some("line"));
+        assert_eq!(attribute(row, "aria-label"), Some("Line 5"));
+        assert_eq!(attribute(view.aria_attributes(Some(1)).unwrap(), "aria-label"), Some("Line 6"));
+        assert!(view.aria_attributes(Some(2)).is_none());
+
+        // Scrolling moves the pooled row to a new line and relabels it.
+        view.begin_frame();
+        view.render_line(9, "fn main() {}", "[]", 0.0);
+        view.end_frame();
+        let row = view.aria_attributes(Some(0)).unwrap();
+        assert_eq!(attribute(row, "aria-label"), Some("Line 9"));
+    }
+
+    fn render_frame(view: &mut EditorView, first_line: i32, rows: i32) {
+        view.begin_frame();
+        for i in 0..rows {
+            let line = first_line + i;
+            view.render_line(line, &format!("line {line}"), "[]", i as f64 * 21.0);
+        }
+        view.end_frame();
+    }
+
+    #[test]
+    fn scrolling_a_steady_viewport_creates_no_line_elements() {
+        let mut view = EditorView::new(800.0, 600.0);
+        render_frame(&mut view, 1, 30);
+        let (created, removed, _) = view.dom_node_stats();
+        assert_eq!((created, removed), (30, 0));
+
+        for frame in 1..100 {
+            render_frame(&mut view, 1 + frame, 30);
+        }
+        let (created, removed, _) = view.dom_node_stats();
+        assert_eq!((created, removed), (30, 0));
+    }
+
+    #[test]
+    fn viewport_height_changes_create_and_remove_only_the_difference() {
+        let mut view = EditorView::new(800.0, 600.0);
+        render_frame(&mut view, 1, 30);
+        render_frame(&mut view, 1, 20);
+        assert_eq!(view.dom_node_stats().0, 30);
+        assert_eq!(view.dom_node_stats().1, 10);
+        render_frame(&mut view, 1, 25);
+        assert_eq!(view.dom_node_stats().0, 35);
+    }
+
+    #[test]
+    fn rerendering_an_unchanged_frame_writes_nothing() {
+        let mut view = EditorView::new(800.0, 600.0);
+        let tokens = r##"[{"s":0,"e":2,"c":"#569cd6","st":"normal"}]"##;
+        let frame = |view: &mut EditorView, tokens: &str| {
+            view.begin_frame();
+            view.render_line(1, "fn main() {", tokens, 0.0);
+            view.render_line(2, "}", "[]", 21.0);
+            view.end_frame();
+        };
+        frame(&mut view, tokens);
+        let writes = view.dom_node_stats().2;
+
+        frame(&mut view, tokens);
+        assert_eq!(view.dom_node_stats().2 - writes, 0);
+
+        let recolored = r##"[{"s":0,"e":2,"c":"#c586c0","st":"normal"}]"##;
+        frame(&mut view, recolored);
+        assert!(view.dom_node_stats().2 - writes > 0);
+    }
+
+    #[test]
+    fn hit_test_and_measure_prefix_use_utf16_columns() {
+        let view = EditorView::new(800.0, 600.0);
+        // U+1F600 is two UTF-16 units, so "a" starts at column 2.
+        let text = "\u{1F600}ab";
+        let emoji = view.measure_text("\u{1F600}");
+        assert_eq!(view.measure_prefix(text, 1), 0.0);
+        assert_eq!(view.measure_prefix(text, 2), emoji);
+        assert_eq!(view.measure_prefix(text, 99), view.measure_text(text));
+
+        let a = view.measure_text("a");
+        let hit = view.hit_test(3, text, emoji + a * 0.6);
+        assert_eq!((hit.line, hit.col, hit.x), (3, 3, view.measure_text("\u{1F600}a")));
+        let hit = view.hit_test(3, text, emoji * 0.4);
+        assert_eq!((hit.col, hit.x), (0, 0.0));
+    }
 }
@@ -11,98 +11,384 @@ use wasm_bindgen::prelude::*;
 mod editor_view;
 mod dom_renderer;
 
-use editor_view::EditorView;
+use editor_view::{EditorView, HitTestResult};
+use hone_editor_common::handles::{HandleTable, ViewHandle};
+use std::cell::RefCell;
 use std::ffi::{c_char, CStr};
 
+thread_local! {
+    /// Views created on this thread. Views are single-threaded, so a handle
+    /// only resolves on the thread that created it.
+    static VIEWS: RefCell<HandleTable<EditorView>> = const { RefCell::new(HandleTable::new()) };
+}
+
+/// Resolve a handle from the host to its live view. Returns None for 0, for
+/// a destroyed view's handle, and on any thread other than the view's own,
+/// so such calls are ignored (mutating ones panic in debug builds).
+fn view_ref<'a>(view: ViewHandle) -> Option<&'a EditorView> {
+    VIEWS.with(|views| views.borrow().get(view)).map(|view| unsafe { &*view })
+}
+
+/// `view_ref` for calls that mutate the view. Views are single-threaded, so
+/// debug builds panic if the handle belongs to a view on another thread.
+fn view_mut<'a>(view: ViewHandle) -> Option<&'a mut EditorView> {
+    let found = VIEWS.with(|views| views.borrow().get(view));
+    #[cfg(debug_assertions)]
+    if found.is_none() {
+        hone_editor_common::handles::owners::assert_not_foreign(view);
+    }
+    found.map(|view| unsafe { &mut *view })
+}
+
+/// Move a new view into this thread's table and return its handle, or 0 if
+/// the table is full (the view is dropped).
+fn register(view: Box<EditorView>) -> ViewHandle {
+    let Some(handle) = VIEWS.with(|views| views.borrow_mut().insert(view)) else {
+        return 0;
+    };
+    #[cfg(debug_assertions)]
+    hone_editor_common::handles::owners::claim(handle);
+    handle
+}
+
 // Note: For WASM, we use wasm_bindgen exports instead of extern "C".
 // Perry's web target handles the bridging between C FFI and WASM exports.
 
-/// Create a new editor view.
+/// Create a new editor view and return its handle.
 #[wasm_bindgen]
-pub fn hone_editor_create(width: f64, height: f64) -> *mut EditorView {
-    Box::into_raw(Box::new(EditorView::new(width, height)))
+pub fn hone_editor_create_handle(width: f64, height: f64) -> ViewHandle {
+    register(Box::new(EditorView::new(width, height)))
 }
 
 /// Attach the editor view to a parent DOM element by its ID.
 #[wasm_bindgen]
-pub fn hone_editor_attach_to_view(view: *mut EditorView, parent_element_id: &str) {
-    let view = unsafe { &mut *view };
+pub fn hone_editor_attach_to_view(view: ViewHandle, parent_element_id: &str) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.parent_element_id = Some(parent_element_id.to_string());
 }
 
-/// Destroy an editor view.
+/// Destroy an editor view. Stale handles are ignored.
 #[wasm_bindgen]
-pub fn hone_editor_destroy(view: *mut EditorView) {
-    if !view.is_null() {
-        unsafe { drop(Box::from_raw(view)); }
-    }
+pub fn hone_editor_destroy_handle(view: ViewHandle) {
+    // Drop outside the table borrow so teardown can't observe it.
+    let Some(removed) = VIEWS.with(|views| views.borrow_mut().remove(view)) else {
+        return;
+    };
+    #[cfg(debug_assertions)]
+    hone_editor_common::handles::owners::release(view);
+    drop(removed);
 }
 
 /// Set font (WASM-friendly string version).
 #[wasm_bindgen]
-pub fn hone_editor_set_font_str(view: *mut EditorView, family: &str, size: f64) {
-    let view = unsafe { &mut *view };
+pub fn hone_editor_set_font_str(view: ViewHandle, family: &str, size: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_font(family, size);
 }
 
 /// Render a line (WASM-friendly string version).
 #[wasm_bindgen]
 pub fn hone_editor_render_line_str(
-    view: *mut EditorView,
+    view: ViewHandle,
     line_number: i32,
     text: &str,
     tokens_json: &str,
     y_offset: f64,
 ) {
-    let view = unsafe { &mut *view };
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.render_line(line_number, text, tokens_json, y_offset);
 }
 
+/// Render a batch of lines in one call (WASM-friendly string version).
+#[wasm_bindgen]
+pub fn hone_editor_render_lines_str(view: ViewHandle, lines_json: &str) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.render_lines(lines_json);
+}
+
 /// Set cursor.
 #[wasm_bindgen]
-pub fn hone_editor_set_cursor(view: *mut EditorView, x: f64, y: f64, style: i32) {
-    let view = unsafe { &mut *view };
+pub fn hone_editor_set_cursor(view: ViewHandle, x: f64, y: f64, style: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_cursor(x, y, style);
 }
 
 /// Set selection.
 #[wasm_bindgen]
-pub fn hone_editor_set_selection_str(view: *mut EditorView, regions_json: &str) {
-    let view = unsafe { &mut *view };
+pub fn hone_editor_set_selection_str(view: ViewHandle, regions_json: &str) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.set_selection(regions_json);
 }
 
 /// Scroll.
 #[wasm_bindgen]
-pub fn hone_editor_scroll(view: *mut EditorView, offset_y: f64) {
-    let view = unsafe { &mut *view };
+pub fn hone_editor_scroll(view: ViewHandle, offset_y: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.scroll(offset_y);
 }
 
 /// Measure text.
 #[wasm_bindgen]
-pub fn hone_editor_measure_text_str(view: *mut EditorView, text: &str) -> f64 {
-    let view = unsafe { &*view };
+pub fn hone_editor_measure_text_str(view: ViewHandle, text: &str) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
     view.measure_text(text)
 }
 
+/// Measure a byte range of text.
+#[wasm_bindgen]
+pub fn hone_editor_measure_range_str(view: ViewHandle, text: &str, start_byte: usize, end_byte: usize) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
+    view.measure_range(text, start_byte, end_byte)
+}
+
+/// Width of the first `col` UTF-16 code units of `text`, indexed the way JS
+/// strings are, instead of slicing and measuring on the JS side.
+#[wasm_bindgen]
+pub fn hone_editor_measure_prefix_str(view: ViewHandle, text: &str, col: u32) -> f64 {
+    let Some(view) = view_ref(view) else {
+        return 0.0;
+    };
+    view.measure_prefix(text, col)
+}
+
+/// Hit-test `x` (relative to the text's left edge) against the text of
+/// `line`, returning the nearest caret column and its x as a `HitTestResult`.
+#[wasm_bindgen]
+pub fn hone_editor_hit_test_str(
+    view: ViewHandle,
+    line: i32,
+    text: &str,
+    x: f64,
+) -> HitTestResult {
+    let Some(view) = view_ref(view) else {
+        return HitTestResult { line, col: 0, x: 0.0 };
+    };
+    view.hit_test(line, text, x)
+}
+
+/// Grapheme cluster boundaries of `text` as a Uint32Array of UTF-16
+/// offsets, so JS hosts move the caret by user-perceived characters.
+#[wasm_bindgen]
+pub fn hone_editor_grapheme_boundaries(view: ViewHandle, text: &str) -> Vec<u32> {
+    let Some(view) = view_ref(view) else {
+        return Vec::new();
+    };
+    view.grapheme_boundaries(text)
+}
+
+/// UTF-16 range of the word around UTF-16 column `col` in `text` as
+/// `[start, end]`, by Unicode word segmentation. `subword` also splits
+/// identifiers at underscores and case changes.
+#[wasm_bindgen]
+pub fn hone_editor_word_range_at(
+    view: ViewHandle,
+    text: &str,
+    col: u32,
+    subword: bool,
+) -> Vec<u32> {
+    let Some(view) = view_ref(view) else {
+        return Vec::new();
+    };
+    let (start, end) = view.word_range_at(text, col, subword);
+    vec![start, end]
+}
+
+/// Line elements created and removed and line DOM writes so far, as
+/// `[created, removed, writes]`, for tests and profiling of the element pool
+/// and the unchanged-line skip.
+#[wasm_bindgen]
+pub fn hone_editor_dom_node_stats(view: ViewHandle) -> Vec<f64> {
+    let Some(view) = view_ref(view) else {
+        return Vec::new();
+    };
+    let (created, removed, writes) = view.dom_node_stats();
+    vec![created as f64, removed as f64, writes as f64]
+}
+
+/// ARIA attributes as a JSON object of name to value, for the container when
+/// `row` is negative or for the line `<div>` at visible `row`. The DOM layer
+/// sets these when it creates the container and when a pooled row changes
+/// line. Returns "{}" for a row past the pool.
+#[wasm_bindgen]
+pub fn hone_editor_aria_attributes(view: ViewHandle, row: i32) -> String {
+    let Some(view) = view_ref(view) else {
+        return "{}".to_string();
+    };
+    let attributes = view.aria_attributes(usize::try_from(row).ok()).unwrap_or_default();
+    let map: serde_json::Map<String, serde_json::Value> = attributes
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone().into()))
+        .collect();
+    serde_json::Value::Object(map).to_string()
+}
+
 /// Invalidate.
 #[wasm_bindgen]
-pub fn hone_editor_invalidate(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub fn hone_editor_invalidate(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.invalidate();
+}
+
+/// Invalidate a rect. The DOM renderer has no partial repaint, so this
+/// invalidates the whole view.
+#[wasm_bindgen]
+pub fn hone_editor_invalidate_rect(view: ViewHandle, _x: f64, _y: f64, _w: f64, _h: f64) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.invalidate();
 }
 
 /// Begin frame.
 #[wasm_bindgen]
-pub fn hone_editor_begin_frame(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub fn hone_editor_begin_frame(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.begin_frame();
 }
 
 /// End frame.
 #[wasm_bindgen]
-pub fn hone_editor_end_frame(view: *mut EditorView) {
-    let view = unsafe { &mut *view };
+pub fn hone_editor_end_frame(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
     view.end_frame();
 }
+
+/// Clear the view outside a frame, removing all line elements.
+#[wasm_bindgen]
+pub fn hone_editor_clear(view: ViewHandle) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.clear();
+}
+
+/// Check that the caller is on the thread that created `view`. Handles only
+/// resolve on their owning thread, so this is false for a foreign thread (or
+/// a destroyed view): exactly the calls the rest of the API ignores.
+#[wasm_bindgen]
+pub fn hone_editor_assert_thread(view: ViewHandle) -> bool {
+    view_ref(view).is_some()
+}
+
+/// Follow the OS light/dark setting, switching between the vscode-dark and
+/// vscode-light presets as it changes. Disabling keeps the current colors.
+#[wasm_bindgen]
+pub fn hone_editor_set_follow_system_appearance(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_follow_system_appearance(enabled);
+}
+
+/// Force the high-contrast palette (system Canvas/CanvasText colors, token
+/// colors flattened), as the `forced-colors: active` media query does.
+#[wasm_bindgen]
+pub fn hone_editor_set_force_high_contrast(view: ViewHandle, enabled: bool) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_force_high_contrast(enabled);
+}
+
+/// Set the accessible name of the editor container (`role="textbox"`), read
+/// by screen readers in place of the default "Code editor".
+#[wasm_bindgen]
+pub fn hone_editor_set_aria_label_str(view: ViewHandle, label: &str) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_aria_label(label);
+}
+
+/// Show faint placeholder text while the document is empty (WASM-friendly
+/// string version). Pass an empty string to remove it.
+#[wasm_bindgen]
+pub fn hone_editor_set_placeholder_str(view: ViewHandle, text: &str, color: &str) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_placeholder(text, color);
+}
+
+/// Set the total number of document lines, used by `hone_editor_content_size`.
+#[wasm_bindgen]
+pub fn hone_editor_set_line_count(view: ViewHandle, count: i32) {
+    let Some(view) = view_mut(view) else {
+        return;
+    };
+    view.set_line_count(count);
+}
+
+/// Scrollable content size as `[width, height]`: the widest line pushed so
+/// far and `line_count * line_height`, for sizing the scroll container.
+#[wasm_bindgen]
+pub fn hone_editor_content_size(view: ViewHandle) -> Vec<f64> {
+    let Some(view) = view_ref(view) else {
+        return vec![0.0, 0.0];
+    };
+    let (width, height) = view.content_size();
+    vec![width, height]
+}
+
+// === Pointer Compatibility ===
+//
+// Hosts written against the original pointer-based contract keep working
+// while they migrate: `hone_editor_create` and `hone_editor_destroy` keep
+// their pointer signatures, and `hone_editor_handle_for_view` turns such a
+// pointer into the handle every other entry point takes. Pointers are looked
+// up in this thread's handle table, never dereferenced, so one used after
+// destroy is ignored unless a newer view was allocated at the same address.
+// Only handles rule that out.
+
+/// Create a new editor view and return its address. Deprecated: use
+/// `hone_editor_create_handle`.
+#[wasm_bindgen]
+pub fn hone_editor_create(width: f64, height: f64) -> *mut EditorView {
+    hone_editor_view_for_handle(hone_editor_create_handle(width, height))
+}
+
+/// Destroy a view created with `hone_editor_create`. Pointers that aren't a
+/// live view on this thread are ignored. Deprecated: use
+/// `hone_editor_destroy_handle`.
+#[wasm_bindgen]
+pub fn hone_editor_destroy(view: *mut EditorView) {
+    hone_editor_destroy_handle(hone_editor_handle_for_view(view));
+}
+
+/// The handle of a view created with `hone_editor_create`, or 0 if the
+/// pointer isn't a live view on this thread.
+#[wasm_bindgen]
+pub fn hone_editor_handle_for_view(view: *mut EditorView) -> ViewHandle {
+    VIEWS.with(|views| views.borrow().find(view)).unwrap_or(0)
+}
+
+/// The address of the view behind a handle, for code that still takes a
+/// pointer, or null if the handle doesn't resolve on this thread.
+#[wasm_bindgen]
+pub fn hone_editor_view_for_handle(view: ViewHandle) -> *mut EditorView {
+    VIEWS.with(|views| views.borrow().get(view)).unwrap_or(std::ptr::null_mut())
+}
@@ -7,6 +7,8 @@
 
 use std::ffi::{c_char, CStr, CString};
 
+use unicode_segmentation::UnicodeSegmentation;
+
 use windows::core::{w, PCWSTR};
 use windows::Win32::Foundation::{HANDLE, HGLOBAL, HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::Graphics::Gdi::{UpdateWindow, HBRUSH};
@@ -20,13 +22,16 @@ use windows::Win32::System::Ole::CF_UNICODETEXT;
 use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
 use windows::Win32::UI::WindowsAndMessaging::*;
 
+use hone_editor_windows::ViewHandle;
 use hone_editor_windows::{
-    hone_editor_add_context_menu_item, hone_editor_begin_frame, hone_editor_create,
-    hone_editor_destroy, hone_editor_end_frame, hone_editor_attach_to_view,
-    hone_editor_hwnd, hone_editor_measure_text, hone_editor_render_line,
+    hone_editor_add_context_menu_item, hone_editor_begin_frame, hone_editor_create_handle,
+    hone_editor_destroy_handle, hone_editor_end_frame, hone_editor_attach_to_view,
+    hone_editor_hwnd, hone_editor_measure_text, hone_editor_point_to_position,
+    hone_editor_render_line,
     hone_editor_set_action_callback, hone_editor_set_cursor, hone_editor_set_font,
     hone_editor_set_mouse_down_callback, hone_editor_set_scroll_callback,
-    hone_editor_set_selection, hone_editor_set_text_input_callback,
+    hone_editor_set_selection, hone_editor_set_selection_full_line,
+    hone_editor_set_text_input_callback,
 };
 
 // ── DemoEditor state ────────────────────────────────────────────
@@ -41,7 +46,7 @@ struct DemoEditor {
     sel_anchor: Option<(usize, usize)>,
     scroll_y: f64,
     view_height: f64,
-    editor_ptr: *mut u8,
+    editor: ViewHandle,
     char_width: f64,
     line_height: f64,
 }
@@ -201,7 +206,7 @@ fn validate_tokens_json(tokens_json: &str, orig_text: &str, curr_text: &str) ->
 static mut DEMO: Option<DemoEditor> = None;
 
 impl DemoEditor {
-    fn new(editor_ptr: *mut u8, char_width: f64, line_height: f64, view_height: f64) -> Self {
+    fn new(editor: ViewHandle, char_width: f64, line_height: f64, view_height: f64) -> Self {
         let content = initial_content();
         let lines: Vec<String> = content.iter().map(|(t, _)| t.clone()).collect();
         let line_origins = (0..lines.len()).collect();
@@ -214,7 +219,7 @@ impl DemoEditor {
             sel_anchor: None,
             scroll_y: 0.0,
             view_height,
-            editor_ptr,
+            editor,
             char_width,
             line_height,
         }
@@ -233,40 +238,13 @@ impl DemoEditor {
 
     /// Position cursor from a click at (x, y) in view coordinates.
     fn click_to_cursor(&mut self, x: f64, y: f64) {
-        let editor = self.editor_ptr as *mut hone_editor_windows::EditorView;
-        let gutter_w = self.gutter_width();
-
-        // Determine line from y (account for scroll offset)
-        let line = ((y + self.scroll_y) / self.line_height).floor() as usize;
-        let line = line.min(self.lines.len().saturating_sub(1));
-
-        // Determine column from x
-        let text_x = x - gutter_w;
-        let col = if text_x <= 0.0 {
-            0
-        } else {
-            let line_str = &self.lines[line];
-            let mut best_col = 0;
-            let mut best_dist = text_x;
-            for (byte_idx, _) in line_str.char_indices() {
-                let end = byte_idx + line_str[byte_idx..].chars().next().unwrap().len_utf8();
-                let prefix = &line_str[..end];
-                let c_prefix = CString::new(prefix).unwrap_or_default();
-                let px = hone_editor_measure_text(editor, c_prefix.as_ptr());
-                let dist = (text_x - px).abs();
-                if dist < best_dist {
-                    best_dist = dist;
-                    best_col = end;
-                }
-                if px > text_x + self.char_width {
-                    break;
-                }
-            }
-            best_col
-        };
-
-        self.cursor_line = line;
-        self.cursor_col = col;
+        let editor = self.editor;
+        let (mut line, mut col) = (0, 0);
+        if !hone_editor_point_to_position(editor, x, y, &mut line, &mut col) {
+            return;
+        }
+        self.cursor_line = (line - 1).max(0) as usize;
+        self.cursor_col = col.max(0) as usize;
         self.sel_anchor = None;
     }
 
@@ -480,7 +458,7 @@ impl DemoEditor {
         if self.cursor_col > 0 {
             let line = &self.lines[self.cursor_line];
             self.cursor_col = line[..self.cursor_col]
-                .char_indices()
+                .grapheme_indices(true)
                 .next_back()
                 .map(|(i, _)| i)
                 .unwrap_or(0);
@@ -509,7 +487,7 @@ impl DemoEditor {
         if self.cursor_col < line_len {
             let line = &self.lines[self.cursor_line];
             self.cursor_col = line[self.cursor_col..]
-                .char_indices()
+                .grapheme_indices(true)
                 .nth(1)
                 .map(|(i, _)| self.cursor_col + i)
                 .unwrap_or(line_len);
@@ -668,7 +646,7 @@ impl DemoEditor {
     // ── Rendering ───────────────────────────────────────────────
 
     fn render(&self) {
-        let editor = self.editor_ptr as *mut hone_editor_windows::EditorView;
+        let editor = self.editor;
         let gutter_w = self.gutter_width();
 
         hone_editor_begin_frame(editor);
@@ -734,10 +712,12 @@ impl DemoEditor {
 
                     let y = line_idx as f64 * self.line_height - self.scroll_y;
                     let w = (x_end - x_start).max(0.0);
-                    if w > 0.0 {
+                    // Every row but the last also selects its newline
+                    let eol = line_idx < el;
+                    if w > 0.0 || eol {
                         rects.push(format!(
-                            r#"{{"x":{},"y":{},"w":{},"h":{}}}"#,
-                            x_start, y, w, self.line_height
+                            r#"{{"x":{},"y":{},"w":{},"h":{},"eol":{}}}"#,
+                            x_start, y, w, self.line_height, eol
                         ));
                     }
                 }
@@ -754,7 +734,7 @@ impl DemoEditor {
 // ── Callbacks ───────────────────────────────────────────────────
 
 extern "C" fn on_text_input(
-    _view: *mut hone_editor_windows::EditorView,
+    _view: ViewHandle,
     text: *const c_char,
 ) {
     let text_str = unsafe { CStr::from_ptr(text) }.to_str().unwrap_or("");
@@ -770,7 +750,7 @@ extern "C" fn on_text_input(
 }
 
 extern "C" fn on_action(
-    _view: *mut hone_editor_windows::EditorView,
+    _view: ViewHandle,
     selector: *const c_char,
 ) {
     let sel_str = unsafe { CStr::from_ptr(selector) }.to_str().unwrap_or("");
@@ -828,7 +808,7 @@ extern "C" fn on_action(
 }
 
 extern "C" fn on_mouse_down(
-    _view: *mut hone_editor_windows::EditorView,
+    _view: ViewHandle,
     x: f64,
     y: f64,
 ) {
@@ -841,7 +821,7 @@ extern "C" fn on_mouse_down(
 }
 
 extern "C" fn on_scroll(
-    _view: *mut hone_editor_windows::EditorView,
+    _view: ViewHandle,
     _dx: f64,
     dy: f64,
 ) {
@@ -857,7 +837,7 @@ extern "C" fn on_scroll(
 
 // ── Top-level window WndProc ────────────────────────────────────
 
-static mut EDITOR_PTR: *mut hone_editor_windows::EditorView = std::ptr::null_mut();
+static mut EDITOR: ViewHandle = 0;
 
 unsafe extern "system" fn main_wnd_proc(
     hwnd: HWND,
@@ -868,8 +848,8 @@ unsafe extern "system" fn main_wnd_proc(
     match msg {
         WM_SIZE => {
             // Resize the editor child to fill the client area
-            if !EDITOR_PTR.is_null() {
-                let editor_hwnd = HWND(hone_editor_hwnd(EDITOR_PTR));
+            if EDITOR != 0 {
+                let editor_hwnd = HWND(hone_editor_hwnd(EDITOR));
                 let mut rect = windows::Win32::Foundation::RECT::default();
                 let _ = GetClientRect(hwnd, &mut rect);
                 let _ = SetWindowPos(
@@ -948,11 +928,12 @@ fn main() {
         );
 
         // Create the editor
-        let editor = hone_editor_create(view_width as f64, view_height as f64);
-        EDITOR_PTR = editor;
+        let editor = hone_editor_create_handle(view_width as f64, view_height as f64);
+        EDITOR = editor;
 
         let font_family = CString::new("Consolas").unwrap();
         hone_editor_set_font(editor, font_family.as_ptr(), 14.0);
+        hone_editor_set_selection_full_line(editor, true);
 
         let m_char = CString::new("M").unwrap();
         let char_width = hone_editor_measure_text(editor, m_char.as_ptr());
@@ -967,7 +948,7 @@ fn main() {
 
         // Create the demo state
         DEMO = Some(DemoEditor::new(
-            editor as *mut u8,
+            editor,
             char_width,
             line_height,
             view_height as f64,
@@ -1000,7 +981,7 @@ fn main() {
         }
 
         DEMO = None;
-        hone_editor_destroy(editor);
-        EDITOR_PTR = std::ptr::null_mut();
+        hone_editor_destroy_handle(editor);
+        EDITOR = 0;
     }
 }
@@ -0,0 +1,489 @@
+//! UI Automation provider for the HoneEditorView HWND.
+//!
+//! Narrator and other screen readers query the window with WM_GETOBJECT;
+//! the WndProc answers with an `EditorElement` (IRawElementProviderSimple)
+//! that exposes the Text pattern. Text ranges are UTF-16 offsets into the
+//! host-supplied accessible text (see `EditorView::set_accessible_text`),
+//! falling back to the visible frame lines until the host sets it.
+//! Selecting a range emits `"selectRange:<start>,<end>"` (UTF-16 offsets)
+//! and scrolling one into view emits `"revealLine:<n>"` via the action
+//! callback; the host owns the real selection.
+//!
+//! Providers hold only the HWND and look the view up on every call, so a
+//! provider that outlives its view reports UIA_E_ELEMENTNOTAVAILABLE.
+//! `ProviderOptions_UseComThreading` keeps calls on the view's UI thread.
+
+use std::cell::Cell;
+use std::mem::ManuallyDrop;
+
+use windows::core::{
+    implement, AsImpl, ComInterface, Error, IUnknown, Interface, Result, BSTR, HRESULT,
+};
+use windows::Win32::Foundation::{
+    BOOL, E_INVALIDARG, E_NOTIMPL, HWND, LPARAM, LRESULT, VARIANT_TRUE, WPARAM,
+};
+use windows::Win32::System::Com::SAFEARRAY;
+use windows::Win32::System::Ole::{SafeArrayCreateVector, SafeArrayPutElement};
+use windows::Win32::System::Variant::{
+    VARENUM, VARIANT, VARIANT_0, VARIANT_0_0, VARIANT_0_0_0, VT_BOOL, VT_BSTR, VT_I4, VT_R8,
+    VT_UNKNOWN,
+};
+use windows::Win32::UI::Accessibility::*;
+use windows::Win32::UI::Input::KeyboardAndMouse::GetFocus;
+
+use crate::editor_view::EditorView;
+use crate::input_handler::get_editor;
+
+/// Answer WM_GETOBJECT for the UIA root object; other requests fall through.
+pub fn on_get_object(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> Option<LRESULT> {
+    if lparam.0 as i32 != UiaRootObjectId {
+        return None;
+    }
+    let element: IRawElementProviderSimple = EditorElement { hwnd }.into();
+    Some(unsafe { UiaReturnRawElementProvider(hwnd, wparam, lparam, &element) })
+}
+
+/// Release UIA's references to the window's providers (WM_DESTROY).
+pub fn disconnect(hwnd: HWND) {
+    unsafe {
+        UiaReturnRawElementProvider(hwnd, WPARAM(0), LPARAM(0), None);
+    }
+}
+
+/// Tell listening clients the document text and caret changed.
+pub fn notify_text_changed(hwnd: HWND) {
+    if hwnd.0 == 0 || !unsafe { UiaClientsAreListening() }.as_bool() {
+        return;
+    }
+    let element: IRawElementProviderSimple = EditorElement { hwnd }.into();
+    unsafe {
+        let _ = UiaRaiseAutomationEvent(&element, UIA_Text_TextChangedEventId);
+        let _ = UiaRaiseAutomationEvent(&element, UIA_Text_TextSelectionChangedEventId);
+    }
+}
+
+/// The view behind `hwnd`, or UIA_E_ELEMENTNOTAVAILABLE once it is gone.
+fn editor(hwnd: HWND) -> Result<&'static mut EditorView> {
+    unsafe { get_editor(hwnd) }
+        .ok_or_else(|| Error::from(HRESULT(UIA_E_ELEMENTNOTAVAILABLE as i32)))
+}
+
+/// Accessible document text as UTF-16 code units.
+fn document(hwnd: HWND) -> Result<Vec<u16>> {
+    Ok(editor(hwnd)?.accessibility_value().encode_utf16().collect())
+}
+
+// === Variants and safe arrays ===
+
+fn variant(vt: VARENUM, value: VARIANT_0_0_0) -> VARIANT {
+    VARIANT {
+        Anonymous: VARIANT_0 {
+            Anonymous: ManuallyDrop::new(VARIANT_0_0 {
+                vt,
+                wReserved1: 0,
+                wReserved2: 0,
+                wReserved3: 0,
+                Anonymous: value,
+            }),
+        },
+    }
+}
+
+fn variant_i32(value: i32) -> VARIANT {
+    variant(VT_I4, VARIANT_0_0_0 { lVal: value })
+}
+
+fn variant_bool(value: bool) -> VARIANT {
+    let b = if value { VARIANT_TRUE } else { Default::default() };
+    variant(VT_BOOL, VARIANT_0_0_0 { boolVal: b })
+}
+
+fn variant_str(value: &str) -> VARIANT {
+    let bstr = BSTR::from(value);
+    variant(VT_BSTR, VARIANT_0_0_0 { bstrVal: ManuallyDrop::new(bstr) })
+}
+
+/// A SAFEARRAY of text ranges, as returned by GetSelection / GetVisibleRanges.
+fn range_array(ranges: &[ITextRangeProvider]) -> Result<*mut SAFEARRAY> {
+    unsafe {
+        let array = SafeArrayCreateVector(VT_UNKNOWN, 0, ranges.len() as u32);
+        for (i, range) in ranges.iter().enumerate() {
+            let index = i as i32;
+            SafeArrayPutElement(array, &index, range.as_raw())?;
+        }
+        Ok(array)
+    }
+}
+
+fn empty_array(vt: VARENUM) -> *mut SAFEARRAY {
+    unsafe { SafeArrayCreateVector(vt, 0, 0) }
+}
+
+// === Text units ===
+
+/// Character class for word boundaries: 0 = whitespace, 1 = word, 2 = punctuation.
+fn char_class(unit: u16) -> u8 {
+    match char::from_u32(unit as u32) {
+        Some(c) if c.is_whitespace() => 0,
+        Some(c) if c.is_alphanumeric() || c == '_' => 1,
+        Some(_) => 2,
+        // Surrogate halves belong to a non-BMP character; treat them as word text.
+        None => 1,
+    }
+}
+
+/// Whether a `unit` range may start at `pos`. Format and Paragraph fall
+/// back to Word and Line; Page falls back to Document.
+fn is_boundary(text: &[u16], pos: usize, unit: TextUnit) -> bool {
+    if pos == 0 || pos >= text.len() {
+        return true;
+    }
+    if unit == TextUnit_Character {
+        !(0xDC00..0xE000).contains(&text[pos])
+    } else if unit == TextUnit_Word || unit == TextUnit_Format {
+        let class = char_class(text[pos]);
+        class != 0 && class != char_class(text[pos - 1])
+    } else if unit == TextUnit_Line || unit == TextUnit_Paragraph {
+        text[pos - 1] == u16::from(b'\n')
+    } else {
+        false
+    }
+}
+
+fn next_boundary(text: &[u16], pos: usize, unit: TextUnit) -> Option<usize> {
+    (pos + 1..=text.len()).find(|&i| is_boundary(text, i, unit))
+}
+
+fn prev_boundary(text: &[u16], pos: usize, unit: TextUnit) -> Option<usize> {
+    (0..pos).rev().find(|&i| is_boundary(text, i, unit))
+}
+
+/// Start of the `unit` enclosing `pos`.
+fn unit_start(text: &[u16], pos: usize, unit: TextUnit) -> usize {
+    if is_boundary(text, pos, unit) {
+        pos
+    } else {
+        prev_boundary(text, pos, unit).unwrap_or(0)
+    }
+}
+
+/// Move `pos` by `count` unit boundaries; returns the new position and the
+/// number of units actually moved.
+fn move_by(text: &[u16], mut pos: usize, unit: TextUnit, count: i32) -> (usize, i32) {
+    let mut moved = 0;
+    while moved < count {
+        match next_boundary(text, pos, unit) {
+            Some(next) => pos = next,
+            None => break,
+        }
+        moved += 1;
+    }
+    while moved > count {
+        match prev_boundary(text, pos, unit) {
+            Some(prev) => pos = prev,
+            None => break,
+        }
+        moved -= 1;
+    }
+    (pos, moved)
+}
+
+// === Element ===
+
+/// The editor HWND as a UIA document element.
+#[implement(IRawElementProviderSimple)]
+struct EditorElement {
+    hwnd: HWND,
+}
+
+impl IRawElementProviderSimple_Impl for EditorElement {
+    fn ProviderOptions(&self) -> Result<ProviderOptions> {
+        Ok(ProviderOptions_ServerSideProvider | ProviderOptions_UseComThreading)
+    }
+
+    fn GetPatternProvider(&self, patternid: UIA_PATTERN_ID) -> Result<IUnknown> {
+        if patternid != UIA_TextPatternId {
+            return Err(Error::OK);
+        }
+        editor(self.hwnd)?;
+        let provider: ITextProvider = EditorTextProvider { hwnd: self.hwnd }.into();
+        provider.cast()
+    }
+
+    fn GetPropertyValue(&self, propertyid: UIA_PROPERTY_ID) -> Result<VARIANT> {
+        let value = if propertyid == UIA_ControlTypePropertyId {
+            variant_i32(UIA_DocumentControlTypeId.0 as i32)
+        } else if propertyid == UIA_NamePropertyId {
+            variant_str("Editor")
+        } else if propertyid == UIA_IsKeyboardFocusablePropertyId
+            || propertyid == UIA_IsTextPatternAvailablePropertyId
+        {
+            variant_bool(true)
+        } else if propertyid == UIA_HasKeyboardFocusPropertyId {
+            variant_bool(unsafe { GetFocus() } == self.hwnd)
+        } else {
+            // VT_EMPTY: let UIA supply the default.
+            VARIANT::default()
+        };
+        Ok(value)
+    }
+
+    fn HostRawElementProvider(&self) -> Result<IRawElementProviderSimple> {
+        unsafe { UiaHostProviderFromHwnd(self.hwnd) }
+    }
+}
+
+// === Text pattern ===
+
+#[implement(ITextProvider)]
+struct EditorTextProvider {
+    hwnd: HWND,
+}
+
+impl EditorTextProvider {
+    fn range(&self, start: usize, end: usize) -> ITextRangeProvider {
+        TextRange::new(self.hwnd, start, end).into()
+    }
+}
+
+impl ITextProvider_Impl for EditorTextProvider {
+    fn GetSelection(&self) -> Result<*mut SAFEARRAY> {
+        let caret = editor(self.hwnd)?.accessible_caret();
+        range_array(&[self.range(caret, caret)])
+    }
+
+    fn GetVisibleRanges(&self) -> Result<*mut SAFEARRAY> {
+        let len = document(self.hwnd)?.len();
+        range_array(&[self.range(0, len)])
+    }
+
+    fn RangeFromChild(
+        &self,
+        _childelement: Option<&IRawElementProviderSimple>,
+    ) -> Result<ITextRangeProvider> {
+        // The editor exposes no embedded child elements.
+        Err(E_INVALIDARG.into())
+    }
+
+    fn RangeFromPoint(&self, _point: &UiaPoint) -> Result<ITextRangeProvider> {
+        let caret = editor(self.hwnd)?.accessible_caret();
+        Ok(self.range(caret, caret))
+    }
+
+    fn DocumentRange(&self) -> Result<ITextRangeProvider> {
+        let len = document(self.hwnd)?.len();
+        Ok(self.range(0, len))
+    }
+
+    fn SupportedTextSelection(&self) -> Result<SupportedTextSelection> {
+        Ok(SupportedTextSelection_Single)
+    }
+}
+
+// === Text ranges ===
+
+/// A [start, end) span of UTF-16 offsets into the accessible text.
+#[implement(ITextRangeProvider)]
+struct TextRange {
+    hwnd: HWND,
+    start: Cell<usize>,
+    end: Cell<usize>,
+}
+
+impl TextRange {
+    fn new(hwnd: HWND, start: usize, end: usize) -> Self {
+        Self { hwnd, start: Cell::new(start), end: Cell::new(end) }
+    }
+
+    fn other(range: Option<&ITextRangeProvider>) -> Result<&TextRange> {
+        let range = range.ok_or_else(|| Error::from(E_INVALIDARG))?;
+        Ok(unsafe { range.as_impl() })
+    }
+
+    fn endpoint(&self, endpoint: TextPatternRangeEndpoint) -> usize {
+        if endpoint == TextPatternRangeEndpoint_Start {
+            self.start.get()
+        } else {
+            self.end.get()
+        }
+    }
+
+    /// Move one endpoint, dragging the other along if they would cross.
+    fn set_endpoint(&self, endpoint: TextPatternRangeEndpoint, pos: usize) {
+        if endpoint == TextPatternRangeEndpoint_Start {
+            self.start.set(pos);
+            if self.end.get() < pos {
+                self.end.set(pos);
+            }
+        } else {
+            self.end.set(pos);
+            if self.start.get() > pos {
+                self.start.set(pos);
+            }
+        }
+    }
+
+    /// Clamp both endpoints to the current text, which may have shrunk.
+    fn clamp(&self, text: &[u16]) {
+        self.start.set(self.start.get().min(text.len()));
+        self.end.set(self.end.get().min(text.len()));
+    }
+}
+
+impl ITextRangeProvider_Impl for TextRange {
+    fn Clone(&self) -> Result<ITextRangeProvider> {
+        Ok(TextRange::new(self.hwnd, self.start.get(), self.end.get()).into())
+    }
+
+    fn Compare(&self, range: Option<&ITextRangeProvider>) -> Result<BOOL> {
+        let other = Self::other(range)?;
+        Ok((self.start.get() == other.start.get() && self.end.get() == other.end.get()).into())
+    }
+
+    fn CompareEndpoints(
+        &self,
+        endpoint: TextPatternRangeEndpoint,
+        targetrange: Option<&ITextRangeProvider>,
+        targetendpoint: TextPatternRangeEndpoint,
+    ) -> Result<i32> {
+        let target = Self::other(targetrange)?;
+        Ok(self.endpoint(endpoint) as i32 - target.endpoint(targetendpoint) as i32)
+    }
+
+    fn ExpandToEnclosingUnit(&self, unit: TextUnit) -> Result<()> {
+        let text = document(self.hwnd)?;
+        self.clamp(&text);
+        let start = unit_start(&text, self.start.get(), unit);
+        let end = next_boundary(&text, start, unit).unwrap_or(text.len());
+        self.start.set(start);
+        self.end.set(end);
+        Ok(())
+    }
+
+    fn FindAttribute(
+        &self,
+        _attributeid: UIA_TEXTATTRIBUTE_ID,
+        _val: &VARIANT,
+        _backward: BOOL,
+    ) -> Result<ITextRangeProvider> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn FindText(
+        &self,
+        text: &BSTR,
+        backward: BOOL,
+        ignorecase: BOOL,
+    ) -> Result<ITextRangeProvider> {
+        let doc = document(self.hwnd)?;
+        self.clamp(&doc);
+        let haystack = String::from_utf16_lossy(&doc[self.start.get()..self.end.get()]);
+        let needle = text.to_string();
+        let (haystack, needle) = if ignorecase.as_bool() {
+            (haystack.to_lowercase(), needle.to_lowercase())
+        } else {
+            (haystack, needle)
+        };
+        let found = if backward.as_bool() {
+            haystack.rfind(&needle)
+        } else {
+            haystack.find(&needle)
+        };
+        match found {
+            Some(byte) => {
+                let start = self.start.get() + haystack[..byte].encode_utf16().count();
+                let end = start + needle.encode_utf16().count();
+                Ok(TextRange::new(self.hwnd, start, end).into())
+            }
+            // Not found: S_OK with a null range.
+            None => Err(Error::OK),
+        }
+    }
+
+    fn GetAttributeValue(&self, _attributeid: UIA_TEXTATTRIBUTE_ID) -> Result<VARIANT> {
+        Ok(VARIANT::default())
+    }
+
+    fn GetBoundingRectangles(&self) -> Result<*mut SAFEARRAY> {
+        Ok(empty_array(VT_R8))
+    }
+
+    fn GetEnclosingElement(&self) -> Result<IRawElementProviderSimple> {
+        Ok(EditorElement { hwnd: self.hwnd }.into())
+    }
+
+    fn GetText(&self, maxlength: i32) -> Result<BSTR> {
+        let text = document(self.hwnd)?;
+        self.clamp(&text);
+        let mut end = self.end.get();
+        if maxlength >= 0 {
+            end = end.min(self.start.get() + maxlength as usize);
+        }
+        BSTR::from_wide(&text[self.start.get()..end])
+    }
+
+    fn Move(&self, unit: TextUnit, count: i32) -> Result<i32> {
+        let text = document(self.hwnd)?;
+        self.clamp(&text);
+        let degenerate = self.start.get() == self.end.get();
+        let start = unit_start(&text, self.start.get(), unit);
+        let (pos, moved) = move_by(&text, start, unit, count);
+        self.start.set(pos);
+        if degenerate {
+            self.end.set(pos);
+        } else {
+            self.end.set(next_boundary(&text, pos, unit).unwrap_or(text.len()));
+        }
+        Ok(moved)
+    }
+
+    fn MoveEndpointByUnit(
+        &self,
+        endpoint: TextPatternRangeEndpoint,
+        unit: TextUnit,
+        count: i32,
+    ) -> Result<i32> {
+        let text = document(self.hwnd)?;
+        self.clamp(&text);
+        let (pos, moved) = move_by(&text, self.endpoint(endpoint), unit, count);
+        self.set_endpoint(endpoint, pos);
+        Ok(moved)
+    }
+
+    fn MoveEndpointByRange(
+        &self,
+        endpoint: TextPatternRangeEndpoint,
+        targetrange: Option<&ITextRangeProvider>,
+        targetendpoint: TextPatternRangeEndpoint,
+    ) -> Result<()> {
+        let target = Self::other(targetrange)?;
+        self.set_endpoint(endpoint, target.endpoint(targetendpoint));
+        Ok(())
+    }
+
+    fn Select(&self) -> Result<()> {
+        let editor = editor(self.hwnd)?;
+        editor.on_action(&format!("selectRange:{},{}", self.start.get(), self.end.get()));
+        Ok(())
+    }
+
+    fn AddToSelection(&self) -> Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn RemoveFromSelection(&self) -> Result<()> {
+        Err(E_NOTIMPL.into())
+    }
+
+    fn ScrollIntoView(&self, _aligntotop: BOOL) -> Result<()> {
+        let text = document(self.hwnd)?;
+        self.clamp(&text);
+        let line = text[..self.start.get()].iter().filter(|&&u| u == u16::from(b'\n')).count();
+        editor(self.hwnd)?.on_action(&format!("revealLine:{}", line + 1));
+        Ok(())
+    }
+
+    fn GetChildren(&self) -> Result<*mut SAFEARRAY> {
+        Ok(empty_array(VT_UNKNOWN))
+    }
+}
@@ -5,36 +5,112 @@
 //! endFrame the HWND is invalidated, and WM_PAINT calls draw() which
 //! paints everything via Direct2D / DirectWrite.
 
+use hone_editor_common::columns::visual_column;
+use hone_editor_common::diagnostics::severity_index;
+use hone_editor_common::graphemes::grapheme_boundaries;
+use hone_editor_common::handles::ViewHandle;
+use hone_editor_common::hit_test::column_at_point;
+use hone_editor_common::selection::{
+    push_stacked, rounded_corners, selection_outlines, SelectionRegion,
+};
+use hone_editor_common::selectors::is_edit_selector;
+use hone_editor_common::tokens::{overlay_colors, slice_tokens};
+use hone_editor_common::whitespace::{indent_level_ends, trailing_whitespace_start};
+use hone_editor_common::words::{subword_selector, word_range_at};
 use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::ffi::{c_char, CString};
+use std::time::{Duration, Instant};
+
+use windows::core::{w, ComInterface};
 
-use windows::Win32::Foundation::HWND;
+use windows::Foundation::Numerics::Matrix3x2;
+use windows::Win32::Foundation::{HWND, RECT};
 use windows::Win32::Graphics::Direct2D::Common::{
-    D2D1_COLOR_F, D2D_POINT_2F, D2D_RECT_F, D2D_SIZE_U,
+    D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_COLOR_F, D2D1_FIGURE_BEGIN_FILLED, D2D1_FIGURE_END_CLOSED,
+    D2D1_PIXEL_FORMAT, D2D_POINT_2F, D2D_RECT_F, D2D_SIZE_U,
 };
 use windows::Win32::Graphics::Direct2D::{
-    D2D1CreateFactory, ID2D1Factory, ID2D1HwndRenderTarget,
-    D2D1_FACTORY_TYPE_SINGLE_THREADED, D2D1_HWND_RENDER_TARGET_PROPERTIES,
-    D2D1_PRESENT_OPTIONS_NONE, D2D1_RENDER_TARGET_PROPERTIES,
+    D2D1CreateFactory, ID2D1Factory, ID2D1HwndRenderTarget, ID2D1RenderTarget,
+    D2D1_ANTIALIAS_MODE_ALIASED, D2D1_BITMAP_INTERPOLATION_MODE_LINEAR, D2D1_BITMAP_PROPERTIES,
+    D2D1_EXTEND_MODE_CLAMP, D2D1_FACTORY_TYPE_SINGLE_THREADED,
+    D2D1_GAMMA_2_2, D2D1_GRADIENT_STOP, D2D1_HWND_RENDER_TARGET_PROPERTIES,
+    D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES, D2D1_PRESENT_OPTIONS_NONE,
+    D2D1_QUADRATIC_BEZIER_SEGMENT, D2D1_RENDER_TARGET_PROPERTIES, D2D1_ROUNDED_RECT,
+    D2D1_TEXT_ANTIALIAS_MODE_ALIASED, D2D1_TEXT_ANTIALIAS_MODE_CLEARTYPE,
+    D2D1_TEXT_ANTIALIAS_MODE_DEFAULT, D2D1_TEXT_ANTIALIAS_MODE_GRAYSCALE,
+};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
+use windows::Win32::Graphics::Gdi::{
+    GetSysColor, InvalidateRect, COLOR_GRAYTEXT, COLOR_HIGHLIGHT, COLOR_WINDOW, COLOR_WINDOWTEXT,
+    SYS_COLOR_INDEX,
+};
+use windows::Win32::Graphics::Imaging::{
+    CLSID_WICImagingFactory, GUID_WICPixelFormat32bppPBGRA, IWICImagingFactory,
+    WICBitmapCacheOnLoad,
+};
+use windows::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+use windows::Win32::UI::WindowsAndMessaging::{
+    KillTimer, SetTimer, SystemParametersInfoW, SPI_GETHIGHCONTRAST,
+    SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+};
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+    COINIT_APARTMENTTHREADED,
 };
-use windows::Win32::Graphics::Gdi::InvalidateRect;
 
 use crate::text_renderer::{self, FontSet, RenderToken};
+use crate::theme::{self, Theme};
 
 // ── Callback types ──────────────────────────────────────────────
 
 /// Called when the user types printable text. `text` is a null-terminated UTF-8 C string.
-pub type TextInputCallback = extern "C" fn(view: *mut EditorView, text: *const c_char);
+pub type TextInputCallback = extern "C" fn(view: ViewHandle, text: *const c_char);
 
 /// Called when an action selector fires (arrow keys, delete, enter, etc.).
 /// `selector` is the selector name as a null-terminated UTF-8 C string (e.g. "moveLeft:").
-pub type ActionCallback = extern "C" fn(view: *mut EditorView, selector: *const c_char);
+pub type ActionCallback = extern "C" fn(view: ViewHandle, selector: *const c_char);
 
 /// Called when the user clicks in the editor view. `x` and `y` are in view coordinates.
-pub type MouseDownCallback = extern "C" fn(view: *mut EditorView, x: f64, y: f64);
+pub type MouseDownCallback = extern "C" fn(view: ViewHandle, x: f64, y: f64);
 
 /// Called when the user scrolls. `dx`/`dy` are pixel deltas (dy positive = scroll down).
-pub type ScrollCallback = extern "C" fn(view: *mut EditorView, dx: f64, dy: f64);
+pub type ScrollCallback = extern "C" fn(view: ViewHandle, dx: f64, dy: f64);
+
+/// Called after the view changes its own scroll offset (wheel in content mode,
+/// reveal, center) with the final offsets. Never fired for host `scroll` calls.
+pub type DidScrollCallback = extern "C" fn(view: ViewHandle, offset_x: f64, offset_y: f64);
+
+/// Called after each painted frame with the time spent rendering it, in milliseconds.
+pub type FrameCallback = extern "C" fn(view: ViewHandle, elapsed_ms: f64);
+
+/// Called when font metrics change (font, size, line spacing, or metrics
+/// override), so the host can re-lay-out without polling.
+pub type MetricsCallback =
+    extern "C" fn(view: ViewHandle, char_width: f64, line_height: f64, ascent: f64);
+
+/// Called for every key press before default handling; return true to consume
+/// the event. `keycode` is the Win32 virtual-key code; `modifiers` is a mask of the `KEY_MODIFIER_*` bits.
+pub type KeyCallback = extern "C" fn(view: ViewHandle, keycode: u32, modifiers: u32) -> bool;
+
+/// Modifier bits passed to `KeyCallback`. `KEY_MODIFIER_COMMAND` is Cmd on
+/// macOS and the Super/Windows key elsewhere.
+pub const KEY_MODIFIER_SHIFT: u32 = 1 << 0;
+pub const KEY_MODIFIER_CONTROL: u32 = 1 << 1;
+pub const KEY_MODIFIER_ALT: u32 = 1 << 2;
+pub const KEY_MODIFIER_COMMAND: u32 = 1 << 3;
+
+/// Called after the color theme changes (a preset or the system appearance),
+/// so the host can recolor tokens. `appearance` is "dark", "light",
+/// "high-contrast-dark" or "high-contrast-light"; high-contrast themes
+/// already draw tokens in the plain text color.
+pub type ThemeChangedCallback = extern "C" fn(view: ViewHandle, appearance: *const c_char);
+
+/// Called when the view gains (`focused` = true) or loses keyboard focus.
+pub type FocusCallback = extern "C" fn(view: ViewHandle, focused: bool);
 
 /// A custom context menu item added by the host application.
 pub struct ContextMenuItem {
@@ -45,18 +121,35 @@ pub struct ContextMenuItem {
 // ── Data structures ──────────────────────────────────────────────
 
 #[derive(Debug, Deserialize)]
-pub struct SelectionRegion {
+pub struct CursorData {
     pub x: f64,
     pub y: f64,
-    pub w: f64,
-    pub h: f64,
+    /// 0 = line, 1 = block, 2 = underline. Set per cursor, so a block primary
+    /// can be drawn alongside line secondaries. Missing means line.
+    #[serde(default)]
+    pub style: i32,
 }
 
+/// A collaborator's caret from `set_remote_cursors`.
 #[derive(Debug, Deserialize)]
-pub struct CursorData {
+pub struct RemoteCursor {
     pub x: f64,
     pub y: f64,
-    pub style: i32,
+    /// "#rrggbb" for the caret and its name tag.
+    pub color: String,
+    /// Name drawn in a tag above the caret; empty for none.
+    #[serde(default)]
+    pub label: String,
+}
+
+/// A vertical band from `set_region_shading`, spanning `start_y..end_y`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadedRegion {
+    pub start_y: f64,
+    pub end_y: f64,
+    /// "#rrggbb", drawn faint.
+    pub color: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -70,9 +163,11 @@ pub struct DecorationOverlay {
     pub kind: String,
 }
 
+#[derive(Deserialize)]
 struct LineRenderData {
     line_number: i32,
     text: String,
+    #[serde(default)]
     tokens: Vec<RenderToken>,
     y_offset: f64,
 }
@@ -84,12 +179,205 @@ struct GhostTextData {
     color: D2D1_COLOR_F,
 }
 
+/// A decoration from `flash_decoration` that fades out over `duration`.
+struct FlashDecoration {
+    decor: DecorationOverlay,
+    start: Instant,
+    duration: Duration,
+}
+
+impl FlashDecoration {
+    /// Opacity at `now`: 1 when flashed, falling linearly to 0.
+    fn alpha(&self, now: Instant) -> f64 {
+        let t = now.duration_since(self.start).as_secs_f64() / self.duration.as_secs_f64();
+        (1.0 - t).clamp(0.0, 1.0)
+    }
+}
+
+/// A collapsed-region badge drawn after column `after_col` of `line`.
+struct FoldPlaceholder {
+    line: i32,
+    after_col: usize,
+}
+
+/// A scope header pinned to the top of the viewport (sticky scroll).
+#[derive(Deserialize)]
+struct StickyLine {
+    line_number: i32,
+    text: String,
+    #[serde(default)]
+    tokens: Vec<RenderToken>,
+}
+
+/// Caret and selection summary from `status_info`, for a status bar's
+/// "Ln 3, Col 12 (5 selected)".
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StatusInfo {
+    /// 1-based line of the primary caret; 0 when no caret is set.
+    pub line: i32,
+    /// 1-based display column of the caret, tabs expanded and wide
+    /// characters counted as two cells.
+    pub column: i32,
+    /// Characters in the logical selection, each line break counting one.
+    pub selection_chars: i32,
+    /// Lines the logical selection touches; 0 with no selection.
+    pub selection_lines: i32,
+}
+
+/// One document line from `set_content`; its line number is its index + 1.
+#[derive(Deserialize)]
+struct ContentLine {
+    text: String,
+    #[serde(default)]
+    tokens: Vec<RenderToken>,
+}
+
+/// A range from `set_selection_logical`: columns are character offsets into
+/// the rendered lines and the end is exclusive.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogicalSelection {
+    start_line: i32,
+    start_col: usize,
+    end_line: i32,
+    end_col: usize,
+    #[serde(default)]
+    color: Option<String>,
+}
+
+/// A recolored span from `set_color_overrides`: columns are character
+/// offsets into the rendered line and the end is exclusive.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ColorOverride {
+    line: i32,
+    start_col: usize,
+    end_col: usize,
+    color: String,
+}
+
+/// A link range from `set_links`: columns are character offsets into the
+/// rendered line.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LinkSpec {
+    line: i32,
+    start_col: usize,
+    end_col: usize,
+    url: String,
+}
+
+/// A diagnostic range from `set_diagnostics`: columns are character offsets
+/// into the rendered line and `severity` is "error", "warning" or "info".
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiagnosticSpec {
+    line: i32,
+    start_col: usize,
+    end_col: usize,
+    severity: String,
+}
+
+/// A bitmap from `render_image`, drawn at `(x, y)` one DIP per pixel.
+/// `bgra` is premultiplied, ready for a D2D bitmap on the render target.
+struct InlineImage {
+    x: f64,
+    y: f64,
+    width: u32,
+    height: u32,
+    bgra: Vec<u8>,
+}
+
+/// A label from `render_caret_label`, drawn just above the caret at `(x, y)`.
+struct CaretLabel {
+    x: f64,
+    y: f64,
+    text: String,
+    bg: D2D1_COLOR_F,
+    fg: D2D1_COLOR_F,
+}
+
+/// A card from `render_hover_card`, rows already wrapped, top-left at `(x, y)`.
+struct HoverCard {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    rows: Vec<(String, Vec<RenderToken>)>,
+}
+
+/// One line of a hover card as sent by the host.
+#[derive(Deserialize)]
+struct HoverLine {
+    text: String,
+    #[serde(default)]
+    tokens: Vec<RenderToken>,
+}
+
+/// A list from `render_completion_popup`, top-left at `(x, y)`.
+struct CompletionPopup {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    items: Vec<CompletionItem>,
+    selected: Option<usize>,
+    // Offsets of the label and detail columns from `x`
+    label_x: f64,
+    detail_x: f64,
+}
+
+/// One completion item as sent by the host.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionItem {
+    label: String,
+    #[serde(default)]
+    detail: String,
+    /// Kind glyph drawn before the label; empty for none.
+    #[serde(default)]
+    icon: String,
+    /// "#rrggbb" for the glyph; defaults to the gutter foreground.
+    #[serde(default)]
+    icon_color: Option<String>,
+}
+
+/// A link resolved to view coordinates for hit testing.
+struct LinkRegion {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    url: String,
+}
+
+/// Mouse cursor shape shown over a region of the view.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorKind {
+    #[serde(alias = "ibeam")]
+    Text,
+    Arrow,
+    Hand,
+}
+
+/// A host-supplied cursor region from `set_cursor_regions`, in view coordinates.
+#[derive(Deserialize)]
+struct CursorRegion {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+    cursor: CursorKind,
+}
+
 // ── EditorView ───────────────────────────────────────────────────
 
 /// Top-level editor view state.
 ///
-/// This is the object behind the opaque `*mut EditorView` pointer
-/// returned by `hone_editor_create()`.
+/// This is the object behind the opaque `ViewHandle`
+/// returned by `hone_editor_create_handle()`.
 pub struct EditorView {
     pub renderer: FontSet,
     hwnd: HWND,
@@ -125,6 +413,147 @@ pub struct EditorView {
     default_text_color: D2D1_COLOR_F,
     selection_color: D2D1_COLOR_F,
     cursor_color: D2D1_COLOR_F,
+
+    // Selection style: 0 = rectangles, 1 = rounded merged outline
+    selection_style: i32,
+
+    // Column rulers (character columns) and their color
+    rulers: Vec<u32>,
+    ruler_color: D2D1_COLOR_F,
+
+    // Line the caret is on (-1 = unknown); skipped by trailing-whitespace highlighting
+    active_line: i32,
+    highlight_trailing_whitespace: bool,
+    trailing_whitespace_color: D2D1_COLOR_F,
+
+    // Caret mode: 0 = insert (line caret), 1 = overwrite (block caret).
+    // Supplies the style for cursors pushed with style -1.
+    caret_mode: i32,
+
+    // Text antialiasing: -1 = platform default, 0 = none, 1 = grayscale, 2 = subpixel
+    text_antialiasing: i32,
+
+    // Line height multiplier applied on top of the font's natural line height
+    line_spacing: f64,
+
+    // Fixed (char_width, line_height, ascent) for headless/testing, if set
+    metrics_override: Option<(f64, f64, f64)>,
+
+    // Total document lines (0 = unknown); bounds library-driven scrolling
+    line_count: i32,
+
+    // Lines of context kept between a revealed line and the viewport edge
+    scrolloff: i32,
+    frame_callback: Option<FrameCallback>,
+    selection_full_line: bool,
+    overscroll: i32,
+    // Whole document from `set_content`; `Some` while the view picks its own rows
+    content: Option<Vec<ContentLine>>,
+    // Rows laid out past each viewport edge in content mode
+    overscan: i32,
+    // Handle the host knows this view by; 0 until lib.rs registers it
+    pub(crate) handle: ViewHandle,
+    follow_system_appearance: bool,
+    theme_name: &'static str,
+    theme_changed_callback: Option<ThemeChangedCallback>,
+    key_callback: Option<KeyCallback>,
+    metrics_callback: Option<MetricsCallback>,
+    /// Horizontal scroll offset in pixels; line text slides under the gutter.
+    scroll_x: f64,
+    gutter_shadow: bool,
+    gutter_visible: bool,
+    // Input-field mode: one centered row, no gutter, Enter submits
+    single_line: bool,
+    fold_placeholders: Vec<FoldPlaceholder>,
+    sticky_lines: Vec<StickyLine>,
+    grapheme_buf: Vec<u32>,
+    /// High surrogate from a WM_CHAR awaiting its low half.
+    pending_surrogate: Option<u16>,
+
+    // Accessibility (host-supplied full text, caret as a UTF-16 offset)
+    accessible_text: Option<String>,
+    accessible_cursor: usize,
+
+    // High contrast: `base_theme` is the preset shown when it is off
+    base_theme: &'static Theme,
+    force_high_contrast: bool,
+    system_high_contrast: bool,
+    flatten_tokens: bool,
+
+    // Focus: dim the content by `inactive_dim` (0 = off) while unfocused
+    focused: bool,
+    focus_callback: Option<FocusCallback>,
+    inactive_dim: f64,
+    hollow_caret_unfocused: bool,
+    links: Vec<LinkRegion>,
+    cursor_regions: Vec<CursorRegion>,
+    flashes: Vec<FlashDecoration>,
+    // When the visual bell last rang; cleared once it has faded
+    bell: Option<Instant>,
+    flash_timer_active: bool,
+    placeholder: Option<(String, D2D1_COLOR_F)>,
+    caret_width: f64,
+    max_line_width: f64,
+    occurrences: Vec<SelectionRegion>,
+    occurrence_color: D2D1_COLOR_F,
+    // Tint gutter rows covered by a selection, with an optional color
+    gutter_selection: Option<Option<D2D1_COLOR_F>>,
+    // Bitmaps from render_image, cleared on begin_frame
+    images: Vec<InlineImage>,
+    // Keep lines across begin_frame in persistent_lines instead
+    persistent_frame: bool,
+    // Persistent mode's lines by line number, pruned to the viewport
+    persistent_lines: BTreeMap<i32, LineRenderData>,
+    // Collaborators' carets and selections, cleared on begin_frame
+    remote_cursors: Vec<RemoteCursor>,
+    remote_selections: Vec<SelectionRegion>,
+    // Labels from render_caret_label, cleared on begin_frame
+    caret_labels: Vec<CaretLabel>,
+    // Hover card from render_hover_card, cleared on begin_frame
+    hover_card: Option<HoverCard>,
+    // Hover card fill and border, set by the theme
+    widget_bg_color: D2D1_COLOR_F,
+    widget_border_color: D2D1_COLOR_F,
+    // Completion list from render_completion_popup, cleared on begin_frame;
+    // its scroll survives frames so the host can re-render it freely
+    completion: Option<CompletionPopup>,
+    completion_open: bool,
+    completion_scroll: f64,
+    completion_selected: Option<usize>,
+    // Indent rainbow colors, cycled per indent level; empty when off
+    indent_rainbow: Vec<D2D1_COLOR_F>,
+    tab_width: usize,
+    // Host-shaded vertical bands, cleared on begin_frame
+    shaded_regions: Vec<ShadedRegion>,
+    // Emit moveSubword… selectors and split subwords in word ranges
+    subword_navigation: bool,
+    // Recolored spans over line tokens, cleared on begin_frame
+    color_overrides: Vec<ColorOverride>,
+    // Fonts registered from memory; unregistered when the view is dropped
+    registered_fonts: Vec<text_renderer::RegisteredFont>,
+    // Ordered (start, end) line/char-column ends of the logical selection,
+    // kept for status_info; cleared on begin_frame
+    selection_ranges: Vec<((i32, usize), (i32, usize))>,
+    // Top and bottom colors of a vertical background gradient; None fills
+    // with background_color
+    background_gradient: Option<(D2D1_COLOR_F, D2D1_COLOR_F)>,
+    // Draw the active line's number bold, with an optional color
+    active_line_gutter: Option<Option<D2D1_COLOR_F>>,
+    // Read-only mode, with an optional text-area tint
+    read_only: Option<Option<D2D1_COLOR_F>>,
+    did_scroll_callback: Option<DidScrollCallback>,
+    // Set while did_scroll_callback runs, so a scroll it triggers isn't re-reported
+    in_did_scroll: bool,
+    // End-of-file markers: 0 = none, 1 = a tilde on each row past the last
+    // line (vim style), 2 = one end symbol below the last line
+    eof_markers: i32,
+    // Squiggle colors ("#rrggbb") for error, warning and info diagnostics
+    diagnostic_colors: [String; 3],
+    // Gutter numbering: 0 = absolute, 1 = relative to the active line,
+    // 2 = relative with the active line's absolute number
+    line_number_mode: i32,
+    // This thread's COM apartment; last so it is left after everything else drops
+    _com: ComApartment,
 }
 
 fn is_null_hwnd(hwnd: HWND) -> bool {
@@ -133,7 +562,8 @@ fn is_null_hwnd(hwnd: HWND) -> bool {
 
 impl EditorView {
     pub fn new(width: f64, height: f64) -> Self {
-        let renderer = FontSet::new("Consolas", 14.0);
+        let com = ComApartment::enter();
+        let renderer = FontSet::new("Consolas", 14.0, 400, false);
 
         let d2d_factory: ID2D1Factory = unsafe {
             D2D1CreateFactory(D2D1_FACTORY_TYPE_SINGLE_THREADED, None)
@@ -198,6 +628,117 @@ impl EditorView {
                 b: 0.918,
                 a: 1.0,
             },
+            selection_style: 0,
+            rulers: Vec::new(),
+            ruler_color: D2D1_COLOR_F {
+                r: 0.353,
+                g: 0.353,
+                b: 0.353,
+                a: 0.5,
+            },
+            active_line: -1,
+            highlight_trailing_whitespace: false,
+            trailing_whitespace_color: D2D1_COLOR_F {
+                r: 1.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.3,
+            },
+            caret_mode: 0,
+            text_antialiasing: -1,
+            line_spacing: 1.0,
+            metrics_override: None,
+            line_count: 0,
+            scrolloff: 0,
+            frame_callback: None,
+            selection_full_line: false,
+            overscroll: 0,
+            content: None,
+            overscan: 2,
+            handle: 0,
+            follow_system_appearance: false,
+            theme_name: theme::VSCODE_DARK.name,
+            theme_changed_callback: None,
+            key_callback: None,
+            metrics_callback: None,
+            scroll_x: 0.0,
+            gutter_shadow: true,
+            gutter_visible: true,
+            single_line: false,
+            fold_placeholders: Vec::new(),
+            sticky_lines: Vec::new(),
+            grapheme_buf: Vec::new(),
+            pending_surrogate: None,
+            accessible_text: None,
+            accessible_cursor: 0,
+            base_theme: &theme::VSCODE_DARK,
+            force_high_contrast: false,
+            system_high_contrast: false,
+            flatten_tokens: false,
+            focused: false,
+            focus_callback: None,
+            inactive_dim: 0.0,
+            hollow_caret_unfocused: true,
+            links: Vec::new(),
+            cursor_regions: Vec::new(),
+            flashes: Vec::new(),
+            bell: None,
+            flash_timer_active: false,
+            placeholder: None,
+            caret_width: 2.0,
+            max_line_width: 0.0,
+            occurrences: Vec::new(),
+            occurrence_color: D2D1_COLOR_F {
+                r: 0.541,
+                g: 0.541,
+                b: 0.541,
+                a: 1.0,
+            },
+            gutter_selection: None,
+            images: Vec::new(),
+            persistent_frame: false,
+            persistent_lines: BTreeMap::new(),
+            remote_cursors: Vec::new(),
+            remote_selections: Vec::new(),
+            caret_labels: Vec::new(),
+            hover_card: None,
+            widget_bg_color: D2D1_COLOR_F {
+                r: 0.145,
+                g: 0.145,
+                b: 0.149,
+                a: 1.0,
+            },
+            widget_border_color: D2D1_COLOR_F {
+                r: 0.271,
+                g: 0.271,
+                b: 0.271,
+                a: 1.0,
+            },
+            completion: None,
+            completion_open: false,
+            completion_scroll: 0.0,
+            completion_selected: None,
+            indent_rainbow: Vec::new(),
+            tab_width: 4,
+            shaded_regions: Vec::new(),
+            subword_navigation: false,
+            color_overrides: Vec::new(),
+            registered_fonts: Vec::new(),
+            selection_ranges: Vec::new(),
+            background_gradient: None,
+            active_line_gutter: None,
+            read_only: None,
+            did_scroll_callback: None,
+            in_did_scroll: false,
+            eof_markers: 0,
+            diagnostic_colors: [
+                theme::VSCODE_DARK.error,
+                theme::VSCODE_DARK.warning,
+                theme::VSCODE_DARK.info,
+            ]
+            .map(String::from),
+            line_number_mode: 0,
+            _com: com,
         }
     }
 
@@ -220,35 +761,98 @@ impl EditorView {
         self.action_callback = Some(cb);
     }
 
-    /// Called from the WndProc's WM_CHAR handler.
+    /// Called from the WndProc's WM_CHAR handler with one UTF-16 unit.
+    /// Characters outside the BMP arrive as a surrogate pair over two
+    /// messages, so a high surrogate is held until its low half follows;
+    /// an unpaired surrogate is dropped. Control characters are ignored.
+    pub fn on_char(&mut self, unit: u16) {
+        if let Some(c) = combine_utf16(&mut self.pending_surrogate, unit) {
+            if c >= ' ' {
+                let mut buf = [0u8; 4];
+                self.on_text_input(c.encode_utf8(&mut buf));
+            }
+        }
+    }
+
+    /// Text typed or committed by the input method.
     pub fn on_text_input(&mut self, text: &str) {
+        if self.read_only.is_some() {
+            return;
+        }
         if let Some(cb) = self.text_input_callback {
             if let Ok(c_text) = CString::new(text) {
-                let self_ptr = self as *mut EditorView;
-                cb(self_ptr, c_text.as_ptr());
+                cb(self.handle, c_text.as_ptr());
             }
         }
     }
 
     /// Called from the WndProc's WM_KEYDOWN handler.
     pub fn on_action(&mut self, selector: &str) {
+        let selector = if self.single_line && selector == "insertNewline:" {
+            "submit:"
+        } else if self.subword_navigation {
+            subword_selector(selector)
+        } else {
+            selector
+        };
+        if self.read_only.is_some() && is_edit_selector(selector) {
+            return;
+        }
         if let Some(cb) = self.action_callback {
             if let Ok(c_sel) = CString::new(selector) {
-                let self_ptr = self as *mut EditorView;
-                cb(self_ptr, c_sel.as_ptr());
+                cb(self.handle, c_sel.as_ptr());
             }
         }
     }
 
+    /// Make word movement stop at camelCase humps and snake_case underscores:
+    /// the word-movement selectors are emitted as their `moveSubword…`
+    /// counterparts and `word_range_at` always splits subwords.
+    pub fn set_subword_navigation(&mut self, enabled: bool) {
+        self.subword_navigation = enabled;
+    }
+
+    /// Called for Tab / Shift+Tab. Emits "indent:" / "outdent:" when a
+    /// selection crosses a line break, "insertTab:" / "insertBacktab:"
+    /// otherwise, so multi-cursor selections within lines insert tabs.
+    pub fn on_tab(&mut self, backward: bool) {
+        let multi_line = self.selections.iter().any(|s| s.eol);
+        let selector = match (backward, multi_line) {
+            (false, false) => "insertTab:",
+            (true, false) => "insertBacktab:",
+            (false, true) => "indent:",
+            (true, true) => "outdent:",
+        };
+        self.on_action(selector);
+    }
+
     pub fn set_mouse_down_callback(&mut self, cb: MouseDownCallback) {
         self.mouse_down_callback = Some(cb);
     }
 
     /// Called from the WndProc's WM_LBUTTONDOWN handler.
     pub fn on_mouse_down(&mut self, x: f64, y: f64) {
+        let sticky_row = (y / self.renderer.line_height).floor();
+        if y >= 0.0 && (sticky_row as usize) < self.sticky_lines.len() {
+            let line = self.sticky_lines[sticky_row as usize].line_number;
+            self.on_action(&format!("revealLine:{line}"));
+            return;
+        }
+        if let Some(popup) = &self.completion {
+            if x >= popup.x && x < popup.x + popup.w && y >= popup.y && y < popup.y + popup.h {
+                let row = (y - popup.y + self.completion_scroll) / self.renderer.line_height;
+                if (row as usize) < popup.items.len() {
+                    self.on_action(&format!("acceptCompletion:{}", row as usize));
+                }
+                return;
+            }
+        }
+        if let Some(line) = self.fold_placeholder_at(x, y) {
+            self.on_action(&format!("unfold:{line}"));
+            return;
+        }
         if let Some(cb) = self.mouse_down_callback {
-            let self_ptr = self as *mut EditorView;
-            cb(self_ptr, x, y);
+            cb(self.handle, x, y);
         }
     }
 
@@ -256,11 +860,93 @@ impl EditorView {
         self.scroll_callback = Some(cb);
     }
 
+    pub fn set_did_scroll_callback(&mut self, cb: DidScrollCallback) {
+        self.did_scroll_callback = Some(cb);
+    }
+
+    pub fn set_frame_callback(&mut self, cb: FrameCallback) {
+        self.frame_callback = Some(cb);
+    }
+
+    pub fn set_metrics_callback(&mut self, cb: MetricsCallback) {
+        self.metrics_callback = Some(cb);
+    }
+
+    /// After a metric change, scroll so the line that was at the top of the
+    /// viewport stays there instead of jumping with the old pixel offset.
+    fn restore_top_line(&mut self, old_line_h: f64) {
+        let line_h = self.renderer.line_height;
+        if old_line_h > 0.0 && line_h != old_line_h {
+            self.scroll_to(self.scroll_offset / old_line_h * line_h);
+        }
+    }
+
+    fn notify_metrics(&mut self) {
+        // Widths measured with the old metrics are stale
+        self.max_line_width = 0.0;
+        self.fill_visible_lines();
+        if let Some(cb) = self.metrics_callback {
+            let (char_width, line_height, ascent) =
+                (self.renderer.char_width, self.renderer.line_height, self.renderer.ascent);
+            cb(self.handle, char_width, line_height, ascent);
+        }
+    }
+
+    pub fn set_key_callback(&mut self, cb: KeyCallback) {
+        self.key_callback = Some(cb);
+    }
+
+    /// Called from the WndProc's WM_KEYDOWN handler before any default handling.
+    /// Returns true if the host consumed the key.
+    pub fn on_key(&mut self, keycode: u32, modifiers: u32) -> bool {
+        match self.key_callback {
+            Some(cb) => cb(self.handle, keycode, modifiers),
+            None => false,
+        }
+    }
+
     /// Called from the WndProc's WM_MOUSEWHEEL handler.
     pub fn on_scroll(&mut self, dx: f64, dy: f64) {
+        // An open completion list takes the wheel while it overflows
+        if let Some(popup) = &self.completion {
+            let max = popup.items.len() as f64 * self.renderer.line_height - popup.h;
+            if max > 0.0 {
+                self.completion_scroll = (self.completion_scroll + dy).clamp(0.0, max);
+                self.invalidate();
+                return;
+            }
+        }
+        // A single-line input never scrolls vertically
+        let mut dy = if self.single_line { 0.0 } else { dy };
+        // In content mode the view scrolls itself; the host hears the delta
+        // left after clamping
+        if self.content.is_some() {
+            let offset = self.clamp_scroll(self.scroll_offset + dy);
+            dy = offset - self.scroll_offset;
+            self.scroll_offset = offset;
+            self.fill_visible_lines();
+            self.invalidate();
+        }
+        self.notify_scroll(dx, dy);
+        if self.content.is_some() && dy != 0.0 {
+            self.notify_did_scroll();
+        }
+    }
+
+    fn notify_scroll(&mut self, dx: f64, dy: f64) {
         if let Some(cb) = self.scroll_callback {
-            let self_ptr = self as *mut EditorView;
-            cb(self_ptr, dx, dy);
+            cb(self.handle, dx, dy);
+        }
+    }
+
+    fn notify_did_scroll(&mut self) {
+        if self.in_did_scroll {
+            return;
+        }
+        if let Some(cb) = self.did_scroll_callback {
+            self.in_did_scroll = true;
+            cb(self.handle, self.scroll_x, self.scroll_offset);
+            self.in_did_scroll = false;
         }
     }
 
@@ -280,131 +966,1517 @@ impl EditorView {
     }
 
     pub fn set_font(&mut self, family: &str, size: f64) {
-        self.renderer = FontSet::new(family, size);
+        self.set_font_ex(family, size, 400, false);
+    }
+
+    /// Like `set_font`, with the base face's weight (100–900, 400 is
+    /// regular) and slant. Bold runs use a heavier weight and keep the slant.
+    pub fn set_font_ex(&mut self, family: &str, size: f64, weight: u16, italic: bool) {
+        let old_line_h = self.renderer.line_height;
+        self.renderer = FontSet::new(family, size, weight, italic);
+        self.renderer.set_metrics_override(self.metrics_override);
+        self.renderer.set_line_spacing(self.line_spacing);
         self.invalidate();
+        self.restore_top_line(old_line_h);
+        self.notify_metrics();
     }
 
-    pub fn measure_text(&self, text: &str) -> f64 {
-        self.renderer.measure_text(text)
+    /// Register a TrueType/OpenType font from `bytes` so `set_font(name, ..)`
+    /// finds it, for fonts bundled with the app rather than installed. The
+    /// registration lasts until the view is destroyed. Returns false if the
+    /// bytes are not a font.
+    pub fn register_font_bytes(&mut self, name: &str, bytes: &[u8]) -> bool {
+        match text_renderer::register_font(name, bytes) {
+            Some(font) => {
+                self.registered_fonts.push(font);
+                true
+            }
+            None => false,
+        }
     }
 
-    // ── Frame buffer API ─────────────────────────────────────────
+    /// Change the point size but keep the family. Cheaper than `set_font`:
+    /// only size-dependent fonts and metrics are rebuilt.
+    pub fn set_font_size(&mut self, size: f64) {
+        let old_line_h = self.renderer.line_height;
+        self.renderer = self.renderer.resized(size);
+        self.renderer.set_metrics_override(self.metrics_override);
+        self.renderer.set_line_spacing(self.line_spacing);
+        self.invalidate();
+        self.restore_top_line(old_line_h);
+        self.notify_metrics();
+    }
 
-    pub fn begin_frame(&mut self) {
-        self.frame_lines.clear();
-        self.cursor = None;
-        self.cursors.clear();
-        self.selections.clear();
-        self.decorations.clear();
-        self.ghost_text = None;
-        self.max_line_number = 0;
+    /// Apply a built-in color preset by name. Returns false (and changes
+    /// nothing) for an unknown name. While high contrast is on, the preset
+    /// is remembered and shown once it turns off.
+    pub fn set_theme_preset(&mut self, name: &str) -> bool {
+        match theme::preset(name) {
+            Some(t) => {
+                self.base_theme = t;
+                if !self.high_contrast() {
+                    self.apply_theme(t);
+                }
+                true
+            }
+            None => false,
+        }
     }
 
-    pub fn render_line(
-        &mut self,
-        line_number: i32,
-        text: &str,
-        tokens_json: &str,
-        y_offset: f64,
-    ) {
-        let tokens: Vec<RenderToken> = serde_json::from_str(tokens_json).unwrap_or_default();
-        if line_number > self.max_line_number {
-            self.max_line_number = line_number;
+    /// Whether the high-contrast palette is in force, by the host or the OS.
+    fn high_contrast(&self) -> bool {
+        self.force_high_contrast || self.system_high_contrast
+    }
+
+    /// Force the high-contrast palette on, whatever the system setting.
+    pub fn set_force_high_contrast(&mut self, enabled: bool) {
+        self.force_high_contrast = enabled;
+        self.refresh_theme();
+    }
+
+    fn apply_theme(&mut self, t: &'static Theme) {
+        let hex = text_renderer::parse_hex_color;
+        self.background_color = hex(t.background);
+        self.gutter_bg_color = hex(t.gutter_background);
+        self.gutter_fg_color = hex(t.gutter_foreground);
+        self.default_text_color = hex(t.text);
+        self.selection_color = D2D1_COLOR_F {
+            a: t.selection_alpha as f32,
+            ..hex(t.selection)
+        };
+        self.occurrence_color = hex(t.occurrence);
+        self.diagnostic_colors = [t.error, t.warning, t.info].map(String::from);
+        self.widget_bg_color = hex(t.widget_background);
+        self.widget_border_color = hex(t.widget_border);
+        self.cursor_color = hex(t.cursor);
+        self.flatten_tokens = t.high_contrast;
+        self.ruler_color = D2D1_COLOR_F {
+            a: self.ruler_color.a,
+            ..hex(t.ruler)
+        };
+        if self.theme_name != t.name {
+            self.theme_name = t.name;
+            self.on_theme_changed(t);
         }
-        self.frame_lines.push(LineRenderData {
-            line_number,
-            text: text.to_string(),
-            tokens,
-            y_offset,
-        });
     }
 
-    pub fn set_cursor(&mut self, x: f64, y: f64, style: i32) {
-        self.cursor = Some(CursorData { x, y, style });
+    /// Tokens to draw for a line: none under a high-contrast theme, so every
+    /// glyph takes the plain text color.
+    fn visible_tokens<'a>(&self, tokens: &'a [RenderToken]) -> &'a [RenderToken] {
+        if self.flatten_tokens {
+            &[]
+        } else {
+            tokens
+        }
     }
 
-    pub fn set_cursors(&mut self, cursors_json: &str) {
-        self.cursors = serde_json::from_str(cursors_json).unwrap_or_default();
+    /// Tokens to draw for `line`: its own, with any color overrides for it
+    /// laid on top.
+    fn line_tokens<'a>(&self, line: &'a LineRenderData) -> Cow<'a, [RenderToken]> {
+        let tokens = self.visible_tokens(&line.tokens);
+        if self.flatten_tokens {
+            return Cow::Borrowed(tokens);
+        }
+        let col = |c: usize| line.text.char_indices().nth(c).map_or(line.text.len(), |(i, _)| i);
+        let spans: Vec<(usize, usize, &str)> = self
+            .color_overrides
+            .iter()
+            .filter(|o| o.line == line.line_number && o.end_col > o.start_col)
+            .map(|o| (col(o.start_col), col(o.end_col), o.color.as_str()))
+            .collect();
+        if spans.is_empty() {
+            return Cow::Borrowed(tokens);
+        }
+        Cow::Owned(overlay_colors(tokens, &spans))
     }
 
-    pub fn set_selection(&mut self, regions_json: &str) {
-        self.selections = serde_json::from_str(regions_json).unwrap_or_default();
+    pub fn set_focus_callback(&mut self, cb: FocusCallback) {
+        self.focus_callback = Some(cb);
     }
 
-    pub fn scroll(&mut self, offset_y: f64) {
-        self.scroll_offset = offset_y;
+    /// Called from the native focus events. Repaints (for the inactive dim)
+    /// and notifies the host when the state actually changes.
+    pub fn on_focus_changed(&mut self, focused: bool) {
+        if self.focused == focused {
+            return;
+        }
+        self.focused = focused;
+        if let Some(cb) = self.focus_callback {
+            cb(self.handle, focused);
+        }
+        self.invalidate();
     }
 
-    pub fn render_decorations(&mut self, decorations_json: &str) {
-        let mut decors: Vec<DecorationOverlay> =
-            serde_json::from_str(decorations_json).unwrap_or_default();
-        self.decorations.append(&mut decors);
+    /// Dim the content while the view is unfocused, so the active pane
+    /// stands out in split layouts. `alpha` is the opacity of a
+    /// background-colored overlay (0 turns it off, 1 hides the content).
+    pub fn set_inactive_dim(&mut self, alpha: f64) {
+        self.inactive_dim = alpha.clamp(0.0, 1.0);
+        self.invalidate();
     }
 
-    pub fn render_ghost_text(&mut self, text: &str, x: f64, y: f64, color: &str) {
-        self.ghost_text = Some(GhostTextData {
-            text: text.to_string(),
-            x,
-            y,
-            color: text_renderer::parse_hex_color(color),
-        });
+    /// Draw carets as a 1px outline instead of filled while the view is
+    /// unfocused (the default). Hosts can opt out to keep them solid.
+    pub fn set_hollow_caret_when_unfocused(&mut self, enabled: bool) {
+        self.hollow_caret_unfocused = enabled;
+        self.invalidate();
     }
 
-    pub fn end_frame(&mut self) {
+    /// Present non-editable content (diffs, output): carets are hidden, text
+    /// input and IME composition are dropped, and selectors that edit
+    /// (`insert…`, `delete…`, `cut:`, `paste:`, `undo:`, ...) are swallowed.
+    /// Movement, selection, `copy:` and `selectAll:` still reach the host.
+    /// `tint` ("#rrggbb") is drawn faint over the text area; `None` for none.
+    pub fn set_read_only(&mut self, enabled: bool, tint: Option<&str>) {
+        self.read_only = enabled.then(|| tint.map(text_renderer::parse_hex_color));
         self.invalidate();
     }
 
-    pub fn invalidate(&self) {
-        if !is_null_hwnd(self.hwnd) {
-            unsafe {
-                let _ = InvalidateRect(self.hwnd, None, false);
-            }
-        }
+    pub fn set_theme_changed_callback(&mut self, cb: ThemeChangedCallback) {
+        self.theme_changed_callback = Some(cb);
     }
 
-    pub fn attach_to_parent(&mut self, parent: *mut std::ffi::c_void) {
-        self.parent_view = parent;
-        if parent.is_null() {
-            return;
+    fn on_theme_changed(&mut self, t: &'static Theme) {
+        if let Some(cb) = self.theme_changed_callback {
+            let appearance = CString::new(t.appearance()).unwrap();
+            cb(self.handle, appearance.as_ptr());
         }
-        let parent_hwnd = HWND(parent as isize);
+    }
 
-        unsafe {
-            // Get parent client area for sizing
-            let mut rect = windows::Win32::Foundation::RECT::default();
-            let _ = windows::Win32::UI::WindowsAndMessaging::GetClientRect(
-                parent_hwnd,
-                &mut rect,
-            );
-            let w = rect.right - rect.left;
-            let h = rect.bottom - rect.top;
+    /// Track the OS light/dark setting, switching between the vscode-light
+    /// and vscode-dark presets now and whenever it changes.
+    pub fn set_follow_system_appearance(&mut self, enabled: bool) {
+        self.follow_system_appearance = enabled;
+        self.sync_system_appearance();
+    }
 
-            if is_null_hwnd(self.hwnd) {
-                // Create the child HWND now that we have a valid parent
-                let self_ptr = self as *mut EditorView;
-                self.hwnd =
-                    crate::input_handler::create_editor_hwnd(parent_hwnd, w, h, self_ptr);
-            } else {
-                // Re-parent an existing HWND
-                let _ = windows::Win32::UI::WindowsAndMessaging::SetParent(
-                    self.hwnd,
-                    parent_hwnd,
-                );
-                let _ = windows::Win32::UI::WindowsAndMessaging::SetWindowPos(
-                    self.hwnd,
-                    None,
-                    0,
-                    0,
-                    w,
-                    h,
-                    windows::Win32::UI::WindowsAndMessaging::SWP_NOZORDER,
-                );
-            }
+    /// Pick up High Contrast mode and, if following it, the light/dark
+    /// setting. Called when the HWND is created and from the WndProc on
+    /// WM_SETTINGCHANGE and WM_THEMECHANGED.
+    pub fn sync_system_appearance(&mut self) {
+        self.system_high_contrast = system_high_contrast();
+        if self.follow_system_appearance {
+            let dark = system_prefers_dark();
+            self.base_theme = if dark { &theme::VSCODE_DARK } else { &theme::VSCODE_LIGHT };
         }
+        self.refresh_theme();
     }
 
-    /// Ensure the render target exists for the current HWND.
-    fn ensure_render_target(&mut self) {
+    /// Show the high-contrast variant of the base theme while high contrast
+    /// is on, and the base theme otherwise. System High Contrast mode uses
+    /// the user's own scheme colors.
+    fn refresh_theme(&mut self) {
+        let t = if self.system_high_contrast {
+            theme::high_contrast(luminance(sys_color(COLOR_WINDOW)) < 0.5)
+        } else if self.force_high_contrast {
+            theme::high_contrast(self.base_theme.dark)
+        } else {
+            self.base_theme
+        };
+        if t.name != self.theme_name {
+            self.apply_theme(t);
+        }
+        if self.system_high_contrast {
+            self.background_color = sys_color(COLOR_WINDOW);
+            self.gutter_bg_color = sys_color(COLOR_WINDOW);
+            self.gutter_fg_color = sys_color(COLOR_WINDOWTEXT);
+            self.default_text_color = sys_color(COLOR_WINDOWTEXT);
+            self.selection_color = D2D1_COLOR_F {
+                a: self.selection_color.a,
+                ..sys_color(COLOR_HIGHLIGHT)
+            };
+            self.cursor_color = sys_color(COLOR_WINDOWTEXT);
+            self.widget_bg_color = sys_color(COLOR_WINDOW);
+            self.widget_border_color = sys_color(COLOR_WINDOWTEXT);
+            self.ruler_color = D2D1_COLOR_F {
+                a: self.ruler_color.a,
+                ..sys_color(COLOR_GRAYTEXT)
+            };
+        }
+        self.invalidate();
+    }
+
+    pub fn set_text_antialiasing(&mut self, mode: i32) {
+        self.text_antialiasing = mode;
+        self.invalidate();
+    }
+
+    pub fn set_metrics_override(&mut self, metrics: Option<(f64, f64, f64)>) {
+        let old_line_h = self.renderer.line_height;
+        self.metrics_override = metrics;
+        self.renderer.set_metrics_override(metrics);
+        self.renderer.set_line_spacing(self.line_spacing);
+        self.invalidate();
+        self.restore_top_line(old_line_h);
+        self.notify_metrics();
+    }
+
+    pub fn set_line_spacing(&mut self, multiplier: f64) {
+        let old_line_h = self.renderer.line_height;
+        self.line_spacing = multiplier;
+        self.renderer.set_line_spacing(multiplier);
+        self.invalidate();
+        self.restore_top_line(old_line_h);
+        self.notify_metrics();
+    }
+
+    /// Current line height in points (font metrics times line spacing).
+    pub fn line_height(&self) -> f64 {
+        self.renderer.line_height
+    }
+
+    /// Width of one character cell, as used for the gutter, rulers and block
+    /// caret: the advance of "0" (the metrics override's width while set).
+    pub fn char_width(&self) -> f64 {
+        self.renderer.char_width
+    }
+
+    /// Whether every character of `text` can be drawn with a real glyph,
+    /// from the view's font or the system fallback, instead of a missing
+    /// glyph box.
+    pub fn font_has_glyphs(&self, text: &str) -> bool {
+        self.renderer.has_glyphs(text)
+    }
+
+    pub fn measure_text(&self, text: &str) -> f64 {
+        self.renderer.measure_text(text)
+    }
+
+    /// Advance width of `text[start..end]` (byte offsets). Returns 0 for an
+    /// empty range or offsets that are out of bounds or not on a char boundary.
+    pub fn measure_range(&self, text: &str, start: usize, end: usize) -> f64 {
+        match text.get(start..end) {
+            Some(span) if !span.is_empty() => self.measure_text(span),
+            _ => 0.0,
+        }
+    }
+
+    /// Byte offsets of the extended grapheme cluster boundaries in `text`,
+    /// from 0 through `text.len()`. Valid until the next call.
+    pub fn grapheme_boundaries(&mut self, text: &str) -> &[u32] {
+        self.grapheme_buf.clear();
+        self.grapheme_buf.extend(grapheme_boundaries(text).map(|i| i as u32));
+        &self.grapheme_buf
+    }
+
+    /// Line, column and selection size for a status bar, from this frame's
+    /// caret (`set_cursor`) and logical selection (`set_selection_logical`;
+    /// pixel regions from `set_selection` aren't counted). Line text comes
+    /// from `set_content` or the lines pushed this frame; a selected line
+    /// that is in neither counts only its line break.
+    pub fn status_info(&self) -> StatusInfo {
+        let mut info = StatusInfo::default();
+        // The caret's line and byte column, hit-tested at the middle of its row
+        let caret =
+            self.caret_rect().and_then(|(x, y, _, h)| self.point_to_position(x, y + h / 2.0));
+        if let Some((line, byte)) = caret {
+            info.line = line;
+            info.column = self.visual_column(self.line_text(line).unwrap_or(""), byte) as i32 + 1;
+        }
+        let chars = |line: i32| self.line_text(line).map_or(0, |t| t.chars().count());
+        for &((start_line, start_col), (end_line, end_col)) in &self.selection_ranges {
+            if (start_line, start_col) == (end_line, end_col) {
+                continue;
+            }
+            let selected = if start_line == end_line {
+                end_col - start_col
+            } else {
+                let middle: usize = (start_line + 1..end_line).map(|l| chars(l) + 1).sum();
+                chars(start_line).saturating_sub(start_col) + 1 + middle + end_col
+            };
+            info.selection_chars += selected as i32;
+            info.selection_lines += end_line - start_line + 1;
+        }
+        info
+    }
+
+    /// Text of line `line_number` from `set_content`, or as pushed this frame.
+    fn line_text(&self, line_number: i32) -> Option<&str> {
+        if let Some(content) = &self.content {
+            let index = usize::try_from(line_number - 1).ok()?;
+            return content.get(index).map(|l| l.text.as_str());
+        }
+        self.buffered_line(line_number).map(|l| l.text.as_str())
+    }
+
+    /// Display column of byte offset `byte_offset` in `text`: tabs expand to
+    /// the next tab stop and wide (CJK, fullwidth, emoji) graphemes take two
+    /// cells. For "Col N" status text and aligning on a character grid,
+    /// where byte and char offsets are wrong.
+    pub fn visual_column(&self, text: &str, byte_offset: usize) -> usize {
+        visual_column(text, byte_offset, self.tab_width)
+    }
+
+    /// Byte range of the word around byte column `col` in `text`, by Unicode
+    /// word segmentation. A caret just after a word picks that word;
+    /// otherwise the segment under `col` is used, so a run of punctuation or
+    /// whitespace selects as a unit. With `subword` (or subword navigation
+    /// on), identifiers also split at underscores and case changes (`fooBar`
+    /// gives `foo` and `Bar`).
+    pub fn word_range_at(&self, text: &str, col: usize, subword: bool) -> (usize, usize) {
+        word_range_at(text, col, subword || self.subword_navigation)
+    }
+
+    // ── Frame buffer API ─────────────────────────────────────────
+
+    pub fn begin_frame(&mut self) {
+        if !self.persistent_frame && self.content.is_none() {
+            self.frame_lines.clear();
+            self.max_line_number = 0;
+        }
+        self.cursor = None;
+        self.cursors.clear();
+        self.selections.clear();
+        self.selection_ranges.clear();
+        self.occurrences.clear();
+        self.decorations.clear();
+        self.ghost_text = None;
+        self.fold_placeholders.clear();
+        self.links.clear();
+        self.color_overrides.clear();
+        self.images.clear();
+        self.remote_cursors.clear();
+        self.remote_selections.clear();
+        self.caret_labels.clear();
+        self.hover_card = None;
+        self.shaded_regions.clear();
+        self.completion_open = self.completion.take().is_some();
+    }
+
+    pub fn render_line(
+        &mut self,
+        line_number: i32,
+        text: &str,
+        tokens_json: &str,
+        y_offset: f64,
+    ) {
+        let tokens: Vec<RenderToken> = serde_json::from_str(tokens_json).unwrap_or_default();
+        let line = LineRenderData {
+            line_number,
+            text: text.to_string(),
+            tokens,
+            y_offset,
+        };
+        self.store_line(line, false);
+    }
+
+    /// Queue a whole viewport in one call. `lines_json` is an array of
+    /// `{line_number, text, tokens, y_offset}` objects, where `tokens` is
+    /// the same token array `render_line` takes as a string.
+    pub fn render_lines(&mut self, lines_json: &str) {
+        let lines: Vec<LineRenderData> = serde_json::from_str(lines_json).unwrap_or_default();
+        for line in lines {
+            self.store_line(line, false);
+        }
+    }
+
+    /// Add a line to the frame buffer. In persistent mode, or with `replace`,
+    /// a line with the same number is overwritten instead of duplicated.
+    fn store_line(&mut self, line: LineRenderData, replace: bool) {
+        if line.line_number > self.max_line_number {
+            self.max_line_number = line.line_number;
+        }
+        self.note_line_width(&line.text);
+        if self.persistent_frame && self.content.is_none() {
+            self.persistent_lines.insert(line.line_number, line);
+            return;
+        }
+        if replace {
+            let slot = self.frame_lines.iter_mut().find(|l| l.line_number == line.line_number);
+            if let Some(slot) = slot {
+                *slot = line;
+                return;
+            }
+        }
+        self.frame_lines.push(line);
+    }
+
+    /// Buffered rows: this frame's, or those kept in persistent mode.
+    fn lines(&self) -> impl Iterator<Item = &LineRenderData> {
+        self.frame_lines.iter().chain(self.persistent_lines.values())
+    }
+
+    /// The buffered row for `line_number` (the first one, for wrapped lines).
+    fn buffered_line(&self, line_number: i32) -> Option<&LineRenderData> {
+        self.persistent_lines
+            .get(&line_number)
+            .or_else(|| self.frame_lines.iter().find(|l| l.line_number == line_number))
+    }
+
+    /// Hand the whole document to the view, as a JSON array of
+    /// `{text, tokens?}` (line numbers are 1-based indexes), and let it pick
+    /// the rows to lay out from the scroll offset instead of the host
+    /// pushing them each frame. Also sets the line count. Frames keep these
+    /// rows; `begin_frame` only resets overlays. `null` hands rows back to
+    /// the host.
+    pub fn set_content(&mut self, lines_json: &str) {
+        let lines: Option<Vec<ContentLine>> = serde_json::from_str(lines_json).unwrap_or_default();
+        self.frame_lines.clear();
+        self.persistent_lines.clear();
+        self.max_line_number = 0;
+        if let Some(lines) = &lines {
+            self.line_count = lines.len() as i32;
+        }
+        self.content = lines;
+        self.fill_visible_lines();
+        self.invalidate();
+    }
+
+    /// Rows laid out above and below the viewport in `set_content` mode
+    /// (default 2). More rows cost layout and memory on every scroll but
+    /// leave fewer blank bands when the view moves before the rows are
+    /// rebuilt; 0 lays out only what is on screen.
+    pub fn set_overscan(&mut self, lines: i32) {
+        self.overscan = lines.max(0);
+        self.fill_visible_lines();
+        self.invalidate();
+    }
+
+    /// In content mode, rebuild the frame buffer from the document: the
+    /// rows in view plus `overscan` on each side, placed for the current
+    /// scroll offset.
+    fn fill_visible_lines(&mut self) {
+        let Some(content) = self.content.take() else {
+            return;
+        };
+        self.frame_lines.clear();
+        self.max_line_number = 0;
+        let line_h = self.renderer.line_height;
+        if line_h > 0.0 {
+            let overscan = self.overscan as f64;
+            let first = ((self.scroll_offset / line_h).floor() - overscan).max(0.0) as usize;
+            let last = ((self.scroll_offset + self.height) / line_h).ceil() + overscan;
+            for (i, line) in content.iter().enumerate().take(last.max(0.0) as usize).skip(first) {
+                let row = LineRenderData {
+                    line_number: i as i32 + 1,
+                    text: line.text.clone(),
+                    tokens: line.tokens.clone(),
+                    y_offset: i as f64 * line_h - self.scroll_offset,
+                };
+                self.store_line(row, false);
+            }
+        }
+        self.content = Some(content);
+    }
+
+    /// Keep buffered lines across frames so hosts can push only the lines
+    /// that changed (`render_line` or `update_line` replace by line number).
+    /// Cursors, selections and other overlays are still per-frame.
+    /// `end_frame` drops lines more than `overscan` rows out of view; call
+    /// `clear` after deleting lines.
+    pub fn set_persistent_frame(&mut self, enabled: bool) {
+        self.persistent_frame = enabled;
+        if enabled {
+            for line in std::mem::take(&mut self.frame_lines) {
+                self.persistent_lines.insert(line.line_number, line);
+            }
+        } else {
+            self.frame_lines.extend(std::mem::take(&mut self.persistent_lines).into_values());
+        }
+    }
+
+    /// Replace (or add) one buffered line outside a `begin_frame`/`end_frame`
+    /// cycle and redraw just its row. Meant for persistent mode, where the
+    /// rest of the frame is still valid.
+    pub fn update_line(&mut self, line_number: i32, text: &str, tokens_json: &str, y_offset: f64) {
+        let tokens: Vec<RenderToken> = serde_json::from_str(tokens_json).unwrap_or_default();
+        let line_h = self.renderer.line_height;
+        let old = self.buffered_line(line_number);
+        let old_y = old.map(|l| l.y_offset);
+        let line = LineRenderData {
+            line_number,
+            text: text.to_string(),
+            tokens,
+            y_offset,
+        };
+        let top = old_y.map_or(y_offset, |y| y.min(y_offset));
+        let bottom = old_y.map_or(y_offset, |y| y.max(y_offset)) + line_h;
+        // A new widest line number widens the gutter and shifts every row.
+        let gutter_w = self.gutter_width();
+        self.store_line(line, true);
+        if self.gutter_width() != gutter_w {
+            self.invalidate();
+        } else {
+            self.invalidate_rows(top, bottom);
+        }
+    }
+
+    /// Draw a `width` x `height` bitmap at view point `(x, y)` this frame.
+    /// `rgba` is tightly packed, top row first, 8-bit straight (not
+    /// premultiplied) RGBA. Cleared on `begin_frame`.
+    pub fn render_image(&mut self, rgba: &[u8], width: u32, height: u32, x: f64, y: f64) {
+        // Direct2D wants premultiplied BGRA.
+        let mut bgra = Vec::with_capacity(rgba.len());
+        for px in rgba.chunks_exact(4) {
+            let a = px[3] as u32;
+            let pm = |c: u8| ((c as u32 * a + 127) / 255) as u8;
+            bgra.extend_from_slice(&[pm(px[2]), pm(px[1]), pm(px[0]), px[3]]);
+        }
+        self.images.push(InlineImage { x, y, width, height, bgra });
+    }
+
+    pub fn set_caret_mode(&mut self, mode: i32) {
+        self.caret_mode = mode;
+    }
+
+    /// Mark where the document ends: 0 = off, 1 = tildes on rows past the
+    /// last line, 2 = an end symbol below it. Uses `set_line_count`.
+    pub fn set_eof_markers(&mut self, mode: i32) {
+        self.eof_markers = mode;
+        self.invalidate();
+    }
+
+    /// Resolve style -1 ("use mode default") to the caret mode's shape.
+    /// Explicit styles (0, 1, 2) are kept as-is.
+    fn resolve_cursor_style(&self, style: i32) -> i32 {
+        match style {
+            -1 if self.caret_mode == 1 => 1,
+            -1 => 0,
+            s => s,
+        }
+    }
+
+    /// Width of the line caret and thickness of the underline caret, in
+    /// points (default 2). Clamped to at least 1.
+    pub fn set_caret_width(&mut self, px: f64) {
+        self.caret_width = if px.is_finite() { px.max(1.0) } else { 2.0 };
+        self.invalidate();
+    }
+
+    pub fn set_cursor(&mut self, x: f64, y: f64, style: i32) {
+        let style = self.resolve_cursor_style(style);
+        self.cursor = Some(CursorData { x, y, style });
+    }
+
+    /// The primary caret's rect in view coordinates as `(x, y, w, h)`: the
+    /// position from `set_cursor`, the drawn caret width (a cell for block
+    /// and underline carets) and the row height. `None` when no caret was
+    /// set this frame.
+    pub fn caret_rect(&self) -> Option<(f64, f64, f64, f64)> {
+        let c = self.cursor.as_ref()?;
+        let w = if c.style == 0 { self.caret_width } else { self.renderer.char_width };
+        let y = c.y + self.single_line_offset();
+        Some((c.x, y, w, self.renderer.line_height))
+    }
+
+    /// Replace the multi-cursor list for this frame. `begin_frame` clears it;
+    /// calling this again within a frame overwrites rather than appends.
+    pub fn set_cursors(&mut self, cursors_json: &str) {
+        let mut cursors: Vec<CursorData> = serde_json::from_str(cursors_json).unwrap_or_default();
+        for c in &mut cursors {
+            c.style = self.resolve_cursor_style(c.style);
+        }
+        self.cursors = cursors;
+    }
+
+    pub fn set_selection(&mut self, regions_json: &str) {
+        self.selections = serde_json::from_str(regions_json).unwrap_or_default();
+        self.selection_ranges.clear();
+    }
+
+    /// Replace the selection with ranges (`[{startLine, startCol, endLine,
+    /// endCol, color?}]`, character columns, either end first) and measure
+    /// the regions here instead of in the host. Rows a range continues past
+    /// are marked `eol`, so empty lines inside it still show. The lines must
+    /// already be rendered this frame; rows outside them are skipped.
+    pub fn set_selection_logical(&mut self, ranges_json: &str) {
+        let ranges: Vec<LogicalSelection> = serde_json::from_str(ranges_json).unwrap_or_default();
+        let text_x = self.gutter_width() - self.scroll_x;
+        let mut regions = Vec::new();
+        self.selection_ranges.clear();
+        for range in ranges {
+            let (mut start, mut end) =
+                ((range.start_line, range.start_col), (range.end_line, range.end_col));
+            if end < start {
+                std::mem::swap(&mut start, &mut end);
+            }
+            self.selection_ranges.push((start, end));
+            let rows = start.0..=end.0;
+            for line in self.lines().filter(|l| rows.contains(&l.line_number)) {
+                let byte = |col: usize| {
+                    line.text.char_indices().nth(col).map_or(line.text.len(), |(i, _)| i)
+                };
+                let from = if line.line_number == start.0 { byte(start.1) } else { 0 };
+                let to = if line.line_number == end.0 { byte(end.1) } else { line.text.len() };
+                let eol = line.line_number < end.0;
+                let w = self.renderer.measure_text(&line.text[from..to.max(from)]);
+                if w <= 0.0 && !eol {
+                    continue;
+                }
+                let region = SelectionRegion {
+                    x: text_x + self.renderer.measure_text(&line.text[..from]),
+                    y: line.y_offset,
+                    w,
+                    h: self.renderer.line_height,
+                    color: range.color.clone(),
+                    eol,
+                };
+                push_stacked(&mut regions, region);
+            }
+        }
+        self.selections = regions;
+    }
+
+    /// Outline other occurrences of the selected text. Same geometry as
+    /// `set_selection`; a region's `color` overrides the theme occurrence
+    /// color. Drawn under the selection and cleared on `begin_frame`.
+    pub fn set_occurrence_highlights(&mut self, regions_json: &str) {
+        self.occurrences = serde_json::from_str(regions_json).unwrap_or_default();
+    }
+
+    pub fn set_occurrence_color(&mut self, color: &str) {
+        self.occurrence_color = text_renderer::parse_hex_color(color);
+    }
+
+    /// Override the squiggle color for one severity ("error", "warning" or
+    /// "info") until the next theme change. Returns false for an unknown
+    /// severity.
+    pub fn set_diagnostic_color(&mut self, severity: &str, color: &str) -> bool {
+        match severity_index(severity) {
+            Some(i) => {
+                self.diagnostic_colors[i] = color.to_string();
+                self.invalidate();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Tint the gutter behind lines touched by a selection region. `color`
+    /// is "#rrggbb" (drawn at the selection alpha); `None` uses the theme
+    /// selection color.
+    pub fn set_gutter_selection_highlight(&mut self, enabled: bool, color: Option<&str>) {
+        self.gutter_selection = enabled.then(|| color.map(text_renderer::parse_hex_color));
+    }
+
+    /// Shade vertical spans across the text area (`[{startY, endY, color}]`),
+    /// such as the block under a hovered fold chevron. Drawn faint under the
+    /// text; cleared on `begin_frame`.
+    pub fn set_region_shading(&mut self, regions_json: &str) {
+        self.shaded_regions = serde_json::from_str(regions_json).unwrap_or_default();
+    }
+
+    /// Collaborators' carets as `[{x, y, color, label}]`, drawn with a name
+    /// tag above each. Cleared on `begin_frame`.
+    pub fn set_remote_cursors(&mut self, cursors_json: &str) {
+        self.remote_cursors = serde_json::from_str(cursors_json).unwrap_or_default();
+    }
+
+    /// Collaborators' selections, same shape as `set_selection`; each
+    /// region's `color` is its owner's. Drawn over the local selection and
+    /// under the local caret. Cleared on `begin_frame`.
+    pub fn set_remote_selections(&mut self, regions_json: &str) {
+        self.remote_selections = serde_json::from_str(regions_json).unwrap_or_default();
+    }
+
+    /// Draw a rounded `text` label just above the caret at `(x, y)` this
+    /// frame (presence, active signature parameter). Colors are "#rrggbb".
+    pub fn render_caret_label(&mut self, x: f64, y: f64, text: &str, bg: &str, fg: &str) {
+        self.caret_labels.push(CaretLabel {
+            x,
+            y,
+            text: text.to_string(),
+            bg: text_renderer::parse_hex_color(bg),
+            fg: text_renderer::parse_hex_color(fg),
+        });
+    }
+
+    /// Show a bordered card of `lines_json` (`[{ text, tokens? }]`, tokens as
+    /// in `render_line`) below the caret row at `(x, y)`, wrapped to
+    /// `max_width`. It flips above the row when it would run off the bottom.
+    /// Drawn over everything else; cleared on `begin_frame`.
+    pub fn render_hover_card(&mut self, x: f64, y: f64, lines_json: &str, max_width: f64) {
+        let lines: Vec<HoverLine> = serde_json::from_str(lines_json).unwrap_or_default();
+        let text_max = (max_width - CARD_PADDING * 2.0).max(self.renderer.char_width);
+        let mut rows = Vec::new();
+        let mut text_w: f64 = 0.0;
+        for line in &lines {
+            for (start, end) in self.wrap_ranges(&line.text, text_max) {
+                let text = line.text[start..end].trim_end();
+                text_w = text_w.max(self.measure_text(text));
+                let tokens = slice_tokens(&line.tokens, start, end);
+                rows.push((text.to_string(), tokens));
+            }
+        }
+        if rows.is_empty() {
+            self.hover_card = None;
+            return;
+        }
+        let w = text_w + CARD_PADDING * 2.0;
+        let h = rows.len() as f64 * self.renderer.line_height + CARD_PADDING * 2.0;
+        let (x, y) = self.popup_origin(x, y, w, h);
+        self.hover_card = Some(HoverCard {
+            x,
+            y,
+            w,
+            h,
+            rows,
+        });
+    }
+
+    /// Byte ranges of `text` wrapped to `max_w`: after whitespace where
+    /// possible, otherwise between graphemes.
+    fn wrap_ranges(&self, text: &str, max_w: f64) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        let mut last_space = None;
+        for (i, g) in text.grapheme_indices(true) {
+            let end = i + g.len();
+            if i > start && self.measure_range(text, start, end) > max_w {
+                let brk = last_space.filter(|&b| b > start).unwrap_or(i);
+                ranges.push((start, brk));
+                start = brk;
+            }
+            if g.chars().all(char::is_whitespace) {
+                last_space = Some(end);
+            }
+        }
+        ranges.push((start, text.len()));
+        ranges
+    }
+
+    /// Show a completion list of `items_json` (`[{ label, detail?, icon?,
+    /// iconColor? }]`) below the caret row at `(x, y)`, flipped above it near
+    /// the bottom edge, with `selected_index` highlighted (negative for none).
+    /// At most `COMPLETION_MAX_ROWS` rows show; the rest scroll with the wheel,
+    /// and a newly selected item is scrolled into view. Clicking an item emits
+    /// `"acceptCompletion:<index>"`; arrow keys reach the host through the key
+    /// callback as usual. Drawn over everything else; cleared on `begin_frame`.
+    pub fn render_completion_popup(
+        &mut self,
+        x: f64,
+        y: f64,
+        items_json: &str,
+        selected_index: i32,
+    ) {
+        let items: Vec<CompletionItem> = serde_json::from_str(items_json).unwrap_or_default();
+        if items.is_empty() {
+            self.completion = None;
+            return;
+        }
+        let row_h = self.renderer.line_height;
+        let char_w = self.renderer.char_width;
+        let widest = |text: fn(&CompletionItem) -> &str| {
+            items.iter().map(|i| self.measure_text(text(i))).fold(0.0, f64::max)
+        };
+        let label_x = CARD_PADDING
+            + if items.iter().any(|i| !i.icon.is_empty()) { char_w * 2.0 } else { 0.0 };
+        let detail_x = label_x + widest(|i| &i.label) + char_w * 2.0;
+        let detail_w = widest(|i| &i.detail);
+        let w = if detail_w > 0.0 { detail_x + detail_w } else { detail_x - char_w * 2.0 };
+        let w = (w + CARD_PADDING).min(self.width);
+        let h = items.len().min(COMPLETION_MAX_ROWS) as f64 * row_h;
+
+        let selected = usize::try_from(selected_index).ok().filter(|&i| i < items.len());
+        if !self.completion_open {
+            self.completion_scroll = 0.0;
+        }
+        let reveal = !self.completion_open || selected != self.completion_selected;
+        if let Some(i) = selected.filter(|_| reveal) {
+            let top = i as f64 * row_h;
+            if top < self.completion_scroll {
+                self.completion_scroll = top;
+            } else if top + row_h > self.completion_scroll + h {
+                self.completion_scroll = top + row_h - h;
+            }
+        }
+        let max = items.len() as f64 * row_h - h;
+        self.completion_scroll = self.completion_scroll.clamp(0.0, max.max(0.0));
+        self.completion_selected = selected;
+
+        let (x, y) = self.popup_origin(x, y, w, h);
+        self.completion = Some(CompletionPopup {
+            x,
+            y,
+            w,
+            h,
+            items,
+            selected,
+            label_x,
+            detail_x,
+        });
+    }
+
+    /// Top-left for a `w` x `h` popup anchored below the caret row at
+    /// `(x, y)`: flipped above the row when it would run off the bottom and
+    /// kept inside the view horizontally.
+    fn popup_origin(&self, x: f64, y: f64, w: f64, h: f64) -> (f64, f64) {
+        let below = y + self.renderer.line_height;
+        let top = if below + h > self.height && y >= h { y - h } else { below };
+        (x.min(self.width - w).max(0.0), top)
+    }
+
+    pub fn set_selection_style(&mut self, style: i32) {
+        self.selection_style = style;
+    }
+
+    pub fn set_selection_full_line(&mut self, enabled: bool) {
+        self.selection_full_line = enabled;
+    }
+
+    /// Width added past line end for regions whose newline is selected.
+    fn eol_selection_width(&self) -> f64 {
+        if self.selection_full_line {
+            self.renderer.char_width
+        } else {
+            0.0
+        }
+    }
+
+    pub fn set_rulers(&mut self, columns_json: &str) {
+        self.rulers = serde_json::from_str(columns_json).unwrap_or_default();
+    }
+
+    /// Tint each indent level of a line's leading whitespace with the next
+    /// of `colors_json` (`["#rrggbb", ...]`), cycling, drawn faint under the
+    /// text and selection. An empty array turns it off.
+    pub fn set_indent_rainbow(&mut self, colors_json: &str) {
+        let colors: Vec<String> = serde_json::from_str(colors_json).unwrap_or_default();
+        self.indent_rainbow = colors.iter().map(|c| text_renderer::parse_hex_color(c)).collect();
+    }
+
+    /// Columns per indent level and per tab stop, for the indent rainbow.
+    pub fn set_tab_width(&mut self, width: u32) {
+        self.tab_width = (width as usize).max(1);
+    }
+
+    pub fn set_ruler_color(&mut self, color: &str) {
+        let mut c = text_renderer::parse_hex_color(color);
+        c.a = self.ruler_color.a;
+        self.ruler_color = c;
+    }
+
+    /// Fill the text area with a vertical gradient from `top` to `bottom`
+    /// ("#rrggbb") instead of the flat theme background. The gutter keeps its
+    /// own color. `enabled = false` goes back to the flat fill (the default).
+    pub fn set_background_gradient(&mut self, enabled: bool, top: &str, bottom: &str) {
+        self.background_gradient = enabled
+            .then(|| (text_renderer::parse_hex_color(top), text_renderer::parse_hex_color(bottom)));
+    }
+
+    /// Supply the authoritative document text and caret position (a UTF-16
+    /// offset into `text`) for UI Automation clients such as Narrator. Until
+    /// set, the text pattern falls back to the lines in the current frame.
+    pub fn set_accessible_text(&mut self, text: &str, cursor_offset: usize) {
+        self.accessible_text = Some(text.to_string());
+        self.accessible_cursor = cursor_offset;
+        crate::accessibility::notify_text_changed(self.hwnd);
+    }
+
+    /// Text exposed through the UIA text pattern.
+    pub fn accessibility_value(&self) -> String {
+        match self.accessible_text {
+            Some(ref text) => text.clone(),
+            None => self
+                .lines()
+                .map(|l| l.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Caret as a UTF-16 offset into `accessibility_value()`.
+    pub fn accessible_caret(&self) -> usize {
+        match self.accessible_text {
+            Some(ref text) => self.accessible_cursor.min(text.encode_utf16().count()),
+            None => 0,
+        }
+    }
+
+    pub fn set_active_line(&mut self, line_number: i32) {
+        self.active_line = line_number;
+    }
+
+    /// Draw the active line's number (see `set_active_line`) bold and in
+    /// `color` ("#rrggbb"), so the caret's row stands out in the gutter
+    /// without a full-line highlight. `None` uses the theme text color.
+    pub fn set_active_line_gutter(&mut self, enabled: bool, color: Option<&str>) {
+        self.active_line_gutter = enabled.then(|| color.map(text_renderer::parse_hex_color));
+    }
+
+    /// Number gutter rows 0 = absolutely, 1 = by distance from the active
+    /// line (which shows 0), or 2 = by distance, with the active line's own
+    /// number. In mode 2 the relative numbers stay right-aligned while the
+    /// absolute one is left-aligned in the number column, so its width
+    /// never shifts them. Without an active line rows are numbered
+    /// absolutely.
+    pub fn set_line_number_mode(&mut self, mode: i32) {
+        self.line_number_mode = mode;
+        self.invalidate();
+    }
+
+    pub fn set_highlight_trailing_whitespace(&mut self, enabled: bool, color: &str) {
+        self.highlight_trailing_whitespace = enabled;
+        let mut c = text_renderer::parse_hex_color(color);
+        c.a = 0.3;
+        self.trailing_whitespace_color = c;
+    }
+
+    pub fn set_line_count(&mut self, count: i32) {
+        self.line_count = count;
+    }
+
+    /// Allow scrolling `lines` lines past the point where the last line sits
+    /// at the bottom of the view.
+    pub fn set_overscroll(&mut self, lines: i32) {
+        self.overscroll = lines.max(0);
+    }
+
+    /// Number of whole lines that fit in the viewport, for page movement.
+    /// Always at least 1 so paging makes progress in tiny views.
+    pub fn page_line_count(&self) -> i32 {
+        let line_h = self.line_height();
+        if line_h <= 0.0 {
+            return 1;
+        }
+        ((self.height / line_h).floor() as i32).max(1)
+    }
+
+    /// Largest valid scroll offset, or `None` while the line count is unknown.
+    pub fn max_scroll(&self) -> Option<f64> {
+        (self.line_count > 0).then(|| {
+            let line_h = self.renderer.line_height;
+            let content = (self.line_count + self.overscroll) as f64 * line_h;
+            (content - self.height).max(0.0)
+        })
+    }
+
+    fn clamp_scroll(&self, offset: f64) -> f64 {
+        let offset = offset.max(0.0);
+        match self.max_scroll() {
+            Some(max) => offset.min(max),
+            None => offset,
+        }
+    }
+
+    pub fn set_scrolloff(&mut self, lines: i32) {
+        self.scrolloff = lines.max(0);
+    }
+
+    /// Scroll so the line at content-space `y_offset` is visible.
+    /// Mode 0 moves to the nearest edge (no-op if already visible), keeping
+    /// `scrolloff` lines of context; mode 1 centers it.
+    pub fn reveal(&mut self, y_offset: f64, mode: i32) {
+        let line_h = self.renderer.line_height;
+        if mode == 1 {
+            self.center_on(y_offset);
+            return;
+        }
+        // Never ask for more margin than fits around a single line.
+        let margin = (self.scrolloff as f64 * line_h).min(((self.height - line_h) / 2.0).max(0.0));
+        let target = if y_offset - margin < self.scroll_offset {
+            y_offset - margin
+        } else if y_offset + line_h + margin > self.scroll_offset + self.height {
+            y_offset + line_h + margin - self.height
+        } else {
+            self.scroll_offset
+        };
+        self.scroll_to(target);
+    }
+
+    /// Scroll so the line at content-space `y_offset` is vertically centered
+    /// (clamped to the content bounds).
+    pub fn center_on(&mut self, y_offset: f64) {
+        let line_h = self.renderer.line_height;
+        self.scroll_to(y_offset + line_h / 2.0 - self.height / 2.0);
+    }
+
+    /// Move to a clamped scroll offset, report it to the host through the
+    /// scroll callback (as a wheel-style delta) and redraw.
+    fn scroll_to(&mut self, offset: f64) {
+        let offset = self.clamp_scroll(offset);
+        if offset == self.scroll_offset {
+            return;
+        }
+        // Wheel convention here: positive dy scrolls down.
+        let delta = offset - self.scroll_offset;
+        self.scroll_offset = offset;
+        self.fill_visible_lines();
+        self.notify_scroll(0.0, delta);
+        self.notify_did_scroll();
+        self.invalidate();
+    }
+
+    /// Set the scroll offset. Clamped to the document in content mode, where
+    /// the view owns scrolling; host-pushed frames take it as given.
+    pub fn scroll(&mut self, offset_y: f64) {
+        self.scroll_offset = if self.content.is_some() {
+            self.clamp_scroll(offset_y)
+        } else {
+            offset_y
+        };
+        self.fill_visible_lines();
+    }
+
+    /// Scroll line text horizontally by `offset_x` pixels (clamped at 0).
+    /// The gutter stays fixed; host-positioned overlays are unaffected.
+    pub fn set_scroll_x(&mut self, offset_x: f64) {
+        self.scroll_x = offset_x.max(0.0);
+        self.invalidate();
+    }
+
+    /// Toggle the shadow drawn at the gutter edge while scrolled horizontally.
+    pub fn set_gutter_shadow(&mut self, enabled: bool) {
+        self.gutter_shadow = enabled;
+        self.invalidate();
+    }
+
+    /// Show or hide the whole gutter. Hidden, it takes no width (no line
+    /// numbers, fold/diff area or background) and text starts at x = 0, for
+    /// inline snippets and single-line inputs.
+    pub fn set_gutter_visible(&mut self, visible: bool) {
+        self.gutter_visible = visible;
+        self.invalidate();
+    }
+
+    /// One-line input mode for search boxes and inline rename: the gutter is
+    /// hidden, `insertNewline:` is emitted as `submit:`, vertical scrolling
+    /// is dropped, and the first row is drawn centered in the view and
+    /// clipped to one row.
+    pub fn set_single_line(&mut self, enabled: bool) {
+        self.single_line = enabled;
+        self.invalidate();
+    }
+
+    /// Replace the sticky scroll headers: a JSON array of
+    /// `{line_number, text, tokens}` pinned to the top rows, in order.
+    /// An empty array (or invalid JSON) removes them.
+    pub fn set_sticky_lines(&mut self, lines_json: &str) {
+        self.sticky_lines = serde_json::from_str(lines_json).unwrap_or_default();
+        self.invalidate();
+    }
+
+    /// Show a "⋯" badge after column `after_col` of `line` (a folded region).
+    /// The line must also be rendered this frame. Cleared on `begin_frame`.
+    pub fn render_fold_placeholder(&mut self, line: i32, after_col: i32) {
+        self.fold_placeholders.push(FoldPlaceholder {
+            line,
+            after_col: after_col.max(0) as usize,
+        });
+    }
+
+    /// Left edge, line top, and width of a placeholder pill, or `None` when
+    /// its line isn't in the current frame.
+    fn fold_placeholder_rect(&self, fold: &FoldPlaceholder) -> Option<(f64, f64, f64)> {
+        let line = self.buffered_line(fold.line)?;
+        let end = line
+            .text
+            .char_indices()
+            .nth(fold.after_col)
+            .map_or(line.text.len(), |(i, _)| i);
+        let cw = self.renderer.char_width;
+        let x = self.gutter_width() - self.scroll_x + self.renderer.measure_text(&line.text[..end]);
+        let w = self.renderer.measure_text(FOLD_PLACEHOLDER) + cw;
+        Some((x + cw / 2.0, line.y_offset, w))
+    }
+
+    /// Line number of the fold placeholder under view point `(x, y)`, if any.
+    fn fold_placeholder_at(&self, x: f64, y: f64) -> Option<i32> {
+        let line_h = self.renderer.line_height;
+        self.fold_placeholders.iter().find_map(|fold| {
+            let (px, py, w) = self.fold_placeholder_rect(fold)?;
+            (x >= px && x < px + w && y >= py && y < py + line_h).then_some(fold.line)
+        })
+    }
+
+    /// Underline link ranges (`[{line, startCol, endCol, url}]`) and make
+    /// them Ctrl/Cmd+clickable, emitting `"openLink:<url>"`. The lines must
+    /// already be rendered this frame. Cleared on `begin_frame`.
+    pub fn set_links(&mut self, links_json: &str) {
+        let specs: Vec<LinkSpec> = serde_json::from_str(links_json).unwrap_or_default();
+        let text_x = self.gutter_width() - self.scroll_x;
+        let line_h = self.renderer.line_height;
+        for spec in specs {
+            let Some(line) = self.buffered_line(spec.line) else {
+                continue;
+            };
+            let byte = |col: usize| {
+                line.text.char_indices().nth(col).map_or(line.text.len(), |(i, _)| i)
+            };
+            let (start, end) = (byte(spec.start_col), byte(spec.end_col.max(spec.start_col)));
+            let x = text_x + self.renderer.measure_text(&line.text[..start]);
+            let w = self.renderer.measure_text(&line.text[start..end]);
+            let y = line.y_offset;
+            // Drawn by the decoration pass like any other underline
+            self.decorations.push(DecorationOverlay {
+                x,
+                y,
+                w,
+                h: line_h,
+                color: LINK_COLOR.to_string(),
+                kind: "underline".to_string(),
+            });
+            self.links.push(LinkRegion { x, y, w, h: line_h, url: spec.url });
+        }
+    }
+
+    /// Squiggle diagnostic ranges (`[{line, startCol, endCol, severity}]`)
+    /// in the theme's color for each severity, measured here so they line
+    /// up with the glyphs. An empty range marks one character cell, for
+    /// diagnostics at the end of a line. The lines must already be rendered
+    /// this frame; unknown severities are skipped. Cleared on `begin_frame`.
+    pub fn set_diagnostics(&mut self, diagnostics_json: &str) {
+        let specs: Vec<DiagnosticSpec> =
+            serde_json::from_str(diagnostics_json).unwrap_or_default();
+        let text_x = self.gutter_width() - self.scroll_x;
+        let line_h = self.renderer.line_height;
+        for spec in specs {
+            let Some(severity) = severity_index(&spec.severity) else {
+                continue;
+            };
+            let Some(line) = self.buffered_line(spec.line) else {
+                continue;
+            };
+            let byte = |col: usize| {
+                line.text.char_indices().nth(col).map_or(line.text.len(), |(i, _)| i)
+            };
+            let (start, end) = (byte(spec.start_col), byte(spec.end_col.max(spec.start_col)));
+            let x = text_x + self.renderer.measure_text(&line.text[..start]);
+            let w = self.renderer.measure_text(&line.text[start..end]);
+            self.decorations.push(DecorationOverlay {
+                x,
+                y: line.y_offset,
+                w: w.max(self.renderer.char_width),
+                h: line_h,
+                color: self.diagnostic_colors[severity].clone(),
+                kind: "underline-wavy".to_string(),
+            });
+        }
+    }
+
+    /// Recolor spans of already rendered lines (`[{line, startCol, endCol,
+    /// color}]`) on top of their tokens, e.g. LSP semantic tokens over
+    /// TextMate ones. Only the color changes; style and size stay with the
+    /// underlying token. Later entries win where they overlap. Cleared on
+    /// `begin_frame`.
+    pub fn set_color_overrides(&mut self, overrides_json: &str) {
+        self.color_overrides = serde_json::from_str(overrides_json).unwrap_or_default();
+    }
+
+    /// Buffered line number and byte column under view point `(x, y)`.
+    /// Rows above or below the pushed lines clamp to the nearest one, points
+    /// in the gutter map to column 0 and points past the end of a line to
+    /// its length; otherwise the column snaps to the nearest grapheme
+    /// boundary. `None` when no lines were pushed this frame.
+    pub fn point_to_position(&self, x: f64, y: f64) -> Option<(i32, usize)> {
+        let y = y - self.single_line_offset();
+        let line_h = self.renderer.line_height;
+        let row_dist = |l: &&LineRenderData| {
+            if y < l.y_offset {
+                l.y_offset - y
+            } else {
+                (y - (l.y_offset + line_h)).max(0.0)
+            }
+        };
+        let line = self
+            .lines()
+            .min_by(|a, b| row_dist(a).total_cmp(&row_dist(b)))?;
+        let gutter_w = self.gutter_width();
+        let text_x = gutter_w - self.scroll_x;
+        let col = column_at_point(&line.text, x, gutter_w, text_x, |p| self.measure_text(p));
+        Some((line.line_number, col))
+    }
+
+    /// Byte span and `0xRRGGBB` color of the token covering byte column
+    /// `col` on buffered line `line`, as the line was pushed and drawn.
+    /// `None` when the line isn't buffered or no token covers `col`.
+    pub fn token_at(&self, line: i32, col: usize) -> Option<(usize, usize, u32)> {
+        let data = self.buffered_line(line)?;
+        let token = data.tokens.iter().find(|t| t.s <= col && col < t.e)?;
+        let hex = token.c.trim_start_matches('#');
+        let color = hex.get(..6).and_then(|h| u32::from_str_radix(h, 16).ok()).unwrap_or(0);
+        Some((token.s, token.e, color))
+    }
+
+    /// URL of the link under view point `(x, y)`, if any.
+    pub fn link_at(&self, x: f64, y: f64) -> Option<&str> {
+        self.links
+            .iter()
+            .find(|l| x >= l.x && x < l.x + l.w && y >= l.y && y < l.y + l.h)
+            .map(|l| l.url.as_str())
+    }
+
+    /// Replace the host cursor regions (`[{x, y, w, h, cursor}]`, cursor one of
+    /// `"text"`, `"arrow"`, `"hand"`). They take precedence over the built-in
+    /// arrow over the gutter and hand over links; everything else is an I-beam.
+    pub fn set_cursor_regions(&mut self, regions_json: &str) {
+        self.cursor_regions = serde_json::from_str(regions_json).unwrap_or_default();
+    }
+
+    /// Cursor shape for view point `(x, y)`.
+    pub fn cursor_at(&self, x: f64, y: f64) -> CursorKind {
+        let hit = |rx: f64, ry: f64, rw: f64, rh: f64| {
+            x >= rx && x < rx + rw && y >= ry && y < ry + rh
+        };
+        if let Some(r) = self.cursor_regions.iter().rev().find(|r| hit(r.x, r.y, r.w, r.h)) {
+            r.cursor
+        } else if self.link_at(x, y).is_some() {
+            CursorKind::Hand
+        } else if x < self.gutter_width() {
+            CursorKind::Arrow
+        } else {
+            CursorKind::Text
+        }
+    }
+
+    /// Handle a Ctrl/Cmd+click: emits `"openLink:<url>"` and returns true
+    /// when `(x, y)` is on a link.
+    pub fn on_link_click(&mut self, x: f64, y: f64) -> bool {
+        match self.link_at(x, y).map(str::to_string) {
+            Some(url) => {
+                self.on_action(&format!("openLink:{url}"));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Draw one decoration (`DecorationOverlay` JSON) and fade it out over
+    /// `duration_ms`. Unlike `render_decorations` it survives `begin_frame`;
+    /// a platform timer redraws until it has faded, then it is removed.
+    pub fn flash_decoration(&mut self, decoration_json: &str, duration_ms: u32) {
+        let Ok(decor) = serde_json::from_str::<DecorationOverlay>(decoration_json) else {
+            return;
+        };
+        self.flashes.push(FlashDecoration {
+            decor,
+            start: Instant::now(),
+            duration: Duration::from_millis(duration_ms.max(1) as u64),
+        });
+        self.start_flash_timer();
+        self.invalidate();
+    }
+
+    /// Visual bell: wash the whole view in the theme's text color and fade it
+    /// out over `VISUAL_BELL_DURATION`, driven by the flash timer. Frame
+    /// state is untouched; ringing again restarts the fade.
+    pub fn visual_bell(&mut self) {
+        self.bell = Some(Instant::now());
+        self.start_flash_timer();
+        self.invalidate();
+    }
+
+    /// Opacity of the bell wash at `now`, falling from `VISUAL_BELL_ALPHA`
+    /// to 0; 0 when the bell isn't ringing.
+    fn bell_alpha(&self, now: Instant) -> f64 {
+        let Some(start) = self.bell else {
+            return 0.0;
+        };
+        let t = now.duration_since(start).as_secs_f64() / VISUAL_BELL_DURATION.as_secs_f64();
+        VISUAL_BELL_ALPHA * (1.0 - t).clamp(0.0, 1.0)
+    }
+
+    /// Advance flash animations and the visual bell from the platform timer:
+    /// drop finished ones and redraw. Stops the timer and returns false once
+    /// none remain.
+    pub fn tick_flashes(&mut self) -> bool {
+        let now = Instant::now();
+        self.flashes.retain(|f| f.alpha(now) > 0.0);
+        if self.bell_alpha(now) <= 0.0 {
+            self.bell = None;
+        }
+        self.invalidate();
+        if self.flashes.is_empty() && self.bell.is_none() {
+            self.stop_flash_timer();
+            return false;
+        }
+        true
+    }
+
+    /// Start the ~60 Hz WM_TIMER for flash animation unless it is running.
+    fn start_flash_timer(&mut self) {
+        if !self.flash_timer_active && !is_null_hwnd(self.hwnd) {
+            unsafe { SetTimer(self.hwnd, FLASH_TIMER_ID, 16, None) };
+            self.flash_timer_active = true;
+        }
+    }
+
+    fn stop_flash_timer(&mut self) {
+        if self.flash_timer_active {
+            unsafe {
+                let _ = KillTimer(self.hwnd, FLASH_TIMER_ID);
+            }
+            self.flash_timer_active = false;
+        }
+    }
+
+    pub fn render_decorations(&mut self, decorations_json: &str) {
+        let mut decors: Vec<DecorationOverlay> =
+            serde_json::from_str(decorations_json).unwrap_or_default();
+        self.decorations.append(&mut decors);
+    }
+
+    /// Faint text shown on the first row while the document is empty (no
+    /// lines, or one empty line). It is not part of the frame, so it can't be
+    /// selected or measured. An empty `text` removes it.
+    pub fn set_placeholder(&mut self, text: &str, color: &str) {
+        self.placeholder = if text.is_empty() {
+            None
+        } else {
+            Some((text.to_string(), text_renderer::parse_hex_color(color)))
+        };
+        self.invalidate();
+    }
+
+    /// Top of the row to draw the placeholder on, or `None` when there is no
+    /// placeholder or the document isn't empty.
+    fn placeholder_y(&self) -> Option<f64> {
+        self.placeholder.as_ref()?;
+        let mut lines = self.lines();
+        match (lines.next(), lines.next()) {
+            (None, _) => Some(0.0),
+            (Some(line), None) if line.text.is_empty() => Some(line.y_offset),
+            _ => None,
+        }
+    }
+
+    pub fn render_ghost_text(&mut self, text: &str, x: f64, y: f64, color: &str) {
+        self.ghost_text = Some(GhostTextData {
+            text: text.to_string(),
+            x,
+            y,
+            color: text_renderer::parse_hex_color(color),
+        });
+    }
+
+    pub fn end_frame(&mut self) {
+        self.prune_persistent_lines();
+        self.invalidate();
+    }
+
+    /// Drop persistent-mode lines more than `overscan` rows above or below
+    /// the viewport, so the buffer stays about one screen long.
+    fn prune_persistent_lines(&mut self) {
+        let line_h = self.renderer.line_height;
+        let margin = self.overscan as f64 * line_h;
+        let (top, bottom) = (-margin, self.height + margin);
+        self.persistent_lines.retain(|_, l| l.y_offset + line_h > top && l.y_offset < bottom);
+    }
+
+    /// Blank the view outside a frame (e.g. when the file closes): drops all
+    /// frame content, as an empty `begin_frame`/`end_frame` pair would, and
+    /// redraws.
+    pub fn clear(&mut self) {
+        self.max_line_width = 0.0;
+        self.content = None;
+        self.frame_lines.clear();
+        self.persistent_lines.clear();
+        self.max_line_number = 0;
+        self.begin_frame();
+        self.end_frame();
+    }
+
+    /// Grow the widest-line width reported by `content_size`.
+    fn note_line_width(&mut self, text: &str) {
+        let w = self.renderer.measure_text(text);
+        if w > self.max_line_width {
+            self.max_line_width = w;
+        }
+    }
+
+    /// Scrollable content size as `(width, height)`: the widest line pushed
+    /// since the last `clear` or metrics change (text only, gutter excluded)
+    /// and `(line_count + overscroll) * line_height`, which is 0 until
+    /// `set_line_count` is called.
+    pub fn content_size(&self) -> (f64, f64) {
+        if self.single_line {
+            return (self.max_line_width, self.renderer.line_height);
+        }
+        let lines = if self.line_count > 0 { self.line_count + self.overscroll } else { 0 };
+        (self.max_line_width, lines as f64 * self.renderer.line_height)
+    }
+
+    pub fn invalidate(&self) {
+        if !is_null_hwnd(self.hwnd) {
+            unsafe {
+                let _ = InvalidateRect(self.hwnd, None, false);
+            }
+        }
+    }
+
+    /// Repaint only the `(x, y, w, h)` rect, in view coordinates, clamped to
+    /// the view. For hosts that know a small region changed (a flashed
+    /// decoration, say). A rect that is empty or entirely outside the view
+    /// does nothing.
+    pub fn invalidate_rect(&self, x: f64, y: f64, w: f64, h: f64) {
+        let (left, top) = (x.max(0.0), y.max(0.0));
+        let (right, bottom) = ((x + w).min(self.width), (y + h).min(self.height));
+        // Also rejects NaN, which fails every comparison
+        if !(right > left && bottom > top) {
+            return;
+        }
+        if !is_null_hwnd(self.hwnd) {
+            let rect = RECT {
+                left: left.floor() as i32,
+                top: top.floor() as i32,
+                right: right.ceil() as i32,
+                bottom: bottom.ceil() as i32,
+            };
+            unsafe {
+                let _ = InvalidateRect(self.hwnd, Some(&rect), false);
+            }
+        }
+    }
+
+    /// Invalidate the full-width band between `top` and `bottom`.
+    fn invalidate_rows(&self, top: f64, bottom: f64) {
+        if !is_null_hwnd(self.hwnd) {
+            let rect = RECT {
+                left: 0,
+                top: top.floor() as i32,
+                right: self.width.ceil() as i32,
+                bottom: bottom.ceil() as i32,
+            };
+            unsafe {
+                let _ = InvalidateRect(self.hwnd, Some(&rect), false);
+            }
+        }
+    }
+
+    pub fn attach_to_parent(&mut self, parent: *mut std::ffi::c_void) {
+        self.parent_view = parent;
+        if parent.is_null() {
+            return;
+        }
+        let parent_hwnd = HWND(parent as isize);
+
+        unsafe {
+            // Get parent client area for sizing
+            let mut rect = windows::Win32::Foundation::RECT::default();
+            let _ = windows::Win32::UI::WindowsAndMessaging::GetClientRect(
+                parent_hwnd,
+                &mut rect,
+            );
+            let w = rect.right - rect.left;
+            let h = rect.bottom - rect.top;
+
+            if is_null_hwnd(self.hwnd) {
+                // Create the child HWND now that we have a valid parent
+                let self_ptr = self as *mut EditorView;
+                self.hwnd =
+                    crate::input_handler::create_editor_hwnd(parent_hwnd, w, h, self_ptr);
+                self.sync_system_appearance();
+            } else {
+                // Re-parent an existing HWND
+                let _ = windows::Win32::UI::WindowsAndMessaging::SetParent(
+                    self.hwnd,
+                    parent_hwnd,
+                );
+                let _ = windows::Win32::UI::WindowsAndMessaging::SetWindowPos(
+                    self.hwnd,
+                    None,
+                    0,
+                    0,
+                    w,
+                    h,
+                    windows::Win32::UI::WindowsAndMessaging::SWP_NOZORDER,
+                );
+            }
+        }
+    }
+
+    /// Ensure the render target exists for the current HWND.
+    fn ensure_render_target(&mut self) {
         if self.render_target.is_some() {
             return;
         }
@@ -412,239 +2484,1124 @@ impl EditorView {
             return;
         }
 
-        unsafe {
-            let mut rc = windows::Win32::Foundation::RECT::default();
-            let _ = windows::Win32::UI::WindowsAndMessaging::GetClientRect(self.hwnd, &mut rc);
+        unsafe {
+            let mut rc = windows::Win32::Foundation::RECT::default();
+            let _ = windows::Win32::UI::WindowsAndMessaging::GetClientRect(self.hwnd, &mut rc);
+
+            let size = D2D_SIZE_U {
+                width: (rc.right - rc.left).max(1) as u32,
+                height: (rc.bottom - rc.top).max(1) as u32,
+            };
+
+            let rt_props = D2D1_RENDER_TARGET_PROPERTIES::default();
+            let hwnd_props = D2D1_HWND_RENDER_TARGET_PROPERTIES {
+                hwnd: self.hwnd,
+                pixelSize: size,
+                presentOptions: D2D1_PRESENT_OPTIONS_NONE,
+            };
+
+            match self.d2d_factory.CreateHwndRenderTarget(&rt_props, &hwnd_props) {
+                Ok(rt) => {
+                    self.render_target = Some(rt);
+                }
+                Err(e) => {
+                    eprintln!("Failed to create render target: {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// Resize the render target when the window size changes.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as f64;
+        self.height = height as f64;
+        if let Some(ref rt) = self.render_target {
+            let size = D2D_SIZE_U {
+                width: width.max(1),
+                height: height.max(1),
+            };
+            unsafe {
+                let _ = rt.Resize(&size);
+            }
+        }
+    }
+
+    /// Called from WM_PAINT — paint the frame buffer using Direct2D.
+    pub fn paint(&mut self) {
+        self.ensure_render_target();
+
+        let rt = match self.render_target.as_ref() {
+            Some(rt) => rt.clone(),
+            None => return,
+        };
+        let target: ID2D1RenderTarget = match rt.cast() {
+            Ok(target) => target,
+            Err(_) => return,
+        };
+
+        let start = self.frame_callback.map(|cb| (cb, Instant::now()));
+
+        unsafe {
+            rt.BeginDraw();
+        }
+
+        self.draw(&target);
+
+        unsafe {
+            let hr = rt.EndDraw(None, None);
+            if hr.is_err() {
+                // D2DERR_RECREATE_TARGET — discard and recreate on next paint
+                self.render_target = None;
+            }
+        }
+
+        if let Some((cb, start)) = start {
+            cb(self.handle, start.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+
+    /// Render the current frame buffer into `out` (RGBA, premultiplied,
+    /// top row first, `width * 4` bytes per row) via a WIC bitmap render
+    /// target. No HWND is required. Returns false if any step fails.
+    pub fn render_to_buffer(&self, width: u32, height: u32, out: &mut [u8]) -> bool {
+        unsafe {
+            // WIC is a COM API; an already-initialized apartment is fine.
+            let _com = ComApartment::enter();
+
+            let wic: IWICImagingFactory =
+                match CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER) {
+                    Ok(wic) => wic,
+                    Err(_) => return false,
+                };
+            let bitmap = match wic.CreateBitmap(
+                width,
+                height,
+                &GUID_WICPixelFormat32bppPBGRA,
+                WICBitmapCacheOnLoad,
+            ) {
+                Ok(bitmap) => bitmap,
+                Err(_) => return false,
+            };
+            let props = D2D1_RENDER_TARGET_PROPERTIES::default();
+            let rt = match self.d2d_factory.CreateWicBitmapRenderTarget(&bitmap, &props) {
+                Ok(rt) => rt,
+                Err(_) => return false,
+            };
+
+            rt.BeginDraw();
+            self.draw(&rt);
+            if rt.EndDraw(None, None).is_err() {
+                return false;
+            }
+            if bitmap.CopyPixels(std::ptr::null(), width * 4, out).is_err() {
+                return false;
+            }
+        }
+
+        // WIC gives BGRA; swap to RGBA.
+        for px in out.chunks_exact_mut(4) {
+            px.swap(0, 2);
+        }
+        true
+    }
+
+    // ── Drawing ──────────────────────────────────────────────────
+
+    /// Bottom of the document's last line when it is buffered and EOF
+    /// markers are on; None while the end is out of view.
+    fn eof_y(&self) -> Option<f64> {
+        if self.eof_markers == 0 || self.line_count <= 0 {
+            return None;
+        }
+        self.lines()
+            .filter(|l| l.line_number == self.line_count)
+            .map(|l| l.y_offset + self.renderer.line_height)
+            .reduce(f64::max)
+    }
+
+    fn gutter_shown(&self) -> bool {
+        self.gutter_visible && !self.single_line
+    }
+
+    /// How far single-line mode shifts the row down to center it; 0 otherwise.
+    fn single_line_offset(&self) -> f64 {
+        if !self.single_line {
+            return 0.0;
+        }
+        ((self.height - self.renderer.line_height) / 2.0).max(0.0).round()
+    }
+
+    /// Compute gutter width matching the TS GutterRenderer formula:
+    /// max(2, digits) * charWidth + 36  (16px fold + 16px padding + 4px diff)
+    fn gutter_width(&self) -> f64 {
+        if !self.gutter_shown() {
+            return 0.0;
+        }
+        self.gutter_digits() as f64 * self.renderer.char_width + 36.0
+    }
+
+    /// Digits the gutter reserves for line numbers: those of the largest
+    /// number pushed so far, at least 2.
+    fn gutter_digits(&self) -> i32 {
+        if self.max_line_number <= 0 {
+            2
+        } else {
+            let d = (self.max_line_number as f64).log10().floor() as i32 + 1;
+            d.max(2)
+        }
+    }
+
+    /// Gutter label for `line_number` under the line-number mode.
+    fn line_number_label(&self, line_number: i32) -> String {
+        if self.line_number_mode == 0 || self.active_line <= 0 {
+            return line_number.to_string();
+        }
+        match (line_number - self.active_line).abs() {
+            0 if self.line_number_mode == 2 => line_number.to_string(),
+            distance => distance.to_string(),
+        }
+    }
+
+    /// Whether `line_number` is drawn as the hybrid mode's absolute number,
+    /// left-aligned in the number column.
+    fn is_hybrid_anchor(&self, line_number: i32) -> bool {
+        self.line_number_mode == 2 && self.active_line > 0 && line_number == self.active_line
+    }
+
+    fn draw(&self, rt: &ID2D1RenderTarget) {
+        let aa_mode = match self.text_antialiasing {
+            0 => D2D1_TEXT_ANTIALIAS_MODE_ALIASED,
+            1 => D2D1_TEXT_ANTIALIAS_MODE_GRAYSCALE,
+            2 => D2D1_TEXT_ANTIALIAS_MODE_CLEARTYPE,
+            _ => D2D1_TEXT_ANTIALIAS_MODE_DEFAULT,
+        };
+        unsafe {
+            rt.SetTextAntialiasMode(aa_mode);
+        }
+
+        // 1. Fill background
+        unsafe {
+            rt.Clear(Some(&self.background_color));
+        }
+        if self.background_gradient.is_some() {
+            self.fill_background(rt, self.height);
+        }
+
+        // 1a. Single-line mode: center the row and clip drawing to it
+        if self.single_line {
+            let row = D2D_RECT_F {
+                left: 0.0,
+                top: 0.0,
+                right: self.width as f32,
+                bottom: self.renderer.line_height as f32,
+            };
+            unsafe {
+                rt.SetTransform(&Matrix3x2::translation(0.0, self.single_line_offset() as f32));
+                rt.PushAxisAlignedClip(&row, D2D1_ANTIALIAS_MODE_ALIASED);
+            }
+        }
+
+        let gutter_w = self.gutter_width();
+        let text_x = gutter_w - self.scroll_x;
+
+        // 2. Draw gutter background
+        unsafe {
+            let brush = rt
+                .CreateSolidColorBrush(&self.gutter_bg_color, None)
+                .unwrap();
+            let gutter_rect = D2D_RECT_F {
+                left: 0.0,
+                top: 0.0,
+                right: gutter_w as f32,
+                bottom: self.height as f32,
+            };
+            rt.FillRectangle(&gutter_rect, &brush);
+        }
+
+        // 2a. Draw column rulers
+        if !self.rulers.is_empty() {
+            unsafe {
+                let brush = rt.CreateSolidColorBrush(&self.ruler_color, None).unwrap();
+                for &col in &self.rulers {
+                    let x = (text_x + col as f64 * self.renderer.char_width).floor();
+                    if x < gutter_w {
+                        continue;
+                    }
+                    let rect = D2D_RECT_F {
+                        left: x as f32,
+                        top: 0.0,
+                        right: (x + 1.0) as f32,
+                        bottom: self.height as f32,
+                    };
+                    rt.FillRectangle(&rect, &brush);
+                }
+            }
+        }
+
+        // 2b. Tint the gutter rows of selected lines
+        if let Some(color) = self.gutter_selection {
+            let line_h = self.renderer.line_height;
+            let mut color = color.unwrap_or(self.selection_color);
+            color.a = self.selection_color.a;
+            for line in self.lines() {
+                if !self.row_selected(line.y_offset, line_h) {
+                    continue;
+                }
+                unsafe {
+                    let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                    let rect = D2D_RECT_F {
+                        left: 0.0,
+                        top: line.y_offset as f32,
+                        right: gutter_w as f32,
+                        bottom: (line.y_offset + line_h) as f32,
+                    };
+                    rt.FillRectangle(&rect, &brush);
+                }
+            }
+        }
+
+        // Read-only tint across the text area, under the shading below
+        if let Some(Some(tint)) = self.read_only {
+            let color = D2D1_COLOR_F { a: REGION_SHADING_ALPHA as f32, ..tint };
+            let area = D2D_RECT_F {
+                left: gutter_w as f32,
+                top: 0.0,
+                right: self.width as f32,
+                bottom: self.height as f32,
+            };
+            unsafe {
+                let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                rt.FillRectangle(&area, &brush);
+            }
+        }
+
+        // 2c. Shade host regions (e.g. a hovered fold range) across the
+        // text area, under the text
+        for region in &self.shaded_regions {
+            let color = D2D1_COLOR_F {
+                a: REGION_SHADING_ALPHA as f32,
+                ..text_renderer::parse_hex_color(&region.color)
+            };
+            let band = D2D_RECT_F {
+                left: gutter_w as f32,
+                top: region.start_y as f32,
+                right: self.width as f32,
+                bottom: region.end_y as f32,
+            };
+            unsafe {
+                let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                rt.FillRectangle(&band, &brush);
+            }
+        }
+
+        // 3. Draw each buffered line's number in the gutter, when shown
+        let lead = self.renderer.half_leading();
+        if self.gutter_shown() {
+            for line in self.lines() {
+                let text_y = line.y_offset + lead;
+
+                // Draw line number in gutter (right-aligned, except the
+                // hybrid mode's absolute number, which starts the column)
+                let num_str = self.line_number_label(line.line_number);
+                let num_width = self.renderer.char_width * num_str.len() as f64;
+                let num_x = if self.is_hybrid_anchor(line.line_number) {
+                    gutter_w - 20.0 - self.gutter_digits() as f64 * self.renderer.char_width
+                } else {
+                    gutter_w - 20.0 - num_width
+                };
+                let (format, color) = match self.active_line_gutter {
+                    Some(color) if line.line_number == self.active_line => {
+                        (&self.renderer.bold, color.unwrap_or(self.default_text_color))
+                    }
+                    _ => (&self.renderer.normal, self.gutter_fg_color),
+                };
+
+                text_renderer::draw_text(rt, &num_str, num_x, text_y, format, color);
+            }
+        }
+
+        // 3a. Draw line text, shifted by the horizontal scroll and clipped
+        // to the text area so it slides under the gutter
+        unsafe {
+            let text_area = D2D_RECT_F {
+                left: gutter_w as f32,
+                top: 0.0,
+                right: self.width as f32,
+                bottom: self.height as f32,
+            };
+            rt.PushAxisAlignedClip(&text_area, D2D1_ANTIALIAS_MODE_ALIASED);
+        }
+        // Indent rainbow bands, under the text
+        if !self.indent_rainbow.is_empty() {
+            self.draw_indent_rainbow(rt, text_x);
+        }
+        for line in self.lines() {
+            let text_y = line.y_offset + lead;
+            text_renderer::draw_line(
+                rt,
+                &line.text,
+                &self.line_tokens(line),
+                text_x,
+                text_y,
+                &self.renderer,
+                self.default_text_color,
+            );
+        }
+
+        // Inline images, under the same clip
+        for img in &self.images {
+            self.draw_image(rt, img);
+        }
+        unsafe {
+            rt.PopAxisAlignedClip();
+        }
+
+        // 3b. Placeholder for an empty document
+        if let (Some((text, color)), Some(y)) = (&self.placeholder, self.placeholder_y()) {
+            text_renderer::draw_text(
+                rt,
+                text,
+                text_x,
+                y + lead,
+                &self.renderer.italic,
+                *color,
+            );
+        }
+
+        // 3c. End-of-file markers in the gutter, below the last line
+        if let Some(eof) = self.eof_y().filter(|_| self.gutter_shown()) {
+            let (marker, rows) = match self.eof_markers {
+                1 => ("~", usize::MAX),
+                _ => (EOF_SYMBOL, 1),
+            };
+            let x = gutter_w - 20.0 - self.renderer.char_width;
+            let line_h = self.renderer.line_height;
+            let ys = (0..rows).map(|i| eof + i as f64 * line_h);
+            for y in ys.take_while(|&y| y < self.height) {
+                let format = &self.renderer.normal;
+                text_renderer::draw_text(rt, marker, x, y + lead, format, self.gutter_fg_color);
+            }
+        }
+
+        // 4. Draw decorations (underlines, backgrounds), then fading flashes
+        for decor in &self.decorations {
+            self.draw_decoration(rt, decor, gutter_w, 1.0);
+        }
+        let now = Instant::now();
+        for flash in &self.flashes {
+            self.draw_decoration(rt, &flash.decor, gutter_w, flash.alpha(now));
+        }
+
+        // 4a. Highlight trailing whitespace
+        if self.highlight_trailing_whitespace {
+            let brush = unsafe {
+                rt.CreateSolidColorBrush(&self.trailing_whitespace_color, None)
+                    .unwrap()
+            };
+            for line in self.lines() {
+                if line.line_number == self.active_line {
+                    continue;
+                }
+                let Some(start) = trailing_whitespace_start(&line.text) else {
+                    continue;
+                };
+                let x = (text_x + self.renderer.measure_text(&line.text[..start])).max(gutter_w);
+                let w = text_x + self.renderer.measure_text(&line.text) - x;
+                if w <= 0.0 {
+                    continue;
+                }
+                let rect = D2D_RECT_F {
+                    left: x as f32,
+                    top: line.y_offset as f32,
+                    right: (x + w) as f32,
+                    bottom: (line.y_offset + self.renderer.line_height) as f32,
+                };
+                unsafe { rt.FillRectangle(&rect, &brush) };
+            }
+        }
+
+        // 4b. Draw fold placeholders
+        for fold in &self.fold_placeholders {
+            let Some((x, line_y, w)) = self.fold_placeholder_rect(fold) else {
+                continue;
+            };
+            if x < gutter_w {
+                continue;
+            }
+            let h = self.renderer.line_height - 4.0;
+            let pill = D2D1_ROUNDED_RECT {
+                rect: D2D_RECT_F {
+                    left: x as f32,
+                    top: (line_y + 2.0) as f32,
+                    right: (x + w) as f32,
+                    bottom: (line_y + 2.0 + h) as f32,
+                },
+                radiusX: (h / 2.0) as f32,
+                radiusY: (h / 2.0) as f32,
+            };
+            let fill = D2D1_COLOR_F {
+                a: 0.2,
+                ..self.gutter_fg_color
+            };
+            unsafe {
+                let brush = rt.CreateSolidColorBrush(&fill, None).unwrap();
+                rt.FillRoundedRectangle(&pill, &brush);
+            }
+            text_renderer::draw_text(
+                rt,
+                FOLD_PLACEHOLDER,
+                x + self.renderer.char_width / 2.0,
+                line_y + lead,
+                &self.renderer.normal,
+                self.gutter_fg_color,
+            );
+        }
+
+        // 4c. Occurrence highlights: outlined, under the selection
+        for occ in &self.occurrences {
+            let color = occ
+                .color
+                .as_deref()
+                .map_or(self.occurrence_color, text_renderer::parse_hex_color);
+            let rect = D2D_RECT_F {
+                left: (occ.x + 0.5) as f32,
+                top: (occ.y + 0.5) as f32,
+                right: (occ.x + occ.w - 0.5) as f32,
+                bottom: (occ.y + occ.h - 0.5) as f32,
+            };
+            unsafe {
+                let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                rt.DrawRectangle(&rect, &brush, 1.0, None);
+            }
+        }
+
+        // 5. Draw selection rectangles
+        if self.selection_style == 1 {
+            self.draw_rounded_selections(rt);
+        } else {
+            let eol_w = self.eol_selection_width();
+            for sel in &self.selections {
+                let w = if sel.eol { sel.w + eol_w } else { sel.w };
+                let color = self.selection_fill(sel);
+                unsafe {
+                    let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                    let rect = D2D_RECT_F {
+                        left: sel.x as f32,
+                        top: sel.y as f32,
+                        right: (sel.x + w) as f32,
+                        bottom: (sel.y + sel.h) as f32,
+                    };
+                    rt.FillRectangle(&rect, &brush);
+                }
+            }
+        }
+
+        // 5a. Collaborators' selections and carets, under the local caret
+        self.draw_remote_presence(rt, lead);
+
+        // 6. Draw ghost text
+        if let Some(ref ghost) = self.ghost_text {
+            text_renderer::draw_text(
+                rt,
+                &ghost.text,
+                ghost.x,
+                ghost.y + lead,
+                &self.renderer.normal,
+                ghost.color,
+            );
+        }
+
+        // 7. Draw cursors
+        self.draw_cursors(rt);
+
+        // 7a. Caret labels, above their carets
+        for label in &self.caret_labels {
+            let y = self.label_y(label.y, self.renderer.line_height);
+            self.draw_label(rt, label.x, y, &label.text, label.bg, label.fg, lead);
+        }
 
-            let size = D2D_SIZE_U {
-                width: (rc.right - rc.left).max(1) as u32,
-                height: (rc.bottom - rc.top).max(1) as u32,
-            };
+        // 8. Sticky scroll headers
+        if !self.sticky_lines.is_empty() {
+            self.draw_sticky_lines(rt, gutter_w, text_x);
+        }
 
-            let rt_props = D2D1_RENDER_TARGET_PROPERTIES::default();
-            let hwnd_props = D2D1_HWND_RENDER_TARGET_PROPERTIES {
-                hwnd: self.hwnd,
-                pixelSize: size,
-                presentOptions: D2D1_PRESENT_OPTIONS_NONE,
-            };
+        // 9. Gutter shadow while horizontally scrolled
+        if self.gutter_shown() && self.gutter_shadow && self.scroll_x > 0.0 {
+            self.draw_gutter_shadow(rt, gutter_w);
+        }
 
-            match self.d2d_factory.CreateHwndRenderTarget(&rt_props, &hwnd_props) {
-                Ok(rt) => {
-                    self.render_target = Some(rt);
-                }
-                Err(e) => {
-                    eprintln!("Failed to create render target: {:?}", e);
-                }
+        if self.single_line {
+            unsafe {
+                rt.PopAxisAlignedClip();
+                rt.SetTransform(&Matrix3x2::identity());
             }
         }
-    }
 
-    /// Resize the render target when the window size changes.
-    pub fn resize(&mut self, width: u32, height: u32) {
-        self.width = width as f64;
-        self.height = height as f64;
-        if let Some(ref rt) = self.render_target {
-            let size = D2D_SIZE_U {
-                width: width.max(1),
-                height: height.max(1),
-            };
+        // 10. Inactive dim overlay
+        if !self.focused && self.inactive_dim > 0.0 {
             unsafe {
-                let _ = rt.Resize(&size);
+                let dim = D2D1_COLOR_F {
+                    a: self.inactive_dim as f32,
+                    ..self.background_color
+                };
+                let brush = rt.CreateSolidColorBrush(&dim, None).unwrap();
+                let bounds = D2D_RECT_F {
+                    left: 0.0,
+                    top: 0.0,
+                    right: self.width as f32,
+                    bottom: self.height as f32,
+                };
+                rt.FillRectangle(&bounds, &brush);
             }
         }
-    }
-
-    /// Called from WM_PAINT — paint the frame buffer using Direct2D.
-    pub fn paint(&mut self) {
-        self.ensure_render_target();
-
-        let rt = match self.render_target.as_ref() {
-            Some(rt) => rt.clone(),
-            None => return,
-        };
 
-        unsafe {
-            rt.BeginDraw();
+        // 10a. Visual bell, fading out over everything but popups
+        let bell = self.bell_alpha(now);
+        if bell > 0.0 {
+            unsafe {
+                let wash = D2D1_COLOR_F {
+                    a: bell as f32,
+                    ..self.default_text_color
+                };
+                let brush = rt.CreateSolidColorBrush(&wash, None).unwrap();
+                let bounds = D2D_RECT_F {
+                    left: 0.0,
+                    top: 0.0,
+                    right: self.width as f32,
+                    bottom: self.height as f32,
+                };
+                rt.FillRectangle(&bounds, &brush);
+            }
         }
 
-        self.draw(&rt);
+        // 11. Completion list, then the hover card, over everything else
+        if let Some(ref popup) = self.completion {
+            self.draw_completion_popup(rt, popup, lead);
+        }
+        if let Some(ref card) = self.hover_card {
+            self.draw_hover_card(rt, card, lead);
+        }
+    }
 
-        unsafe {
-            let hr = rt.EndDraw(None, None);
-            if hr.is_err() {
-                // D2DERR_RECREATE_TARGET — discard and recreate on next paint
-                self.render_target = None;
+    /// Fill each indent level of every line's leading whitespace with the
+    /// next rainbow color. Widths are measured, so tabs match the text.
+    fn draw_indent_rainbow(&self, rt: &ID2D1RenderTarget, text_x: f64) {
+        let line_h = self.renderer.line_height;
+        for line in self.lines() {
+            let mut x = text_x;
+            let ends = indent_level_ends(&line.text, self.tab_width);
+            for (level, end) in ends.into_iter().enumerate() {
+                let end_x = text_x + self.renderer.measure_text(&line.text[..end]);
+                let color = D2D1_COLOR_F {
+                    a: INDENT_RAINBOW_ALPHA as f32,
+                    ..self.indent_rainbow[level % self.indent_rainbow.len()]
+                };
+                let band = D2D_RECT_F {
+                    left: x as f32,
+                    top: line.y_offset as f32,
+                    right: end_x as f32,
+                    bottom: (line.y_offset + line_h) as f32,
+                };
+                unsafe {
+                    let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                    rt.FillRectangle(&band, &brush);
+                }
+                x = end_x;
             }
         }
     }
 
-    // ── Drawing ──────────────────────────────────────────────────
-
-    /// Compute gutter width matching the TS GutterRenderer formula:
-    /// max(2, digits) * charWidth + 36  (16px fold + 16px padding + 4px diff)
-    fn gutter_width(&self) -> f64 {
-        let digits = if self.max_line_number <= 0 {
-            2
-        } else {
-            let d = (self.max_line_number as f64).log10().floor() as i32 + 1;
-            d.max(2)
+    /// Upload an inline image to a bitmap on `rt` and draw it. Bitmaps are
+    /// device resources, so they're rebuilt each paint rather than kept.
+    fn draw_image(&self, rt: &ID2D1RenderTarget, img: &InlineImage) {
+        let props = D2D1_BITMAP_PROPERTIES {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: 96.0,
+            dpiY: 96.0,
         };
-        digits as f64 * self.renderer.char_width + 36.0
+        let size = D2D_SIZE_U {
+            width: img.width,
+            height: img.height,
+        };
+        unsafe {
+            let Ok(bitmap) =
+                rt.CreateBitmap(size, Some(img.bgra.as_ptr() as *const _), img.width * 4, &props)
+            else {
+                return;
+            };
+            let dest = D2D_RECT_F {
+                left: img.x as f32,
+                top: img.y as f32,
+                right: (img.x + img.width as f64) as f32,
+                bottom: (img.y + img.height as f64) as f32,
+            };
+            rt.DrawBitmap(&bitmap, Some(&dest), 1.0, D2D1_BITMAP_INTERPOLATION_MODE_LINEAR, None);
+        }
     }
 
-    fn draw(&self, rt: &ID2D1HwndRenderTarget) {
-        // 1. Fill background
+    /// Fill the top `fill_h` of the view with the editor background: the
+    /// gradient from `set_background_gradient`, spanning the full view
+    /// height, or the flat background color.
+    fn fill_background(&self, rt: &ID2D1RenderTarget, fill_h: f64) {
+        let rect =
+            D2D_RECT_F { left: 0.0, top: 0.0, right: self.width as f32, bottom: fill_h as f32 };
         unsafe {
-            rt.Clear(Some(&self.background_color));
+            let Some((top, bottom)) = self.background_gradient else {
+                let brush = rt.CreateSolidColorBrush(&self.background_color, None).unwrap();
+                rt.FillRectangle(&rect, &brush);
+                return;
+            };
+            let stops = [
+                D2D1_GRADIENT_STOP { position: 0.0, color: top },
+                D2D1_GRADIENT_STOP { position: 1.0, color: bottom },
+            ];
+            let Ok(collection) =
+                rt.CreateGradientStopCollection(&stops, D2D1_GAMMA_2_2, D2D1_EXTEND_MODE_CLAMP)
+            else {
+                return;
+            };
+            let props = D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
+                startPoint: D2D_POINT_2F { x: 0.0, y: 0.0 },
+                endPoint: D2D_POINT_2F { x: 0.0, y: self.height as f32 },
+            };
+            let Ok(brush) = rt.CreateLinearGradientBrush(&props, None, &collection) else {
+                return;
+            };
+            rt.FillRectangle(&rect, &brush);
         }
+    }
 
-        let gutter_w = self.gutter_width();
-
-        // 2. Draw gutter background
+    /// Soft shadow just right of the gutter, signalling that text is
+    /// scrolled underneath it.
+    fn draw_gutter_shadow(&self, rt: &ID2D1RenderTarget, gutter_w: f64) {
+        let shadow_w = 6.0;
+        let black = |a: f32| D2D1_COLOR_F { r: 0.0, g: 0.0, b: 0.0, a };
+        let stops = [
+            D2D1_GRADIENT_STOP { position: 0.0, color: black(0.25) },
+            D2D1_GRADIENT_STOP { position: 1.0, color: black(0.0) },
+        ];
         unsafe {
-            let brush = rt
-                .CreateSolidColorBrush(&self.gutter_bg_color, None)
-                .unwrap();
-            let gutter_rect = D2D_RECT_F {
-                left: 0.0,
+            let Ok(collection) =
+                rt.CreateGradientStopCollection(&stops, D2D1_GAMMA_2_2, D2D1_EXTEND_MODE_CLAMP)
+            else {
+                return;
+            };
+            let props = D2D1_LINEAR_GRADIENT_BRUSH_PROPERTIES {
+                startPoint: D2D_POINT_2F { x: gutter_w as f32, y: 0.0 },
+                endPoint: D2D_POINT_2F { x: (gutter_w + shadow_w) as f32, y: 0.0 },
+            };
+            let Ok(brush) = rt.CreateLinearGradientBrush(&props, None, &collection) else {
+                return;
+            };
+            let rect = D2D_RECT_F {
+                left: gutter_w as f32,
                 top: 0.0,
-                right: gutter_w as f32,
+                right: (gutter_w + shadow_w) as f32,
                 bottom: self.height as f32,
             };
-            rt.FillRectangle(&gutter_rect, &brush);
+            rt.FillRectangle(&rect, &brush);
         }
+    }
 
-        // 3. Draw each buffered line
-        for line in &self.frame_lines {
-            // Draw line number in gutter (right-aligned)
-            let num_str = format!("{}", line.line_number);
-            let num_width = self.renderer.char_width * num_str.len() as f64;
-            let num_x = gutter_w - 20.0 - num_width;
+    /// Segment length and height of the `underline-wavy` zigzag. Both scale
+    /// with the font (half a cell wide, half that high; about 4px by 2px at
+    /// 13pt) so the squiggle keeps its shape from small to large sizes.
+    fn squiggle_wave(&self) -> (f64, f64) {
+        let wave_len = (self.renderer.char_width / 2.0).max(2.0);
+        (wave_len, (wave_len / 2.0).max(1.0))
+    }
 
-            text_renderer::draw_text(
-                rt,
-                &num_str,
-                num_x,
-                line.y_offset,
-                &self.renderer.normal,
-                self.gutter_fg_color,
-            );
+    /// Draw one decoration overlay, with its opacity scaled by `alpha`.
+    fn draw_decoration(
+        &self,
+        rt: &ID2D1RenderTarget,
+        decor: &DecorationOverlay,
+        gutter_w: f64,
+        alpha: f64,
+    ) {
+        let mut color = text_renderer::parse_hex_color(&decor.color);
+        color.a *= alpha as f32;
+        unsafe {
+            match decor.kind.as_str() {
+                "background" => {
+                    let mut bg_color = color;
+                    bg_color.a = 0.3 * alpha as f32;
+                    let brush = rt.CreateSolidColorBrush(&bg_color, None).unwrap();
+                    let rect = D2D_RECT_F {
+                        left: decor.x as f32,
+                        top: decor.y as f32,
+                        right: (decor.x + decor.w) as f32,
+                        bottom: (decor.y + decor.h) as f32,
+                    };
+                    rt.FillRectangle(&rect, &brush);
+                }
+                "underline" => {
+                    let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                    let y_bottom = (decor.y + decor.h - 1.0) as f32;
+                    rt.DrawLine(
+                        D2D_POINT_2F {
+                            x: decor.x as f32,
+                            y: y_bottom,
+                        },
+                        D2D_POINT_2F {
+                            x: (decor.x + decor.w) as f32,
+                            y: y_bottom,
+                        },
+                        &brush,
+                        1.0,
+                        None,
+                    );
+                }
+                "underline-wavy" => {
+                    let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                    let y_base = (decor.y + decor.h - 1.0) as f32;
+                    let (wave_len, wave_height) = self.squiggle_wave();
+                    let (wave_len, wave_height) = (wave_len as f32, wave_height as f32);
+                    let mut x = decor.x as f32;
+                    let x_end = (decor.x + decor.w) as f32;
+                    let mut up = true;
+                    let mut prev = D2D_POINT_2F { x, y: y_base };
+                    while x < x_end {
+                        let y_target = if up {
+                            y_base - wave_height
+                        } else {
+                            y_base
+                        };
+                        x += wave_len;
+                        let next = D2D_POINT_2F { x, y: y_target };
+                        rt.DrawLine(prev, next, &brush, 1.0, None);
+                        prev = next;
+                        up = !up;
+                    }
+                }
+                "line-accent" => {
+                    // Thin bar at the content's left edge, ignoring scroll_x
+                    let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                    let rect = D2D_RECT_F {
+                        left: gutter_w as f32,
+                        top: decor.y as f32,
+                        right: (gutter_w + LINE_ACCENT_WIDTH) as f32,
+                        bottom: (decor.y + decor.h) as f32,
+                    };
+                    rt.FillRectangle(&rect, &brush);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Pin the sticky scope headers over the top rows, on top of the normal
+    /// lines, selections, and cursors.
+    fn draw_sticky_lines(&self, rt: &ID2D1RenderTarget, gutter_w: f64, text_x: f64) {
+        let line_h = self.renderer.line_height;
+        let lead = self.renderer.half_leading();
+        let bottom = self.sticky_lines.len() as f64 * line_h;
+        let rect = |left: f64, top: f64, right: f64, bottom: f64| D2D_RECT_F {
+            left: left as f32,
+            top: top as f32,
+            right: right as f32,
+            bottom: bottom as f32,
+        };
+        unsafe {
+            self.fill_background(rt, bottom);
+            let gutter_bg = rt.CreateSolidColorBrush(&self.gutter_bg_color, None).unwrap();
+            rt.FillRectangle(&rect(0.0, 0.0, gutter_w, bottom), &gutter_bg);
+        }
+
+        if self.gutter_shown() {
+            for (i, sticky) in self.sticky_lines.iter().enumerate() {
+                let num_str = format!("{}", sticky.line_number);
+                let num_x = gutter_w - 20.0 - self.renderer.char_width * num_str.len() as f64;
+                text_renderer::draw_text(
+                    rt,
+                    &num_str,
+                    num_x,
+                    i as f64 * line_h + lead,
+                    &self.renderer.normal,
+                    self.gutter_fg_color,
+                );
+            }
+        }
 
-            // Draw text content with tokens starting at gutter_w
+        unsafe {
+            let text_area = rect(gutter_w, 0.0, self.width, bottom);
+            rt.PushAxisAlignedClip(&text_area, D2D1_ANTIALIAS_MODE_ALIASED);
+        }
+        for (i, sticky) in self.sticky_lines.iter().enumerate() {
             text_renderer::draw_line(
                 rt,
-                &line.text,
-                &line.tokens,
-                gutter_w,
-                line.y_offset,
+                &sticky.text,
+                self.visible_tokens(&sticky.tokens),
+                text_x,
+                i as f64 * line_h + lead,
                 &self.renderer,
                 self.default_text_color,
             );
         }
+        unsafe {
+            rt.PopAxisAlignedClip();
+        }
 
-        // 4. Draw decorations (underlines, backgrounds)
-        for decor in &self.decorations {
-            let color = text_renderer::parse_hex_color(&decor.color);
-            unsafe {
-                match decor.kind.as_str() {
-                    "background" => {
-                        let mut bg_color = color;
-                        bg_color.a = 0.3;
-                        let brush = rt.CreateSolidColorBrush(&bg_color, None).unwrap();
-                        let rect = D2D_RECT_F {
-                            left: decor.x as f32,
-                            top: decor.y as f32,
-                            right: (decor.x + decor.w) as f32,
-                            bottom: (decor.y + decor.h) as f32,
-                        };
-                        rt.FillRectangle(&rect, &brush);
-                    }
-                    "underline" => {
-                        let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
-                        let y_bottom = (decor.y + decor.h - 1.0) as f32;
-                        rt.DrawLine(
-                            D2D_POINT_2F {
-                                x: decor.x as f32,
-                                y: y_bottom,
-                            },
-                            D2D_POINT_2F {
-                                x: (decor.x + decor.w) as f32,
-                                y: y_bottom,
-                            },
-                            &brush,
-                            1.0,
-                            None,
-                        );
-                    }
-                    "underline-wavy" => {
-                        let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
-                        let y_base = (decor.y + decor.h - 1.0) as f32;
-                        let wave_height: f32 = 2.0;
-                        let wave_len: f32 = 4.0;
-                        let mut x = decor.x as f32;
-                        let x_end = (decor.x + decor.w) as f32;
-                        let mut up = true;
-                        let mut prev = D2D_POINT_2F { x, y: y_base };
-                        while x < x_end {
-                            let y_target = if up {
-                                y_base - wave_height
-                            } else {
-                                y_base
-                            };
-                            x += wave_len;
-                            let next = D2D_POINT_2F { x, y: y_target };
-                            rt.DrawLine(prev, next, &brush, 1.0, None);
-                            prev = next;
-                            up = !up;
-                        }
-                    }
-                    _ => {}
-                }
+        // Bottom border separating the pinned rows from scrolling content
+        let border = D2D1_COLOR_F {
+            a: 0.4,
+            ..self.gutter_fg_color
+        };
+        unsafe {
+            let brush = rt.CreateSolidColorBrush(&border, None).unwrap();
+            rt.FillRectangle(&rect(0.0, bottom, self.width, bottom + 1.0), &brush);
+        }
+    }
+
+    /// Fill color for a selection region. Unfocused views draw it as the
+    /// gray of equal luminance, so the live pane's selection stands out.
+    fn selection_fill(&self, sel: &SelectionRegion) -> D2D1_COLOR_F {
+        let c = match sel.color {
+            Some(ref hex) => {
+                let mut c = text_renderer::parse_hex_color(hex);
+                c.a = self.selection_color.a;
+                c
             }
+            None => self.selection_color,
+        };
+        if self.focused {
+            return c;
         }
+        let l = luminance(c);
+        D2D1_COLOR_F { r: l, g: l, b: l, a: c.a }
+    }
 
-        // 5. Draw selection rectangles
-        for sel in &self.selections {
+    /// True when any selection region overlaps the row `[y, y + h)`.
+    fn row_selected(&self, y: f64, h: f64) -> bool {
+        self.selections.iter().any(|s| s.y < y + h && s.y + s.h > y)
+    }
+
+    /// Fill each run of contiguous selection rows as one outline with rounded corners.
+    fn draw_rounded_selections(&self, rt: &ID2D1RenderTarget) {
+        let pt = |p: (f64, f64)| D2D_POINT_2F {
+            x: p.0 as f32,
+            y: p.1 as f32,
+        };
+        for (outline, first) in selection_outlines(&self.selections, self.eol_selection_width()) {
+            if outline.len() < 3 {
+                continue;
+            }
+            let color = self.selection_fill(&self.selections[first]);
+            let corners = rounded_corners(&outline, SELECTION_CORNER_RADIUS);
             unsafe {
-                let brush = rt
-                    .CreateSolidColorBrush(&self.selection_color, None)
-                    .unwrap();
-                let rect = D2D_RECT_F {
-                    left: sel.x as f32,
-                    top: sel.y as f32,
-                    right: (sel.x + sel.w) as f32,
-                    bottom: (sel.y + sel.h) as f32,
+                let Ok(geometry) = self.d2d_factory.CreatePathGeometry() else {
+                    continue;
                 };
-                rt.FillRectangle(&rect, &brush);
+                let Ok(sink) = geometry.Open() else {
+                    continue;
+                };
+                sink.BeginFigure(pt(corners[corners.len() - 1].end), D2D1_FIGURE_BEGIN_FILLED);
+                for c in &corners {
+                    sink.AddLine(pt(c.start));
+                    sink.AddQuadraticBezier(&D2D1_QUADRATIC_BEZIER_SEGMENT {
+                        point1: pt(c.ctrl),
+                        point2: pt(c.end),
+                    });
+                }
+                sink.EndFigure(D2D1_FIGURE_END_CLOSED);
+                if sink.Close().is_err() {
+                    continue;
+                }
+                let brush = rt.CreateSolidColorBrush(&color, None).unwrap();
+                rt.FillGeometry(&geometry, &brush, None);
             }
         }
+    }
 
-        // 6. Draw ghost text
-        if let Some(ref ghost) = self.ghost_text {
-            text_renderer::draw_text(
+    /// The hover card: fill, a one-pixel border, then its rows.
+    fn draw_hover_card(&self, rt: &ID2D1RenderTarget, card: &HoverCard, lead: f64) {
+        let rect = D2D_RECT_F {
+            left: card.x as f32,
+            top: card.y as f32,
+            right: (card.x + card.w) as f32,
+            bottom: (card.y + card.h) as f32,
+        };
+        let border = D2D_RECT_F {
+            left: rect.left + 0.5,
+            top: rect.top + 0.5,
+            right: rect.right - 0.5,
+            bottom: rect.bottom - 0.5,
+        };
+        unsafe {
+            let brush = rt.CreateSolidColorBrush(&self.widget_bg_color, None).unwrap();
+            rt.FillRectangle(&rect, &brush);
+            let brush = rt.CreateSolidColorBrush(&self.widget_border_color, None).unwrap();
+            rt.DrawRectangle(&border, &brush, 1.0, None);
+        }
+        let line_h = self.renderer.line_height;
+        for (i, (text, tokens)) in card.rows.iter().enumerate() {
+            text_renderer::draw_line(
                 rt,
-                &ghost.text,
-                ghost.x,
-                ghost.y,
-                &self.renderer.normal,
-                ghost.color,
+                text,
+                self.visible_tokens(tokens),
+                card.x + CARD_PADDING,
+                card.y + CARD_PADDING + i as f64 * line_h + lead,
+                &self.renderer,
+                self.default_text_color,
             );
         }
+    }
 
-        // 7. Draw cursors
-        self.draw_cursors(rt);
+    /// The completion list: fill, the visible rows (selected one highlighted,
+    /// then glyph, label and dimmed detail), a scroll thumb when it overflows,
+    /// and a one-pixel border.
+    fn draw_completion_popup(&self, rt: &ID2D1RenderTarget, popup: &CompletionPopup, lead: f64) {
+        let row_h = self.renderer.line_height;
+        let rect = |x: f64, y: f64, w: f64, h: f64| D2D_RECT_F {
+            left: x as f32,
+            top: y as f32,
+            right: (x + w) as f32,
+            bottom: (y + h) as f32,
+        };
+        let fill = |r: &D2D_RECT_F, color: &D2D1_COLOR_F| unsafe {
+            let brush = rt.CreateSolidColorBrush(color, None).unwrap();
+            rt.FillRectangle(r, &brush);
+        };
+        let bounds = rect(popup.x, popup.y, popup.w, popup.h);
+        fill(&bounds, &self.widget_bg_color);
+        unsafe {
+            rt.PushAxisAlignedClip(&bounds, D2D1_ANTIALIAS_MODE_ALIASED);
+        }
+        let first = (self.completion_scroll / row_h) as usize;
+        for (i, item) in popup.items.iter().enumerate().skip(first) {
+            let row_y = popup.y + i as f64 * row_h - self.completion_scroll;
+            if row_y >= popup.y + popup.h {
+                break;
+            }
+            if popup.selected == Some(i) {
+                let color = D2D1_COLOR_F {
+                    a: 1.0,
+                    ..self.selection_color
+                };
+                fill(&rect(popup.x, row_y, popup.w, row_h), &color);
+            }
+            let text = |s: &str, x: f64, color: D2D1_COLOR_F| {
+                text_renderer::draw_text(rt, s, x, row_y + lead, &self.renderer.normal, color);
+            };
+            let icon_color = item
+                .icon_color
+                .as_deref()
+                .map_or(self.gutter_fg_color, text_renderer::parse_hex_color);
+            text(&item.icon, popup.x + CARD_PADDING, icon_color);
+            text(&item.label, popup.x + popup.label_x, self.default_text_color);
+            text(&item.detail, popup.x + popup.detail_x, self.gutter_fg_color);
+        }
+        let content_h = popup.items.len() as f64 * row_h;
+        if content_h > popup.h {
+            let thumb = rect(
+                popup.x + popup.w - SCROLL_THUMB_WIDTH - 1.0,
+                popup.y + self.completion_scroll / content_h * popup.h,
+                SCROLL_THUMB_WIDTH,
+                popup.h * popup.h / content_h,
+            );
+            fill(&thumb, &self.widget_border_color);
+        }
+        let border = D2D_RECT_F {
+            left: bounds.left + 0.5,
+            top: bounds.top + 0.5,
+            right: bounds.right - 0.5,
+            bottom: bounds.bottom - 0.5,
+        };
+        unsafe {
+            rt.PopAxisAlignedClip();
+            let brush = rt.CreateSolidColorBrush(&self.widget_border_color, None).unwrap();
+            rt.DrawRectangle(&border, &brush, 1.0, None);
+        }
+    }
+
+    /// Top of a label for a caret row at `y`: above the row, or below it
+    /// when the row is at the top edge.
+    fn label_y(&self, y: f64, line_h: f64) -> f64 {
+        let h = self.renderer.line_height;
+        if y >= h { y - h } else { y + line_h }
+    }
+
+    /// A rounded label with its top-left at `(x, y)`, sized to `text`.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_label(
+        &self,
+        rt: &ID2D1RenderTarget,
+        x: f64,
+        y: f64,
+        text: &str,
+        bg: D2D1_COLOR_F,
+        fg: D2D1_COLOR_F,
+        lead: f64,
+    ) {
+        let h = self.renderer.line_height;
+        let w = self.renderer.measure_text(text) + LABEL_PADDING * 2.0;
+        let label = D2D1_ROUNDED_RECT {
+            rect: D2D_RECT_F {
+                left: x as f32,
+                top: y as f32,
+                right: (x + w) as f32,
+                bottom: (y + h) as f32,
+            },
+            radiusX: LABEL_CORNER_RADIUS as f32,
+            radiusY: LABEL_CORNER_RADIUS as f32,
+        };
+        unsafe {
+            let brush = rt.CreateSolidColorBrush(&bg, None).unwrap();
+            rt.FillRoundedRectangle(&label, &brush);
+        }
+        text_renderer::draw_text(rt, text, x + LABEL_PADDING, y + lead, &self.renderer.normal, fg);
+    }
+
+    /// Collaborators' selections, then their carets with name tags.
+    fn draw_remote_presence(&self, rt: &ID2D1RenderTarget, lead: f64) {
+        let fill = |color: &D2D1_COLOR_F, x: f64, y: f64, w: f64, h: f64| unsafe {
+            let brush = rt.CreateSolidColorBrush(color, None).unwrap();
+            let rect = D2D_RECT_F {
+                left: x as f32,
+                top: y as f32,
+                right: (x + w) as f32,
+                bottom: (y + h) as f32,
+            };
+            rt.FillRectangle(&rect, &brush);
+        };
+        for sel in &self.remote_selections {
+            let mut color = match sel.color {
+                Some(ref hex) => text_renderer::parse_hex_color(hex),
+                None => self.selection_color,
+            };
+            color.a = self.selection_color.a;
+            fill(&color, sel.x, sel.y, sel.w, sel.h);
+        }
+        let line_h = self.renderer.line_height;
+        for cursor in &self.remote_cursors {
+            let color = text_renderer::parse_hex_color(&cursor.color);
+            fill(&color, cursor.x, cursor.y, self.caret_width, line_h);
+            if cursor.label.is_empty() {
+                continue;
+            }
+            let tag_y = self.label_y(cursor.y, line_h);
+            self.draw_label(rt, cursor.x, tag_y, &cursor.label, color, self.background_color, lead);
+        }
     }
 
-    fn draw_cursors(&self, rt: &ID2D1HwndRenderTarget) {
+    fn draw_cursors(&self, rt: &ID2D1RenderTarget) {
+        // Read-only content has no caret to blink
+        if self.read_only.is_some() {
+            return;
+        }
+        let hollow = self.hollow_caret_unfocused && !self.focused;
         let draw_one = |cursor: &CursorData| {
             let (w, h) = match cursor.style {
-                0 => (2.0, self.renderer.line_height),
+                0 => (self.caret_width, self.renderer.line_height),
                 1 => (self.renderer.char_width, self.renderer.line_height),
-                2 => (self.renderer.char_width, 2.0),
-                _ => (2.0, self.renderer.line_height),
+                2 => (self.renderer.char_width, self.caret_width),
+                _ => (self.caret_width, self.renderer.line_height),
             };
             let y = if cursor.style == 2 {
-                cursor.y + self.renderer.line_height - 2.0
+                cursor.y + self.renderer.line_height - self.caret_width
             } else {
                 cursor.y
             };
@@ -652,6 +3609,17 @@ impl EditorView {
                 let brush = rt
                     .CreateSolidColorBrush(&self.cursor_color, None)
                     .unwrap();
+                if hollow {
+                    // Stroke inside the caret's bounds so it covers the same pixels
+                    let rect = D2D_RECT_F {
+                        left: (cursor.x + 0.5) as f32,
+                        top: (y + 0.5) as f32,
+                        right: (cursor.x + w - 0.5).max(cursor.x + 0.5) as f32,
+                        bottom: (y + h - 0.5).max(y + 0.5) as f32,
+                    };
+                    rt.DrawRectangle(&rect, &brush, 1.0, None);
+                    return;
+                }
                 let rect = D2D_RECT_F {
                     left: cursor.x as f32,
                     top: y as f32,
@@ -672,6 +3640,144 @@ impl EditorView {
     }
 }
 
+// ── Selection geometry ───────────────────────────────────────────
+
+/// Corner radius used by the rounded selection style.
+const SELECTION_CORNER_RADIUS: f64 = 3.0;
+
+/// WM_TIMER id driving flash decoration animation.
+pub const FLASH_TIMER_ID: usize = 1;
+
+/// Width of the `"line-accent"` decoration bar.
+const LINE_ACCENT_WIDTH: f64 = 2.0;
+
+/// Underline color for links from `set_links`.
+const LINK_COLOR: &str = "#3794ff";
+
+/// Horizontal padding inside caret labels and remote name tags.
+const LABEL_PADDING: f64 = 4.0;
+
+/// Corner radius of caret labels and remote name tags.
+const LABEL_CORNER_RADIUS: f64 = 3.0;
+
+/// Space between a hover card's border and its text.
+const CARD_PADDING: f64 = 6.0;
+
+/// Rows a completion list shows before it scrolls.
+const COMPLETION_MAX_ROWS: usize = 12;
+
+/// Width of the scroll thumb in an overflowing completion list.
+const SCROLL_THUMB_WIDTH: f64 = 4.0;
+
+/// Opacity of indent rainbow bands over the background.
+const INDENT_RAINBOW_ALPHA: f64 = 0.12;
+
+/// Opacity of host-shaded regions over the background.
+const REGION_SHADING_ALPHA: f64 = 0.1;
+
+/// Peak opacity of the visual bell wash and how long it takes to fade.
+const VISUAL_BELL_ALPHA: f64 = 0.25;
+const VISUAL_BELL_DURATION: Duration = Duration::from_millis(150);
+
+/// Text drawn inside a fold placeholder pill.
+const FOLD_PLACEHOLDER: &str = "\u{22EF}";
+
+/// Gutter marker below the last line in end-symbol EOF mode.
+const EOF_SYMBOL: &str = "\u{220E}";
+
+/// Decode one WM_CHAR UTF-16 unit, pairing surrogates across calls.
+///
+/// A high surrogate is held in `pending` until the next unit: a low
+/// surrogate completes the pair, anything else drops it. None while waiting
+/// and for an unpaired low surrogate.
+fn combine_utf16(pending: &mut Option<u16>, unit: u16) -> Option<char> {
+    match (pending.take(), unit) {
+        (_, 0xD800..=0xDBFF) => {
+            *pending = Some(unit);
+            None
+        }
+        (Some(high), 0xDC00..=0xDFFF) => char::decode_utf16([high, unit]).next()?.ok(),
+        _ => char::decode_utf16([unit]).next()?.ok(),
+    }
+}
+
+/// Whether Windows is set to dark mode for apps, read from the documented
+/// `AppsUseLightTheme` value under the Personalize key. Defaults to dark
+/// (the editor's own default) if the value is missing.
+fn system_prefers_dark() -> bool {
+    let mut value: u32 = 0;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            w!("AppsUseLightTheme"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as *mut std::ffi::c_void),
+            Some(&mut size),
+        )
+    };
+    status.is_err() || value == 0
+}
+
+/// Whether Windows High Contrast mode is on.
+fn system_high_contrast() -> bool {
+    let mut hc = HIGHCONTRASTW {
+        cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+        ..Default::default()
+    };
+    let result = unsafe {
+        SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            hc.cbSize,
+            Some(&mut hc as *mut HIGHCONTRASTW as *mut std::ffi::c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    result.is_ok() && (hc.dwFlags.0 & HCF_HIGHCONTRASTON.0) != 0
+}
+
+/// A system color (GetSysColor's 0x00BBGGRR) as an opaque D2D color.
+fn sys_color(index: SYS_COLOR_INDEX) -> D2D1_COLOR_F {
+    let c = unsafe { GetSysColor(index) };
+    D2D1_COLOR_F {
+        r: (c & 0xff) as f32 / 255.0,
+        g: ((c >> 8) & 0xff) as f32 / 255.0,
+        b: ((c >> 16) & 0xff) as f32 / 255.0,
+        a: 1.0,
+    }
+}
+
+/// Relative luminance (Rec. 709 weights, no gamma) of a color, 0.0 to 1.0.
+fn luminance(c: D2D1_COLOR_F) -> f32 {
+    0.2126 * c.r + 0.7152 * c.g + 0.0722 * c.b
+}
+
+/// Joins the calling thread to a single-threaded COM apartment and leaves it
+/// on drop. Only a successful `CoInitializeEx` (S_OK or S_FALSE) is paired
+/// with `CoUninitialize`: RPC_E_CHANGED_MODE means the host already put the
+/// thread in a multithreaded apartment, which DirectWrite and WIC also work
+/// in, and that is not ours to undo.
+struct ComApartment {
+    entered: bool,
+}
+
+impl ComApartment {
+    fn enter() -> Self {
+        let entered = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) }.is_ok();
+        ComApartment { entered }
+    }
+}
+
+impl Drop for ComApartment {
+    fn drop(&mut self) {
+        if self.entered {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
 impl Drop for EditorView {
     fn drop(&mut self) {
         if !is_null_hwnd(self.hwnd) {
@@ -681,3 +3787,38 @@ impl Drop for EditorView {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surrogate_pair_combines_into_one_char() {
+        let mut pending = None;
+        // U+1F600 GRINNING FACE is D83D DE00 in UTF-16.
+        assert_eq!(combine_utf16(&mut pending, 0xD83D), None);
+        assert_eq!(pending, Some(0xD83D));
+        assert_eq!(combine_utf16(&mut pending, 0xDE00), Some('\u{1F600}'));
+        assert_eq!(pending, None);
+    }
+
+    #[test]
+    fn lone_low_surrogate_is_dropped() {
+        let mut pending = None;
+        assert_eq!(combine_utf16(&mut pending, 0xDE00), None);
+        assert_eq!(pending, None);
+        assert_eq!(combine_utf16(&mut pending, u16::from(b'a')), Some('a'));
+    }
+
+    #[test]
+    fn high_surrogate_followed_by_a_non_surrogate_is_dropped() {
+        let mut pending = None;
+        assert_eq!(combine_utf16(&mut pending, 0xD83D), None);
+        assert_eq!(combine_utf16(&mut pending, u16::from(b'x')), Some('x'));
+        assert_eq!(pending, None);
+        // A second high surrogate replaces the first.
+        assert_eq!(combine_utf16(&mut pending, 0xD83D), None);
+        assert_eq!(combine_utf16(&mut pending, 0xD83C), None);
+        assert_eq!(combine_utf16(&mut pending, 0xDF89), Some('\u{1F389}'));
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! Registers `HoneEditorView` window class with an I-beam cursor.
 //! WndProc dispatches WM_PAINT, WM_CHAR, WM_KEYDOWN, WM_LBUTTONDOWN,
-//! WM_MOUSEWHEEL, WM_SIZE, and WM_RBUTTONDOWN to the EditorView.
+//! WM_MOUSEWHEEL, WM_SIZE, WM_RBUTTONDOWN, WM_SETCURSOR, WM_TIMER, and appearance changes
+//! (WM_SETTINGCHANGE / WM_THEMECHANGED) to the EditorView. WM_GETOBJECT
+//! returns the UI Automation provider from `accessibility`.
 //!
 //! Key design: VK codes are mapped to macOS-style action selectors
 //! ("moveLeft:", "deleteBackward:", etc.) for cross-platform FFI parity.
@@ -10,13 +12,17 @@
 use std::sync::Once;
 
 use windows::core::{w, PCWSTR};
-use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
-use windows::Win32::Graphics::Gdi::{BeginPaint, EndPaint, HBRUSH, PAINTSTRUCT};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM};
+use windows::Win32::Graphics::Gdi::{BeginPaint, EndPaint, ScreenToClient, HBRUSH, PAINTSTRUCT};
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyState, SetFocus};
 use windows::Win32::UI::WindowsAndMessaging::*;
 
-use crate::editor_view::EditorView;
+use crate::accessibility;
+use crate::editor_view::{
+    CursorKind, EditorView, FLASH_TIMER_ID, KEY_MODIFIER_ALT, KEY_MODIFIER_COMMAND,
+    KEY_MODIFIER_CONTROL, KEY_MODIFIER_SHIFT,
+};
 
 /// VK code constants (u16 values matching Windows API).
 const VK_BACK: u16 = 0x08;
@@ -24,6 +30,7 @@ const VK_TAB: u16 = 0x09;
 const VK_RETURN: u16 = 0x0D;
 const VK_SHIFT: u16 = 0x10;
 const VK_CONTROL: u16 = 0x11;
+const VK_MENU: u16 = 0x12;
 const VK_ESCAPE: u16 = 0x1B;
 const VK_LEFT: u16 = 0x25;
 const VK_UP: u16 = 0x26;
@@ -31,7 +38,11 @@ const VK_RIGHT: u16 = 0x27;
 const VK_DOWN: u16 = 0x28;
 const VK_DELETE: u16 = 0x2E;
 const VK_HOME: u16 = 0x24;
+const VK_PRIOR: u16 = 0x21;
+const VK_NEXT: u16 = 0x22;
 const VK_END: u16 = 0x23;
+const VK_LWIN: u16 = 0x5B;
+const VK_RWIN: u16 = 0x5C;
 
 static REGISTER_CLASS: Once = Once::new();
 
@@ -101,7 +112,7 @@ pub fn create_editor_hwnd(
 }
 
 /// Get the EditorView pointer from a window's GWLP_USERDATA.
-unsafe fn get_editor(hwnd: HWND) -> Option<&'static mut EditorView> {
+pub(crate) unsafe fn get_editor(hwnd: HWND) -> Option<&'static mut EditorView> {
     let ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut EditorView;
     if ptr.is_null() {
         None
@@ -120,6 +131,25 @@ fn ctrl_held() -> bool {
     unsafe { GetKeyState(VK_CONTROL as i32) < 0 }
 }
 
+/// Current modifier state as a `KEY_MODIFIER_*` mask.
+fn key_modifiers() -> u32 {
+    let held = |vk: u16| unsafe { GetKeyState(vk as i32) < 0 };
+    let mut modifiers = 0;
+    if held(VK_SHIFT) {
+        modifiers |= KEY_MODIFIER_SHIFT;
+    }
+    if held(VK_CONTROL) {
+        modifiers |= KEY_MODIFIER_CONTROL;
+    }
+    if held(VK_MENU) {
+        modifiers |= KEY_MODIFIER_ALT;
+    }
+    if held(VK_LWIN) || held(VK_RWIN) {
+        modifiers |= KEY_MODIFIER_COMMAND;
+    }
+    modifiers
+}
+
 /// The WndProc for HoneEditorView windows.
 unsafe extern "system" fn wnd_proc(
     hwnd: HWND,
@@ -139,16 +169,9 @@ unsafe extern "system" fn wnd_proc(
         }
 
         WM_CHAR => {
-            let ch = wparam.0 as u32;
-            // Only handle printable characters (>= 0x20), skip control chars
-            if ch >= 0x20 {
-                if let Some(c) = char::from_u32(ch) {
-                    if let Some(editor) = get_editor(hwnd) {
-                        let mut buf = [0u8; 4];
-                        let s = c.encode_utf8(&mut buf);
-                        editor.on_text_input(s);
-                    }
-                }
+            // One UTF-16 unit; emoji and other astral characters take two
+            if let Some(editor) = get_editor(hwnd) {
+                editor.on_char(wparam.0 as u16);
             }
             LRESULT(0)
         }
@@ -158,6 +181,13 @@ unsafe extern "system" fn wnd_proc(
             let shift = shift_held();
             let ctrl = ctrl_held();
 
+            // Give the host first refusal on every key
+            if let Some(editor) = get_editor(hwnd) {
+                if editor.on_key(vk as u32, key_modifiers()) {
+                    return LRESULT(0);
+                }
+            }
+
             // Map VK codes to macOS-style selector strings for cross-platform parity
             let action: Option<&str> = if ctrl {
                 match vk {
@@ -165,10 +195,36 @@ unsafe extern "system" fn wnd_proc(
                     0x56 /* V */ => Some("paste:"),
                     0x58 /* X */ => Some("cut:"),
                     0x41 /* A */ => Some("selectAll:"),
+                    VK_LEFT => {
+                        if shift { Some("moveWordLeftAndModifySelection:") } else { Some("moveWordLeft:") }
+                    }
+                    VK_RIGHT => {
+                        if shift { Some("moveWordRightAndModifySelection:") } else { Some("moveWordRight:") }
+                    }
+                    VK_HOME => {
+                        if shift {
+                            Some("moveToBeginningOfDocumentAndModifySelection:")
+                        } else {
+                            Some("moveToBeginningOfDocument:")
+                        }
+                    }
+                    VK_END => {
+                        if shift {
+                            Some("moveToEndOfDocumentAndModifySelection:")
+                        } else {
+                            Some("moveToEndOfDocument:")
+                        }
+                    }
                     _ => None,
                 }
             } else {
                 match vk {
+                    VK_TAB => {
+                        if let Some(editor) = get_editor(hwnd) {
+                            editor.on_tab(shift);
+                        }
+                        return LRESULT(0);
+                    }
                     VK_LEFT => {
                         if shift { Some("moveLeftAndModifySelection:") } else { Some("moveLeft:") }
                     }
@@ -195,12 +251,15 @@ unsafe extern "system" fn wnd_proc(
                             Some("moveToEndOfLine:")
                         }
                     }
+                    VK_PRIOR => {
+                        if shift { Some("pageUpAndModifySelection:") } else { Some("pageUp:") }
+                    }
+                    VK_NEXT => {
+                        if shift { Some("pageDownAndModifySelection:") } else { Some("pageDown:") }
+                    }
                     VK_BACK => Some("deleteBackward:"),
                     VK_DELETE => Some("deleteForward:"),
                     VK_RETURN => Some("insertNewline:"),
-                    VK_TAB => {
-                        if shift { Some("insertBacktab:") } else { Some("insertTab:") }
-                    }
                     VK_ESCAPE => Some("cancelOperation:"),
                     _ => None,
                 }
@@ -221,13 +280,41 @@ unsafe extern "system" fn wnd_proc(
             let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as f64;
             let _ = SetFocus(hwnd);
             if let Some(editor) = get_editor(hwnd) {
-                editor.on_mouse_down(x, y);
+                if !(ctrl_held() && editor.on_link_click(x, y)) {
+                    editor.on_mouse_down(x, y);
+                }
             }
             LRESULT(0)
         }
 
+        WM_SETCURSOR => {
+            // Arrow over the gutter, hand over links, host regions, else I-beam
+            if (lparam.0 & 0xFFFF) as u32 == HTCLIENT {
+                if let Some(editor) = get_editor(hwnd) {
+                    let mut pt = POINT::default();
+                    if GetCursorPos(&mut pt).is_ok() && ScreenToClient(hwnd, &mut pt).as_bool() {
+                        let id = match editor.cursor_at(pt.x as f64, pt.y as f64) {
+                            CursorKind::Text => IDC_IBEAM,
+                            CursorKind::Arrow => IDC_ARROW,
+                            CursorKind::Hand => IDC_HAND,
+                        };
+                        SetCursor(LoadCursorW(None, id).unwrap_or_default());
+                        return LRESULT(1);
+                    }
+                }
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
         WM_MOUSEWHEEL => {
             let delta = ((wparam.0 >> 16) & 0xFFFF) as i16;
+            // Ctrl+wheel zooms instead of scrolling
+            if ctrl_held() {
+                if let Some(editor) = get_editor(hwnd) {
+                    editor.on_action(if delta > 0 { "zoomIn:" } else { "zoomOut:" });
+                }
+                return LRESULT(0);
+            }
             // Normalize: WHEEL_DELTA (120) = ~3 lines, convert to pixel delta
             let dy = -(delta as f64) * 40.0 / 120.0;
             if let Some(editor) = get_editor(hwnd) {
@@ -333,9 +420,36 @@ unsafe extern "system" fn wnd_proc(
             LRESULT(1)
         }
 
+        // Light/dark mode changes. Only top-level windows get
+        // WM_SETTINGCHANGE, so hosts embedding the view as a child should
+        // forward it (WM_THEMECHANGED reaches every window).
+        WM_SETTINGCHANGE | WM_THEMECHANGED => {
+            if let Some(editor) = get_editor(hwnd) {
+                editor.sync_system_appearance();
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        WM_GETOBJECT => match accessibility::on_get_object(hwnd, wparam, lparam) {
+            Some(result) => result,
+            None => DefWindowProcW(hwnd, msg, wparam, lparam),
+        },
+
+        WM_DESTROY => {
+            accessibility::disconnect(hwnd);
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        WM_TIMER if wparam.0 == FLASH_TIMER_ID => {
+            if let Some(editor) = get_editor(hwnd) {
+                editor.tick_flashes();
+            }
+            LRESULT(0)
+        }
+
         WM_SETFOCUS | WM_KILLFOCUS => {
             if let Some(editor) = get_editor(hwnd) {
-                editor.invalidate();
+                editor.on_focus_changed(msg == WM_SETFOCUS);
             }
             LRESULT(0)
         }
@@ -3,128 +3,283 @@
 //! Provides FontSet (normal/bold/italic IDWriteTextFormat variants) and functions
 //! to measure and draw text with per-token syntax coloring via IDWriteTextLayout.
 
-use serde::Deserialize;
-use windows::core::HSTRING;
+use hone_editor_common::font_cache::FontCache;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::rc::Rc;
+use windows::core::{ComInterface, HSTRING};
 use windows::Win32::Foundation::BOOL;
 use windows::Win32::Graphics::Direct2D::Common::{D2D1_COLOR_F, D2D_RECT_F};
 use windows::Win32::Graphics::Direct2D::{
-    ID2D1HwndRenderTarget, D2D1_DRAW_TEXT_OPTIONS_NONE,
+    ID2D1RenderTarget, D2D1_DRAW_TEXT_OPTIONS_NONE,
 };
 use windows::Win32::Graphics::DirectWrite::{
-    DWriteCreateFactory, IDWriteFactory, IDWriteFontCollection, IDWriteTextFormat,
+    DWriteCreateFactory, IDWriteFactory, IDWriteFactory5, IDWriteFont, IDWriteFontCollection,
+    IDWriteFontFace, IDWriteInMemoryFontFileLoader, IDWriteTextFormat,
     DWRITE_FACTORY_TYPE_SHARED, DWRITE_FONT_METRICS, DWRITE_FONT_STRETCH_NORMAL,
-    DWRITE_FONT_STYLE_ITALIC, DWRITE_FONT_STYLE_NORMAL, DWRITE_FONT_WEIGHT_BOLD,
+    DWRITE_FONT_STYLE, DWRITE_FONT_STYLE_ITALIC, DWRITE_FONT_STYLE_NORMAL, DWRITE_FONT_WEIGHT,
     DWRITE_FONT_WEIGHT_REGULAR, DWRITE_MEASURING_MODE_NATURAL, DWRITE_TEXT_METRICS,
 };
 
-/// Token data from the TypeScript layer.
-#[derive(Debug, Deserialize)]
-pub struct RenderToken {
-    /// Start column (byte offset).
-    pub s: usize,
-    /// End column (byte offset).
-    pub e: usize,
-    /// Hex color string (e.g., "#569cd6").
-    pub c: String,
-    /// Font style: "normal", "italic", or "bold".
-    pub st: String,
-}
+pub use hone_editor_common::tokens::RenderToken;
 
-/// A set of font variants (normal, bold, italic) with cached metrics.
-pub struct FontSet {
+/// Font variants (normal, bold, italic text formats) for one family and
+/// size, with the metrics measured from them. Shared by every view using
+/// that font.
+pub struct Fonts {
     pub factory: IDWriteFactory,
     pub normal: IDWriteTextFormat,
     pub bold: IDWriteTextFormat,
     pub italic: IDWriteTextFormat,
-    pub char_width: f64,
-    pub ascent: f64,
-    pub descent: f64,
-    pub line_height: f64,
     pub font_size: f32,
+    /// Family name, kept to build text formats for sized runs.
+    pub family: HSTRING,
+    /// Font registered from memory this family comes from; `None` for
+    /// system fonts.
+    private: Option<Rc<PrivateFont>>,
+    /// Weight (100–900) and slant of `normal`.
+    weight: u16,
+    slanted: bool,
+    char_width: f64,
+    ascent: f64,
+    descent: f64,
+    line_height: f64,
 }
 
-impl FontSet {
-    /// Create a new FontSet from a font family name and size.
-    pub fn new(family: &str, size: f64) -> Self {
-        let size_f32 = size as f32;
+thread_local! {
+    /// Live `Fonts` by family and point size. Views are created on the UI
+    /// thread; entries are weak, so fonts are freed with their last view.
+    static FONTS: RefCell<FontCache<Fonts>> = RefCell::new(FontCache::new());
+}
 
-        let factory: IDWriteFactory = unsafe {
-            DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)
-                .expect("Failed to create DWrite factory")
-        };
+thread_local! {
+    /// Fonts registered from memory, by the name passed to `register_font`.
+    static PRIVATE_FONTS: RefCell<HashMap<String, Rc<PrivateFont>>> =
+        RefCell::new(HashMap::new());
+}
 
-        let family_h = HSTRING::from(family);
-        let locale_h = HSTRING::from("en-us");
+/// A one-file font collection loaded from memory. Its loader stays
+/// registered while any `Fonts` built from it is alive.
+struct PrivateFont {
+    factory: IDWriteFactory5,
+    loader: IDWriteInMemoryFontFileLoader,
+    collection: IDWriteFontCollection,
+    /// Family name inside the font file.
+    family: HSTRING,
+}
 
-        let normal = unsafe {
-            factory
-                .CreateTextFormat(
-                    &family_h,
-                    None,
-                    DWRITE_FONT_WEIGHT_REGULAR,
-                    DWRITE_FONT_STYLE_NORMAL,
-                    DWRITE_FONT_STRETCH_NORMAL,
-                    size_f32,
-                    &locale_h,
-                )
-                .expect("Failed to create normal text format")
-        };
+impl Drop for PrivateFont {
+    fn drop(&mut self) {
+        let _ = unsafe { self.factory.UnregisterFontFileLoader(&self.loader) };
+    }
+}
 
-        let bold = unsafe {
-            factory
-                .CreateTextFormat(
-                    &family_h,
-                    None,
-                    DWRITE_FONT_WEIGHT_BOLD,
-                    DWRITE_FONT_STYLE_NORMAL,
-                    DWRITE_FONT_STRETCH_NORMAL,
-                    size_f32,
-                    &locale_h,
-                )
-                .expect("Failed to create bold text format")
-        };
+/// A font registered with `register_font`. Dropping it frees the name;
+/// fonts already created from it keep working.
+pub struct RegisteredFont {
+    name: String,
+    font: Rc<PrivateFont>,
+}
 
-        let italic = unsafe {
-            factory
-                .CreateTextFormat(
+impl Drop for RegisteredFont {
+    fn drop(&mut self) {
+        PRIVATE_FONTS.with(|fonts| {
+            let mut fonts = fonts.borrow_mut();
+            if fonts.get(&self.name).is_some_and(|f| Rc::ptr_eq(f, &self.font)) {
+                fonts.remove(&self.name);
+            }
+        });
+    }
+}
+
+/// Load a TrueType/OpenType font from `bytes` (copied) into an in-memory
+/// DirectWrite collection and make it available to `FontSet::new` as
+/// `name`. Returns `None` if the bytes are not a font or DirectWrite is
+/// older than Windows 10 1703.
+pub fn register_font(name: &str, bytes: &[u8]) -> Option<RegisteredFont> {
+    let font = unsafe { load_private_font(bytes) }.ok()?;
+    let font = Rc::new(font);
+    PRIVATE_FONTS.with(|fonts| fonts.borrow_mut().insert(name.to_string(), font.clone()));
+    Some(RegisteredFont { name: name.to_string(), font })
+}
+
+unsafe fn load_private_font(bytes: &[u8]) -> windows::core::Result<PrivateFont> {
+    let factory: IDWriteFactory5 =
+        DWriteCreateFactory::<IDWriteFactory>(DWRITE_FACTORY_TYPE_SHARED)?.cast()?;
+    let loader = factory.CreateInMemoryFontFileLoader()?;
+    factory.RegisterFontFileLoader(&loader)?;
+    let font = (|| {
+        // No owner object, so the loader keeps its own copy of the bytes
+        let file = loader.CreateInMemoryFontFileReference(
+            &factory,
+            bytes.as_ptr() as *const _,
+            bytes.len() as u32,
+            None,
+        )?;
+        let builder = factory.CreateFontSetBuilder2()?;
+        builder.AddFontFile(&file)?;
+        let collection: IDWriteFontCollection =
+            factory.CreateFontCollectionFromFontSet(&builder.CreateFontSet()?)?.cast()?;
+        let names = collection.GetFontFamily(0)?.GetFamilyNames()?;
+        let mut buf = vec![0u16; names.GetStringLength(0)? as usize + 1];
+        names.GetString(0, &mut buf)?;
+        let family = HSTRING::from_wide(&buf[..buf.len() - 1])?;
+        Ok((collection, family))
+    })();
+    match font {
+        Ok((collection, family)) => Ok(PrivateFont { factory, loader, collection, family }),
+        Err(e) => {
+            let _ = factory.UnregisterFontFileLoader(&loader);
+            Err(e)
+        }
+    }
+}
+
+impl Fonts {
+    /// The `Fonts` another view already holds for `family` at `size` in
+    /// this weight and slant, or the result of `load`, registered for the
+    /// next view to reuse.
+    fn shared(
+        family: &str,
+        size: f64,
+        weight: u16,
+        italic: bool,
+        load: impl FnOnce() -> Fonts,
+    ) -> Rc<Fonts> {
+        FONTS.with(|fonts| fonts.borrow_mut().shared(family, size, weight, italic, load))
+    }
+
+    fn with_factory(
+        factory: IDWriteFactory,
+        family_h: HSTRING,
+        private: Option<Rc<PrivateFont>>,
+        size: f64,
+        weight: u16,
+        italic: bool,
+    ) -> Fonts {
+        let size_f32 = size as f32;
+        let locale_h = HSTRING::from("en-us");
+        let collection = private.as_ref().map(|p| &p.collection);
+        let format = |style: &str| {
+            let (run_weight, run_slant) = run_style(weight, italic, style);
+            unsafe {
+                factory.CreateTextFormat(
                     &family_h,
-                    None,
-                    DWRITE_FONT_WEIGHT_REGULAR,
-                    DWRITE_FONT_STYLE_ITALIC,
+                    collection,
+                    run_weight,
+                    run_slant,
                     DWRITE_FONT_STRETCH_NORMAL,
                     size_f32,
                     &locale_h,
                 )
-                .expect("Failed to create italic text format")
+            }
+            .unwrap_or_else(|_| panic!("Failed to create {style} text format"))
         };
+        let normal = format("normal");
+        let bold = format("bold");
+        let italic_format = format("italic");
 
         // Extract font metrics
-        let (ascent, descent, line_height) = Self::extract_metrics(&factory, &family_h, size_f32);
+        let (ascent, descent, line_height) =
+            FontSet::extract_metrics(&factory, &family_h, collection, size_f32);
 
-        // Measure "M" width for monospace char width
-        let char_width = Self::measure_text_internal(&factory, &normal, "M");
+        // Width of "0", the CSS `ch` unit: the cell width for monospace
+        // fonts and a fair average for proportional ones
+        let char_width = FontSet::measure_text_internal(&factory, &normal, "0");
 
-        FontSet {
+        Fonts {
             factory,
             normal,
             bold,
-            italic,
+            italic: italic_format,
+            font_size: size_f32,
+            family: family_h,
+            private,
+            weight,
+            slanted: italic,
             char_width: char_width as f64,
             ascent,
             descent,
             line_height,
-            font_size: size_f32,
         }
     }
+}
+
+/// A view's font: shared `Fonts` (the formats are reached through `Deref`)
+/// plus the metrics, which line spacing and a metrics override adjust per
+/// view.
+pub struct FontSet {
+    fonts: Rc<Fonts>,
+    pub char_width: f64,
+    pub ascent: f64,
+    pub descent: f64,
+    pub line_height: f64,
+    /// Line height from font metrics alone, before any line spacing.
+    pub natural_line_height: f64,
+    /// Font-derived (char_width, ascent, natural_line_height), saved while a
+    /// metrics override is active.
+    saved_metrics: Option<(f64, f64, f64)>,
+}
+
+impl Deref for FontSet {
+    type Target = Fonts;
 
-    /// Extract font metrics using the system font collection.
-    fn extract_metrics(factory: &IDWriteFactory, family: &HSTRING, size: f32) -> (f64, f64, f64) {
+    fn deref(&self) -> &Fonts {
+        &self.fonts
+    }
+}
+
+impl FontSet {
+    /// Create a FontSet from a font family name, size, weight (100–900,
+    /// 400 is regular) and slant, sharing the fonts of any other view
+    /// already using them.
+    pub fn new(family: &str, size: f64, weight: u16, italic: bool) -> Self {
+        Self::with_fonts(Fonts::shared(family, size, weight, italic, || {
+            let factory: IDWriteFactory = unsafe {
+                DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)
+                    .expect("Failed to create DWrite factory")
+            };
+            // A font registered from memory under this name wins over system fonts
+            let private = PRIVATE_FONTS.with(|fonts| fonts.borrow().get(family).cloned());
+            let family_h =
+                private.as_ref().map_or_else(|| HSTRING::from(family), |p| p.family.clone());
+            Fonts::with_factory(factory, family_h, private, size, weight, italic)
+        }))
+    }
+
+    /// Same family and style at a new point size, reusing the DWrite factory.
+    pub fn resized(&self, size: f64) -> Self {
+        let (weight, italic) = (self.weight, self.slanted);
+        Self::with_fonts(Fonts::shared(&self.family.to_string(), size, weight, italic, || {
+            let (factory, family) = (self.factory.clone(), self.family.clone());
+            Fonts::with_factory(factory, family, self.private.clone(), size, weight, italic)
+        }))
+    }
+
+    fn with_fonts(fonts: Rc<Fonts>) -> Self {
+        FontSet {
+            char_width: fonts.char_width,
+            ascent: fonts.ascent,
+            descent: fonts.descent,
+            line_height: fonts.line_height,
+            natural_line_height: fonts.line_height,
+            saved_metrics: None,
+            fonts,
+        }
+    }
+
+    /// Extract font metrics from `collection`, or the system font collection.
+    fn extract_metrics(
+        factory: &IDWriteFactory,
+        family: &HSTRING,
+        collection: Option<&IDWriteFontCollection>,
+        size: f32,
+    ) -> (f64, f64, f64) {
         unsafe {
-            let mut collection: Option<IDWriteFontCollection> = None;
-            if factory
-                .GetSystemFontCollection(&mut collection, false)
-                .is_ok()
-            {
+            let mut system: Option<IDWriteFontCollection> = None;
+            if collection.is_some() || factory.GetSystemFontCollection(&mut system, false).is_ok() {
+                let collection = collection.cloned().or(system);
                 if let Some(collection) = collection {
                     let mut index = 0u32;
                     let mut exists = BOOL(0);
@@ -189,9 +344,130 @@ impl FontSet {
         if text.is_empty() {
             return 0.0;
         }
+        if self.saved_metrics.is_some() {
+            return text.chars().count() as f64 * self.char_width;
+        }
         Self::measure_text_internal(&self.factory, &self.normal, text) as f64
     }
 
+    /// Measure a run drawn with `format`, honoring any metrics override.
+    pub fn measure_run(&self, format: &IDWriteTextFormat, text: &str) -> f64 {
+        if self.saved_metrics.is_some() {
+            return text.chars().count() as f64 * self.char_width;
+        }
+        Self::measure_text_internal(&self.factory, format, text) as f64
+    }
+
+    /// Whether every character of `text` has a glyph in the view's font or,
+    /// for ones it lacks, in an installed font DirectWrite can fall back to.
+    /// Control characters are not drawn and always pass.
+    pub fn has_glyphs(&self, text: &str) -> bool {
+        let codes: Vec<u32> = text.chars().filter(|c| !c.is_control()).map(u32::from).collect();
+        unsafe {
+            let mut system: Option<IDWriteFontCollection> = None;
+            if self.factory.GetSystemFontCollection(&mut system, false).is_err() {
+                return false;
+            }
+            let Some(system) = system else {
+                return false;
+            };
+            // Glyph 0 is .notdef: the font lacks the character
+            let mut glyphs = vec![0u16; codes.len()];
+            let collection = self.private.as_ref().map_or(&system, |p| &p.collection);
+            let (weight, style) = run_style(self.weight, self.slanted, "normal");
+            if let Some(face) = font_face(collection, &self.family, weight, style) {
+                let count = codes.len() as u32;
+                let _ = face.GetGlyphIndices(codes.as_ptr(), count, glyphs.as_mut_ptr());
+            }
+            let missing: Vec<u32> =
+                codes.iter().zip(&glyphs).filter(|(_, &g)| g == 0).map(|(&c, _)| c).collect();
+            if missing.is_empty() {
+                return true;
+            }
+            let fallback: Vec<IDWriteFont> = (0..system.GetFontFamilyCount())
+                .filter_map(|i| system.GetFontFamily(i).ok())
+                .filter_map(|family| {
+                    family
+                        .GetFirstMatchingFont(
+                            DWRITE_FONT_WEIGHT_REGULAR,
+                            DWRITE_FONT_STRETCH_NORMAL,
+                            DWRITE_FONT_STYLE_NORMAL,
+                        )
+                        .ok()
+                })
+                .collect();
+            missing.iter().all(|&c| {
+                fallback.iter().any(|font| font.HasCharacter(c).is_ok_and(|has| has.as_bool()))
+            })
+        }
+    }
+
+    /// Scale `line_height` to `multiplier` times the font's natural height.
+    /// Glyph widths (and therefore `measure_text`) are unaffected.
+    pub fn set_line_spacing(&mut self, multiplier: f64) {
+        self.line_height = (self.natural_line_height * multiplier.max(0.5)).ceil();
+    }
+
+    /// Force fixed monospace metrics, for tests and headless runs where real
+    /// font loading is unavailable or varies. While set, `measure_text` is
+    /// `char_count * char_width`. `None` restores the font's own metrics.
+    pub fn set_metrics_override(&mut self, metrics: Option<(f64, f64, f64)>) {
+        match metrics {
+            Some((char_width, line_height, ascent)) => {
+                if self.saved_metrics.is_none() {
+                    self.saved_metrics =
+                        Some((self.char_width, self.ascent, self.natural_line_height));
+                }
+                self.char_width = char_width;
+                self.ascent = ascent;
+                self.natural_line_height = line_height;
+            }
+            None => {
+                if let Some((char_width, ascent, natural)) = self.saved_metrics.take() {
+                    self.char_width = char_width;
+                    self.ascent = ascent;
+                    self.natural_line_height = natural;
+                }
+            }
+        }
+        self.line_height = self.natural_line_height;
+    }
+
+    /// Space above the glyphs that centers them vertically in a spaced line.
+    pub fn half_leading(&self) -> f64 {
+        ((self.line_height - self.natural_line_height) / 2.0).floor()
+    }
+
+    /// Get the text format for a run, honoring an optional per-run font size.
+    pub fn format_for_run(&self, style: &str, size: Option<f64>) -> IDWriteTextFormat {
+        let base = self.format_for_style(style);
+        let Some(size) = size else {
+            return base.clone();
+        };
+        let (weight, font_style) = run_style(self.weight, self.slanted, style);
+        unsafe {
+            self.factory.CreateTextFormat(
+                &self.family,
+                self.private.as_ref().map(|p| &p.collection),
+                weight,
+                font_style,
+                DWRITE_FONT_STRETCH_NORMAL,
+                size as f32,
+                &HSTRING::from("en-us"),
+            )
+        }
+        .unwrap_or_else(|_| base.clone())
+    }
+
+    /// Distance from the top of a run's layout box to its baseline.
+    /// Metrics scale linearly with size within a family.
+    pub fn run_ascent(&self, size: Option<f64>) -> f64 {
+        match size {
+            Some(size) => self.ascent * size / self.font_size as f64,
+            None => self.ascent,
+        }
+    }
+
     /// Get the text format for a given style string.
     pub fn format_for_style(&self, style: &str) -> &IDWriteTextFormat {
         match style {
@@ -202,6 +478,42 @@ impl FontSet {
     }
 }
 
+/// The face `family` is drawn with from `collection`, in this weight and
+/// slant; `None` if the collection doesn't have the family.
+unsafe fn font_face(
+    collection: &IDWriteFontCollection,
+    family: &HSTRING,
+    weight: DWRITE_FONT_WEIGHT,
+    style: DWRITE_FONT_STYLE,
+) -> Option<IDWriteFontFace> {
+    let (mut index, mut exists) = (0u32, BOOL(0));
+    collection.FindFamilyName(family, &mut index, &mut exists).ok()?;
+    if !exists.as_bool() {
+        return None;
+    }
+    let family = collection.GetFontFamily(index).ok()?;
+    let font = family.GetFirstMatchingFont(weight, DWRITE_FONT_STRETCH_NORMAL, style).ok()?;
+    font.CreateFontFace().ok()
+}
+
+/// Weight for bold runs over a base of `weight`: at least bold, and
+/// heavier than the base.
+fn bold_weight(weight: u16) -> u16 {
+    (weight + 300).clamp(700, 900)
+}
+
+/// DirectWrite weight and style for a token style ("normal", "bold",
+/// "italic") over a base face of `weight`, slanted when `italic`.
+fn run_style(weight: u16, italic: bool, style: &str) -> (DWRITE_FONT_WEIGHT, DWRITE_FONT_STYLE) {
+    let weight = if style == "bold" { bold_weight(weight) } else { weight };
+    let slant = if italic || style == "italic" {
+        DWRITE_FONT_STYLE_ITALIC
+    } else {
+        DWRITE_FONT_STYLE_NORMAL
+    };
+    (DWRITE_FONT_WEIGHT(weight as i32), slant)
+}
+
 /// Parse a "#rrggbb" hex color string to D2D1_COLOR_F.
 pub fn parse_hex_color(hex: &str) -> D2D1_COLOR_F {
     let hex = hex.trim_start_matches('#');
@@ -225,7 +537,7 @@ pub fn parse_hex_color(hex: &str) -> D2D1_COLOR_F {
 /// are drawn individually at computed x offsets so UTF-8/UTF-16 column
 /// index issues are avoided.
 pub fn draw_line(
-    rt: &ID2D1HwndRenderTarget,
+    rt: &ID2D1RenderTarget,
     text: &str,
     tokens: &[RenderToken],
     x: f64,
@@ -257,20 +569,17 @@ pub fn draw_line(
         if last_end < start {
             let gap_text = &text[last_end..start];
             draw_text(rt, gap_text, current_x, y, &font_set.normal, default_color);
-            current_x += FontSet::measure_text_internal(
-                &font_set.factory,
-                &font_set.normal,
-                gap_text,
-            ) as f64;
+            current_x += font_set.measure_run(&font_set.normal, gap_text);
         }
 
-        // Draw the token segment
+        // Draw the token segment. DirectWrite lays each segment out from the
+        // top of its box, so shift sized runs down onto the line's baseline.
         let segment = &text[start..end];
         let color = parse_hex_color(&token.c);
-        let format = font_set.format_for_style(&token.st);
-        draw_text(rt, segment, current_x, y, format, color);
-        current_x +=
-            FontSet::measure_text_internal(&font_set.factory, format, segment) as f64;
+        let format = font_set.format_for_run(&token.st, token.sz);
+        let run_y = y + font_set.ascent - font_set.run_ascent(token.sz);
+        draw_text(rt, segment, current_x, run_y, &format, color);
+        current_x += font_set.measure_run(&format, segment);
 
         last_end = end;
     }
@@ -284,7 +593,7 @@ pub fn draw_line(
 
 /// Draw simple single-color text (used for gutter line numbers, ghost text, etc.).
 pub fn draw_text(
-    rt: &ID2D1HwndRenderTarget,
+    rt: &ID2D1RenderTarget,
     text: &str,
     x: f64,
     y: f64,
@@ -319,3 +628,17 @@ pub fn draw_text(
         );
     }
 }
+
+// Needs DirectWrite, so it only links on Windows hosts.
+#[cfg(all(test, windows))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ten_views_share_one_fonts_object() {
+        let views: Vec<FontSet> =
+            (0..10).map(|_| FontSet::new("Consolas", 13.0, 400, false)).collect();
+        assert!(views.iter().all(|v| Rc::ptr_eq(&v.fonts, &views[0].fonts)));
+        assert_eq!(Rc::strong_count(&views[0].fonts), 10);
+    }
+}
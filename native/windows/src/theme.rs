@@ -0,0 +1,153 @@
+//! Built-in color theme presets.
+//!
+//! A `Theme` holds the chrome colors `EditorView` draws itself: background,
+//! gutter, default text, selection, occurrence outlines, cursor, rulers,
+//! hover cards and diagnostic squiggles. Token colors arrive with each line
+//! from the host and are not part of a theme, except that high-contrast
+//! themes draw every token in the plain text color.
+
+/// Editor chrome colors as "#rrggbb" hex strings.
+pub struct Theme {
+    /// Preset name, as accepted by `preset`.
+    pub name: &'static str,
+    /// Whether this is a dark theme; reported to the theme-changed callback.
+    pub dark: bool,
+    /// High-contrast palette: token colors are flattened to `text`.
+    pub high_contrast: bool,
+    pub background: &'static str,
+    pub gutter_background: &'static str,
+    pub gutter_foreground: &'static str,
+    pub text: &'static str,
+    pub selection: &'static str,
+    /// Opacity the selection is drawn with; it is painted over the text.
+    pub selection_alpha: f64,
+    /// Outline for other occurrences of the selected text.
+    pub occurrence: &'static str,
+    pub cursor: &'static str,
+    pub ruler: &'static str,
+    /// Fill behind hover cards and popups drawn over the text.
+    pub widget_background: &'static str,
+    pub widget_border: &'static str,
+    /// Squiggle colors for error, warning and info diagnostics.
+    pub error: &'static str,
+    pub warning: &'static str,
+    pub info: &'static str,
+}
+
+/// VS Code Dark+ (the default).
+pub const VSCODE_DARK: Theme = Theme {
+    name: "vscode-dark",
+    dark: true,
+    high_contrast: false,
+    background: "#1e1e1e",
+    gutter_background: "#1e1e1e",
+    gutter_foreground: "#858585",
+    text: "#d7d7d7",
+    selection: "#264f7a",
+    selection_alpha: 0.4,
+    occurrence: "#8a8a8a",
+    cursor: "#eaeaea",
+    ruler: "#5a5a5a",
+    widget_background: "#252526",
+    widget_border: "#454545",
+    error: "#f14c4c",
+    warning: "#cca700",
+    info: "#3794ff",
+};
+
+/// VS Code Light+.
+pub const VSCODE_LIGHT: Theme = Theme {
+    name: "vscode-light",
+    dark: false,
+    high_contrast: false,
+    background: "#ffffff",
+    gutter_background: "#ffffff",
+    gutter_foreground: "#237893",
+    text: "#000000",
+    selection: "#add6ff",
+    selection_alpha: 0.6,
+    occurrence: "#8c8c8c",
+    cursor: "#000000",
+    ruler: "#b0b0b0",
+    widget_background: "#f3f3f3",
+    widget_border: "#c8c8c8",
+    error: "#e51400",
+    warning: "#bf8803",
+    info: "#1a85ff",
+};
+
+/// White on black, for Windows High Contrast and similar modes.
+pub const HIGH_CONTRAST_DARK: Theme = Theme {
+    name: "high-contrast-dark",
+    dark: true,
+    high_contrast: true,
+    background: "#000000",
+    gutter_background: "#000000",
+    gutter_foreground: "#ffffff",
+    text: "#ffffff",
+    selection: "#f38518",
+    selection_alpha: 0.5,
+    occurrence: "#f38518",
+    cursor: "#ffffff",
+    ruler: "#6fc3df",
+    widget_background: "#0c141f",
+    widget_border: "#6fc3df",
+    error: "#ff3c3c",
+    warning: "#ffd700",
+    info: "#6fc3df",
+};
+
+/// Black on white.
+pub const HIGH_CONTRAST_LIGHT: Theme = Theme {
+    name: "high-contrast-light",
+    dark: false,
+    high_contrast: true,
+    background: "#ffffff",
+    gutter_background: "#ffffff",
+    gutter_foreground: "#000000",
+    text: "#000000",
+    selection: "#0f4a85",
+    selection_alpha: 0.4,
+    occurrence: "#0f4a85",
+    cursor: "#000000",
+    ruler: "#0f4a85",
+    widget_background: "#ffffff",
+    widget_border: "#0f4a85",
+    error: "#b5200d",
+    warning: "#895503",
+    info: "#0f4a85",
+};
+
+impl Theme {
+    /// Appearance string for the theme-changed callback: "dark", "light",
+    /// "high-contrast-dark" or "high-contrast-light".
+    pub fn appearance(&self) -> &'static str {
+        match (self.high_contrast, self.dark) {
+            (false, true) => "dark",
+            (false, false) => "light",
+            (true, true) => "high-contrast-dark",
+            (true, false) => "high-contrast-light",
+        }
+    }
+}
+
+/// Look up a preset by name: "vscode-dark", "vscode-light",
+/// "high-contrast-dark" or "high-contrast-light".
+pub fn preset(name: &str) -> Option<&'static Theme> {
+    match name {
+        "vscode-dark" => Some(&VSCODE_DARK),
+        "vscode-light" => Some(&VSCODE_LIGHT),
+        "high-contrast-dark" => Some(&HIGH_CONTRAST_DARK),
+        "high-contrast-light" => Some(&HIGH_CONTRAST_LIGHT),
+        _ => None,
+    }
+}
+
+/// The high-contrast preset matching a dark or light base theme.
+pub fn high_contrast(dark: bool) -> &'static Theme {
+    if dark {
+        &HIGH_CONTRAST_DARK
+    } else {
+        &HIGH_CONTRAST_LIGHT
+    }
+}
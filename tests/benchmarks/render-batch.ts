@@ -0,0 +1,73 @@
+/**
+ * Benchmark: batched vs per-line rendering.
+ *
+ * Compares a cold 60-line viewport render sent as one renderLine call per
+ * line against a single renderLines batch. Reports FFI crossings per frame
+ * and the host-side cost of building the calls.
+ *
+ * Run: bun run tests/benchmarks/render-batch.ts
+ */
+
+import { EditorDocument } from '../../core/document/document';
+import { EditorViewModel } from '../../view-model/editor-view-model';
+import { NativeRenderCoordinator } from '../../native/render-coordinator';
+import { NoOpFFI } from '../../native/ffi-bridge';
+
+// --- Setup: a document taller than the viewport, 60 visible lines ---
+
+const LINE_HEIGHT = 21; // 14pt * 1.5
+const VIEWPORT_LINES = 60;
+const HEIGHT = LINE_HEIGHT * VIEWPORT_LINES;
+
+const lines: string[] = [];
+for (let i = 0; i < 1000; i++) {
+  lines.push(`line ${i}: const val = ${i * 3} + ${i * 7}; // some code here for rendering benchmark`);
+}
+const content = lines.join('\n');
+
+function bench(batchLines: boolean, frames: number): { avgFrame: number; crossings: number } {
+  const doc = new EditorDocument('file:///render-batch.ts', content, 'typescript');
+  const vm = new EditorViewModel(doc);
+  vm.onResize(1200, HEIGHT);
+
+  const ffi = new NoOpFFI();
+  const coordinator = new NativeRenderCoordinator(ffi, {
+    fontFamily: 'Menlo',
+    fontSize: 14,
+    lineHeight: 1.5,
+    batchLines,
+  });
+  coordinator.create(1200, HEIGHT);
+  coordinator.attach(vm);
+
+  let total = 0;
+  let crossings = 0;
+  for (let i = 0; i < frames; i++) {
+    ffi.reset();
+    const start = performance.now();
+    coordinator.invalidate(); // cold frame: every visible line is dirty
+    total += performance.now() - start;
+    crossings = ffi.getCalls('renderLine').length + ffi.getCalls('renderLines').length;
+  }
+
+  coordinator.destroy();
+  return { avgFrame: total / frames, crossings };
+}
+
+console.log(`=== Render Batch Benchmark (${VIEWPORT_LINES}-line viewport) ===\n`);
+
+const perLine = bench(false, 500);
+const batched = bench(true, 500);
+
+console.log('Per-line renderLine:');
+console.log(`  line FFI calls/frame: ${perLine.crossings}`);
+console.log(`  avg frame: ${perLine.avgFrame.toFixed(3)}ms`);
+
+console.log('\nBatched renderLines:');
+console.log(`  line FFI calls/frame: ${batched.crossings}`);
+console.log(`  avg frame: ${batched.avgFrame.toFixed(3)}ms`);
+
+console.log(`\n  Crossings saved: ${perLine.crossings - batched.crossings} per frame`);
+console.log(`  ${batched.crossings === 1 ? 'PASS' : 'FAIL'}`);
+
+console.log('\nDone.');
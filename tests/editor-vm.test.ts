@@ -131,6 +131,42 @@ describe('EditorViewModel', () => {
     expect(vm2.gutterWidth).toBeGreaterThanOrEqual(vm1.gutterWidth);
   });
 
+  test('hidden gutter takes no width and moves clicks to x = 0', () => {
+    const vm = makeEditor('line0\nline1\nline2');
+    vm.onResize(800, 600);
+    vm.setCharWidth(8);
+    vm.setGutterVisible(false);
+    expect(vm.gutterWidth).toBe(0);
+    expect(vm.visibleLines[0].gutterItems).toEqual([]);
+    const lineHeight = vm.theme.fontSize * vm.theme.lineHeight;
+    vm.onMouseDown({
+      x: 3 * 8, y: lineHeight + 1,
+      button: 0, clickCount: 1,
+      ctrlKey: false, shiftKey: false, altKey: false, metaKey: false,
+    });
+    expect(vm.cursors[0].line).toBe(1);
+    expect(vm.cursors[0].column).toBe(3);
+    vm.setGutterVisible(true);
+    expect(vm.gutterWidth).toBeGreaterThan(0);
+  });
+
+  test('single-line mode submits on Enter instead of inserting a line', () => {
+    const vm = makeEditor('query');
+    let submits = 0;
+    vm.onSubmit(() => submits++);
+    vm.setSingleLine(true);
+    expect(vm.singleLine).toBe(true);
+    expect(vm.gutterWidth).toBe(0);
+    vm.onKeyDown({
+      key: 'Enter', code: 'Enter',
+      ctrlKey: false, shiftKey: false, altKey: false, metaKey: false,
+    });
+    expect(submits).toBe(1);
+    expect(vm.document.buffer.getLineCount()).toBe(1);
+    vm.setSingleLine(false);
+    expect(vm.gutterWidth).toBeGreaterThan(0);
+  });
+
   test('scroll state', () => {
     const vm = makeEditor(Array(100).fill('line').join('\n'));
     vm.onResize(800, 200);
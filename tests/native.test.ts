@@ -1,5 +1,11 @@
 import { describe, test, expect } from 'bun:test';
-import { NoOpFFI, CursorStyle } from '../native/ffi-bridge';
+import {
+  NoOpFFI,
+  CursorStyle,
+  CaretMode,
+  EofMarkers,
+  SelectionStyle,
+} from '../native/ffi-bridge';
 import type { NativeEditorFFI } from '../native/ffi-bridge';
 import { NativeRenderCoordinator } from '../native/render-coordinator';
 import { TouchInputHandler, type TouchPoint } from '../native/touch-input';
@@ -89,6 +95,23 @@ describe('NoOpFFI', () => {
     expect(CursorStyle.Line).toBe(0);
     expect(CursorStyle.Block).toBe(1);
     expect(CursorStyle.Underline).toBe(2);
+    expect(CursorStyle.ModeDefault).toBe(-1);
+  });
+
+  test('CaretMode constants', () => {
+    expect(CaretMode.Insert).toBe(0);
+    expect(CaretMode.Overwrite).toBe(1);
+  });
+
+  test('EofMarkers constants', () => {
+    expect(EofMarkers.None).toBe(0);
+    expect(EofMarkers.Tilde).toBe(1);
+    expect(EofMarkers.EndSymbol).toBe(2);
+  });
+
+  test('SelectionStyle constants', () => {
+    expect(SelectionStyle.Rect).toBe(0);
+    expect(SelectionStyle.Rounded).toBe(1);
   });
 });
 
@@ -120,7 +143,7 @@ describe('NativeRenderCoordinator', () => {
     expect(coordinator.handle).toBe(1);
     expect(ffi.getCalls('create')).toEqual([[800, 600]]);
     expect(ffi.getCalls('setFont')[0]).toEqual([1, 'Menlo', 14]);
-    expect(ffi.getCalls('measureText').length).toBe(1); // measure 'M'
+    expect(ffi.getCalls('charWidth')).toEqual([[1]]);
   });
 
   test('destroy cleans up', () => {
@@ -140,11 +163,25 @@ describe('NativeRenderCoordinator', () => {
     coordinator.destroy(); // already destroyed
   });
 
-  test('charWidth comes from measureText', () => {
-    const { coordinator } = createCoordinator();
+  test('charWidth comes from the native char width', () => {
+    const { ffi, coordinator } = createCoordinator();
+    coordinator.create(800, 600);
+    expect(coordinator.charWidth).toBe(8);
+    expect(ffi.getCalls('measureText').length).toBe(0);
+  });
+
+  test('charWidth falls back to measuring M without native charWidth', () => {
+    const ffi = new NoOpFFI();
+    const bare: NativeEditorFFI = Object.assign(Object.create(ffi), { charWidth: undefined });
+    const coordinator = new NativeRenderCoordinator(bare, {
+      fontFamily: 'Menlo',
+      fontSize: 14,
+      lineHeight: 1.5,
+    });
     coordinator.create(800, 600);
     // NoOpFFI returns 8px per char, 'M' is 1 char = 8px
     expect(coordinator.charWidth).toBe(8);
+    expect(ffi.getCalls('measureText')).toEqual([[1, 'M']]);
   });
 
   test('attach wires to view model', () => {
@@ -178,6 +215,35 @@ describe('NativeRenderCoordinator', () => {
     expect(renderCalls[0][2]).toBe('line one'); // text
   });
 
+  test('batchLines sends the viewport in one renderLines call', () => {
+    const ffi = new NoOpFFI();
+    const coordinator = new NativeRenderCoordinator(ffi, {
+      fontFamily: 'Menlo',
+      fontSize: 14,
+      lineHeight: 1.5,
+      batchLines: true,
+    });
+    coordinator.create(800, 600);
+    const vm = createViewModelWith('line one\nline "two"\nline three');
+    vm.onResize(800, 600);
+
+    ffi.reset();
+    coordinator.attach(vm);
+
+    expect(ffi.getCalls('renderLine').length).toBe(0);
+    const batchCalls = ffi.getCalls('renderLines');
+    expect(batchCalls.length).toBe(1);
+    const lines = JSON.parse(batchCalls[0][1]);
+    expect(lines.length).toBeGreaterThanOrEqual(3);
+    expect(lines[0].line_number).toBe(1);
+    expect(lines[1].text).toBe('line "two"');
+    expect(Array.isArray(lines[0].tokens)).toBe(true);
+
+    ffi.reset();
+    coordinator.render();
+    expect(ffi.getCalls('renderLines').length).toBe(0);
+  });
+
   test('dirty tracking avoids redundant renders', () => {
     const { ffi, coordinator } = createCoordinator();
     coordinator.create(800, 600);
@@ -216,7 +282,7 @@ describe('NativeRenderCoordinator', () => {
     coordinator.setFont('Fira Code', 16);
 
     expect(ffi.getCalls('setFont')[0]).toEqual([1, 'Fira Code', 16]);
-    expect(ffi.getCalls('measureText').length).toBe(1); // re-measure 'M'
+    expect(ffi.getCalls('charWidth').length).toBe(1); // re-measure the cell
   });
 
   test('measureText uses native measurement', () => {
@@ -277,6 +343,32 @@ describe('NativeRenderCoordinator', () => {
     expect(ffi.getCalls('setCursor').length).toBe(1);
   });
 
+  test('line count is sent once until it changes', () => {
+    const { ffi, coordinator } = createCoordinator();
+    coordinator.create(800, 600);
+    const vm = createViewModelWith('a\nb\nc');
+    vm.onResize(800, 600);
+    coordinator.attach(vm);
+
+    expect(ffi.getCalls('setLineCount')).toEqual([[coordinator.handle, 3]]);
+    coordinator.render();
+    expect(ffi.getCalls('setLineCount').length).toBe(1);
+  });
+
+  test('active line hint follows the primary cursor', () => {
+    const { ffi, coordinator } = createCoordinator();
+    coordinator.create(800, 600);
+    const vm = createViewModelWith('hello\nworld');
+    vm.onResize(800, 600);
+    coordinator.attach(vm);
+
+    vm.cursorManager.moveToPosition(1, 2, false);
+    ffi.reset();
+    coordinator.invalidate();
+
+    expect(ffi.getCalls('setActiveLine')).toEqual([[coordinator.handle, 2]]);
+  });
+
   test('selection regions are sent to FFI', () => {
     const { ffi, coordinator } = createCoordinator();
     coordinator.create(800, 600);
@@ -298,6 +390,27 @@ describe('NativeRenderCoordinator', () => {
     expect(regions.length).toBe(1);
     expect(regions[0].w).toBeGreaterThan(0);
   });
+
+  test('multi-line selection marks rows whose newline is selected', () => {
+    const { ffi, coordinator } = createCoordinator();
+    coordinator.create(800, 600);
+    const vm = createViewModelWith('one\n\nthree');
+    vm.onResize(800, 600);
+    coordinator.attach(vm);
+
+    vm.cursorManager.moveToPosition(0, 1, false);
+    vm.cursorManager.moveToPosition(2, 2, true);
+
+    ffi.reset();
+    coordinator.invalidate();
+
+    const regions = JSON.parse(ffi.getCalls('setSelection')[0][1]);
+    expect(regions.length).toBe(3);
+    expect(regions[0].eol).toBe(true);
+    expect(regions[1].w).toBe(0); // empty line, shown via eol
+    expect(regions[1].eol).toBe(true);
+    expect(regions[2].eol).toBeUndefined();
+  });
 });
 
 // ============================================================
@@ -90,7 +90,10 @@ export class EditorViewModel {
   private _gutter: GutterRenderer;
   private _theme: EditorTheme;
   private _charWidth: number = 8; // default, updated by native renderer
+  private _gutterVisible = true;
+  private _singleLine = false;
   private _listeners: ChangeListener[] = [];
+  private _submitListeners: (() => void)[] = [];
 
   // Token/decoration providers (set by syntax engine)
   private _tokenProvider: ((lineNumber: number) => LineToken[]) | null = null;
@@ -172,6 +175,32 @@ export class EditorViewModel {
     this._gutter.setCharWidth(width);
   }
 
+  /**
+   * Show or hide the whole gutter. Hidden, gutterWidth is 0 and lines carry
+   * no gutter items, so text and hit-testing start at x = 0.
+   */
+  setGutterVisible(visible: boolean): void {
+    this._gutterVisible = visible;
+    this._gutter.setVisible(visible && !this._singleLine);
+    this.notifyChange();
+  }
+
+  /**
+   * One-line input mode for search boxes and inline rename: the gutter is
+   * hidden, Enter runs editor.action.submit (firing onSubmit listeners)
+   * instead of inserting a line, and vertical scrolling is ignored.
+   * Renderers should center the row vertically while singleLine is set.
+   */
+  setSingleLine(enabled: boolean): void {
+    this._singleLine = enabled;
+    this._gutter.setVisible(this._gutterVisible && !enabled);
+    this.notifyChange();
+  }
+
+  get singleLine(): boolean {
+    return this._singleLine;
+  }
+
   setTokenProvider(provider: (lineNumber: number) => LineToken[]): void {
     this._tokenProvider = provider;
   }
@@ -193,6 +222,15 @@ export class EditorViewModel {
     };
   }
 
+  /** Subscribe to Enter in single-line mode. */
+  onSubmit(listener: () => void): () => void {
+    this._submitListeners.push(listener);
+    return () => {
+      const idx = this._submitListeners.indexOf(listener);
+      if (idx !== -1) this._submitListeners.splice(idx, 1);
+    };
+  }
+
   private notifyChange(): void {
     for (const listener of this._listeners) listener();
   }
@@ -261,6 +299,9 @@ export class EditorViewModel {
         this.findWidget.prevMatch();
         this.notifyChange();
         return true;
+      case 'editor.action.submit':
+        for (const listener of this._submitListeners) listener();
+        return true;
       case 'editor.action.escape':
         if (this.findWidget.state.isOpen) {
           this.findWidget.close();
@@ -366,7 +407,7 @@ export class EditorViewModel {
 
   /** Handle scroll events. */
   onScroll(event: ScrollEvent): void {
-    this.viewport.scroll.scrollBy(event.deltaX, event.deltaY);
+    this.viewport.scroll.scrollBy(event.deltaX, this._singleLine ? 0 : event.deltaY);
     this.notifyChange();
   }
 
@@ -515,7 +556,9 @@ export class EditorViewModel {
     // Editing
     if (event.key === 'Backspace') return 'editor.action.deleteLeft';
     if (event.key === 'Delete') return 'editor.action.deleteRight';
-    if (event.key === 'Enter') return 'editor.action.insertLineAfter';
+    if (event.key === 'Enter') {
+      return this._singleLine ? 'editor.action.submit' : 'editor.action.insertLineAfter';
+    }
     if (event.key === 'Tab') {
       if (shift) return 'editor.action.outdent';
       return 'editor.action.indent';
@@ -11,6 +11,8 @@ export interface GutterItem {
 }
 
 export interface GutterConfig {
+  /** False hides the whole gutter: no items and no width. */
+  visible: boolean;
   showLineNumbers: boolean;
   showFoldIndicators: boolean;
   showBreakpoints: boolean;
@@ -18,6 +20,7 @@ export interface GutterConfig {
 }
 
 const DEFAULT_CONFIG: GutterConfig = {
+  visible: true,
   showLineNumbers: true,
   showFoldIndicators: true,
   showBreakpoints: false,
@@ -36,10 +39,15 @@ export class GutterRenderer {
     this.charWidth = width;
   }
 
+  setVisible(visible: boolean): void {
+    this.config.visible = visible;
+  }
+
   /**
    * Compute gutter width in pixels.
    */
   computeGutterWidth(totalLineCount: number): number {
+    if (!this.config.visible) return 0;
     let width = 0;
 
     if (this.config.showLineNumbers) {
@@ -73,6 +81,7 @@ export class GutterRenderer {
     diagnosticSeverity: 'error' | 'warning' | null,
   ): GutterItem[] {
     const items: GutterItem[] = [];
+    if (!this.config.visible) return items;
 
     if (this.config.showLineNumbers) {
       items.push({